@@ -0,0 +1,9 @@
+@echo off
+rem Callscribe launcher, written by `dsd_event_parser init`.
+rem Drop DSDPlus event logs or SRT files onto this file to convert them,
+rem or run it from a prompt with the usual options.
+set "CALLSCRIBE_CONFIG=%~dp0callscribe.toml"
+set "CALLSCRIBE_RECORD_DIR=%~dp0Record"
+set "CALLSCRIBE_MODELS_DIR=%~dp0models"
+"{exe}" %*
+if "%~1"=="" pause
@@ -0,0 +1,8 @@
+#!/bin/sh
+# Callscribe launcher, written by `dsd_event_parser init`: runs it with the
+# configuration, Record directory and model cache next to this script.
+here=$(cd "$(dirname "$0")" && pwd)
+export CALLSCRIBE_CONFIG="$here/callscribe.toml"
+export CALLSCRIBE_RECORD_DIR="$here/Record"
+export CALLSCRIBE_MODELS_DIR="$here/models"
+exec "{exe}" "$@"
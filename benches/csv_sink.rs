@@ -0,0 +1,59 @@
+// benches/csv_sink.rs
+//
+// CSV sink throughput on a million-row stream, one row per write against the
+// default batch. `cargo bench --bench csv_sink`.
+
+use chrono::{Duration, FixedOffset, TimeZone};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use dsd_event_parser::csv_sink::{self, CsvSinkOptions};
+use dsd_event_parser::model::RadioRecord;
+use dsd_event_parser::stats::PipelineStats;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+const ROWS: usize = 1_000_000;
+
+fn record(n: usize) -> RadioRecord {
+    let start = FixedOffset::east_opt(0).unwrap().with_ymd_and_hms(2025, 9, 10, 6, 0, 0).unwrap();
+    let mut rec = RadioRecord::new(n, start + Duration::seconds(n as i64));
+    rec.frequency = Some("153.450000".into());
+    rec.radio_type = Some("P25p1".into());
+    rec.nac = Some("293".into());
+    rec.slot1.tg = Some((n % 40).to_string());
+    rec.slot1.rid = Some((4500 + n % 300).to_string());
+    rec.slot1.text = Some("Engine 5 responding, en route to Main Street".into());
+    rec.duration = 7;
+    rec
+}
+
+fn csv_sink(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let dir = std::env::temp_dir().join(format!("callscribe-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let out = dir.join("calls.csv");
+
+    let mut group = c.benchmark_group("csv_sink");
+    group.sample_size(10).throughput(Throughput::Elements(ROWS as u64));
+    for batch in [1, csv_sink::DEFAULT_BATCH] {
+        group.bench_with_input(BenchmarkId::new("batch", batch), &batch, |b, &batch| {
+            b.to_async(&rt).iter(|| async {
+                let (tx, rx) = mpsc::channel::<RadioRecord>(1024);
+                let feed = tokio::spawn(async move {
+                    for n in 1..=ROWS {
+                        if tx.send(record(n)).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+                let opts = CsvSinkOptions { batch, ..Default::default() };
+                csv_sink::write_csv_stream(&out, rx, Arc::new(PipelineStats::default()), opts).await.unwrap();
+                feed.await.unwrap();
+            })
+        });
+    }
+    group.finish();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+criterion_group!(benches, csv_sink);
+criterion_main!(benches);
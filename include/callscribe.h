@@ -0,0 +1,62 @@
+/* callscribe.h — C ABI for the dsd_event_parser parsers.
+ *
+ * Build the shared library with:
+ *     cargo build --release --features ffi
+ * and link against libdsd_event_parser (.so / .dylib / .dll).
+ */
+#ifndef CALLSCRIBE_H
+#define CALLSCRIBE_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define CALLSCRIBE_OK            0
+#define CALLSCRIBE_STOPPED       1   /* callback returned non-zero */
+#define CALLSCRIBE_ERR_ARG      -1   /* NULL / non-UTF-8 argument */
+#define CALLSCRIBE_ERR_PARSE    -2   /* open/read failure */
+#define CALLSCRIBE_ERR_RUNTIME  -3   /* internal runtime failure */
+#define CALLSCRIBE_ERR_PANIC    -4   /* internal panic (caught) */
+
+/* String fields are NUL-terminated, NULL when absent, and only valid for
+ * the duration of the callback. */
+typedef struct CallscribeRecord {
+    uint64_t    record_number;
+    int64_t     timestamp;    /* Unix seconds, UTC */
+    int32_t     utc_offset;   /* seconds east of UTC */
+    uint32_t    duration;
+    const char *frequency;
+    const char *radio_type;
+    const char *dcc;          /* NAC or DCC */
+    const char *slot1_tg;
+    const char *slot1_rid;
+    const char *slot2_tg;
+    const char *slot2_rid;
+    const char *record_id;    /* same id the sinks write; never NULL */
+    const char *call_type;    /* "group", "private", "data", "emergency" or "unknown" */
+    const char *target_rid;   /* called unit of a private call */
+    int32_t     errors;       /* decode errors, -1 when not logged */
+    int32_t     quality;      /* signal quality in percent, -1 when not logged */
+    const char *nac;          /* P25 NAC only */
+    const char *color_code;   /* DMR color code only */
+} CallscribeRecord;
+
+/* Return non-zero to stop parsing early. */
+typedef int (*callscribe_record_cb)(const CallscribeRecord *rec, void *user_data);
+
+/* Parse an SRT or event file (chosen by extension).
+ *   tz   optional IANA timezone name, NULL for local time
+ *   rle  non-zero to run-length compress adjacent identical records */
+int callscribe_parse_file(const char *path,
+                          const char *tz,
+                          int rle,
+                          callscribe_record_cb callback,
+                          void *user_data);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CALLSCRIBE_H */
@@ -0,0 +1,155 @@
+// src/affiliation.rs
+//
+// `--affiliations`: control-channel events in DSDPlus event logs, i.e. unit
+// registrations, deregistrations and group affiliations, which the call
+// parser otherwise skips. The event parser records them here as it reads,
+// so each voice call can be given the talkgroup its unit was affiliated to
+// at that moment (`affiliated_tg`), and the pipeline writes a report next
+// to the output once the input ends:
+//
+//   <out stem>.affiliations.tsv
+//   rid  event  tg  first_seen  last_seen  count
+//
+// one line per unit, event kind and talkgroup, sorted by RID. Recognised
+// lines carry `RID=` and one of the words below, plus `TG=` for affiliations:
+//
+//   2025/09/09  18:39:10  Freq=851.012500  NAC=293  Group Affiliation; TG=2  RID=4506
+//   2025/09/09  18:39:05  Freq=851.012500  NAC=293  Unit Registration; RID=4506
+//   2025/09/09  23:10:00  Freq=851.012500  NAC=293  Deregistration; RID=4506
+
+use crate::errors::AppError;
+use chrono::{DateTime, FixedOffset};
+use log::info;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventKind {
+    Registration,
+    Deregistration,
+    Affiliation,
+}
+
+impl EventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Registration => "registration",
+            EventKind::Deregistration => "deregistration",
+            EventKind::Affiliation => "affiliation",
+        }
+    }
+
+    /// Classify a non-call log line; `None` if it is none of these.
+    pub fn detect(line: &str) -> Option<EventKind> {
+        let t = line.to_ascii_lowercase();
+        if t.contains("deregistration") || t.contains("de-registration") || t.contains("unregistration") {
+            Some(EventKind::Deregistration)
+        } else if t.contains("registration") {
+            Some(EventKind::Registration)
+        } else if t.contains("affiliation") {
+            Some(EventKind::Affiliation)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Event {
+    pub datetime: DateTime<FixedOffset>,
+    pub kind: EventKind,
+    pub rid: String,
+    /// Talkgroup affiliated to; registrations carry none.
+    pub tg: Option<String>,
+}
+
+#[derive(Debug)]
+struct Seen {
+    first: DateTime<FixedOffset>,
+    last: DateTime<FixedOffset>,
+    count: u64,
+}
+
+/// Affiliation state and report of one pipeline (shared by the files of a
+/// `--merge`).
+#[derive(Debug, Default)]
+pub struct Tracker {
+    /// Talkgroup each unit is affiliated to right now.
+    current: HashMap<String, String>,
+    report: BTreeMap<(String, EventKind, String), Seen>,
+}
+
+pub type SharedTracker = Arc<Mutex<Tracker>>;
+
+impl Tracker {
+    pub fn record(&mut self, ev: Event) {
+        match (ev.kind, &ev.tg) {
+            (EventKind::Affiliation, Some(tg)) => {
+                self.current.insert(ev.rid.clone(), tg.clone());
+            }
+            (EventKind::Deregistration, _) => {
+                self.current.remove(&ev.rid);
+            }
+            _ => {}
+        }
+        let key = (ev.rid, ev.kind, ev.tg.unwrap_or_default());
+        self.report
+            .entry(key)
+            .and_modify(|s| {
+                s.first = s.first.min(ev.datetime);
+                s.last = s.last.max(ev.datetime);
+                s.count += 1;
+            })
+            .or_insert(Seen { first: ev.datetime, last: ev.datetime, count: 1 });
+    }
+
+    /// The talkgroup `rid` is affiliated to, if it has affiliated since it
+    /// last deregistered.
+    pub fn current(&self, rid: &str) -> Option<String> {
+        self.current.get(rid).cloned()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.report.is_empty()
+    }
+}
+
+/// `<out stem>.affiliations.tsv` next to the main output.
+pub fn report_path(out_path: &Path) -> PathBuf {
+    out_path.with_extension("affiliations.tsv")
+}
+
+/// Write the report; nothing is written for inputs without any such events.
+pub async fn write_report(out_path: &Path, tracker: &SharedTracker) -> Result<Option<PathBuf>, AppError> {
+    let body = {
+        let t = tracker.lock();
+        if t.is_empty() {
+            return Ok(None);
+        }
+        let mut body = String::from("rid\tevent\ttg\tfirst_seen\tlast_seen\tcount\n");
+        for ((rid, kind, tg), seen) in &t.report {
+            body.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\n",
+                rid,
+                kind.as_str(),
+                tg,
+                seen.first.to_rfc3339(),
+                seen.last.to_rfc3339(),
+                seen.count
+            ));
+        }
+        body
+    };
+    let path = report_path(out_path);
+    let file = tokio::fs::File::create(&path)
+        .await
+        .map_err(|e| AppError::IO(format!("open affiliation report '{}': {}", path.display(), e)))?;
+    let mut w = BufWriter::new(file);
+    w.write_all(body.as_bytes()).await?;
+    w.flush().await?;
+    info!("Affiliations: {} lines -> {}", body.lines().count() - 1, path.display());
+    Ok(Some(path))
+}
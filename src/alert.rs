@@ -0,0 +1,266 @@
+// src/alert.rs
+//
+// Watchlist alerting (`--watch-rid`, `--watch-tg`). Unlike filtering, every
+// record passes through; calls by a watched unit or on a watched talkgroup
+// get their `alert` field set and are handed to the notifiers as soon as RLE
+// closes the call, before transcription, unless the throttle (`throttle.rs`)
+// holds them back. Watch entries are IDs or names from the config file's
+// `[alias]` tables. With `--alert-severity N`, calls scoring N or more by
+// the `[severity]` rules (`severity.rs`) alert too; the stage then runs
+// after transcription and scoring, so keyword rules can count.
+
+use crate::config::Aliases;
+use crate::errors::AppError;
+use crate::model::{RadioRecord, SlotData};
+use crate::throttle::{Throttle, Verdict};
+use log::{debug, info, warn};
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use tokio::sync::mpsc::{Receiver, Sender};
+
+/// Watched radio IDs and talkgroups, resolved to IDs, plus the aliases used
+/// to name them in alerts.
+#[derive(Debug, Clone, Default)]
+pub struct WatchList {
+    rids: BTreeSet<String>,
+    tgs: BTreeSet<String>,
+    aliases: Aliases,
+}
+
+impl WatchList {
+    /// Resolve `rids`/`tgs` (IDs or alias names); an unknown name is an error.
+    pub fn new(rids: &[String], tgs: &[String], aliases: &Aliases) -> Result<Self, AppError> {
+        let resolve = |kind: &str, table, names: &[String]| {
+            names
+                .iter()
+                .map(|n| {
+                    Aliases::resolve(table, n).ok_or_else(|| {
+                        AppError::Parse(format!("--watch-{}: '{}' is neither an ID nor a [alias.{}] name", kind, n, kind))
+                    })
+                })
+                .collect::<Result<BTreeSet<_>, _>>()
+        };
+        Ok(Self {
+            rids: resolve("rid", &aliases.rid, rids)?,
+            tgs: resolve("tg", &aliases.tg, tgs)?,
+            aliases: aliases.clone(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rids.is_empty() && self.tgs.is_empty()
+    }
+
+    /// The watched radio IDs and talkgroups `rec` matches, in slot order.
+    pub fn keys(&self, rec: &RadioRecord) -> Vec<WatchKey> {
+        let mut keys: Vec<WatchKey> = Vec::new();
+        let slots: [&SlotData; 2] = [&rec.slot1, &rec.slot2];
+        for s in slots {
+            if let Some(rid) = s.rid.as_deref().filter(|r| self.rids.contains(*r)) {
+                keys.push(WatchKey::Rid(rid.to_string()));
+            }
+            if let Some(tg) = s.tg.as_deref().filter(|t| self.tgs.contains(*t)) {
+                keys.push(WatchKey::Tg(tg.to_string()));
+            }
+        }
+        keys.dedup();
+        keys
+    }
+
+    /// `RID 4506 (Engine 5), TG 2` for a watched call, else `None`.
+    pub fn reason(&self, rec: &RadioRecord) -> Option<String> {
+        let named = |kind: &str, id: &str, table: &std::collections::BTreeMap<String, String>| match table.get(id) {
+            Some(name) => format!("{} {} ({})", kind, id, name),
+            None => format!("{} {}", kind, id),
+        };
+        let hits: Vec<String> = self
+            .keys(rec)
+            .iter()
+            .map(|k| match k {
+                WatchKey::Rid(id) => named("RID", id, &self.aliases.rid),
+                WatchKey::Tg(id) => named("TG", id, &self.aliases.tg),
+            })
+            .collect();
+        (!hits.is_empty()).then(|| hits.join(", "))
+    }
+}
+
+/// A watched radio ID or talkgroup.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WatchKey {
+    Rid(String),
+    Tg(String),
+}
+
+/// Somewhere alerts go. Called off the async runtime, so it may block.
+pub trait Notifier: Send + Sync {
+    fn notify(&self, rec: &RadioRecord) -> Result<(), AppError>;
+}
+
+/// What every pipeline's alert stage shares: the watchlist, where alerts
+/// go, and the throttle in front of all notifiers.
+#[derive(Clone)]
+pub struct Alerting {
+    pub watch: Arc<WatchList>,
+    /// `--alert-severity`: also alert on calls scoring this much.
+    pub min_severity: Option<u32>,
+    pub notifiers: Arc<Vec<Arc<dyn Notifier>>>,
+    pub throttle: Arc<Throttle>,
+}
+
+impl Alerting {
+    fn severe(&self, rec: &RadioRecord) -> Option<u32> {
+        rec.severity.filter(|s| self.min_severity.is_some_and(|min| *s >= min))
+    }
+
+    /// The watchlist's reason, and `severity N` for a call over the threshold.
+    pub fn reason(&self, rec: &RadioRecord) -> Option<String> {
+        let mut why: Vec<String> = self.watch.reason(rec).into_iter().collect();
+        if let Some(s) = self.severe(rec) {
+            why.push(format!("severity {}", s));
+        }
+        (!why.is_empty()).then(|| why.join(", "))
+    }
+
+    /// What the throttle keys an alert on: the watched TGs/RIDs, else (a
+    /// severity alert) the call's own.
+    fn keys(&self, rec: &RadioRecord) -> Vec<WatchKey> {
+        let mut keys = self.watch.keys(rec);
+        if keys.is_empty() && self.severe(rec).is_some() {
+            for s in [&rec.slot1, &rec.slot2] {
+                keys.extend(s.rid.clone().map(WatchKey::Rid));
+                keys.extend(s.tg.clone().map(WatchKey::Tg));
+            }
+            keys.dedup();
+        }
+        keys
+    }
+}
+
+/// Always on: a warning in the log.
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn notify(&self, rec: &RadioRecord) -> Result<(), AppError> {
+        warn!(
+            "ALERT {} on {} at {} ({}s)",
+            rec.alert.as_deref().unwrap_or_default(),
+            rec.frequency.as_deref().unwrap_or("-"),
+            rec.datetime.format("%Y-%m-%d %H:%M:%S"),
+            rec.duration
+        );
+        Ok(())
+    }
+}
+
+/// `--notify-exec CMD`: run CMD through the shell per alert, with the
+/// record's JSON on stdin and the reason in `CALLSCRIBE_ALERT`.
+pub struct ExecNotifier {
+    pub command: String,
+}
+
+impl Notifier for ExecNotifier {
+    fn notify(&self, rec: &RadioRecord) -> Result<(), AppError> {
+        let mut cmd = if cfg!(windows) {
+            let mut c = Command::new("cmd");
+            c.arg("/C");
+            c
+        } else {
+            let mut c = Command::new("sh");
+            c.arg("-c");
+            c
+        };
+        let mut child = cmd
+            .arg(&self.command)
+            .env("CALLSCRIBE_ALERT", rec.alert.as_deref().unwrap_or_default())
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| AppError::IO(format!("--notify-exec '{}': {}", self.command, e)))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A command that ignores stdin may exit before reading it.
+            let _ = writeln!(stdin, "{}", rec.to_json());
+        }
+        let status = child.wait().map_err(|e| AppError::IO(format!("--notify-exec '{}': {}", self.command, e)))?;
+        if !status.success() {
+            return Err(AppError::Other(format!("--notify-exec '{}' exited with {}", self.command, status)));
+        }
+        Ok(())
+    }
+}
+
+async fn report(t: tokio::task::JoinHandle<Result<(), AppError>>) {
+    match t.await {
+        Ok(Err(e)) => warn!("notifier: {}", e),
+        Err(e) => warn!("notifier join: {}", e),
+        Ok(Ok(())) => {}
+    }
+}
+
+/// Forward every record, setting `alert` on alerting calls and notifying for
+/// the ones the throttle lets through. Notifiers run in the background so a
+/// slow one doesn't hold up the pipeline; the stage waits for them before it
+/// ends.
+pub async fn alert_stream(
+    mut rx: Receiver<RadioRecord>,
+    tx: Sender<RadioRecord>,
+    alerting: Alerting,
+) -> Result<Vec<PathBuf>, AppError> {
+    let mut pending = Vec::new();
+    let (mut alerts, mut cooldown, mut burst) = (0usize, 0usize, 0usize);
+    while let Some(mut rec) = rx.recv().await {
+        rec.alert = alerting.reason(&rec);
+        let verdict = match &rec.alert {
+            None => None,
+            Some(_) => {
+                alerts += 1;
+                Some(alerting.throttle.check(&alerting.keys(&rec), rec.datetime.timestamp()))
+            }
+        };
+        match verdict {
+            None => {}
+            Some(Verdict::Notify) => {
+                for n in alerting.notifiers.iter() {
+                    let (n, r) = (Arc::clone(n), rec.clone());
+                    pending.push(tokio::task::spawn_blocking(move || n.notify(&r)));
+                }
+                let (done, running): (Vec<_>, Vec<_>) = pending.into_iter().partition(|t| t.is_finished());
+                pending = running;
+                for t in done {
+                    report(t).await;
+                }
+            }
+            Some(held) => {
+                let why = if held == Verdict::Cooldown {
+                    cooldown += 1;
+                    "in cooldown"
+                } else {
+                    burst += 1;
+                    "burst limit reached"
+                };
+                debug!("alert: {} at {}: {}, not notified", rec.alert.as_deref().unwrap_or_default(), rec.datetime, why);
+            }
+        }
+        if tx.send(rec).await.is_err() {
+            warn!("alert: downstream closed");
+            break;
+        }
+    }
+    for t in pending {
+        report(t).await;
+    }
+    if cooldown + burst > 0 {
+        info!(
+            "Alerts: {} calls ({} not notified: {} in cooldown, {} over the burst limit)",
+            alerts,
+            cooldown + burst,
+            cooldown,
+            burst
+        );
+    } else {
+        info!("Alerts: {} calls", alerts);
+    }
+    Ok(vec![])
+}
@@ -0,0 +1,202 @@
+// src/analog_log.rs
+//
+// Activity logs of analog scanners: rtl_airband (`log_scan_activity`, on
+// stdout or via syslog) and the SDR++ scanner, so conventional AM/FM
+// channels end up in the same call log as the digital systems. Such a line
+// carries a timestamp and a frequency and nothing else of interest:
+//
+//   Sep 10 06:43:56 pi rtl_airband[512]: Activity on 118.300 MHz
+//   [2025-09-10 06:43:56.123] (INFO) Scanner: Found signal at 118300000 Hz
+//   2025-09-10 06:44:02  155.475000 MHz  NFM  squelch closed
+//
+// A line mentioning a squelch closing (`closed`, `end`, `lost`) ends the
+// transmission its frequency opened, which gives the call's duration; so
+// does an explicit `duration=12s` / `for 12s` on the opening line. An
+// opening without either is a one-second call once the next opening on the
+// same frequency arrives or the log ends. Syslog timestamps carry no year:
+// it comes from `--date-from-filename`, else the current year.
+//
+// Calls have no TG or RID; RLE keeps transmissions more than a few seconds
+// apart separate (see `rle_filter.rs`).
+
+use crate::errors::AppError;
+use crate::input::{self, NumberedLines, ParseNote, ParseOptions, TimeOnlyClock};
+use crate::model::RadioRecord;
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use log::{debug, warn};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::mpsc::Sender;
+
+struct Patterns {
+    iso: Regex,
+    syslog: Regex,
+    time_only: Regex,
+    mhz: Regex,
+    hz: Regex,
+    duration: Regex,
+    mode: Regex,
+    close: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static P: OnceLock<Patterns> = OnceLock::new();
+    P.get_or_init(|| Patterns {
+        iso: Regex::new(r"^\[?(\d{4}[-/]\d{2}[-/]\d{2})[ T](\d{2}:\d{2}:\d{2})").unwrap(),
+        syslog: Regex::new(r"^\[?([A-Z][a-z]{2}) +(\d{1,2}) (\d{2}:\d{2}:\d{2})").unwrap(),
+        time_only: Regex::new(r"^\[?(\d{2}:\d{2}:\d{2})").unwrap(),
+        mhz: Regex::new(r"(?i)\b(\d{1,4}\.\d{1,6}) ?MHz").unwrap(),
+        hz: Regex::new(r"(?i)\b(\d{7,10})(?: ?Hz)?\b").unwrap(),
+        duration: Regex::new(r"(?i)\b(?:duration|length|for)[=: ]*(\d+(?:\.\d+)?) ?s").unwrap(),
+        mode: Regex::new(r"\b(AM|NFM|WFM|FM|USB|LSB)\b").unwrap(),
+        close: Regex::new(r"(?i)\b(closed?|end(?:ed)?|lost)\b").unwrap(),
+    })
+}
+
+/// What one log line says.
+enum Activity {
+    Open { at: NaiveDateTime, freq: String, mode: Option<String>, seconds: Option<u32> },
+    Close { at: NaiveDateTime, freq: String },
+}
+
+/// The timestamp a line starts with, and where the rest of the line begins.
+fn timestamp(s: &str, year: i32, clock: Option<&mut TimeOnlyClock>) -> Option<(NaiveDateTime, usize)> {
+    let p = patterns();
+    let end = |c: &regex::Captures| c.get(0).map_or(0, |m| m.end());
+    if let Some(c) = p.iso.captures(s) {
+        let date = c[1].replace('/', "-");
+        let at = NaiveDateTime::parse_from_str(&format!("{} {}", date, &c[2]), "%Y-%m-%d %H:%M:%S").ok()?;
+        return Some((at, end(&c)));
+    }
+    if let Some(c) = p.syslog.captures(s) {
+        let dt = format!("{} {} {} {}", year, &c[1], &c[2], &c[3]);
+        return Some((NaiveDateTime::parse_from_str(&dt, "%Y %b %d %H:%M:%S").ok()?, end(&c)));
+    }
+    let c = p.time_only.captures(s)?;
+    let t = NaiveTime::parse_from_str(&c[1], "%H:%M:%S").ok()?;
+    clock.map(|clock| (clock.resolve(t), end(&c)))
+}
+
+fn frequency(s: &str) -> Option<String> {
+    let p = patterns();
+    let mhz = match p.mhz.captures(s) {
+        Some(c) => c[1].parse::<f64>().ok()?,
+        None => p.hz.captures(s)?[1].parse::<f64>().ok()? / 1e6,
+    };
+    Some(format!("{:.6}", mhz))
+}
+
+/// Classify a line; `Err` with the reason it is not activity.
+fn parse_line(line: &str, year: i32, clock: Option<&mut TimeOnlyClock>) -> Result<Activity, String> {
+    let s = line.trim().trim_start_matches('\u{FEFF}');
+    if s.is_empty() {
+        return Err("blank".into());
+    }
+    let (at, from) = timestamp(s, year, clock).ok_or("no timestamp")?;
+    // Fractional seconds (`06:43:56.123]`) belong to the timestamp.
+    let rest = s[from..].trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == ',' || c == ']');
+    let freq = frequency(rest).ok_or("no frequency")?;
+    let p = patterns();
+    if p.close.is_match(rest) {
+        return Ok(Activity::Close { at, freq });
+    }
+    let seconds = p.duration.captures(rest).and_then(|c| c[1].parse::<f64>().ok()).map(|v| v.round() as u32);
+    let mode = p.mode.captures(rest).map(|c| c[1].to_string());
+    Ok(Activity::Open { at, freq, mode, seconds })
+}
+
+fn to_local(naive: NaiveDateTime, tz_offset: Option<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+    match tz_offset {
+        Some(off) => off.from_local_datetime(&naive).single().map(|dt| dt.fixed_offset()),
+        None => Local.from_local_datetime(&naive).single().map(|dt| dt.fixed_offset()),
+    }
+}
+
+/// A transmission whose squelch hasn't closed yet.
+struct Open {
+    at: NaiveDateTime,
+    mode: Option<String>,
+}
+
+/// Whether the start of a file looks like a scanner activity log: a line
+/// with a timestamp and a frequency.
+pub fn sniff(head: &str) -> bool {
+    head.lines().take(20).any(|l| parse_line(l, 2000, None).is_ok())
+}
+
+pub async fn stream_file(path: &Path, opts: ParseOptions, tx: Sender<RadioRecord>) -> Result<(), AppError> {
+    let file = input::open_input(path, &opts).await?;
+    let mut lines = NumberedLines::new(file, &opts);
+    let source = input::source_name(path);
+    let mut clock = opts.base_date.map(TimeOnlyClock::new);
+    let year = opts.base_date.map_or_else(|| Local::now().year(), |d: NaiveDate| d.year());
+
+    let mut open: BTreeMap<String, Open> = BTreeMap::new();
+    let mut recno: usize = 1;
+    // Build and send one call, noted at `line` (the line that completed it,
+    // for `explain`); false once downstream is gone.
+    let mut emit = async |freq: String, o: Open, seconds: u32, line: usize| -> bool {
+        let Some(datetime) = to_local(o.at, opts.tz_offset) else {
+            opts.note(ParseNote::Skipped { line, reason: format!("ambiguous/invalid local datetime {}", o.at) });
+            return true;
+        };
+        let mut rec = RadioRecord {
+            frequency: Some(freq),
+            radio_type: o.mode,
+            duration: seconds.max(1),
+            source: Some(Arc::clone(&source)),
+            ..RadioRecord::new(recno, datetime)
+        };
+        opts.finish(&mut rec);
+        opts.note(ParseNote::Record { line });
+        recno += 1;
+        tx.send(rec).await.is_ok()
+    };
+
+    while let Some(line) = lines.next_line().await? {
+        let sent = match parse_line(&line, year, clock.as_mut()) {
+            Ok(Activity::Open { at, freq, mode, seconds }) => {
+                let o = Open { at, mode };
+                match seconds {
+                    Some(s) => emit(freq, o, s, lines.line).await,
+                    None => match open.insert(freq.clone(), o) {
+                        Some(prev) => emit(freq, prev, 1, lines.line).await,
+                        None => true,
+                    },
+                }
+            }
+            Ok(Activity::Close { at, freq }) => match open.remove(&freq) {
+                Some(o) => {
+                    let seconds = (at - o.at).num_seconds().max(1) as u32;
+                    emit(freq, o, seconds, lines.line).await
+                }
+                None => {
+                    let reason = "squelch close without an opening".into();
+                    opts.note(ParseNote::Skipped { line: lines.line, reason });
+                    true
+                }
+            },
+            Err(reason) => {
+                debug!("analog_log: line {}: {}", lines.line, reason);
+                opts.note(ParseNote::Skipped { line: lines.line, reason });
+                true
+            }
+        };
+        if !sent {
+            warn!("analog_log: downstream closed; aborting");
+            return Ok(());
+        }
+    }
+
+    // Still open at the end of the log, oldest first.
+    let mut rest: Vec<(String, Open)> = open.into_iter().collect();
+    rest.sort_by_key(|(_, o)| o.at);
+    for (freq, o) in rest {
+        if !emit(freq, o, 1, lines.line + 1).await {
+            break;
+        }
+    }
+    Ok(())
+}
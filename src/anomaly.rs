@@ -0,0 +1,116 @@
+// src/anomaly.rs
+//
+// `callscribe anomalies`: unusual call volume per talkgroup, from the
+// history in a `--sink sqlite` database. For every talkgroup and hour of
+// the day, the calls in that hour on each earlier day form a baseline
+// (days without calls count as zero); an hour in the evaluated window is
+// flagged when its count is at least `min_calls` and more than `sigma`
+// standard deviations above the baseline mean. The deviation is taken as
+// at least one call, so a talkgroup that is normally silent at that hour
+// isn't flagged for two calls.
+//
+// Hours are wall-clock hours as stored with each call.
+
+use crate::errors::AppError;
+use chrono::{Duration, NaiveDateTime, Timelike};
+use std::collections::{BTreeMap, HashMap};
+
+/// Earlier days needed before anything is flagged.
+pub const MIN_HISTORY_DAYS: i64 = 3;
+
+#[derive(Clone, Debug)]
+pub struct AnomalyOptions {
+    /// Flag hours this many standard deviations above the mean.
+    pub sigma: f64,
+    /// Ignore hours with fewer calls than this.
+    pub min_calls: u64,
+    /// Evaluate the last `days` days of data; everything before is baseline.
+    pub days: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Anomaly {
+    pub hour: NaiveDateTime,
+    pub tg: String,
+    pub calls: u64,
+    pub mean: f64,
+    pub sd: f64,
+    /// Standard deviations above the mean.
+    pub z: f64,
+}
+
+/// Parse the `YYYY-MM-DDTHH` prefix of a stored datetime.
+pub fn parse_hour(s: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(&format!("{}:00:00", s.get(..13)?), "%Y-%m-%dT%H:%M:%S").ok()
+}
+
+/// Flag the anomalous hours in `counts` (talkgroup, hour, calls), sorted by
+/// hour and then most unusual first.
+pub fn detect(counts: &[(String, NaiveDateTime, u64)], opts: &AnomalyOptions) -> Result<Vec<Anomaly>, AppError> {
+    let (Some(first), Some(last)) = (counts.iter().map(|c| c.1).min(), counts.iter().map(|c| c.1).max()) else {
+        return Ok(vec![]);
+    };
+    let window_start = last - Duration::hours(24 * opts.days.max(1) as i64) + Duration::hours(1);
+    let history_days = (window_start - first).num_hours() / 24;
+    if history_days < MIN_HISTORY_DAYS {
+        return Err(AppError::Other(format!(
+            "anomalies: {} day(s) of history before the last {} day(s); need at least {}",
+            history_days, opts.days, MIN_HISTORY_DAYS
+        )));
+    }
+
+    let mut by_tg: BTreeMap<&str, HashMap<NaiveDateTime, u64>> = BTreeMap::new();
+    for (tg, hour, n) in counts {
+        if !tg.is_empty() {
+            *by_tg.entry(tg.as_str()).or_default().entry(*hour).or_default() += n;
+        }
+    }
+
+    let mut found = Vec::new();
+    for (tg, hours) in &by_tg {
+        // Baseline per hour of day, over every day from the first on record.
+        let mut baseline: HashMap<u32, (f64, f64)> = HashMap::new();
+        for h in 0..24 {
+            let mut samples = Vec::new();
+            let mut slot = first.date().and_hms_opt(h, 0, 0).unwrap_or(first);
+            while slot < window_start {
+                if slot >= first {
+                    samples.push(hours.get(&slot).copied().unwrap_or(0) as f64);
+                }
+                slot += Duration::days(1);
+            }
+            if samples.is_empty() {
+                continue;
+            }
+            let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+            let var = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+            baseline.insert(h, (mean, var.sqrt()));
+        }
+        for (hour, &calls) in hours.iter().filter(|(h, _)| **h >= window_start) {
+            let (mean, sd) = baseline.get(&hour.hour()).copied().unwrap_or((0.0, 0.0));
+            let z = (calls as f64 - mean) / sd.max(1.0);
+            if calls >= opts.min_calls && z > opts.sigma {
+                found.push(Anomaly { hour: *hour, tg: tg.to_string(), calls, mean, sd, z });
+            }
+        }
+    }
+    found.sort_by(|a, b| a.hour.cmp(&b.hour).then(b.z.total_cmp(&a.z)));
+    Ok(found)
+}
+
+/// The report as CSV: `hour,tg,calls,baseline_mean,baseline_sd,z`.
+pub fn to_csv(found: &[Anomaly]) -> String {
+    let mut out = String::from("hour,tg,calls,baseline_mean,baseline_sd,z\n");
+    for a in found {
+        out.push_str(&format!(
+            "{},{},{},{:.2},{:.2},{:.1}\n",
+            a.hour.format("%Y-%m-%d %H:00"),
+            a.tg,
+            a.calls,
+            a.mean,
+            a.sd,
+            a.z
+        ));
+    }
+    out
+}
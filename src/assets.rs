@@ -0,0 +1,14 @@
+// src/assets.rs
+//
+// Files compiled into the binary from `assets/`, so the executable alone is
+// a complete install: the web viewer `serve` hosts and the starter files
+// `init` writes (`assets/init/`).
+
+use include_dir::{Dir, include_dir};
+
+static ASSETS: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/assets");
+
+/// The bundled text file at `path` (relative to `assets/`).
+pub fn text(path: &str) -> Option<&'static str> {
+    ASSETS.get_file(path).and_then(|f| f.contents_utf8())
+}
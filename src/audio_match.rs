@@ -0,0 +1,227 @@
+// src/audio_match.rs
+//
+// `--match-audio`: find each call's recording in the Record directory (same
+// file-name grammar and day index as transcripts, with audio extensions) and
+// set its `audio_path`, which the CSV/database sinks and `--playlist` use.
+// Recorders name files by when the audio started, which is often a second
+// or two off the logged call start, so files within `--audio-window`
+// seconds on the same frequency are candidates: each second off and each
+// TG/RID that doesn't agree costs a point, and the lowest score wins.
+//
+// `--audio-dedup` (feature `audio-fingerprint`): setups that record the same
+// call twice, e.g. a conventional channel and a trunk trace, give two calls
+// with the same audio in different files. Each matched recording is
+// fingerprinted and compared with the recordings of calls that started
+// shortly before or after; a duplicate is logged and the call gets the
+// recording that was kept, so each call points at one file for that audio.
+//
+// The stage looks up several calls at once (`AUDIO_CONCURRENCY` in the
+// binary) but forwards them in order, and fingerprints are compared in call
+// order too. Day folders are listed once, on first use (see
+// `RecordFileIndex`).
+
+use crate::errors::AppError;
+#[cfg(feature = "audio-fingerprint")]
+use crate::fingerprint::{DUPLICATE_BER, Fingerprint};
+use crate::freq::FreqTolerance;
+use crate::model::RadioRecord;
+use crate::transcriber::{AUDIO_EXTENSIONS, RecordFileIndex};
+use crate::winpath;
+use log::{debug, info, warn};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::task::JoinHandle;
+
+/// Recordings of calls this far apart (seconds) may be duplicates.
+#[cfg(feature = "audio-fingerprint")]
+pub const DEDUP_WINDOW_S: i64 = 10;
+
+/// Fingerprinted recordings kept for comparison.
+#[cfg(feature = "audio-fingerprint")]
+const DEDUP_RECENT: usize = 256;
+
+#[cfg(feature = "audio-fingerprint")]
+#[derive(Default)]
+struct Recent {
+    /// Call start (Unix seconds), recording, its fingerprint; oldest first.
+    entries: std::collections::VecDeque<(i64, PathBuf, Fingerprint)>,
+}
+
+pub struct AudioMatcher {
+    root: PathBuf,
+    files: RecordFileIndex,
+    #[cfg(feature = "audio-fingerprint")]
+    dedup: Option<parking_lot::Mutex<Recent>>,
+}
+
+/// A recording found for a call, before deduplication.
+pub struct Candidate {
+    path: PathBuf,
+    #[cfg(feature = "audio-fingerprint")]
+    fingerprint: Option<Fingerprint>,
+}
+
+/// A call's recording.
+pub struct Match {
+    pub path: PathBuf,
+    /// The recording found for the call, when it duplicated `path`.
+    pub duplicate: Option<PathBuf>,
+}
+
+impl AudioMatcher {
+    /// Recordings under `root` with `extensions` ([`AUDIO_EXTENSIONS`] when `None`).
+    pub fn new(root: &Path, extensions: Option<&[String]>, freq_tolerance: FreqTolerance) -> Self {
+        let files = match extensions {
+            Some(exts) => RecordFileIndex::new(exts),
+            None => RecordFileIndex::new(AUDIO_EXTENSIONS),
+        };
+        Self {
+            root: root.to_path_buf(),
+            files: files.with_freq_tolerance(freq_tolerance),
+            #[cfg(feature = "audio-fingerprint")]
+            dedup: None,
+        }
+    }
+
+    /// Also match recordings named up to `seconds` off the call's start;
+    /// the best candidate by time distance and TG/RID agreement wins.
+    pub fn with_time_window(mut self, seconds: u32) -> Self {
+        self.files = self.files.with_time_window(seconds);
+        self
+    }
+
+    /// Compare recordings by fingerprint and keep one per duplicated call.
+    #[cfg(feature = "audio-fingerprint")]
+    pub fn with_dedup(mut self) -> Self {
+        self.dedup = Some(parking_lot::Mutex::new(Recent::default()));
+        self
+    }
+
+    /// Look up `rec`'s recording and, when deduplicating, fingerprint it:
+    /// the blocking part, safe to run for several calls at once.
+    pub fn locate(&self, rec: &RadioRecord) -> Result<Option<Candidate>, AppError> {
+        let Some(path) = self.files.find(rec, &self.root)? else { return Ok(None) };
+        #[cfg(feature = "audio-fingerprint")]
+        let fingerprint = match &self.dedup {
+            Some(_) => Fingerprint::compute(&path)?,
+            None => None,
+        };
+        Ok(Some(Candidate {
+            path,
+            #[cfg(feature = "audio-fingerprint")]
+            fingerprint,
+        }))
+    }
+
+    #[cfg(not(feature = "audio-fingerprint"))]
+    pub fn resolve(&self, _rec: &RadioRecord, found: Candidate) -> Match {
+        Match { path: found.path, duplicate: None }
+    }
+
+    /// Compare `found` with the recordings of recent calls. Call in call
+    /// order, so the first of two duplicates is the one kept.
+    #[cfg(feature = "audio-fingerprint")]
+    pub fn resolve(&self, rec: &RadioRecord, found: Candidate) -> Match {
+        let Candidate { path, fingerprint } = found;
+        let (Some(recent), Some(fp)) = (&self.dedup, fingerprint) else { return Match { path, duplicate: None } };
+        let at = rec.datetime.timestamp();
+        let mut recent = recent.lock();
+        // The same file found again (e.g. by a neighbouring row) isn't a duplicate.
+        if recent.entries.iter().any(|(_, p, _)| *p == path) {
+            return Match { path, duplicate: None };
+        }
+        let kept = recent
+            .entries
+            .iter()
+            .find(|(t, p, other)| {
+                if (at - t).abs() > DEDUP_WINDOW_S {
+                    return false;
+                }
+                let ber = fp.bit_error_rate(other);
+                log::trace!("audio_match: {} vs {}: bit error rate {:?}", path.display(), p.display(), ber);
+                ber.is_some_and(|ber| ber < DUPLICATE_BER)
+            })
+            .map(|(_, p, _)| p.clone());
+        if let Some(kept) = kept {
+            return Match { path: kept, duplicate: Some(path) };
+        }
+        if recent.entries.len() >= DEDUP_RECENT {
+            recent.entries.pop_front();
+        }
+        recent.entries.push_back((at, path.clone(), fp));
+        Match { path, duplicate: None }
+    }
+}
+
+/// Forward every record, with `audio_path` set where a recording was found.
+/// Lookups (which may walk a day folder and fingerprint a recording) run off
+/// the async runtime, up to `max_concurrent` at once; records leave in the
+/// order they came in.
+pub async fn match_audio_stream(
+    mut rx: Receiver<RadioRecord>,
+    tx: Sender<RadioRecord>,
+    matcher: Arc<AudioMatcher>,
+    max_concurrent: usize,
+) -> Result<Vec<PathBuf>, AppError> {
+    type Lookup = JoinHandle<Result<Option<Candidate>, AppError>>;
+    let (mut found, mut calls, mut duplicates) = (0usize, 0usize, 0usize);
+    // Records in arrival order, with the lookup still running for each.
+    let mut pending: VecDeque<(RadioRecord, Option<Lookup>)> = VecDeque::new();
+    let mut open = true;
+    'records: loop {
+        // Records that came with a recording need no lookup.
+        while let Some((_, None)) = pending.front() {
+            let (rec, _) = pending.pop_front().expect("front checked");
+            if tx.send(rec).await.is_err() {
+                warn!("audio_match: downstream closed");
+                break 'records;
+            }
+        }
+        if !open && pending.is_empty() {
+            break;
+        }
+        tokio::select! {
+            got = rx.recv(), if open && pending.len() < max_concurrent.max(1) => match got {
+                None => open = false,
+                Some(rec) => {
+                    calls += 1;
+                    let lookup = rec.audio_path.is_none().then(|| {
+                        let (m, r) = (Arc::clone(&matcher), rec.clone());
+                        tokio::task::spawn_blocking(move || m.locate(&r))
+                    });
+                    pending.push_back((rec, lookup));
+                }
+            },
+            done = async { pending.front_mut().and_then(|(_, l)| l.as_mut()).expect("front has a lookup").await },
+                if pending.front().is_some_and(|(_, l)| l.is_some()) =>
+            {
+                let (mut rec, _) = pending.pop_front().expect("front checked");
+                match done {
+                    Ok(Ok(Some(c))) => {
+                        let m = matcher.resolve(&rec, c);
+                        found += 1;
+                        if let Some(dup) = &m.duplicate {
+                            duplicates += 1;
+                            debug!("audio_match: rec#{}: {} duplicates {}", rec.record_number, dup.display(), m.path.display());
+                        }
+                        rec.audio_path = Some(winpath::simplified(&m.path));
+                    }
+                    Ok(Ok(None)) => {}
+                    Ok(Err(e)) => debug!("audio_match: rec#{}: {}", rec.record_number, e),
+                    Err(e) => return Err(AppError::IO(format!("audio match join error: {e}"))),
+                }
+                if tx.send(rec).await.is_err() {
+                    warn!("audio_match: downstream closed");
+                    break;
+                }
+            }
+        }
+    }
+    info!("Audio: recordings found for {} of {} calls", found, calls);
+    if duplicates > 0 {
+        info!("Audio: {} recordings duplicated another call's and were replaced by it", duplicates);
+    }
+    Ok(vec![])
+}
@@ -0,0 +1,178 @@
+// src/audio_prep.rs
+//
+// Optional clean-up of matched recordings before speech-to-text: downmix to
+// mono, resample to the 16 kHz whisper expects, trim leading/trailing
+// silence, and normalise level (simple AGC). Scanner audio is narrowband and
+// often quiet with long squelch tails, which costs both accuracy and time.
+// Only WAV input is processed; other formats are handed over unchanged.
+// `--stt-segments` reuses the WAV reading and writing without whisper, and
+// `--audio-dedup` the reading.
+
+#![cfg_attr(not(feature = "stt-whisper"), allow(dead_code))]
+
+use crate::errors::AppError;
+use log::debug;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub const TARGET_RATE: u32 = 16_000;
+/// Frames quieter than this (dBFS, RMS over 20 ms) count as silence.
+pub(crate) const SILENCE_DBFS: f32 = -45.0;
+/// Audio kept on either side of the detected speech.
+pub(crate) const PAD_MS: usize = 200;
+/// AGC aims for this RMS level, never amplifying by more than `MAX_GAIN`
+/// and never pushing the peak above `PEAK_LIMIT`.
+const TARGET_RMS_DBFS: f32 = -20.0;
+const MAX_GAIN: f32 = 20.0;
+const PEAK_LIMIT: f32 = 0.98;
+
+/// Temporary preprocessed file; deleted on drop.
+pub struct PreparedAudio {
+    pub path: PathBuf,
+    temporary: bool,
+}
+
+impl Drop for PreparedAudio {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+pub(crate) fn db_to_lin(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+pub(crate) fn read_mono(path: &Path) -> Result<(Vec<f32>, u32), AppError> {
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| AppError::IO(format!("read wav {}: {}", path.display(), e)))?;
+    let spec = reader.spec();
+    let channels = spec.channels.max(1) as usize;
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>(),
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader.samples::<i32>().map(|s| s.map(|v| v as f32 * scale)).collect::<Result<_, _>>()
+        }
+    }
+    .map_err(|e| AppError::IO(format!("decode wav {}: {}", path.display(), e)))?;
+    let mono = interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+    Ok((mono, spec.sample_rate))
+}
+
+/// Linear-interpolation resampler; adequate for voice-band audio going to STT.
+pub(crate) fn resample(input: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || input.is_empty() {
+        return input.to_vec();
+    }
+    let ratio = from as f64 / to as f64;
+    let out_len = ((input.len() as f64) / ratio).floor() as usize;
+    (0..out_len)
+        .map(|i| {
+            let pos = i as f64 * ratio;
+            let idx = pos as usize;
+            let frac = (pos - idx as f64) as f32;
+            let a = input[idx];
+            let b = *input.get(idx + 1).unwrap_or(&a);
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+/// Drop leading/trailing 20 ms frames below the silence threshold, keeping
+/// `PAD_MS` of context. All-silent clips are left untouched.
+fn trim_silence(samples: &[f32], rate: u32) -> &[f32] {
+    let frame = (rate as usize / 50).max(1);
+    let threshold = db_to_lin(SILENCE_DBFS);
+    let loud = |chunk: &[f32]| (chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32).sqrt() >= threshold;
+    let frames: Vec<&[f32]> = samples.chunks(frame).collect();
+    let (Some(first), Some(last)) = (frames.iter().position(|f| loud(f)), frames.iter().rposition(|f| loud(f)))
+    else {
+        return samples;
+    };
+    let pad = rate as usize * PAD_MS / 1000;
+    let start = (first * frame).saturating_sub(pad);
+    let end = ((last + 1) * frame + pad).min(samples.len());
+    &samples[start..end]
+}
+
+/// Single gain for the whole clip towards `TARGET_RMS_DBFS`, peak-limited.
+fn agc(samples: &mut [f32]) {
+    if samples.is_empty() {
+        return;
+    }
+    let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+    let peak = samples.iter().fold(0f32, |m, s| m.max(s.abs()));
+    if rms <= f32::EPSILON || peak <= f32::EPSILON {
+        return;
+    }
+    let gain = (db_to_lin(TARGET_RMS_DBFS) / rms).min(MAX_GAIN).min(PEAK_LIMIT / peak);
+    for s in samples.iter_mut() {
+        *s *= gain;
+    }
+}
+
+static SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Write 16 kHz mono samples to a temporary 16-bit WAV.
+pub(crate) fn write_temporary(samples: &[f32]) -> Result<PreparedAudio, AppError> {
+    let out = std::env::temp_dir().join(format!(
+        "callscribe-{}-{}.wav",
+        std::process::id(),
+        SEQ.fetch_add(1, Ordering::Relaxed)
+    ));
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: TARGET_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let prepared = PreparedAudio { path: out, temporary: true };
+    let mut w = hound::WavWriter::create(&prepared.path, spec)
+        .map_err(|e| AppError::IO(format!("write {}: {}", prepared.path.display(), e)))?;
+    for s in samples {
+        w.write_sample((s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+            .map_err(|e| AppError::IO(format!("write {}: {}", prepared.path.display(), e)))?;
+    }
+    w.finalize()
+        .map_err(|e| AppError::IO(format!("write {}: {}", prepared.path.display(), e)))?;
+    Ok(prepared)
+}
+
+/// A temporary WAV of one second of silence, for the startup check.
+pub fn silence() -> Result<PreparedAudio, AppError> {
+    write_temporary(&vec![0.0; TARGET_RATE as usize])
+}
+
+/// Preprocess `audio` into a temporary 16 kHz mono 16-bit WAV. Non-WAV files
+/// are returned as-is.
+pub fn prepare(audio: &Path) -> Result<PreparedAudio, AppError> {
+    let is_wav = audio
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("wav"));
+    if !is_wav {
+        debug!("audio_prep: {} is not WAV; passing through", audio.display());
+        return Ok(PreparedAudio { path: audio.to_path_buf(), temporary: false });
+    }
+
+    let (mono, rate) = read_mono(audio)?;
+    let resampled = resample(&mono, rate, TARGET_RATE);
+    let mut speech = trim_silence(&resampled, TARGET_RATE).to_vec();
+    agc(&mut speech);
+
+    let prepared = write_temporary(&speech)?;
+    debug!(
+        "audio_prep: {} {} Hz -> {} Hz mono, {:.2}s -> {:.2}s",
+        audio.display(),
+        rate,
+        TARGET_RATE,
+        mono.len() as f32 / rate.max(1) as f32,
+        speech.len() as f32 / TARGET_RATE as f32
+    );
+    Ok(prepared)
+}
@@ -0,0 +1,313 @@
+// src/backfill.rs
+//
+// Deferred transcription: rows written without transcripts (e.g. with
+// `--defer-transcription`, or by a run without a transcriber) are looked up
+// again with a transcriber and updated, so a slow STT backend never holds up
+// the call log. SQLite databases are updated row by row; CSV files are
+// rewritten with every other column left as it was.
+
+use crate::errors::AppError;
+use crate::model::{CallType, RadioRecord, SlotData};
+#[cfg(feature = "sink-sqlite")]
+use crate::sqlite_sink;
+use crate::transcriber::Transcriber;
+use log::{debug, info, warn};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::io::SeekFrom;
+use tokio::io::{AsyncBufReadExt, AsyncSeekExt, AsyncWriteExt, BufWriter};
+#[cfg(feature = "sink-sqlite")]
+use tokio::task::JoinSet;
+use tokio::task::JoinHandle;
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BackfillSummary {
+    /// Rows without a transcript when the pass started.
+    pub pending: usize,
+    pub transcribed: usize,
+    /// Transcriber found nothing for the call.
+    pub missing: usize,
+    pub errors: usize,
+}
+
+/// Transcribe every untranscribed row of the SQLite database at `db`,
+/// `concurrency` calls at a time. Each row is committed as soon as its
+/// transcript is ready, so an interrupted pass can simply be re-run.
+#[cfg(feature = "sink-sqlite")]
+pub async fn backfill_sqlite(
+    db: &Path,
+    transcriber: Arc<dyn Transcriber + Send + Sync>,
+    record_dir: PathBuf,
+    concurrency: usize,
+) -> Result<BackfillSummary, AppError> {
+    if !db.exists() {
+        return Err(AppError::IO(format!("no such database '{}'", db.display())));
+    }
+    let pool = sqlite_sink::open_db(db, false).await?;
+    let rows = sqlite_sink::untranscribed(&pool).await?;
+    let mut summary = BackfillSummary { pending: rows.len(), ..Default::default() };
+    info!("{} rows without transcripts in {}", rows.len(), db.display());
+
+    let mut rows = rows.into_iter();
+    let mut running = JoinSet::new();
+    loop {
+        while running.len() < concurrency.max(1) {
+            let Some((id, rec)) = rows.next() else { break };
+            let (t, dir) = (Arc::clone(&transcriber), record_dir.clone());
+            running.spawn_blocking(move || (id, rec.record_number, t.transcribe(&rec, &dir)));
+        }
+        let Some(done) = running.join_next().await else { break };
+        let (id, recno, res) = match done {
+            Ok(done) => done,
+            Err(e) if e.is_panic() => {
+                warn!("backfill: transcriber panicked; row left for the next pass");
+                summary.errors += 1;
+                continue;
+            }
+            Err(e) => return Err(AppError::IO(format!("transcriber join error: {e}"))),
+        };
+        match res {
+            Ok(Some(text)) => {
+                sqlite_sink::set_transcript(&pool, id, &text).await?;
+                summary.transcribed += 1;
+            }
+            Ok(None) | Err(None) => summary.missing += 1,
+            Err(Some(e)) => {
+                debug!("backfill: rec#{} transcription error: {}", recno, e);
+                summary.errors += 1;
+            }
+        }
+        let finished = summary.transcribed + summary.missing + summary.errors;
+        if finished.is_multiple_of(100) {
+            info!("  {}/{} rows processed", finished, summary.pending);
+        }
+    }
+    Ok(summary)
+}
+
+/// Positions of the CSV sink's columns that a record is rebuilt from.
+struct Columns {
+    record_number: Option<usize>,
+    datetime: usize,
+    duration: Option<usize>,
+    frequency: Option<usize>,
+    radio_type: Option<usize>,
+    dcc: Option<usize>,
+    nac: Option<usize>,
+    color_code: Option<usize>,
+    slot1_tg: Option<usize>,
+    slot1_rid: Option<usize>,
+    slot1_text: usize,
+    slot2_tg: Option<usize>,
+    slot2_rid: Option<usize>,
+    slot2_text: Option<usize>,
+    audio_path: Option<usize>,
+    call_type: Option<usize>,
+    target_rid: Option<usize>,
+}
+
+impl Columns {
+    fn find(header: &csv_async::StringRecord, path: &Path) -> Result<Self, AppError> {
+        let col = |name: &str| header.iter().position(|h| h.trim_start_matches('\u{feff}') == name);
+        let need = |name: &str| {
+            col(name).ok_or_else(|| {
+                AppError::Parse(format!("{}: no '{}' column; is this a CSV written by callscribe?", path.display(), name))
+            })
+        };
+        Ok(Self {
+            record_number: col("record_number"),
+            datetime: need("datetime")?,
+            // `--duration-format hms` keeps the seconds in `duration_s`.
+            duration: col("duration_s").or_else(|| col("duration")),
+            frequency: col("frequency"),
+            radio_type: col("radio_type"),
+            dcc: col("dcc"),
+            nac: col("nac"),
+            color_code: col("color_code"),
+            slot1_tg: col("slot1_tg"),
+            slot1_rid: col("slot1_rid"),
+            slot1_text: need("slot1_text")?,
+            slot2_tg: col("slot2_tg"),
+            slot2_rid: col("slot2_rid"),
+            slot2_text: col("slot2_text"),
+            audio_path: col("audio_path"),
+            call_type: col("call_type"),
+            target_rid: col("target_rid"),
+        })
+    }
+
+    fn get<'a>(&self, row: &'a csv_async::StringRecord, i: Option<usize>) -> Option<&'a str> {
+        i.and_then(|i| row.get(i)).map(str::trim).filter(|v| !v.is_empty())
+    }
+
+    fn untranscribed(&self, row: &csv_async::StringRecord) -> bool {
+        self.get(row, Some(self.slot1_text)).is_none() && self.get(row, self.slot2_text).is_none()
+    }
+
+    /// The call a row describes, enough for a transcriber to find it. CSV
+    /// times carry no offset; they are taken as they are written, which is
+    /// what Record file names are matched against.
+    fn record(&self, row: &csv_async::StringRecord) -> Option<RadioRecord> {
+        let s = |i| self.get(row, i).map(str::to_string);
+        let naive = chrono::NaiveDateTime::parse_from_str(self.get(row, Some(self.datetime))?, "%Y-%m-%d %H:%M:%S").ok()?;
+        let datetime = naive.and_utc().fixed_offset();
+        let number = self.get(row, self.record_number).and_then(|n| n.parse().ok()).unwrap_or(0);
+        let mut rec = RadioRecord {
+            duration: self.get(row, self.duration).and_then(|d| d.parse().ok()).unwrap_or(1),
+            frequency: s(self.frequency),
+            radio_type: s(self.radio_type),
+            slot1: SlotData { tg: s(self.slot1_tg), rid: s(self.slot1_rid), text: None },
+            slot2: SlotData { tg: s(self.slot2_tg), rid: s(self.slot2_rid), text: None },
+            audio_path: s(self.audio_path).map(PathBuf::from),
+            call_type: self.get(row, self.call_type).and_then(CallType::from_name).unwrap_or_default(),
+            target_rid: s(self.target_rid),
+            ..RadioRecord::new(number, datetime)
+        };
+        if self.nac.is_some() || self.color_code.is_some() {
+            rec.nac = s(self.nac);
+            rec.dcc = s(self.color_code);
+        } else {
+            rec.set_nac_or_dcc(s(self.dcc));
+        }
+        Some(rec)
+    }
+}
+
+fn ioe(path: &Path) -> impl Fn(std::io::Error) -> AppError + '_ {
+    move |e| AppError::IO(format!("{}: {}", path.display(), e))
+}
+
+fn csve(path: &Path) -> impl Fn(csv_async::Error) -> AppError + '_ {
+    move |e| AppError::IO(format!("{}: {}", path.display(), e))
+}
+
+/// Rows read ahead per worker while the front row's lookup runs.
+const READ_AHEAD_PER_WORKER: usize = 64;
+
+type Lookup = JoinHandle<(u64, Result<Option<String>, Option<AppError>>)>;
+
+/// Transcribe the rows of `input` (a CSV written by the CSV sink) that have
+/// no transcript, `concurrency` calls at a time, and write the whole file to
+/// `out` in the same row order. Without `out` the input is replaced once the
+/// new file is complete; re-running retries rows still without text.
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Whether `path` starts with a BOM (`--excel-compat`, which also means
+/// CRLFs), and the `#` lines after it (`--preamble`) exactly as written, so
+/// the rewritten file keeps both.
+async fn read_preamble(path: &Path) -> Result<(bool, Vec<u8>), AppError> {
+    let mut r = tokio::io::BufReader::new(tokio::fs::File::open(path).await.map_err(ioe(path))?);
+    let excel = r.fill_buf().await.map_err(ioe(path))?.starts_with(BOM);
+    if excel {
+        r.consume(BOM.len());
+    }
+    let mut preamble = Vec::new();
+    while r.fill_buf().await.map_err(ioe(path))?.first() == Some(&b'#') {
+        r.read_until(b'\n', &mut preamble).await.map_err(ioe(path))?;
+    }
+    Ok((excel, preamble))
+}
+
+pub async fn backfill_csv(
+    input: &Path,
+    out: Option<&Path>,
+    transcriber: Arc<dyn Transcriber + Send + Sync>,
+    record_dir: PathBuf,
+    concurrency: usize,
+) -> Result<BackfillSummary, AppError> {
+    let target = out.map_or_else(|| input.with_extension("csv.tmp"), Path::to_path_buf);
+
+    let (excel, preamble) = read_preamble(input).await?;
+    let mut file = tokio::fs::File::open(input).await.map_err(ioe(input))?;
+    let start = if excel { BOM.len() } else { 0 } + preamble.len();
+    file.seek(SeekFrom::Start(start as u64)).await.map_err(ioe(input))?;
+    let mut rdr = csv_async::AsyncReaderBuilder::new().flexible(true).create_reader(file.compat());
+    let header = rdr.headers().await.map_err(csve(input))?.clone();
+    let cols = Columns::find(&header, input)?;
+
+    let mut w = BufWriter::new(tokio::fs::File::create(&target).await.map_err(ioe(&target))?);
+    if excel {
+        w.write_all(BOM).await.map_err(ioe(&target))?;
+    }
+    w.write_all(&preamble).await.map_err(ioe(&target))?;
+    let mut builder = csv_async::AsyncWriterBuilder::new();
+    builder.flexible(true);
+    if excel {
+        builder.terminator(csv_async::Terminator::CRLF);
+    }
+    let mut wtr = builder.create_writer(w.compat_write());
+    let header_out: Vec<&str> = header.iter().map(|h| h.trim_start_matches('\u{feff}')).collect();
+    wtr.write_record(&header_out).await.map_err(csve(&target))?;
+
+    let mut summary = BackfillSummary::default();
+    // Rows wait here, in file order, for their lookups.
+    let mut queue: VecDeque<(csv_async::StringRecord, Option<Lookup>)> = VecDeque::new();
+    let mut row = csv_async::StringRecord::new();
+    let mut more = true;
+    let workers = concurrency.max(1);
+    loop {
+        // Read ahead until every worker is busy (or enough rows are waiting).
+        while more && queue.len() < workers * READ_AHEAD_PER_WORKER {
+            if queue.iter().filter(|(_, l)| l.is_some()).count() >= workers {
+                break;
+            }
+            if !rdr.read_record(&mut row).await.map_err(csve(input))? {
+                more = false;
+                break;
+            }
+            let lookup = if !cols.untranscribed(&row) {
+                None
+            } else if let Some(rec) = cols.record(&row) {
+                summary.pending += 1;
+                let (t, dir) = (Arc::clone(&transcriber), record_dir.clone());
+                Some(tokio::task::spawn_blocking(move || (rec.record_number as u64, t.transcribe(&rec, &dir))))
+            } else {
+                debug!("backfill: {}: a row has no usable datetime; left as is", input.display());
+                summary.pending += 1;
+                summary.errors += 1;
+                None
+            };
+            queue.push_back((row.clone(), lookup));
+        }
+        let Some((mut out_row, lookup)) = queue.pop_front() else { break };
+        if let Some(lookup) = lookup {
+            match lookup.await {
+                Ok((_, Ok(Some(text)))) => {
+                    let mut fields: Vec<String> = out_row.iter().map(str::to_string).collect();
+                    if fields.len() <= cols.slot1_text {
+                        fields.resize(cols.slot1_text + 1, String::new());
+                    }
+                    fields[cols.slot1_text] = text;
+                    out_row = csv_async::StringRecord::from(fields);
+                    summary.transcribed += 1;
+                }
+                Ok((_, Ok(None) | Err(None))) => summary.missing += 1,
+                Ok((recno, Err(Some(e)))) => {
+                    debug!("backfill: rec#{} transcription error: {}", recno, e);
+                    summary.errors += 1;
+                }
+                Err(e) if e.is_panic() => {
+                    warn!("backfill: transcriber panicked; row left for the next pass");
+                    summary.errors += 1;
+                }
+                Err(e) => return Err(AppError::IO(format!("transcriber join error: {e}"))),
+            }
+            let finished = summary.transcribed + summary.missing + summary.errors;
+            if finished.is_multiple_of(100) {
+                info!("  {} rows processed", finished);
+            }
+        }
+        wtr.write_record(&out_row).await.map_err(csve(&target))?;
+    }
+    wtr.flush().await.map_err(ioe(&target))?;
+    let mut w = wtr.into_inner().await.map_err(|e| AppError::IO(format!("{}: {}", target.display(), e)))?.into_inner();
+    w.flush().await.map_err(ioe(&target))?;
+    drop(w);
+    if out.is_none() {
+        tokio::fs::rename(&target, input).await.map_err(ioe(input))?;
+    }
+    info!("Wrote {}", out.unwrap_or(input).display());
+    Ok(summary)
+}
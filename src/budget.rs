@@ -0,0 +1,112 @@
+// src/budget.rs
+//
+// Global cap on records in flight across all pipelines (`--max-memory`).
+// A record takes a permit when the filter stage receives it and gives it
+// back when it is dropped by the filter, merged away by RLE, or handed on by
+// the transcription stage; the channels outside that span are sized from the
+// same budget, so total buffering stays bounded however many inputs run.
+
+use crate::errors::AppError;
+use crate::rotation::parse_size;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::Semaphore;
+
+/// Rough heap footprint of one in-flight record including a short transcript.
+pub const RECORD_BYTES_ESTIMATE: u64 = 1024;
+/// Channel depth without a budget.
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 1024;
+
+#[derive(Debug)]
+pub struct MemoryBudget {
+    sem: Semaphore,
+    permits: usize,
+}
+
+impl MemoryBudget {
+    /// Budget from a size such as `256MB`.
+    pub fn parse(s: &str) -> Result<Self, AppError> {
+        let bytes = parse_size(s)
+            .ok_or_else(|| AppError::Parse(format!("invalid --max-memory '{}': expected a size like 256MB", s)))?;
+        let permits = (bytes / RECORD_BYTES_ESTIMATE).max(1) as usize;
+        Ok(Self { sem: Semaphore::new(permits), permits })
+    }
+
+    pub fn permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Wait for room for one more record.
+    pub async fn acquire(&self) {
+        // The semaphore is never closed.
+        if let Ok(p) = self.sem.acquire().await {
+            p.forget();
+        }
+    }
+
+    /// Return `n` records' worth of room.
+    /// Take a permit if one is free, without waiting.
+    fn try_take(&self) -> bool {
+        self.sem.try_acquire().map(|p| p.forget()).is_ok()
+    }
+
+    pub fn release(&self, n: usize) {
+        self.sem.add_permits(n);
+    }
+
+    /// Depth for each unbudgeted channel so that `pipelines` pipelines with
+    /// `channels` such channels each together hold at most the budget again.
+    pub fn channel_capacity(&self, pipelines: usize, channels: usize) -> usize {
+        (self.permits / (pipelines.max(1) * channels.max(1))).clamp(1, DEFAULT_CHANNEL_CAPACITY)
+    }
+}
+
+/// One pipeline's share of the global budget. Permits still held when the
+/// last clone is dropped (e.g. records discarded when a pipeline fails) go
+/// back to the pool, so one failing input can't starve the others.
+#[derive(Debug)]
+pub struct PipelineBudget {
+    global: Arc<MemoryBudget>,
+    held: AtomicUsize,
+    /// Records charged while no permit was free; paid off by later releases.
+    debt: AtomicUsize,
+}
+
+impl PipelineBudget {
+    pub fn new(global: Arc<MemoryBudget>) -> Self {
+        Self { global, held: AtomicUsize::new(0), debt: AtomicUsize::new(0) }
+    }
+
+    pub async fn acquire(&self) {
+        self.global.acquire().await;
+        self.held.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a record a stage created itself (RLE splitting a two-slot
+    /// record) without waiting, which could deadlock mid-pipeline: it takes a
+    /// free permit if there is one and is otherwise owed back.
+    pub fn charge(&self) {
+        self.held.fetch_add(1, Ordering::Relaxed);
+        if !self.global.try_take() {
+            self.debt.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn release(&self, n: usize) {
+        self.held.fetch_sub(n, Ordering::Relaxed);
+        let owed = self
+            .debt
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |d| Some(d.saturating_sub(n)))
+            .unwrap_or(0);
+        self.global.release(n - owed.min(n));
+    }
+}
+
+impl Drop for PipelineBudget {
+    fn drop(&mut self) {
+        let n = self.held.get_mut().saturating_sub(*self.debt.get_mut());
+        if n > 0 {
+            self.global.release(n);
+        }
+    }
+}
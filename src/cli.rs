@@ -1,47 +1,902 @@
-use argparse::{ArgumentParser, Store, StoreOption, Collect};
+use argparse::{ArgumentParser, Store, StoreOption, StoreTrue, Collect, List, Print};
+use dsd_event_parser::{features, sdrtrunk, serve, unit_registry};
 use std::path::PathBuf;
 
 pub struct CliArgs {
     pub input_files: Vec<PathBuf>,
+    pub inputs: Vec<String>,
+    pub date_from_filename: bool,
     pub freqs: Vec<String>,
     pub rtypes: Vec<String>,
     pub rids: Vec<String>,
     pub tgs: Vec<String>,
     pub nacs: Vec<String>,
+    pub dccs: Vec<String>,
+    pub watch_rids: Vec<String>,
+    pub watch_tgs: Vec<String>,
+    pub notify_exec: Option<String>,
+    pub alert_severity: Option<u32>,
+    pub max_errors: Option<u32>,
+    pub min_quality: Option<u32>,
+    pub extras: bool,
+    pub fill_forward: Option<String>,
+    pub rle_per_slot: bool,
+    pub renumber: String,
+    pub replay_speed: Option<String>,
+    pub rle_min_blocks: Option<u32>,
+    pub run_details: bool,
+    pub merge: bool,
+    pub sort: bool,
+    pub sort_memory: Option<String>,
+    pub sort_tmp: Option<PathBuf>,
     pub tz: Option<String>,
     pub record_dir: Option<PathBuf>,
-    pub transcriber: String,
     pub log_level: String,
-    pub out: PathBuf,
+    pub out: Option<PathBuf>,
+    pub manifest: Option<PathBuf>,
+    pub stats_json: Option<PathBuf>,
+    pub encryption_report: Option<PathBuf>,
+    pub skip_processed: bool,
+    pub processed_state: Option<PathBuf>,
+    pub unit_registry: Option<PathBuf>,
+    pub follow: bool,
+    pub lag_warn: Option<String>,
+    pub lag_warn_calls: Option<u64>,
+    pub lag_shed_stt: bool,
+    pub listen: Option<String>,
+    pub device: Option<PathBuf>,
+    pub listen_format: String,
+    pub rotate: Option<String>,
+    pub rotate_keep: Option<usize>,
+    pub csv_quote: Option<String>,
+    pub csv_escape: Option<String>,
+    pub csv_batch: usize,
+    pub csv_flush_ms: Option<u64>,
+    pub excel_compat: bool,
+    pub preamble: bool,
+    pub fsync: bool,
+    pub duration_format: String,
+    pub progress: bool,
+    pub lenient: bool,
+    pub freq_tolerance: Option<String>,
+    pub sink: String,
+    pub pg_url: Option<String>,
+    pub pg_table: String,
+    pub pg_batch: usize,
+    pub retention: RetentionArgs,
+    pub es_url: Option<String>,
+    pub es_index: String,
+    pub es_api_key: Option<String>,
+    pub es_batch: usize,
+    pub es_retries: u32,
+    pub syslog_addr: Option<String>,
+    pub syslog_facility: String,
+    pub template: Option<PathBuf>,
+    pub keyword_index: bool,
+    pub affiliations: bool,
+    pub match_audio: bool,
+    pub audio_dedup: bool,
+    /// Seconds a recording's name may be off the call's start (`--audio-window`).
+    pub audio_window: u32,
+    pub playlist: Option<String>,
+    pub utilization: Option<String>,
+    pub stt: SttArgs,
+    pub defer_transcription: bool,
+    pub stt_priority: bool,
+    pub stt_priority_tgs: Vec<String>,
+    pub config: Option<PathBuf>,
+    pub profile: Option<String>,
+    pub timing: bool,
+    pub coverage_report: bool,
+    pub daemon: bool,
+    pub max_memory: Option<String>,
+    /// Exit 0 even when inputs failed (`--exit-zero`).
+    pub exit_zero: bool,
+    /// `CALLSCRIBE_*` values of the options a `--profile` also sets.
+    pub env: ProfileEnv,
+}
+
+/// Environment values of the options a `[profile.NAME]` also sets, kept
+/// apart from the command line so the profile wins over them.
+#[derive(Default)]
+pub struct ProfileEnv {
+    pub tz: Option<String>,
+    pub record_dir: Option<PathBuf>,
+    pub sink: String,
+    pub out: Option<PathBuf>,
+    pub rotate: Option<String>,
+    pub max_errors: Option<u32>,
+    pub min_quality: Option<u32>,
+}
+
+/// Transcriber selection, shared by the conversion run and `transcribe`.
+pub struct SttArgs {
+    pub transcriber: String,
+    pub model: String,
+    pub device: String,
+    pub threads: Option<usize>,
+    pub whisper_bin: PathBuf,
+    pub models_dir: Option<PathBuf>,
+    pub preprocess: bool,
+    pub transcript_once: bool,
+    /// What a failed startup check of the backend does: fail|warn|off.
+    pub check: String,
+    /// Split long recordings at pauses and transcribe each part.
+    pub segments: bool,
+    pub segments_out: Option<PathBuf>,
+    /// Shortest pause split at, in ms: longer than the pauses within a
+    /// sentence, shorter than the turnaround between two transmissions.
+    pub segment_gap: u32,
+    /// Recordings shorter than this (seconds) are rarely more than one
+    /// transmission and go through whole.
+    pub segment_min: u32,
+}
+
+impl Default for SttArgs {
+    fn default() -> Self {
+        Self {
+            transcriber: "none".into(),
+            model: "base.en".into(),
+            device: "cpu".into(),
+            threads: None,
+            whisper_bin: PathBuf::from("whisper-cli"),
+            models_dir: None,
+            preprocess: false,
+            transcript_once: false,
+            check: "fail".into(),
+            segments: false,
+            segments_out: None,
+            segment_gap: 700,
+            segment_min: 20,
+        }
+    }
+}
+
+fn add_stt_options<'a>(ap: &mut ArgumentParser<'a>, stt: &'a mut SttArgs) {
+    ap.refer(&mut stt.transcriber)
+        .add_option(&["--transcriber"], Store, "Transcriber: none|text|whisper, or a [transcriber.NAME] from --config (whisper needs the stt-whisper feature)");
+    ap.refer(&mut stt.model)
+        .add_option(&["--stt-model"], Store, "STT model name from `models list`, or a model file path (default: base.en)");
+    ap.refer(&mut stt.device)
+        .add_option(&["--stt-device"], Store, "STT device: cpu|cuda|cuda:N|metal (default: cpu)");
+    ap.refer(&mut stt.threads)
+        .add_option(&["--stt-threads"], StoreOption, "STT threads per call (default: backend's choice)");
+    ap.refer(&mut stt.whisper_bin)
+        .add_option(&["--whisper-bin"], Store, "whisper.cpp CLI executable (default: whisper-cli)");
+    ap.refer(&mut stt.models_dir)
+        .add_option(&["--models-dir"], StoreOption, "Model cache directory (default: $CALLSCRIBE_MODELS_DIR or the per-user data dir)");
+    ap.refer(&mut stt.preprocess)
+        .add_option(&["--stt-preprocess"], StoreTrue, "Resample WAV audio to 16 kHz mono, trim silence and normalise level before STT");
+    ap.refer(&mut stt.transcript_once)
+        .add_option(&["--transcript-once"], StoreTrue, "text: attach each transcript file to the first matching call only; later matches reference its path");
+    ap.refer(&mut stt.check)
+        .add_option(&["--stt-check"], Store, "Startup check of the transcriber (record dir, model, program, endpoint) on failure: fail (default), warn (run without transcription) or off");
+    ap.refer(&mut stt.segments)
+        .add_option(&["--stt-segments"], StoreTrue, "Split long WAV recordings at pauses, transcribe each part, and store the parts as JSON with offsets and a speaker guess (needs the stt-segments feature)");
+    ap.refer(&mut stt.segments_out)
+        .add_option(&["--stt-segments-out"], StoreOption, "With --stt-segments: append the parts to this JSON-lines file and keep plain text in the transcript");
+    ap.refer(&mut stt.segment_gap)
+        .add_option(&["--stt-segment-gap"], Store, "With --stt-segments: shortest pause to split at, in ms (default: 700)");
+    ap.refer(&mut stt.segment_min)
+        .add_option(&["--stt-segment-min"], Store, "With --stt-segments: recordings shorter than this many seconds are transcribed whole (default: 20)");
 }
 
 impl Default for CliArgs {
     fn default() -> Self {
         Self {
             input_files: vec![],
+            inputs: vec![],
+            date_from_filename: false,
             freqs: vec![],
             rtypes: vec![],
             rids: vec![],
             tgs: vec![],
             nacs: vec![],
+            dccs: vec![],
+            watch_rids: vec![],
+            watch_tgs: vec![],
+            notify_exec: None,
+            alert_severity: None,
+            max_errors: None,
+            min_quality: None,
+            extras: false,
+            fill_forward: None,
+            rle_per_slot: false,
+            renumber: "source".into(),
+            replay_speed: None,
+            rle_min_blocks: None,
+            run_details: false,
+            merge: false,
+            sort: false,
+            sort_memory: None,
+            sort_tmp: None,
             tz: None,
             record_dir: None,
-            transcriber: "none".into(),
             log_level: "essential".into(),
-            out: std::path::PathBuf::from("out.csv"),
+            out: None,
+            manifest: None,
+            stats_json: None,
+            encryption_report: None,
+            skip_processed: false,
+            processed_state: None,
+            unit_registry: None,
+            follow: false,
+            lag_warn: None,
+            lag_warn_calls: None,
+            lag_shed_stt: false,
+            listen: None,
+            device: None,
+            listen_format: "event".into(),
+            rotate: None,
+            rotate_keep: None,
+            csv_quote: None,
+            csv_escape: None,
+            csv_batch: 1000,
+            csv_flush_ms: None,
+            excel_compat: false,
+            preamble: false,
+            fsync: false,
+            duration_format: "seconds".into(),
+            progress: false,
+            lenient: false,
+            freq_tolerance: None,
+            // Empty until a `--profile` has had its say; then csv.
+            sink: String::new(),
+            pg_url: None,
+            pg_table: "calls".into(),
+            pg_batch: 500,
+            retention: RetentionArgs::default(),
+            es_url: None,
+            es_index: "callscribe".into(),
+            es_api_key: None,
+            es_batch: 500,
+            es_retries: 5,
+            syslog_addr: None,
+            syslog_facility: "local0".into(),
+            template: None,
+            keyword_index: false,
+            affiliations: false,
+            match_audio: false,
+            audio_dedup: false,
+            audio_window: 2,
+            playlist: None,
+            utilization: None,
+            stt: SttArgs::default(),
+            defer_transcription: false,
+            stt_priority: false,
+            stt_priority_tgs: vec![],
+            config: None,
+            profile: None,
+            timing: false,
+            coverage_report: false,
+            daemon: false,
+            max_memory: None,
+            exit_zero: false,
+            env: ProfileEnv::default(),
+        }
+    }
+}
+
+/// What to do, chosen by the first argument: a known subcommand name, or
+/// anything else for the default conversion run.
+pub enum Command {
+    Run(Box<CliArgs>),
+    Search(SearchArgs),
+    Anomalies(AnomaliesArgs),
+    Serve(ServeArgs),
+    Models(ModelsArgs),
+    Transcribe(TranscribeArgs),
+    Selftest(SelftestArgs),
+    Golden(GoldenArgs),
+    Formats,
+    Explain(ExplainArgs),
+    Probe(ProbeArgs),
+    Init(InitArgs),
+    Registry(RegistryArgs),
+    Maintain(MaintainArgs),
+    /// `gui`, or no arguments at all in a build with the `gui` feature.
+    Gui,
+    /// `daemon --print-systemd-unit`: the remaining run arguments.
+    SystemdUnit(Vec<String>),
+}
+
+pub fn parse_command() -> Command {
+    let argv: Vec<String> = std::env::args().collect();
+    match argv.get(1).map(String::as_str) {
+        Some("search") => Command::Search(parse_search(subcommand_argv(&argv))),
+        Some("anomalies") => Command::Anomalies(parse_anomalies(subcommand_argv(&argv))),
+        Some("serve") => Command::Serve(parse_serve(subcommand_argv(&argv))),
+        Some("models") => Command::Models(parse_models(subcommand_argv(&argv))),
+        Some("transcribe") => Command::Transcribe(parse_transcribe(subcommand_argv(&argv))),
+        Some("selftest") => Command::Selftest(parse_selftest(subcommand_argv(&argv))),
+        Some("golden") => Command::Golden(parse_golden(subcommand_argv(&argv))),
+        Some("formats") => Command::Formats,
+        Some("explain") => Command::Explain(parse_explain(subcommand_argv(&argv))),
+        Some("probe") => Command::Probe(parse_probe(subcommand_argv(&argv))),
+        Some("init") => Command::Init(parse_init(subcommand_argv(&argv))),
+        Some("registry") => Command::Registry(parse_registry(subcommand_argv(&argv))),
+        Some("maintain") => Command::Maintain(parse_maintain(subcommand_argv(&argv))),
+        Some("gui") => Command::Gui,
+        None if cfg!(feature = "gui") => Command::Gui,
+        Some("daemon") => {
+            let mut rest: Vec<String> = argv[2..].to_vec();
+            if let Some(i) = rest.iter().position(|a| a == "--print-systemd-unit") {
+                rest.remove(i);
+                return Command::SystemdUnit(rest);
+            }
+            // `daemon ARGS...` is `--daemon ARGS...`.
+            let mut v = vec![argv[0].clone(), "--daemon".into()];
+            v.extend(rest);
+            Command::Run(Box::new(parse_cli(v)))
         }
+        _ => Command::Run(Box::new(parse_cli(argv))),
+    }
+}
+
+/// `argv` with the subcommand name folded into the program name, so usage
+/// lines read `callscribe search ...`.
+fn subcommand_argv(argv: &[String]) -> Vec<String> {
+    let mut v = vec![format!("{} {}", argv[0], argv[1])];
+    v.extend_from_slice(&argv[2..]);
+    v
+}
+
+/// STT options shared by `run` and `transcribe` that may come from the environment.
+const STT_ENV: &[&str] =
+    &["--transcriber", "--stt-model", "--stt-device", "--stt-threads", "--whisper-bin", "--models-dir", "--stt-check",
+    "--stt-segments-out", "--stt-segment-gap", "--stt-segment-min"];
+
+/// Options of a normal run that may come from the environment.
+const RUN_ENV: &[&str] = &[
+    "--config", "--pg-url", "--pg-table", "--pg-batch", "--es-url",
+    "--es-index", "--es-api-key", "--es-batch", "--es-retries", "--syslog-addr", "--syslog-facility", "--log",
+    "--manifest", "--stats-json", "--encryption-report", "--max-memory", "--rotate-keep", "--csv-quote", "--csv-escape",
+    "--csv-batch", "--csv-flush-ms", "--duration-format", "--freq-tolerance",
+    "--fill-forward", "--rle-min-blocks", "--renumber", "--notify-exec", "--alert-severity", "--sort-memory", "--sort-tmp", "--playlist", "--utilization",
+    "--processed-state", "--audio-window", "--template", "--replay-speed", "--unit-registry",
+    "--lag-warn", "--lag-warn-calls", "--profile", "--retain-days",
+];
+/// Options of a normal run that a `[profile.NAME]` may also set; their
+/// environment values go to `CliArgs::env`.
+const PROFILE_ENV: &[&str] = &["--record-dir", "--tz", "--sink", "--out", "--rotate", "--max-errors", "--min-quality"];
+const RUN_ENV_SWITCHES: &[&str] = &[
+    "--daemon", "--follow", "--date-from-filename", "--extras", "--rle-per-slot", "--run-details", "--merge", "--sort",
+    "--excel-compat", "--preamble", "--lenient", "--keyword-index", "--affiliations", "--match-audio", "--audio-dedup",
+    "--timing", "--coverage-report", "--progress", "--stt-preprocess", "--transcript-once", "--defer-transcription",
+    "--stt-priority", "--skip-processed", "--fsync", "--lag-shed-stt", "--retain-alerts", "--retain-archive",
+    "--stt-segments", "--exit-zero",
+];
+
+/// `CALLSCRIBE_RECORD_DIR` for `--record-dir`.
+fn env_name(flag: &str) -> String {
+    format!("CALLSCRIBE_{}", flag.trim_start_matches('-').replace('-', "_").to_ascii_uppercase())
+}
+
+/// Put `CALLSCRIBE_*` environment settings for `values` (options taking a
+/// value) and `switches` (on/off flags) ahead of the real arguments, so the
+/// command line overrides the environment. Options a `[profile.NAME]` also
+/// sets are parsed on their own instead (see `parse_cli`), so the profile
+/// can sit between the two.
+fn with_env(argv: Vec<String>, values: &[&str], switches: &[&str]) -> Vec<String> {
+    let mut out = vec![argv[0].clone()];
+    for flag in values {
+        if let Ok(v) = std::env::var(env_name(flag))
+            && !v.is_empty()
+        {
+            out.push(format!("{}={}", flag, v));
+        }
+    }
+    for flag in switches {
+        let name = env_name(flag);
+        match std::env::var(&name).map(|v| v.to_ascii_lowercase()).as_deref() {
+            Ok("1" | "true" | "yes" | "on") => out.push(flag.to_string()),
+            Ok("" | "0" | "false" | "no" | "off") | Err(_) => {}
+            Ok(other) => {
+                eprintln!("{}: expected 1/true/yes/on or 0/false/no/off, got '{}'", name, other);
+                std::process::exit(2);
+            }
+        }
+    }
+    out.extend_from_slice(&argv[1..]);
+    out
+}
+
+fn parse_or_exit(ap: &ArgumentParser, argv: Vec<String>) {
+    if let Err(code) = ap.parse(argv, &mut std::io::stdout(), &mut std::io::stderr()) {
+        std::process::exit(code);
+    }
+}
+
+pub struct SearchArgs {
+    pub query: String,
+    pub db: PathBuf,
+    pub limit: usize,
+    pub log_level: String,
+}
+
+fn parse_search(argv: Vec<String>) -> SearchArgs {
+    let mut args = SearchArgs {
+        query: String::new(),
+        db: PathBuf::from("calls.db"),
+        limit: 100,
+        log_level: "warn".into(),
+    };
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Full-text search over transcripts in a database written by --sink sqlite");
+        ap.refer(&mut args.query)
+            .add_argument("query", Store, "FTS5 query, e.g. 'water main', '\"water main\"', 'fire OR smoke'")
+            .required();
+        ap.refer(&mut args.db)
+            .add_option(&["--out"], Store, "SQLite database to search (default: calls.db)");
+        ap.refer(&mut args.limit)
+            .add_option(&["--limit"], Store, "Maximum matches to print (default: 100)");
+        ap.refer(&mut args.log_level)
+            .add_option(&["--log"], Store, "Log level (essential|debug|trace|warn|error)");
+        parse_or_exit(&ap, with_env(argv, &["--out", "--limit", "--log"], &[]));
+    }
+    args
+}
+
+pub struct AnomaliesArgs {
+    pub db: PathBuf,
+    pub sigma: f64,
+    pub min_calls: u64,
+    pub days: u32,
+    pub report: Option<PathBuf>,
+    pub log_level: String,
+}
+
+fn parse_anomalies(argv: Vec<String>) -> AnomaliesArgs {
+    let mut args = AnomaliesArgs {
+        db: PathBuf::from("calls.db"),
+        sigma: 3.0,
+        min_calls: 5,
+        days: 1,
+        report: None,
+        log_level: "essential".into(),
+    };
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Flag hours with unusually many calls per talk group, against the history in a --sink sqlite database");
+        ap.refer(&mut args.db)
+            .add_option(&["--out"], Store, "SQLite database to analyse (default: calls.db)");
+        ap.refer(&mut args.sigma)
+            .add_option(&["--sigma"], Store, "Flag hours more than N standard deviations above the talk group's usual count for that hour (default: 3)");
+        ap.refer(&mut args.min_calls)
+            .add_option(&["--min-calls"], Store, "Ignore hours with fewer calls (default: 5)");
+        ap.refer(&mut args.days)
+            .add_option(&["--days"], Store, "Check the last N days of data; earlier days are the baseline (default: 1)");
+        ap.refer(&mut args.report)
+            .add_option(&["--report"], StoreOption, "Write the anomalies as CSV here instead of to stdout");
+        ap.refer(&mut args.log_level)
+            .add_option(&["--log"], Store, "Log level (essential|debug|trace|warn|error)");
+        let env = ["--out", "--sigma", "--min-calls", "--days", "--report", "--log"];
+        parse_or_exit(&ap, with_env(argv, &env, &[]));
+    }
+    args
+}
+
+pub struct ServeArgs {
+    pub static_viewer: bool,
+    pub grafana: bool,
+    pub data: PathBuf,
+    pub listen: String,
+    pub log_level: String,
+}
+
+fn parse_serve(argv: Vec<String>) -> ServeArgs {
+    let mut args = ServeArgs {
+        static_viewer: false,
+        grafana: false,
+        data: PathBuf::from("calls.db"),
+        listen: serve::DEFAULT_LISTEN.into(),
+        log_level: "info".into(),
+    };
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Serve results over HTTP on localhost");
+        ap.refer(&mut args.static_viewer)
+            .add_option(&["--static"], StoreTrue, "Serve the bundled web viewer (table, search, audio playback)");
+        ap.refer(&mut args.grafana)
+            .add_option(&["--grafana"], StoreTrue, "Serve Grafana JSON datasource endpoints (calls per TG, recent calls)");
+        ap.refer(&mut args.data)
+            .add_option(&["--out"], Store, "Database written by --sink sqlite, or a JSON lines file of records (default: calls.db)");
+        ap.refer(&mut args.listen)
+            .add_option(&["--listen"], Store, "Address to listen on (default: 127.0.0.1:8080)");
+        ap.refer(&mut args.log_level)
+            .add_option(&["--log"], Store, "Log level (essential|debug|trace|warn|error)");
+        parse_or_exit(&ap, with_env(argv, &["--out", "--listen", "--log"], &["--static", "--grafana"]));
+    }
+    args
+}
+
+pub struct SelftestArgs {
+    pub record_dir: Option<PathBuf>,
+    pub log_level: String,
+}
+
+fn parse_selftest(argv: Vec<String>) -> SelftestArgs {
+    let mut args = SelftestArgs { record_dir: None, log_level: "warn".into() };
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Run the bundled sample inputs through the pipeline and check the output");
+        ap.refer(&mut args.record_dir)
+            .add_option(&["--record-dir"], StoreOption, "Also check this Record directory's layout and file names");
+        ap.refer(&mut args.log_level)
+            .add_option(&["--log"], Store, "Log level (essential|debug|trace|warn|error)");
+        parse_or_exit(&ap, with_env(argv, &["--record-dir", "--log"], &[]));
+    }
+    args
+}
+
+pub struct GoldenArgs {
+    pub dir: PathBuf,
+    pub update: bool,
+    pub log_level: String,
+}
+
+fn parse_golden(argv: Vec<String>) -> GoldenArgs {
+    let mut args = GoldenArgs {
+        dir: PathBuf::from(dsd_event_parser::golden::DEFAULT_DIR),
+        update: false,
+        log_level: "warn".into(),
+    };
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Parse every fixture in a directory and compare it with its golden .jsonl file");
+        ap.refer(&mut args.dir)
+            .add_argument("dir", Store, "Fixture directory (default: tests/fixtures)");
+        ap.refer(&mut args.update)
+            .add_option(&["--update-golden"], StoreTrue, "Rewrite the golden files from the current parsers instead of comparing");
+        ap.refer(&mut args.log_level)
+            .add_option(&["--log"], Store, "Log level (essential|debug|trace|warn|error)");
+        parse_or_exit(&ap, argv);
     }
+    args
+}
+
+pub struct InitArgs {
+    pub dir: PathBuf,
+    pub force: bool,
+    pub log_level: String,
+}
+
+fn parse_init(argv: Vec<String>) -> InitArgs {
+    let mut args = InitArgs { dir: PathBuf::from("."), force: false, log_level: "warn".into() };
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Write a starter configuration, a launcher script and the Record/ and models/ folders");
+        ap.refer(&mut args.dir)
+            .add_argument("dir", Store, "Directory to set up (default: the current one)");
+        ap.refer(&mut args.force)
+            .add_option(&["--force"], StoreTrue, "Overwrite an existing configuration and launcher");
+        ap.refer(&mut args.log_level)
+            .add_option(&["--log"], Store, "Log level (essential|debug|trace|warn|error)");
+        parse_or_exit(&ap, argv);
+    }
+    args
+}
+
+pub struct ExplainArgs {
+    pub input: PathBuf,
+    pub blocks: usize,
+    pub tz: Option<String>,
+    pub date_from_filename: bool,
+    pub log_level: String,
+}
+
+fn parse_explain(argv: Vec<String>) -> ExplainArgs {
+    let mut args = ExplainArgs {
+        input: PathBuf::new(),
+        blocks: 20,
+        tz: None,
+        date_from_filename: false,
+        log_level: "warn".into(),
+    };
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Show how the first blocks of an input file are parsed, and why lines are skipped");
+        ap.refer(&mut args.input)
+            .add_argument("input", Store, "SRT or event file")
+            .required();
+        ap.refer(&mut args.blocks)
+            .add_option(&["-n", "--blocks"], Store, "Records/skipped blocks to show (default: 20)");
+        ap.refer(&mut args.tz)
+            .add_option(&["--tz"], StoreOption, "Timezone of the file's timestamps, as for a normal run");
+        ap.refer(&mut args.date_from_filename)
+            .add_option(&["--date-from-filename"], StoreTrue, "Take the date from the file name for time-only lines");
+        ap.refer(&mut args.log_level)
+            .add_option(&["--log"], Store, "Log level (essential|debug|trace|warn|error)");
+        parse_or_exit(&ap, with_env(argv, &["--tz", "--log"], &["--date-from-filename"]));
+    }
+    args
+}
+
+pub struct ProbeArgs {
+    pub input: PathBuf,
+    pub record_dir: PathBuf,
+    pub tz: Option<String>,
+    pub date_from_filename: bool,
+    pub freq_tolerance: Option<String>,
+    pub audio_window: u32,
+    pub config: Option<PathBuf>,
+    pub examples: usize,
+    pub log_level: String,
+}
+
+fn parse_probe(argv: Vec<String>) -> ProbeArgs {
+    let mut args = ProbeArgs {
+        input: PathBuf::new(),
+        record_dir: PathBuf::new(),
+        tz: None,
+        date_from_filename: false,
+        freq_tolerance: None,
+        audio_window: 2,
+        config: None,
+        examples: 10,
+        log_level: "warn".into(),
+    };
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Estimate, per day, how many calls of an input have a transcript or recording in a Record directory");
+        ap.refer(&mut args.input)
+            .add_option(&["--input"], Store, "Input file, as for a normal run")
+            .required();
+        ap.refer(&mut args.record_dir)
+            .add_option(&["--record-dir"], Store, "Record directory to look the calls up in")
+            .required();
+        ap.refer(&mut args.tz)
+            .add_option(&["--tz"], StoreOption, "Timezone of the file's timestamps, as for a normal run");
+        ap.refer(&mut args.date_from_filename)
+            .add_option(&["--date-from-filename"], StoreTrue, "Take the date from the file name for time-only lines");
+        ap.refer(&mut args.freq_tolerance)
+            .add_option(&["--freq-tolerance"], StoreOption, "Frequency tolerance, as for a normal run (e.g. 2.5k)");
+        ap.refer(&mut args.audio_window)
+            .add_option(&["--audio-window"], Store, "Seconds a recording may be off the call, as for --match-audio (default: 2)");
+        ap.refer(&mut args.config)
+            .add_option(&["--config"], StoreOption, "TOML config file, for [record] file extensions");
+        ap.refer(&mut args.examples)
+            .add_option(&["-n", "--examples"], Store, "Unmatched calls to list (default: 10)");
+        ap.refer(&mut args.log_level)
+            .add_option(&["--log"], Store, "Log level (essential|debug|trace|warn|error)");
+        parse_or_exit(
+            &ap,
+            with_env(
+                argv,
+                &["--record-dir", "--tz", "--freq-tolerance", "--audio-window", "--config", "--log"],
+                &["--date-from-filename"],
+            ),
+        );
+    }
+    args
+}
+
+pub struct RegistryArgs {
+    pub action: String,
+    pub ids: Vec<String>,
+    pub file: PathBuf,
+    pub kind: String,
+    pub sort: String,
+    pub limit: usize,
+    pub format: String,
+    pub out: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    pub alias_list: String,
+    pub protocol: String,
+    pub log_level: String,
+}
+
+fn parse_registry(argv: Vec<String>) -> RegistryArgs {
+    let mut args = RegistryArgs {
+        action: String::new(),
+        ids: Vec::new(),
+        file: PathBuf::from(unit_registry::DEFAULT_FILE),
+        kind: "all".into(),
+        sort: "id".into(),
+        limit: 0,
+        format: "sdrtrunk".into(),
+        out: None,
+        config: None,
+        alias_list: sdrtrunk::DEFAULT_LIST.into(),
+        protocol: sdrtrunk::DEFAULT_PROTOCOL.into(),
+        log_level: "warn".into(),
+    };
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Query or export the talk groups and radio IDs collected by --unit-registry");
+        ap.refer(&mut args.action)
+            .add_argument("action", Store, "show [ID...] | export [ID...]")
+            .required();
+        ap.refer(&mut args.ids)
+            .add_argument("ids", List, "Only these TGs / RIDs");
+        ap.refer(&mut args.file)
+            .add_option(&["--unit-registry"], Store, "Registry file (default: callscribe-registry.json)");
+        ap.refer(&mut args.kind)
+            .add_option(&["--kind"], Store, "all (default), tg or rid");
+        ap.refer(&mut args.sort)
+            .add_option(&["--sort"], Store, "id (default), calls, airtime, first or last (most recently heard first)");
+        ap.refer(&mut args.limit)
+            .add_option(&["--limit"], Store, "Rows per table (default: all)");
+        ap.refer(&mut args.format)
+            .add_option(&["--format"], Store, "export: sdrtrunk (playlist XML, default) or csv (alias list)");
+        ap.refer(&mut args.out)
+            .add_option(&["--out"], StoreOption, "export: write here instead of to stdout");
+        ap.refer(&mut args.config)
+            .add_option(&["--config"], StoreOption, "export: TOML configuration file whose [alias] names the IDs");
+        ap.refer(&mut args.alias_list)
+            .add_option(&["--alias-list"], Store, "export: SDRTrunk alias list the aliases go in (default: Callscribe)");
+        ap.refer(&mut args.protocol)
+            .add_option(&["--protocol"], Store, "export: SDRTrunk protocol for IDs last heard on an unknown radio type (default: APCO25)");
+        ap.refer(&mut args.log_level)
+            .add_option(&["--log"], Store, "Log level (essential|debug|trace|warn|error)");
+        parse_or_exit(&ap, with_env(argv, &["--unit-registry", "--config", "--log"], &[]));
+    }
+    args
+}
+
+pub struct ModelsArgs {
+    pub action: String,
+    pub name: Option<String>,
+    pub dir: Option<PathBuf>,
+    pub log_level: String,
+}
+
+fn parse_models(argv: Vec<String>) -> ModelsArgs {
+    let mut args = ModelsArgs {
+        action: String::new(),
+        name: None,
+        dir: None,
+        log_level: "essential".into(),
+    };
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Manage cached speech-to-text models (whisper ggml, vosk)");
+        ap.refer(&mut args.action)
+            .add_argument("action", Store, "list | download NAME | verify [NAME]")
+            .required();
+        ap.refer(&mut args.name)
+            .add_argument("name", StoreOption, "Model name as shown by `models list`");
+        ap.refer(&mut args.dir)
+            .add_option(&["--models-dir"], StoreOption, "Model cache directory (default: $CALLSCRIBE_MODELS_DIR or the per-user data dir)");
+        ap.refer(&mut args.log_level)
+            .add_option(&["--log"], Store, "Log level (essential|debug|trace|warn|error)");
+        parse_or_exit(&ap, with_env(argv, &["--models-dir"], &[]));
+    }
+    args
+}
+
+pub struct TranscribeArgs {
+    /// `--update DB` or `--in FILE`.
+    pub input: Option<PathBuf>,
+    pub out: Option<PathBuf>,
+    pub record_dir: Option<PathBuf>,
+    pub stt: SttArgs,
+    pub config: Option<PathBuf>,
+    pub concurrency: usize,
+    pub log_level: String,
+}
+
+fn parse_transcribe(argv: Vec<String>) -> TranscribeArgs {
+    let mut args = TranscribeArgs {
+        input: None,
+        out: None,
+        record_dir: None,
+        stt: SttArgs { transcriber: "text".into(), ..SttArgs::default() },
+        config: None,
+        concurrency: 4,
+        log_level: "essential".into(),
+    };
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Fill in transcripts for rows written without them (see --defer-transcription)");
+        ap.refer(&mut args.input)
+            .add_option(&["--in", "--update"], StoreOption, "Output to update: a CSV file, or a SQLite database written by --sink sqlite");
+        ap.refer(&mut args.out)
+            .add_option(&["--out"], StoreOption, "For a CSV: write the updated file here instead of replacing --in");
+        ap.refer(&mut args.record_dir)
+            .add_option(&["--record-dir"], StoreOption, "Record directory (with YYYYMMDD subfolders)");
+        add_stt_options(&mut ap, &mut args.stt);
+        ap.refer(&mut args.config)
+            .add_option(&["--config"], StoreOption, "TOML configuration file (per-talkgroup settings etc.)");
+        ap.refer(&mut args.concurrency)
+            .add_option(&["--concurrency"], Store, "Calls transcribed in parallel (default: 4)");
+        ap.refer(&mut args.log_level)
+            .add_option(&["--log"], Store, "Log level (essential|debug|trace|warn|error)");
+        let argv = with_env(argv, &["--record-dir", "--config", "--concurrency", "--log"], &["--stt-preprocess", "--transcript-once", "--stt-segments"]);
+        parse_or_exit(&ap, with_env(argv, STT_ENV, &[]));
+    }
+    args
+}
+
+/// `--retain-*`, shared by the conversion run and `maintain`.
+#[derive(Default)]
+pub struct RetentionArgs {
+    pub days: Option<u32>,
+    pub keep_alerts: bool,
+    pub archive: bool,
+}
+
+fn add_retention_options<'a>(ap: &mut ArgumentParser<'a>, r: &'a mut RetentionArgs) {
+    ap.refer(&mut r.days)
+        .add_option(&["--retain-days"], StoreOption, "SQLite/PostgreSQL sinks: delete rows of calls more than N days old at the end of the run (default: [retention] days, else keep everything)");
+    ap.refer(&mut r.keep_alerts)
+        .add_option(&["--retain-alerts"], StoreTrue, "With --retain-days: keep rows with an alert however old");
+    ap.refer(&mut r.archive)
+        .add_option(&["--retain-archive"], StoreTrue, "With --retain-days: move old rows into <table>_archive instead of deleting them");
+}
+
+pub struct MaintainArgs {
+    pub db: PathBuf,
+    pub pg_url: Option<String>,
+    pub pg_table: String,
+    pub retention: RetentionArgs,
+    pub config: Option<PathBuf>,
+    pub log_level: String,
+}
+
+fn parse_maintain(argv: Vec<String>) -> MaintainArgs {
+    let mut args = MaintainArgs {
+        db: PathBuf::from("calls.db"),
+        pg_url: None,
+        pg_table: "calls".into(),
+        retention: RetentionArgs::default(),
+        config: None,
+        log_level: "essential".into(),
+    };
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Apply the retention policy to a --sink sqlite database or --sink postgres table now");
+        ap.refer(&mut args.db)
+            .add_option(&["--out"], Store, "SQLite database to prune (default: calls.db)");
+        ap.refer(&mut args.pg_url)
+            .add_option(&["--pg-url"], StoreOption, "Prune this PostgreSQL database instead");
+        ap.refer(&mut args.pg_table)
+            .add_option(&["--pg-table"], Store, "PostgreSQL table (default: calls)");
+        add_retention_options(&mut ap, &mut args.retention);
+        ap.refer(&mut args.config)
+            .add_option(&["--config"], StoreOption, "TOML configuration file; its [retention] sets what the options don't");
+        ap.refer(&mut args.log_level)
+            .add_option(&["--log"], Store, "Log level (essential|debug|trace|warn|error)");
+        let env = ["--out", "--pg-url", "--pg-table", "--retain-days", "--config", "--log"];
+        parse_or_exit(&ap, with_env(argv, &env, &["--retain-alerts", "--retain-archive"]));
+    }
+    args
+}
+
+/// The run's arguments: the command line, then the environment, with the
+/// `PROFILE_ENV` options' environment values in `env`.
+fn parse_cli(argv: Vec<String>) -> CliArgs {
+    let from_env = parse_run(with_env(vec![argv[0].clone()], PROFILE_ENV, &[]));
+    let argv = with_env(argv, RUN_ENV, RUN_ENV_SWITCHES);
+    let mut args = parse_run(with_env(argv, STT_ENV, &[]));
+    args.env = ProfileEnv {
+        tz: from_env.tz,
+        record_dir: from_env.record_dir,
+        sink: from_env.sink,
+        out: from_env.out,
+        rotate: from_env.rotate,
+        max_errors: from_env.max_errors,
+        min_quality: from_env.min_quality,
+    };
+    args
 }
 
-pub fn parse_cli() -> CliArgs {
+fn parse_run(argv: Vec<String>) -> CliArgs {
     let mut args = CliArgs::default();
     {
         let mut ap = ArgumentParser::new();
         ap.set_description("DSDPlus SRT -> CSV converter");
         ap.refer(&mut args.input_files)
             .add_argument("input_files", Collect, "Input SRT files (one or more)");
+        ap.refer(&mut args.inputs)
+            .add_option(&["--input"], Collect, "Input file with optional timezone override: PATH[:IANA_TZ]");
+        ap.refer(&mut args.date_from_filename)
+            .add_option(&["--date-from-filename"], StoreTrue, "Take the date from the file name (YYYYMMDD) for lines that carry only a time");
         ap.refer(&mut args.freqs)
             .add_option(&["-f", "--freq"], Collect, "Filter by frequency");
+        ap.refer(&mut args.freq_tolerance)
+            .add_option(&["--freq-tolerance"], StoreOption, "Frequencies this close are the same channel (filters, RLE, transcript lookup), e.g. 1k, 12.5kHz");
         ap.refer(&mut args.rtypes)
             .add_option(&["-t", "--type"], Collect, "Filter by radio type");
         ap.refer(&mut args.rids)
@@ -49,18 +904,163 @@ pub fn parse_cli() -> CliArgs {
         ap.refer(&mut args.tgs)
             .add_option(&["--tg"], Collect, "Filter by talk group");
         ap.refer(&mut args.nacs)
-            .add_option(&["--nac"], Collect, "Filter by NAC");
+            .add_option(&["--nac"], Collect, "Filter by P25 NAC");
+        ap.refer(&mut args.dccs)
+            .add_option(&["--dcc"], Collect, "Filter by DMR color code");
+        ap.refer(&mut args.watch_rids)
+            .add_option(&["--watch-rid"], Collect, "Alert on calls by this radio ID or [alias.rid] name (all calls are kept)");
+        ap.refer(&mut args.watch_tgs)
+            .add_option(&["--watch-tg"], Collect, "Alert on calls on this talk group or [alias.tg] name (all calls are kept)");
+        ap.refer(&mut args.notify_exec)
+            .add_option(&["--notify-exec"], StoreOption, "Run this shell command per alert (record JSON on stdin, reason in $CALLSCRIBE_ALERT)");
+        ap.refer(&mut args.alert_severity)
+            .add_option(&["--alert-severity"], StoreOption, "Also alert on calls scoring N or more by the config file's [severity] rules");
+        ap.refer(&mut args.max_errors)
+            .add_option(&["--max-errors"], StoreOption, "Drop calls with more than N decode errors (ERR=/errs= tokens)");
+        ap.refer(&mut args.min_quality)
+            .add_option(&["--min-quality"], StoreOption, "Drop calls with signal quality below PCT (Q=/Sync= tokens)");
         ap.refer(&mut args.tz)
             .add_option(&["--tz"], StoreOption, "Timezone (IANA name)");
         ap.refer(&mut args.record_dir)
             .add_option(&["--record-dir"], StoreOption, "Record directory (with YYYYMMDD subfolders)");
-        ap.refer(&mut args.transcriber)
-            .add_option(&["--transcriber"], Store, "Transcriber: none|text");
+        add_stt_options(&mut ap, &mut args.stt);
+        ap.refer(&mut args.config)
+            .add_option(&["--config"], StoreOption, "TOML configuration file (per-talkgroup settings etc.)");
+        ap.refer(&mut args.profile)
+            .add_option(&["--profile"], StoreOption, "Use the config file's [profile.NAME]: its inputs, filters, aliases, record dir and outputs");
+        ap.refer(&mut args.defer_transcription)
+            .add_option(&["--defer-transcription"], StoreTrue, "Write rows without transcripts now; fill them later with `transcribe --in`");
+        ap.refer(&mut args.stt_priority)
+            .add_option(&["--stt-priority"], StoreTrue, "Transcribe watched calls and --stt-priority-tg talk groups first; rows come out in completion order");
+        ap.refer(&mut args.stt_priority_tgs)
+            .add_option(&["--stt-priority-tg"], Collect, "With --stt-priority: also transcribe this talk group or [alias.tg] name first");
         ap.refer(&mut args.log_level)
             .add_option(&["--log"], Store, "Log level (essential|debug|trace|warn|error)");
         ap.refer(&mut args.out)
-            .add_option(&["--out"], Store, "Output CSV path");
-        ap.parse_args_or_exit();
+            .add_option(&["--out"], StoreOption, "Output database for --sink sqlite (default: calls.db)");
+        ap.refer(&mut args.fill_forward)
+            .add_option(&["--fill-forward"], StoreOption, "RLE: blocks missing these fields (rid,tg) continue the current call");
+        ap.refer(&mut args.renumber)
+            .add_option(&["--renumber"], Store, "record_number: source (the parser's, default), sequential (1.. per output) or global (1.. across the run)");
+        ap.refer(&mut args.replay_speed)
+            .add_option(&["--replay-speed"], StoreOption, "Emit calls paced by their timestamps at this speed (1x, 10x, 0.5x), to test live integrations with an old log");
+        ap.refer(&mut args.rle_per_slot)
+            .add_option(&["--rle-per-slot"], StoreTrue, "RLE: compress DMR slot 1 and slot 2 calls independently, then re-merge by time");
+        ap.refer(&mut args.rle_min_blocks)
+            .add_option(&["--rle-min-blocks"], StoreOption, "RLE: drop runs shorter than N blocks (squelch flaps, falsing)");
+        ap.refer(&mut args.run_details)
+            .add_option(&["--run-details"], StoreTrue, "Add first_record/last_record/block_count/end_datetime per call, to audit RLE against the raw log");
+        ap.refer(&mut args.merge)
+            .add_option(&["--merge"], StoreTrue, "Treat the inputs as one log split across files (in the given order): one output, calls continue across file boundaries");
+        ap.refer(&mut args.sort)
+            .add_option(&["--sort"], StoreTrue, "Buffer each input and sort it by time before RLE, for logs too far out of order to compress as they stream");
+        ap.refer(&mut args.sort_memory)
+            .add_option(&["--sort-memory"], StoreOption, "--sort: records held in memory per input before spilling sorted runs to disk (default 256MB)");
+        ap.refer(&mut args.sort_tmp)
+            .add_option(&["--sort-tmp"], StoreOption, "--sort: directory for spilled runs (default: the system temp dir)");
+        ap.refer(&mut args.lenient)
+            .add_option(&["--lenient"], StoreTrue, "Recover from malformed input (binary garbage, truncated or interleaved blocks) instead of failing; recoveries are counted");
+        ap.refer(&mut args.extras)
+            .add_option(&["--extras"], StoreTrue, "Keep KEY=VALUE tokens the parsers don't know, as an `extras` CSV column / JSON object");
+        ap.refer(&mut args.affiliations)
+            .add_option(&["--affiliations"], StoreTrue, "Event logs: track unit registrations/affiliations, add affiliated_tg to calls and write <output>.affiliations.tsv");
+        ap.refer(&mut args.keyword_index)
+            .add_option(&["--keyword-index"], StoreTrue, "Also write <output>.keywords.tsv: transcript word -> records");
+        ap.refer(&mut args.match_audio)
+            .add_option(&["--match-audio"], StoreTrue, "Find each call's recording in --record-dir and fill the audio_path column");
+        ap.refer(&mut args.audio_window)
+            .add_option(&["--audio-window"], Store, "With --match-audio: also match recordings named up to this many seconds before/after the call (default: 2; 0 = same second only)");
+        ap.refer(&mut args.audio_dedup)
+            .add_option(&["--audio-dedup"], StoreTrue, "With --match-audio: give calls whose recording duplicates a nearby call's the same file (needs the audio-fingerprint feature)");
+        ap.refer(&mut args.playlist)
+            .add_option(&["--playlist"], StoreOption, "Also write M3U8 playlists of the recordings in time order: output (one per output) or tg (one per talk group)");
+        ap.refer(&mut args.utilization)
+            .add_option(&["--utilization"], StoreOption, "Also write busy seconds per minute for each frequency: csv or json (<output>.utilization.csv/.json)");
+        ap.refer(&mut args.timing)
+            .add_option(&["--timing"], StoreTrue, "Log per-stage timing (busy / waiting for input / blocked on output) for each input");
+        ap.refer(&mut args.coverage_report)
+            .add_option(&["--coverage-report"], StoreTrue, "Log, per input, how many calls got a transcript by day and frequency, with examples of calls that didn't");
+        ap.refer(&mut args.progress)
+            .add_option(&["--progress"], StoreTrue, "Show a live table (file, stage, records, rows written, ETA) instead of per-file log lines");
+        ap.refer(&mut args.max_memory)
+            .add_option(&["--max-memory"], StoreOption, "Cap records buffered across all inputs to about SIZE (e.g. 256MB)");
+        ap.refer(&mut args.exit_zero)
+            .add_option(&["--exit-zero"], StoreTrue, "Exit with status 0 even when some inputs failed (they are still listed in the exit table)");
+        ap.refer(&mut args.manifest)
+            .add_option(&["--manifest"], StoreOption, "Write a JSON run manifest (e.g. run.json)");
+        ap.refer(&mut args.stats_json)
+            .add_option(&["--stats-json"], StoreOption, "Write call statistics as JSON (e.g. stats.json): per TG/frequency/type, airtime, encrypted share, transcript coverage");
+        ap.refer(&mut args.encryption_report)
+            .add_option(&["--encryption-report"], StoreOption, "Write the encrypted share of calls and airtime per day and talk group as CSV (e.g. encryption.csv)");
+        ap.refer(&mut args.skip_processed)
+            .add_option(&["--skip-processed"], StoreTrue, "Skip inputs already converted completely (same path, size and contents); record the ones converted now");
+        ap.refer(&mut args.processed_state)
+            .add_option(&["--processed-state"], StoreOption, "State file for --skip-processed (default: .callscribe-processed.json next to the first input)");
+        ap.refer(&mut args.unit_registry)
+            .add_option(&["--unit-registry"], StoreOption, "Add every TG and RID heard to this JSON registry (first/last heard, calls, airtime), kept across runs; see `registry show`");
+        ap.refer(&mut args.daemon)
+            .add_option(&["--daemon"], StoreTrue, "Run as a service: --follow, daily CSV rotation unless --rotate, systemd notify/watchdog, periodic status, clean stop on SIGTERM");
+        ap.refer(&mut args.follow)
+            .add_option(&["--follow"], StoreTrue, "Keep reading inputs as they grow (tail -f)");
+        ap.refer(&mut args.lag_warn)
+            .add_option(&["--lag-warn"], StoreOption, "--follow: warn when this much of the input is unread, e.g. 512KB (default: 1MB)");
+        ap.refer(&mut args.lag_warn_calls)
+            .add_option(&["--lag-warn-calls"], StoreOption, "--follow: warn when this many calls wait for transcription (default: 200)");
+        ap.refer(&mut args.lag_shed_stt)
+            .add_option(&["--lag-shed-stt"], StoreTrue, "--follow: while behind, skip STT for calls without an alert or priority talkgroup");
+        ap.refer(&mut args.listen)
+            .add_option(&["--listen"], StoreOption, "Also read lines sent to tcp://HOST:PORT or udp://HOST:PORT (e.g. tcp://0.0.0.0:9000), as with --follow");
+        ap.refer(&mut args.device)
+            .add_option(&["--device"], StoreOption, "Also read a named pipe or COM port (\\\\.\\pipe\\NAME, COM3, /dev/ttyUSB0), reopening it when it closes; implies --follow");
+        ap.refer(&mut args.listen_format)
+            .add_option(&["--listen-format"], Store, "Format of the lines from --listen and --device: event (default) or srt");
+        ap.refer(&mut args.rotate)
+            .add_option(&["--rotate"], StoreOption, "Rotate output: hourly|daily|<size> (e.g. 100MB)");
+        ap.refer(&mut args.rotate_keep)
+            .add_option(&["--rotate-keep"], StoreOption, "Keep only the newest N rotated files");
+        ap.refer(&mut args.csv_quote)
+            .add_option(&["--csv-quote"], StoreOption, "CSV quoting: necessary (default), always, non-numeric or never");
+        ap.refer(&mut args.csv_escape)
+            .add_option(&["--csv-escape"], StoreOption, "CSV: escape quotes in fields with this character (e.g. \\) instead of doubling them");
+        ap.refer(&mut args.csv_batch)
+            .add_option(&["--csv-batch"], Store, "CSV: rows written to the file at a time (default: 1000)");
+        ap.refer(&mut args.csv_flush_ms)
+            .add_option(&["--csv-flush-ms"], StoreOption, "CSV: write a batch that has waited this many ms even when not full (default: 1000)");
+        ap.refer(&mut args.excel_compat)
+            .add_option(&["--excel-compat"], StoreTrue, "CSV: write a UTF-8 BOM and CRLF line endings so Excel opens the file directly");
+        ap.refer(&mut args.preamble)
+            .add_option(&["--preamble"], StoreTrue, "CSV: start each file with # comment lines naming the version, schema_version and run options");
+        ap.refer(&mut args.fsync)
+            .add_option(&["--fsync"], StoreTrue, "Flush output files to disk before renaming them into place (each file is written as NAME.part first)");
+        ap.refer(&mut args.duration_format)
+            .add_option(&["--duration-format"], Store, "Duration as seconds (default) or hms (00:02:37; raw seconds are kept in duration_s / next to duration_hms)");
+        ap.refer(&mut args.sink)
+            .add_option(&["--sink"], Store, "Output sink (default: csv): csv|influx|syslog|postgres|elasticsearch|sqlite|template (the last four need their sink-* feature); several, comma-separated, are written in one pass");
+        ap.refer(&mut args.pg_url)
+            .add_option(&["--pg-url"], StoreOption, "PostgreSQL URL for --sink postgres");
+        ap.refer(&mut args.pg_table)
+            .add_option(&["--pg-table"], Store, "PostgreSQL table (default: calls)");
+        ap.refer(&mut args.pg_batch)
+            .add_option(&["--pg-batch"], Store, "Rows per PostgreSQL INSERT (default: 500)");
+        add_retention_options(&mut ap, &mut args.retention);
+        ap.refer(&mut args.es_url)
+            .add_option(&["--es-url"], StoreOption, "Elasticsearch/OpenSearch base URL for --sink elasticsearch");
+        ap.refer(&mut args.es_index)
+            .add_option(&["--es-index"], Store, "Index prefix; documents go to <prefix>-YYYY.MM.DD (default: callscribe)");
+        ap.refer(&mut args.es_api_key)
+            .add_option(&["--es-api-key"], StoreOption, "Elasticsearch API key (Authorization: ApiKey ...)");
+        ap.refer(&mut args.es_batch)
+            .add_option(&["--es-batch"], Store, "Documents per _bulk request (default: 500)");
+        ap.refer(&mut args.es_retries)
+            .add_option(&["--es-retries"], Store, "Bulk retries with exponential backoff (default: 5)");
+        ap.refer(&mut args.syslog_addr)
+            .add_option(&["--syslog-addr"], StoreOption, "Syslog collector for --sink syslog: udp://host:514 or tcp://host:601");
+        ap.refer(&mut args.syslog_facility)
+            .add_option(&["--syslog-facility"], Store, "Syslog facility name or code (default: local0)");
+        ap.refer(&mut args.template)
+            .add_option(&["--template"], StoreOption, "Tera template for --sink template, rendered once per call (call.txt.tera writes NAME.txt next to each input)");
+        ap.add_option(&["-V", "--version"], Print(features::version_string()), "Show version and compiled-in features");
+        parse_or_exit(&ap, argv);
     }
     args
 }
@@ -0,0 +1,336 @@
+// src/config.rs
+//
+// Optional TOML configuration file (`--config callscribe.toml`) for settings
+// that don't fit on a command line, such as per-talkgroup tables.
+//
+// ```toml
+// [stt.language]
+// default = "en"
+// tg = { 305 = "es", 1201 = "fr" }
+// frequency = { "853.237500" = "es" }
+//
+// [[stt.postprocess]]
+// kind = "numbers"
+//
+// [record]
+// transcript_extensions = ["txt", "text"]
+//
+// [alias]
+// rid = { 4506 = "Engine 5" }
+// tg = { 2 = "Fire Dispatch" }
+//
+// [notify]
+// cooldown_s = 300
+// tg = { 2 = 900 }
+//
+// [retention]
+// days = 90
+// keep_alerts = true
+//
+// [severity]
+// emergency = 50
+// keywords = { mayday = 80, "shots fired" = 60 }
+//
+// [radio_type]
+// "NXDN48" = "NXDN"
+//
+// [slot_policy]
+// "NXDN" = "by_line"
+//
+// [[input]]
+// path = "north/CC-DSDPlus.event"
+// tz = "America/Chicago"
+// system = "County P25"
+// site = "North"
+// record_dir = "north/Record"
+//
+// [profile.county_p25]
+// inputs = ["county/CC-DSDPlus.event"]
+// record_dir = "county/Record"
+// sink = "csv,sqlite"
+// out = "county.db"
+// filter = { nac = ["293"], tg = ["2", "3"] }
+// alias = { tg = { 2 = "Fire Dispatch" } }
+// vocabulary = ["Engine 5", "Medic 12", "Quarry Road", "Millbrook PD"]
+//
+// [transcriber.vosk]
+// command = ["vosk-transcriber", "-i", "{audio}", "-l", "{language}"]
+//
+// [transcriber.asr]
+// url = "http://127.0.0.1:9000/asr"
+// ```
+
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use crate::postprocess::PostProcessConfig;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub stt: SttConfig,
+    pub record: RecordConfig,
+    pub alias: Aliases,
+    pub notify: NotifyConfig,
+    pub severity: SeverityConfig,
+    pub retention: RetentionConfig,
+    /// Extra or replaced radio type spellings (see `radio_type.rs`).
+    pub radio_type: BTreeMap<String, String>,
+    /// Slot policies for SRT detail lines, by radio type (see `slot_policy.rs`).
+    pub slot_policy: BTreeMap<String, String>,
+    /// Per-input settings, for batch runs mixing logs from several receivers.
+    pub input: Vec<InputConfig>,
+    /// External STT backends, selected with `--transcriber NAME`.
+    pub transcriber: BTreeMap<String, ExternalTranscriberConfig>,
+    /// Settings for one monitored system each, selected with `--profile NAME`.
+    pub profile: BTreeMap<String, ProfileConfig>,
+}
+
+/// One `[profile.NAME]` entry: the inputs, filters, aliases, Record
+/// directory, outputs and STT vocabulary of one system. Options given on
+/// the command line win over the profile's; its aliases are added to
+/// `[alias]`, replacing entries for the same ID.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileConfig {
+    /// Inputs, when the command line names none.
+    pub inputs: Vec<PathBuf>,
+    pub tz: Option<String>,
+    pub record_dir: Option<PathBuf>,
+    /// As `--sink`, `--out` and `--rotate`.
+    pub sink: Option<String>,
+    pub out: Option<PathBuf>,
+    pub rotate: Option<String>,
+    pub filter: ProfileFilter,
+    pub alias: Aliases,
+    /// Replaces `[stt] prompt`; the vocabulary is added to `[stt]`'s.
+    pub prompt: Option<String>,
+    pub vocabulary: Vec<String>,
+}
+
+/// A profile's filters, as `--freq`, `--tg`, `--rid`, `--type`, `--nac`,
+/// `--dcc`, `--max-errors` and `--min-quality`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileFilter {
+    pub freq: Vec<String>,
+    pub tg: Vec<String>,
+    pub rid: Vec<String>,
+    pub radio_type: Vec<String>,
+    pub nac: Vec<String>,
+    pub dcc: Vec<String>,
+    pub max_errors: Option<u32>,
+    pub min_quality: Option<u32>,
+}
+
+/// Pruning of old rows from the SQLite / PostgreSQL sinks, as
+/// `--retain-days`, `--retain-alerts` and `--retain-archive` (see
+/// `retention.rs`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionConfig {
+    pub days: Option<u32>,
+    pub keep_alerts: bool,
+    pub archive: bool,
+}
+
+/// One `[transcriber.NAME]` entry: a program to run or an HTTP endpoint to
+/// post to for each call's recording (see `external_stt.rs`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalTranscriberConfig {
+    /// Program and arguments; `{audio}` is replaced by the recording's path
+    /// (appended when absent) and `{language}` by the call's language hint
+    /// (`auto` without one). Standard output is the transcript.
+    pub command: Option<Vec<String>>,
+    /// Endpoint the recording is POSTed to (feature `stt-http`).
+    pub url: Option<String>,
+    /// Seconds to wait for an HTTP response.
+    pub timeout_s: Option<u64>,
+}
+
+/// One `[[input]]` entry. The file is read in addition to the command
+/// line's inputs; naming one of those instead only adds the settings to it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InputConfig {
+    pub path: PathBuf,
+    /// Parser name from `callscribe formats`, when the extension is wrong
+    /// or missing.
+    pub format: Option<String>,
+    /// IANA timezone, as with `--input PATH:TZ`.
+    pub tz: Option<String>,
+    /// Labels for the receiver, added to each call's extras.
+    pub system: Option<String>,
+    pub site: Option<String>,
+    /// Where this input's transcripts and recordings are, instead of
+    /// `--record-dir`.
+    pub record_dir: Option<PathBuf>,
+}
+
+/// Limits on alert notifications (see `throttle.rs`). Times are call
+/// timestamps, so a replayed log is throttled the same way as a live one.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// Seconds after a notification for a watched TG/RID before that TG/RID
+    /// notifies again; 0 turns the cooldown off.
+    pub cooldown_s: u64,
+    /// Notifications allowed in any `burst_window_s` over all watched calls;
+    /// 0 means no limit.
+    pub burst: usize,
+    pub burst_window_s: u64,
+    /// Per-talkgroup / per-radio cooldowns (seconds), keyed by ID or alias.
+    pub tg: BTreeMap<String, u64>,
+    pub rid: BTreeMap<String, u64>,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self { cooldown_s: 60, burst: 20, burst_window_s: 60, tg: BTreeMap::new(), rid: BTreeMap::new() }
+    }
+}
+
+/// Points a call scores toward its `severity` (see `severity.rs`). Tables
+/// are keyed by ID or alias (`tg`, `rid`), phrase (`keywords`) or minimum
+/// seconds (`duration`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SeverityConfig {
+    pub emergency: u32,
+    pub tg: BTreeMap<String, u32>,
+    pub rid: BTreeMap<String, u32>,
+    pub keywords: BTreeMap<String, u32>,
+    pub duration: BTreeMap<String, u32>,
+}
+
+/// Names for radio IDs and talkgroups, keyed by ID. `--watch-rid` and
+/// `--watch-tg` accept either.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Aliases {
+    pub rid: BTreeMap<String, String>,
+    pub tg: BTreeMap<String, String>,
+}
+
+impl Aliases {
+    /// The ID for `name` in `table`, matched case-insensitively; an ID is
+    /// returned as is.
+    pub fn resolve(table: &BTreeMap<String, String>, name: &str) -> Option<String> {
+        let name = name.trim();
+        if !name.is_empty() && name.bytes().all(|b| b.is_ascii_digit()) {
+            return Some(name.to_string());
+        }
+        table.iter().find(|(_, n)| n.eq_ignore_ascii_case(name)).map(|(id, _)| id.clone())
+    }
+}
+
+/// Which files in the Record directory are matched to calls. Extensions are
+/// compared case-insensitively; unset lists keep the built-in defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecordConfig {
+    pub transcript_extensions: Option<Vec<String>>,
+    pub audio_extensions: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SttConfig {
+    pub language: LanguageHints,
+    /// Rewrites applied to every transcript, in order (see `postprocess.rs`).
+    pub postprocess: Vec<PostProcessConfig>,
+    /// Text the backend is primed with, as if it came before the call
+    /// (whisper's initial prompt), and words it should expect: street
+    /// names, unit callsigns, agencies. See [`SttConfig::prompt`].
+    pub prompt: Option<String>,
+    pub vocabulary: Vec<String>,
+}
+
+impl SttConfig {
+    /// `prompt` followed by the vocabulary as a comma-separated list:
+    /// `Dispatch for Millbrook County. Engine 5, Medic 12, Quarry Road.`
+    /// `None` when both are empty.
+    pub fn prompt(&self) -> Option<String> {
+        let prompt = self.prompt.as_deref().map(str::trim).filter(|p| !p.is_empty());
+        let words: Vec<&str> = self.vocabulary.iter().map(|w| w.trim()).filter(|w| !w.is_empty()).collect();
+        let words = (!words.is_empty()).then(|| format!("{}.", words.join(", ")));
+        match (prompt, words) {
+            (Some(p), Some(w)) => Some(format!("{} {}", p, w)),
+            (p, w) => p.map(str::to_string).or(w),
+        }
+    }
+}
+
+/// Language passed to the STT backend for a call: the talkgroup's entry,
+/// else the frequency's (i.e. the system/site), else `default`; with none of
+/// them the backend auto-detects.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LanguageHints {
+    pub default: Option<String>,
+    pub tg: BTreeMap<String, String>,
+    pub frequency: BTreeMap<String, String>,
+}
+
+impl LanguageHints {
+    pub fn for_record(&self, rec: &RadioRecord) -> Option<&str> {
+        let tg = rec.slot1.tg.as_deref().or(rec.slot2.tg.as_deref());
+        if let Some(lang) = tg.and_then(|tg| self.tg.get(tg)) {
+            return Some(lang);
+        }
+        if let Some(freq) = rec.frequency.as_deref() {
+            let freq = crate::transcriber::normalize_freq(freq);
+            if let Some((_, lang)) = self
+                .frequency
+                .iter()
+                .find(|(f, _)| crate::transcriber::normalize_freq(f) == freq)
+            {
+                return Some(lang);
+            }
+        }
+        self.default.as_deref()
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| AppError::IO(format!("read config {}: {}", path.display(), e)))?;
+        let mut config: Self =
+            toml::from_str(&text).map_err(|e| AppError::Parse(format!("config {}: {}", path.display(), e)))?;
+        // Files the config names are next to it.
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for step in &mut config.stt.postprocess {
+            if let PostProcessConfig::Expand { dictionary: Some(file), .. } = step
+                && file.is_relative()
+            {
+                *file = dir.join(&*file);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Profile `name`, its aliases merged into `[alias]`.
+    pub fn use_profile(&mut self, name: &str) -> Result<ProfileConfig, AppError> {
+        let Some(profile) = self.profile.get(name.trim()).cloned() else {
+            let known = self.profile.keys().cloned().collect::<Vec<_>>().join(", ");
+            let known = if known.is_empty() { "none in the config file".to_string() } else { known };
+            return Err(AppError::Other(format!("--profile: no [profile.{}] (profiles: {})", name.trim(), known)));
+        };
+        self.alias.tg.extend(profile.alias.tg.clone());
+        self.alias.rid.extend(profile.alias.rid.clone());
+        if profile.prompt.is_some() {
+            self.stt.prompt = profile.prompt.clone();
+        }
+        self.stt.vocabulary.extend(profile.vocabulary.iter().cloned());
+        Ok(profile)
+    }
+
+    /// `--config` if given, else defaults.
+    pub fn load_optional(path: Option<&Path>) -> Result<Self, AppError> {
+        path.map_or_else(|| Ok(Self::default()), Self::load)
+    }
+}
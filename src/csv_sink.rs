@@ -1,76 +1,341 @@
 use crate::errors::AppError;
-use crate::model::RadioRecord;
+use crate::features;
+use crate::model::{self, RadioRecord};
+use crate::output_file::{OutputFile, WriteMode};
+use crate::rotation::{RotationPolicy, Rotator};
+use crate::sink::DurationFormat;
+use crate::stats::PipelineStats;
 use log::info;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::fs::File;
-use tokio::io::BufWriter;
+use tokio::io::{AsyncWriteExt, BufWriter};
 use tokio::sync::mpsc::Receiver;
-use tokio_util::compat::TokioAsyncWriteCompatExt; // <- compat bridge
+
+/// Formats rows into memory; a batch at a time is then written to the file.
+type RowWriter = csv_async::AsyncWriter<Vec<u8>>;
+
+const RUN_HEADER: [&str; 4] = ["first_record", "last_record", "block_count", "end_datetime"];
+
+const HEADER: [&str; 22] = [
+    "record_number",
+    "datetime",
+    "duration",
+    "frequency",
+    "radio_type",
+    "dcc",
+    "slot1_tg",
+    "slot1_rid",
+    "slot1_text",
+    "slot2_tg",
+    "slot2_rid",
+    "slot2_text",
+    "audio_path",
+    "record_id",
+    "call_type",
+    "target_rid",
+    "errors",
+    "quality",
+    "nac",
+    "color_code",
+    "alert",
+    "encrypted",
+];
+
+/// Position of `duration` in [`HEADER`].
+const DURATION_COLUMN: usize = 2;
+
+/// Rows per write when `CsvSinkOptions::batch` is 0.
+pub const DEFAULT_BATCH: usize = 1000;
+
+/// Longest a formatted row waits for its batch when rows keep coming.
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Knobs for `write_csv_stream`.
+#[derive(Clone, Debug, Default)]
+pub struct CsvSinkOptions {
+    /// Start a new file per hour/day/size instead of writing `out_path` itself.
+    pub rotate: Option<RotationPolicy>,
+    /// With rotation, keep only this many rotated files.
+    pub keep: Option<usize>,
+    /// Write rows to the file this many at a time (`--csv-batch`; 0 for
+    /// [`DEFAULT_BATCH`]).
+    pub batch: usize,
+    /// Write a batch that has waited this long even when not full
+    /// (`--csv-flush-ms`; [`DEFAULT_FLUSH_INTERVAL`] when unset).
+    pub flush_interval: Option<Duration>,
+    /// Also write whenever the input channel runs dry (follow mode), so rows
+    /// show up on disk promptly instead of at end of stream.
+    pub flush_when_idle: bool,
+    /// Add an `extras` column (`KEY=VALUE ...`; `--extras`).
+    pub extras: bool,
+    /// Add `first_record`, `last_record`, `block_count` and `end_datetime`
+    /// columns (`--run-details`).
+    pub run_details: bool,
+    /// Add an `affiliated_tg` column (`--affiliations`).
+    pub affiliations: bool,
+    /// Add a `severity` column (`[severity]` rules in the config file).
+    pub severity: bool,
+    /// Which fields get quoted (`--csv-quote`).
+    pub quote: csv_async::QuoteStyle,
+    /// Escape quotes inside fields with this byte (`\"`) instead of
+    /// doubling them (`""`; `--csv-escape`).
+    pub escape: Option<u8>,
+    /// Start each file with a UTF-8 BOM and end rows with CRLF, so Excel
+    /// reads non-ASCII text correctly (`--excel-compat`).
+    pub excel: bool,
+    /// `duration` as `00:02:37`, with a `duration_s` column of seconds
+    /// after it (`--duration-format hms`).
+    pub duration: DurationFormat,
+    /// `# ...` lines to start each file with (`--preamble`; see [`preamble`]).
+    pub preamble: Option<String>,
+    /// Temporary file and rename, or in place (follow mode); `--fsync`.
+    pub write_mode: WriteMode,
+}
+
+/// Comment lines naming the tool version, the schema version and the run's
+/// options (as JSON), for `--preamble`.
+pub fn preamble(options: &serde_json::Value) -> String {
+    format!(
+        "# {}\n# schema_version: {}\n# options: {}\n",
+        features::version_string(),
+        model::SCHEMA_VERSION,
+        options
+    )
+}
+
+/// `--csv-quote` value: `necessary` (the default), `always`, `non-numeric`
+/// or `never`.
+pub fn parse_quote_style(s: &str) -> Result<csv_async::QuoteStyle, AppError> {
+    use csv_async::QuoteStyle;
+    match s.trim().to_ascii_lowercase().as_str() {
+        "necessary" => Ok(QuoteStyle::Necessary),
+        "always" => Ok(QuoteStyle::Always),
+        "non-numeric" | "nonnumeric" => Ok(QuoteStyle::NonNumeric),
+        "never" => Ok(QuoteStyle::Never),
+        _ => Err(AppError::Parse(format!(
+            "invalid --csv-quote '{}': expected necessary, always, non-numeric or never",
+            s
+        ))),
+    }
+}
+
+/// `--csv-escape` value: a single ASCII character.
+pub fn parse_escape(s: &str) -> Result<u8, AppError> {
+    match s.as_bytes() {
+        [b] if b.is_ascii() && *b != b'"' && *b != b',' => Ok(*b),
+        _ => Err(AppError::Parse(format!("invalid --csv-escape '{}': expected one ASCII character, e.g. \\", s))),
+    }
+}
 
 fn excel_guard_radio_type(s: &str) -> String {
     // Per your request, drop the leading '+' entirely
     s.trim_start_matches('+').to_string()
 }
 
+/// An open output file, and the rows formatted for it since the last write.
+struct CsvFile {
+    file: BufWriter<File>,
+    out: OutputFile,
+    builder: csv_async::AsyncWriterBuilder,
+    rows: RowWriter,
+    pending: usize,
+    since: Instant,
+}
+
+impl CsvFile {
+    async fn open(path: &Path, opts: &CsvSinkOptions) -> Result<Self, AppError> {
+        let (out, file) = OutputFile::create(path, opts.write_mode).await?;
+        let mut file = BufWriter::new(file);
+        if opts.excel {
+            file.write_all(b"\xEF\xBB\xBF")
+                .await
+                .map_err(|e| AppError::IO(format!("csv write BOM: {}", e)))?;
+        }
+        if let Some(text) = &opts.preamble {
+            let text = if opts.excel { text.replace('\n', "\r\n") } else { text.clone() };
+            file.write_all(text.as_bytes())
+                .await
+                .map_err(|e| AppError::IO(format!("csv write preamble: {}", e)))?;
+        }
+
+        let mut builder = csv_async::AsyncWriterBuilder::new();
+        builder.quote_style(opts.quote);
+        if opts.excel {
+            builder.terminator(csv_async::Terminator::CRLF);
+        }
+        if let Some(esc) = opts.escape {
+            builder.double_quote(false).escape(esc);
+        }
+        let rows = builder.create_writer(Vec::new());
+        let mut f = Self { file, out, builder, rows, pending: 0, since: Instant::now() };
+
+        // header once per file
+        let mut header: Vec<&str> = HEADER.to_vec();
+        if opts.duration == DurationFormat::Hms {
+            header.insert(DURATION_COLUMN + 1, "duration_s");
+        }
+        if opts.run_details {
+            header.extend(RUN_HEADER);
+        }
+        if opts.extras {
+            header.push("extras");
+        }
+        if opts.affiliations {
+            header.push("affiliated_tg");
+        }
+        if opts.severity {
+            header.push("severity");
+        }
+        f.rows
+            .write_record(&header)
+            .await
+            .map_err(|e| AppError::IO(format!("csv write header: {}", e)))?;
+        f.pending += 1;
+        Ok(f)
+    }
+
+    /// Format one row into the batch; nothing reaches the file until `write`.
+    async fn push(&mut self, row: &[String]) -> Result<(), AppError> {
+        self.rows
+            .write_record(row)
+            .await
+            .map_err(|e| AppError::IO(format!("csv write row: {}", e)))?;
+        self.pending += 1;
+        Ok(())
+    }
+
+    /// Write the batch to the file in one go, and flush it.
+    async fn write(&mut self) -> Result<(), AppError> {
+        self.since = Instant::now();
+        if self.pending == 0 {
+            return Ok(());
+        }
+        let rows = std::mem::replace(&mut self.rows, self.builder.create_writer(Vec::new()));
+        let bytes = rows.into_inner().await.map_err(|e| AppError::IO(format!("csv write row: {}", e)))?;
+        self.file
+            .write_all(&bytes)
+            .await
+            .map_err(|e| AppError::IO(format!("csv write row: {}", e)))?;
+        self.file.flush().await.map_err(|e| AppError::IO(format!("csv flush: {}", e)))?;
+        self.pending = 0;
+        Ok(())
+    }
+
+    async fn close(mut self) -> Result<(), AppError> {
+        self.write().await?;
+        self.out.commit(self.file.into_inner()).await
+    }
+}
+
+/// Writes records as CSV and returns the files it produced (one, or several
+/// when rotating).
 pub async fn write_csv_stream(
     out_path: &Path,
     mut rx: Receiver<RadioRecord>,
-) -> Result<(), AppError> {
-    let file = File::create(out_path)
-        .await
-        .map_err(|e| AppError::IO(format!("open out csv '{}': {}", out_path.display(), e)))?;
-    let writer = BufWriter::new(file);
-
-    // Bridge Tokio AsyncWrite -> futures::io::AsyncWrite for csv_async
-    let compat_writer = writer.compat_write();
-    let mut wtr = csv_async::AsyncWriter::from_writer(compat_writer);
-
-    // header once
-    wtr.write_record(&[
-        "record_number",
-        "datetime",
-        "duration",
-        "frequency",
-        "radio_type",
-        "dcc",
-        "slot1_tg",
-        "slot1_rid",
-        "slot1_text",
-        "slot2_tg",
-        "slot2_rid",
-        "slot2_text",
-    ])
-    .await
-    .map_err(|e| AppError::IO(format!("csv write header: {}", e)))?;
+    stats: Arc<PipelineStats>,
+    opts: CsvSinkOptions,
+) -> Result<Vec<PathBuf>, AppError> {
+    let _timer = stats.sink.timer();
+    let mut rotator = opts.rotate.map(|p| Rotator::new(out_path, p, opts.keep));
+    let mut written: Vec<PathBuf> = Vec::new();
+    let mut wtr: Option<CsvFile> = None;
+
+    // Without rotation the file exists (header only) even for empty input.
+    if rotator.is_none() {
+        wtr = Some(CsvFile::open(out_path, &opts).await?);
+        written.push(out_path.to_path_buf());
+    }
 
     let mut count: usize = 0;
+    let batch = if opts.batch == 0 { DEFAULT_BATCH } else { opts.batch };
+    let flush_interval = opts.flush_interval.unwrap_or(DEFAULT_FLUSH_INTERVAL);
+
+    while let Some(r) = stats.sink.recv(&mut rx).await {
+        stats.sink.inc_in();
 
-    while let Some(r) = rx.recv().await {
-        let row = [
+        if let Some(rot) = rotator.as_mut()
+            && rot.needs_rotation(&r)
+        {
+            if let Some(old) = wtr.take() {
+                old.close().await?;
+            }
+            let path = rot.next_path(&r);
+            info!("CSV rotating to {}", path.display());
+            wtr = Some(CsvFile::open(&path, &opts).await?);
+            written.push(path);
+            rot.prune().await?;
+        }
+        let Some(w) = wtr.as_mut() else { break; };
+
+        let mut row = vec![
             r.record_number.to_string(),
             r.datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
-            r.duration.to_string(),
+            opts.duration.format(r.duration),
             r.frequency.clone().unwrap_or_default(),
             excel_guard_radio_type(r.radio_type.as_deref().unwrap_or("")),
-            r.dcc.clone().unwrap_or_default(),
+            r.nac_or_dcc().unwrap_or_default().to_string(),
             r.slot1.tg.clone().unwrap_or_default(),
             r.slot1.rid.clone().unwrap_or_default(),
             r.slot1.text.clone().unwrap_or_default(),
             r.slot2.tg.clone().unwrap_or_default(),
             r.slot2.rid.clone().unwrap_or_default(),
             r.slot2.text.clone().unwrap_or_default(),
+            r.audio_path.as_ref().map(|p| p.display().to_string()).unwrap_or_default(),
+            r.record_id(),
+            r.call_type.as_str().to_string(),
+            r.target_rid.clone().unwrap_or_default(),
+            r.errors.map(|e| e.to_string()).unwrap_or_default(),
+            r.quality.map(|q| q.to_string()).unwrap_or_default(),
+            r.nac.clone().unwrap_or_default(),
+            r.dcc.clone().unwrap_or_default(),
+            r.alert.clone().unwrap_or_default(),
+            r.encrypted.to_string(),
         ];
+        if opts.duration == DurationFormat::Hms {
+            row.insert(DURATION_COLUMN + 1, r.duration.to_string());
+        }
+        if opts.run_details {
+            let (first, last, end) = match &r.run {
+                Some(span) => (span.first_record, span.last_record, span.end_datetime),
+                None => (r.record_number, r.record_number, r.datetime),
+            };
+            row.extend([
+                first.to_string(),
+                last.to_string(),
+                r.block_count.to_string(),
+                end.format("%Y-%m-%d %H:%M:%S").to_string(),
+            ]);
+        }
+        if opts.extras {
+            row.push(r.extras_string());
+        }
+        if opts.affiliations {
+            row.push(r.affiliated_tg.clone().unwrap_or_default());
+        }
+        if opts.severity {
+            row.push(r.severity.map(|s| s.to_string()).unwrap_or_default());
+        }
 
-        wtr.write_record(&row)
-            .await
-            .map_err(|e| AppError::IO(format!("csv write row: {}", e)))?;
+        if let Some(rot) = rotator.as_mut() {
+            // Approximate on-disk size: fields + separators + newline.
+            rot.add_bytes(row.iter().map(|f| f.len() as u64 + 1).sum());
+        }
+
+        w.push(&row).await?;
         count += 1;
+        stats.sink.inc_out();
+
+        if w.pending >= batch || w.since.elapsed() >= flush_interval || (opts.flush_when_idle && rx.is_empty()) {
+            w.write().await?;
+        }
     }
 
-    wtr.flush()
-        .await
-        .map_err(|e| AppError::IO(format!("csv flush: {}", e)))?;
+    if let Some(w) = wtr {
+        w.close().await?;
+    }
 
     info!("CSV wrote {} rows to {}", count, out_path.display());
-    Ok(())
+    Ok(written)
 }
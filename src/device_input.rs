@@ -0,0 +1,99 @@
+// src/device_input.rs
+//
+// `--device <PATH>`: read event or SRT lines from a Windows named pipe
+// (`\\.\pipe\dsdplus`) or COM port (`COM3`), for setups where DSDPlus output
+// is relayed that way instead of written to a file. On other systems a FIFO
+// or tty (`/dev/ttyUSB0`) works the same way.
+//
+// The device is read in tail mode: when the other end closes it (the pipe
+// server restarts, a USB serial adapter is unplugged) the device is opened
+// again every few seconds, and a line cut short is terminated so the next
+// one starts clean. Serial settings are left to the system, e.g.
+// `mode COM3 BAUD=115200 DATA=8 PARITY=N` or `stty -F /dev/ttyUSB0 115200`.
+
+use crate::errors::AppError;
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+const PIPE_CAPACITY: usize = 64 * 1024;
+/// Wait between attempts to (re)open the device.
+const RETRY: Duration = Duration::from_secs(3);
+
+/// Name for the device's output and log lines: `COM3`, `dsdplus` for
+/// `\\.\pipe\dsdplus`, `ttyUSB0`.
+pub fn device_name(path: &Path) -> String {
+    let s = path.to_string_lossy();
+    s.rsplit(['\\', '/']).next().filter(|n| !n.is_empty()).unwrap_or("device").to_string()
+}
+
+/// The path to open: bare `COMn` names need the `\\.\` prefix on Windows
+/// (required from COM10 up).
+fn device_path(path: &Path) -> PathBuf {
+    let s = path.to_string_lossy();
+    let is_com = s.len() > 3 && s[..3].eq_ignore_ascii_case("com") && s[3..].bytes().all(|b| b.is_ascii_digit());
+    if cfg!(windows) && is_com { PathBuf::from(format!(r"\\.\{}", s)) } else { path.to_path_buf() }
+}
+
+#[cfg(windows)]
+async fn open_once(path: &Path) -> std::io::Result<Box<dyn AsyncRead + Unpin + Send>> {
+    let s = path.to_string_lossy();
+    if s.len() > 9 && s[..9].eq_ignore_ascii_case(r"\\.\pipe\") {
+        let client = tokio::net::windows::named_pipe::ClientOptions::new().open(path)?;
+        return Ok(Box::new(client));
+    }
+    Ok(Box::new(tokio::fs::File::open(path).await?))
+}
+
+#[cfg(not(windows))]
+async fn open_once(path: &Path) -> std::io::Result<Box<dyn AsyncRead + Unpin + Send>> {
+    Ok(Box::new(tokio::fs::File::open(path).await?))
+}
+
+async fn tail(path: PathBuf, mut pipe: DuplexStream) {
+    let mut buf = vec![0u8; PIPE_CAPACITY];
+    let mut waiting = false;
+    loop {
+        match open_once(&path).await {
+            Ok(mut dev) => {
+                info!("--device: reading {}", path.display());
+                waiting = false;
+                let mut last = b'\n';
+                loop {
+                    match dev.read(&mut buf).await {
+                        Ok(0) => break,
+                        Ok(n) => {
+                            last = buf[n - 1];
+                            if pipe.write_all(&buf[..n]).await.is_err() {
+                                return;
+                            }
+                        }
+                        Err(e) => {
+                            warn!("--device: {}: {}", path.display(), e);
+                            break;
+                        }
+                    }
+                }
+                if last != b'\n' && pipe.write_all(b"\n").await.is_err() {
+                    return;
+                }
+                info!("--device: {} closed; reopening", path.display());
+            }
+            // Logged once per outage, not on every retry.
+            Err(e) if !waiting => {
+                warn!("--device: {}: {}; retrying every {}s", path.display(), e, RETRY.as_secs());
+                waiting = true;
+            }
+            Err(_) => {}
+        }
+        tokio::time::sleep(RETRY).await;
+    }
+}
+
+/// Read `path` as one endless stream, reopening it whenever it closes.
+pub async fn open(path: &Path) -> Result<Box<dyn AsyncRead + Unpin + Send>, AppError> {
+    let (reader, pipe) = tokio::io::duplex(PIPE_CAPACITY);
+    tokio::spawn(tail(device_path(path), pipe));
+    Ok(Box::new(reader))
+}
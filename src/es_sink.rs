@@ -0,0 +1,208 @@
+// src/es_sink.rs
+//
+// Elasticsearch / OpenSearch bulk sink (feature `sink-elasticsearch`).
+// Records are indexed into one index per day (`<prefix>-YYYY.MM.DD`) with a
+// deterministic `_id`, so re-running a conversion overwrites rather than
+// duplicates. Bulk requests are retried with exponential backoff.
+
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use crate::sink::{self, DurationFormat};
+use crate::stats::PipelineStats;
+use log::{debug, info, warn};
+use reqwest::{Client, StatusCode, Url};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
+
+#[derive(Clone, Debug)]
+pub struct EsSinkOptions {
+    /// Cluster base URL; `user:pass@` userinfo is sent as basic auth.
+    pub url: String,
+    pub index_prefix: String,
+    pub api_key: Option<String>,
+    /// Documents per `_bulk` request.
+    pub batch: usize,
+    /// Also flush a partial batch whenever the input goes idle (follow mode).
+    pub flush_when_idle: bool,
+    /// Attempts after the first before a bulk request is given up.
+    pub retries: u32,
+    /// Add a `duration_hms` field (`--duration-format hms`).
+    pub duration: DurationFormat,
+}
+
+/// Client shared by every pipeline.
+#[derive(Clone, Debug)]
+pub struct EsSink {
+    client: Client,
+    bulk_url: Url,
+    auth: Option<(String, Option<String>)>,
+    opts: EsSinkOptions,
+}
+
+impl EsSink {
+    pub fn new(opts: EsSinkOptions) -> Result<Self, AppError> {
+        if opts.batch == 0 {
+            return Err(AppError::Other("--es-batch must be at least 1".into()));
+        }
+        let mut base = Url::parse(&opts.url)
+            .map_err(|e| AppError::Parse(format!("--es-url '{}': {}", opts.url, e)))?;
+        let auth = if base.username().is_empty() {
+            None
+        } else {
+            let user = base.username().to_string();
+            let pass = base.password().map(str::to_string);
+            let _ = base.set_username("");
+            let _ = base.set_password(None);
+            Some((user, pass))
+        };
+        let bulk_url = base
+            .join("_bulk")
+            .map_err(|e| AppError::Parse(format!("--es-url: {}", e)))?;
+        let client = Client::builder()
+            .timeout(Duration::from_secs(60))
+            .build()
+            .map_err(|e| AppError::Other(format!("http client: {}", e)))?;
+        Ok(Self { client, bulk_url, auth, opts })
+    }
+
+    fn index_for(&self, r: &RadioRecord) -> String {
+        format!("{}-{}", self.opts.index_prefix, r.datetime.format("%Y.%m.%d"))
+    }
+
+    /// Stable across runs: same call, same document.
+    fn doc_id(r: &RadioRecord) -> String {
+        let tg = r.slot1.tg.as_ref().or(r.slot2.tg.as_ref()).map(String::as_str).unwrap_or("");
+        let rid = r.slot1.rid.as_ref().or(r.slot2.rid.as_ref()).map(String::as_str).unwrap_or("");
+        format!("{}_{}_{}_{}", r.datetime.timestamp(), r.frequency.as_deref().unwrap_or(""), tg, rid)
+    }
+
+    fn ndjson(&self, batch: &[&RadioRecord], source: &str) -> String {
+        let mut body = String::new();
+        for r in batch {
+            let action = serde_json::json!({ "index": { "_index": self.index_for(r), "_id": Self::doc_id(r) } });
+            let mut doc = r.to_json();
+            doc["source"] = serde_json::Value::from(source);
+            if self.opts.duration == DurationFormat::Hms {
+                doc["duration_hms"] = serde_json::Value::from(sink::hms(r.duration));
+            }
+            body.push_str(&action.to_string());
+            body.push('\n');
+            body.push_str(&doc.to_string());
+            body.push('\n');
+        }
+        body
+    }
+
+    async fn post_bulk(&self, body: String) -> Result<reqwest::Response, reqwest::Error> {
+        let mut req = self
+            .client
+            .post(self.bulk_url.clone())
+            .header("Content-Type", "application/x-ndjson")
+            .body(body);
+        if let Some((user, pass)) = &self.auth {
+            req = req.basic_auth(user, pass.as_ref());
+        }
+        if let Some(key) = &self.opts.api_key {
+            req = req.header("Authorization", format!("ApiKey {}", key));
+        }
+        req.send().await
+    }
+
+    /// Send one batch; retries the whole request on transport errors, 429 and
+    /// 5xx, and re-sends only the items the cluster rejected as retryable.
+    async fn send_batch(&self, batch: &[RadioRecord], source: &str) -> Result<(), AppError> {
+        let mut pending: Vec<&RadioRecord> = batch.iter().collect();
+        let mut attempt: u32 = 0;
+        let mut backoff = Duration::from_millis(500);
+
+        loop {
+            let retry_reason = match self.post_bulk(self.ndjson(&pending, source)).await {
+                Err(e) => format!("transport: {}", e),
+                Ok(resp) if resp.status() == StatusCode::TOO_MANY_REQUESTS || resp.status().is_server_error() => {
+                    format!("HTTP {}", resp.status())
+                }
+                Ok(resp) if !resp.status().is_success() => {
+                    let status = resp.status();
+                    let text = resp.text().await.unwrap_or_default();
+                    return Err(AppError::IO(format!("elasticsearch bulk HTTP {}: {}", status, text)));
+                }
+                Ok(resp) => {
+                    let body: serde_json::Value = resp
+                        .json()
+                        .await
+                        .map_err(|e| AppError::IO(format!("elasticsearch bulk response: {}", e)))?;
+                    if !body["errors"].as_bool().unwrap_or(false) {
+                        return Ok(());
+                    }
+                    let items = body["items"].as_array().cloned().unwrap_or_default();
+                    let mut retry = Vec::new();
+                    let mut failed = 0usize;
+                    for (item, rec) in items.iter().zip(pending.iter()) {
+                        let status = item["index"]["status"].as_u64().unwrap_or(0);
+                        if status == 429 || status >= 500 {
+                            retry.push(*rec);
+                        } else if status >= 300 {
+                            failed += 1;
+                            debug!("es_sink: rejected rec#{}: {}", rec.record_number, item["index"]["error"]);
+                        }
+                    }
+                    if failed > 0 {
+                        warn!("es_sink: {} documents rejected by the cluster", failed);
+                    }
+                    if retry.is_empty() {
+                        return Ok(());
+                    }
+                    let reason = format!("{} documents throttled", retry.len());
+                    pending = retry;
+                    reason
+                }
+            };
+
+            if attempt >= self.opts.retries {
+                return Err(AppError::IO(format!(
+                    "elasticsearch bulk failed after {} attempts ({})",
+                    attempt + 1,
+                    retry_reason
+                )));
+            }
+            attempt += 1;
+            warn!("es_sink: {}; retrying in {:?} ({}/{})", retry_reason, backoff, attempt, self.opts.retries);
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(Duration::from_secs(30));
+        }
+    }
+}
+
+pub async fn write_es_stream(
+    sink: &EsSink,
+    source: &Path,
+    mut rx: Receiver<RadioRecord>,
+    stats: Arc<PipelineStats>,
+) -> Result<(), AppError> {
+    let _timer = stats.sink.timer();
+    let source = source.display().to_string();
+    let mut batch: Vec<RadioRecord> = Vec::with_capacity(sink.opts.batch);
+    let mut count: usize = 0;
+
+    while let Some(r) = stats.sink.recv(&mut rx).await {
+        stats.sink.inc_in();
+        batch.push(r);
+        // Flush on a full batch, or when the stream goes idle (follow mode).
+        if batch.len() >= sink.opts.batch || (sink.opts.flush_when_idle && rx.is_empty()) {
+            sink.send_batch(&batch, &source).await?;
+            count += batch.len();
+            stats.sink.add_out(batch.len() as u64);
+            batch.clear();
+        }
+    }
+    if !batch.is_empty() {
+        sink.send_batch(&batch, &source).await?;
+        count += batch.len();
+        stats.sink.add_out(batch.len() as u64);
+    }
+
+    info!("Elasticsearch indexed {} documents ({}-*)", count, sink.opts.index_prefix);
+    Ok(())
+}
@@ -1,14 +1,15 @@
+use crate::affiliation;
 use crate::errors::AppError;
-use crate::model::{RadioRecord, SlotData};
-use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone};
+use crate::input::{self, NumberedLines, ParseNote, ParseOptions, TimeOnlyClock};
+use crate::model::{CallType, RadioRecord, SlotData};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, NaiveTime, TimeZone};
 use log::{debug, trace, warn};
 use std::path::Path;
-use tokio::fs::File;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 
 #[inline]
-fn strip_bom<'a>(s: &'a str) -> &'a str {
+fn strip_bom(s: &str) -> &str {
     s.strip_prefix('\u{FEFF}').unwrap_or(s)
 }
 
@@ -39,46 +40,96 @@ fn normalize_freq(s: &str) -> String {
     }
 }
 
-/// Parse one event line if it is a "Group call;" line.
-/// Returns a fully-populated RadioRecord (duration set), or None for non-call / noise lines.
-fn parse_event_line(
-    line: &str,
-    record_number: usize,
-    tz_offset: Option<FixedOffset>,
-) -> Result<Option<RadioRecord>, AppError> {
-    let s = strip_bom(line).trim();
-    if s.is_empty() {
-        return Ok(None);
-    }
-    // Only keep group call lines
-    if !s.contains("Group call;") {
-        return Ok(None);
-    }
+/// Why a line produced no record.
+enum Skip {
+    Blank,
+    /// Not a call line (trace-level noise).
+    Noise(&'static str),
+    /// Looked like a call but couldn't be parsed.
+    Bad(String),
+}
 
+/// Date and time at the start of a line.
+fn line_datetime(
+    s: &str,
+    tz_offset: Option<FixedOffset>,
+    clock: Option<&mut TimeOnlyClock>,
+) -> Result<DateTime<FixedOffset>, Skip> {
     // Tokenize coarsely; first two tokens should be date and time.
     // Example:
     // 2025/09/09  18:39:20  Freq=153.450000  NAC=293  Group call; TG=2  RID=4506   Pri0  7s
+    // Time-only logs ("18:39:20  Freq=...") are accepted when a base date is known.
     let mut parts = s.split_whitespace();
 
     let date_tok = match parts.next() {
         Some(t) => t,
-        None => return Ok(None),
-    };
-    let time_tok = match parts.next() {
-        Some(t) => t,
-        None => return Ok(None),
+        None => return Err(Skip::Blank),
     };
 
-    // Compose naive datetime
-    let dt_str = format!("{} {}", date_tok, time_tok);
-    let naive = match NaiveDateTime::parse_from_str(&dt_str, "%Y/%m/%d %H:%M:%S") {
-        Ok(ndt) => ndt,
-        Err(_) => {
-            debug!("event: bad datetime '{}'", dt_str);
-            return Ok(None);
+    let naive = match (NaiveTime::parse_from_str(date_tok, "%H:%M:%S"), clock) {
+        (Ok(t), Some(clock)) => clock.resolve(t),
+        _ => {
+            let time_tok = match parts.next() {
+                Some(t) => t,
+                None => return Err(Skip::Bad(format!("no time after {:?}", date_tok))),
+            };
+
+            // Compose naive datetime
+            let dt_str = format!("{} {}", date_tok, time_tok);
+            match NaiveDateTime::parse_from_str(&dt_str, "%Y/%m/%d %H:%M:%S") {
+                Ok(ndt) => ndt,
+                Err(_) => {
+                    debug!("event: bad datetime '{}'", dt_str);
+                    return Err(Skip::Bad(format!("datetime {:?} is not YYYY/MM/DD HH:MM:SS", dt_str)));
+                }
+            }
         }
     };
-    let datetime = apply_tz(naive, tz_offset)?;
+    apply_tz(naive, tz_offset).map_err(|e| Skip::Bad(e.to_string()))
+}
+
+/// Parse a registration/affiliation line (`--affiliations`); `None` for
+/// anything else.
+fn parse_affiliation_line(
+    line: &str,
+    tz_offset: Option<FixedOffset>,
+    clock: Option<&mut TimeOnlyClock>,
+) -> Option<affiliation::Event> {
+    let s = strip_bom(line).trim();
+    let kind = affiliation::EventKind::detect(s)?;
+    let value = |key: &str| {
+        s.find(key)
+            .and_then(|i| s[i + key.len()..].split_whitespace().next())
+            .map(|v| v.trim_end_matches([';', ',']).to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let rid = value("RID=")?;
+    let tg = value("TG=");
+    if kind == affiliation::EventKind::Affiliation && tg.is_none() {
+        return None;
+    }
+    let datetime = line_datetime(s, tz_offset, clock).ok()?;
+    Some(affiliation::Event { datetime, kind, rid, tg })
+}
+
+/// Parse one event line if it is a call line ("Group call;", "Private call;", ...).
+/// Returns a fully-populated RadioRecord (duration set), or why the line was skipped.
+fn parse_event_line(
+    line: &str,
+    record_number: usize,
+    tz_offset: Option<FixedOffset>,
+    clock: Option<&mut TimeOnlyClock>,
+) -> Result<RadioRecord, Skip> {
+    let s = strip_bom(line).trim();
+    if s.is_empty() {
+        return Err(Skip::Blank);
+    }
+    // Only keep call lines
+    if !s.contains("call;") {
+        return Err(Skip::Noise("not a call line (no \"call;\")"));
+    }
+    let datetime = line_datetime(s, tz_offset, clock)?;
+
 
     // We’ll scan the rest of the line with simple substring searches.
     // Extract Freq
@@ -88,24 +139,14 @@ fn parse_event_line(
         if tok.is_empty() { None } else { Some(normalize_freq(tok)) }
     });
 
-    // Extract NAC or DCC (mutually exclusive in these examples)
-    let mut dcc_or_nac: Option<String> = None;
-    if let Some(i) = s.find("NAC=") {
-        let tail = &s[i + 4..];
-        let tok = tail.split_whitespace().next().unwrap_or("");
-        if !tok.is_empty() {
-            dcc_or_nac = Some(tok.to_string());
-        }
-    }
-    if dcc_or_nac.is_none() {
-        if let Some(i) = s.find("DCC=") {
-            let tail = &s[i + 4..];
-            let tok = tail.split_whitespace().next().unwrap_or("");
-            if !tok.is_empty() {
-                dcc_or_nac = Some(tok.to_string());
-            }
-        }
-    }
+    // Extract NAC (P25) and DCC (DMR)
+    let value = |key: &str| {
+        s.find(key)
+            .and_then(|i| s[i + key.len()..].split_whitespace().next())
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+    let (nac, dcc) = (value("NAC="), value("DCC="));
 
     // Infer radio type based on NAC vs DCC
     // (We keep names consistent with your SRT parser sans leading '+')
@@ -128,6 +169,13 @@ fn parse_event_line(
         let tok = tail.split_whitespace().next().unwrap_or("");
         if tok.is_empty() { None } else { Some(tok.to_string()) }
     });
+    let target = s.find("TGT=").and_then(|i| {
+        let tail = &s[i + 4..];
+        let tok = tail.split_whitespace().next().unwrap_or("");
+        if tok.is_empty() { None } else { Some(tok.to_string()) }
+    });
+
+    let (errors, quality) = input::signal_quality(s);
 
     // Optional Slot=1/2 (on DMR only)
     let slot = s.find("Slot=").and_then(|i| {
@@ -147,8 +195,8 @@ fn parse_event_line(
     }
 
     // Fill slot fields
-    let mut slot1 = SlotData { tg: None, rid: None, text: None };
-    let mut slot2 = SlotData { tg: None, rid: None, text: None };
+    let mut slot1 = SlotData::default();
+    let mut slot2 = SlotData::default();
     match (radio_type.as_deref(), slot) {
         (Some("DMR"), Some(1)) => {
             slot1.tg = tg.clone();
@@ -165,48 +213,87 @@ fn parse_event_line(
         }
     }
 
-    let rec = RadioRecord {
-        record_number,
-        datetime,
+    let mut rec = RadioRecord {
         frequency: freq,
         radio_type,
-        dcc: dcc_or_nac, // NAC or DCC captured here
+        nac,
+        dcc,
         slot1,
         slot2,
+        call_type: CallType::detect(s).unwrap_or_default(),
+        encrypted: input::encrypted(s),
+        target_rid: target,
+        errors,
+        quality,
         duration,
+        ..RadioRecord::new(record_number, datetime)
     };
+    rec.map_private_target();
 
-    Ok(Some(rec))
+    Ok(rec)
+}
+
+/// Whether the start of a file looks like an event log: a line starting
+/// with a `YYYY/MM/DD` date that has a `Freq=` token.
+pub fn sniff(head: &str) -> bool {
+    head.lines().take(20).map(|l| strip_bom(l.trim())).any(|l| {
+        let b = l.as_bytes();
+        b.len() > 10 && b[..4].iter().all(u8::is_ascii_digit) && b[4] == b'/' && l.contains("Freq=")
+    })
 }
 
 pub async fn stream_file(
     path: &Path,
-    tz_offset: Option<FixedOffset>,
+    opts: ParseOptions,
     tx: Sender<RadioRecord>,
 ) -> Result<(), AppError> {
-    let file = File::open(path)
-        .await
-        .map_err(|e| AppError::IO(format!("open {}: {}", path.display(), e)))?;
-    let mut lines = BufReader::new(file).lines();
+    let file = input::open_input(path, &opts).await?;
+    let mut lines = NumberedLines::new(file, &opts);
 
     let mut recno: usize = 1;
+    let mut clock = opts.base_date.map(TimeOnlyClock::new);
+    let source = input::source_name(path);
 
     while let Some(line) = lines.next_line().await? {
-        match parse_event_line(&line, recno, tz_offset) {
-            Ok(Some(rec)) => {
-                // Only send valid “Group call;” lines
+        match parse_event_line(&line, recno, opts.tz_offset, clock.as_mut()) {
+            Ok(mut rec) => {
+                // Only send valid “... call;” lines
+                rec.source = Some(Arc::clone(&source));
+                opts.finish(&mut rec);
+                if let Some(tracker) = &opts.affiliations
+                    && let Some(rid) = rec.slot1.rid.as_deref().or(rec.slot2.rid.as_deref())
+                {
+                    rec.affiliated_tg = tracker.lock().current(rid);
+                }
+                if opts.extras {
+                    input::collect_extras(&line, &mut rec.extras);
+                }
+                opts.note(ParseNote::Record { line: lines.line });
                 if tx.send(rec).await.is_err() {
                     warn!("event_stream: downstream closed; aborting");
                     break;
                 }
                 recno = recno.saturating_add(1);
             }
-            Ok(None) => {
-                // Non-call / noise; skip silently
-                trace!("event_stream: skipped line");
+            Err(Skip::Blank) => {}
+            Err(Skip::Noise(reason)) => {
+                let event = match &opts.affiliations {
+                    Some(_) => parse_affiliation_line(&line, opts.tz_offset, clock.as_mut()),
+                    None => None,
+                };
+                if let (Some(tracker), Some(ev)) = (&opts.affiliations, event) {
+                    trace!("event_stream: {} of RID {}", ev.kind.as_str(), ev.rid);
+                    tracker.lock().record(ev);
+                    opts.note(ParseNote::Skipped { line: lines.line, reason: "registration/affiliation event".into() });
+                } else {
+                    // Non-call / noise; skip silently
+                    trace!("event_stream: skipped line");
+                    opts.note(ParseNote::Skipped { line: lines.line, reason: reason.to_string() });
+                }
             }
-            Err(e) => {
-                debug!("event_stream: parse error: {}", e);
+            Err(Skip::Bad(reason)) => {
+                debug!("event_stream: parse error: {}", reason);
+                opts.note(ParseNote::Skipped { line: lines.line, reason });
             }
         }
     }
@@ -0,0 +1,155 @@
+// src/explain.rs
+//
+// `callscribe explain <file>`: run the normal parser over the start of a file
+// and print, next to the source lines, what each block or line became: the
+// fields of the record it produced, or why it was skipped.
+
+use crate::errors::AppError;
+use crate::formats;
+use crate::input::{self, ParseNote, ParseOptions};
+use crate::model::{RadioRecord, SlotData};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExplainSummary {
+    pub records: usize,
+    pub skipped: usize,
+}
+
+/// Source lines read on demand; notes arrive in line order.
+struct Source {
+    lines: std::io::Lines<BufReader<std::fs::File>>,
+    next: usize,
+}
+
+impl Source {
+    fn next_line(&mut self) -> Option<(usize, String)> {
+        let l = self.lines.next()?.ok()?;
+        self.next += 1;
+        Some((self.next - 1, l))
+    }
+
+    /// Line `from` (1-based) and, for multi-line formats, the rest of its
+    /// block up to the next blank line. Lines before `from` are skipped.
+    fn block(&mut self, from: usize, multiline: bool) -> Vec<(usize, String)> {
+        while self.next < from {
+            if self.next_line().is_none() {
+                return vec![];
+            }
+        }
+        let mut out = Vec::new();
+        if self.next == from
+            && let Some(first) = self.next_line()
+        {
+            out.push(first);
+            if multiline {
+                while let Some((n, l)) = self.next_line() {
+                    if l.trim().is_empty() {
+                        break;
+                    }
+                    out.push((n, l));
+                }
+            }
+        }
+        out
+    }
+}
+
+fn slot(s: &SlotData) -> String {
+    format!("TG {}  RID {}", s.tg.as_deref().unwrap_or("-"), s.rid.as_deref().unwrap_or("-"))
+}
+
+fn describe(rec: &RadioRecord) -> Vec<String> {
+    let opt = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".into());
+    let mut out = vec![
+        format!("record #{} at {}", rec.record_number, rec.datetime.format("%Y-%m-%d %H:%M:%S %:z")),
+        format!(
+            "frequency {}  type {}  NAC {}  DCC {}  duration {}",
+            opt(&rec.frequency),
+            opt(&rec.radio_type),
+            opt(&rec.nac),
+            opt(&rec.dcc),
+            rec.duration
+        ),
+        format!("{} call{}", rec.call_type.as_str(), rec.target_rid.as_ref().map_or(String::new(), |t| format!(" to unit {}", t))),
+    ];
+    if rec.errors.is_some() || rec.quality.is_some() {
+        let num = |v: Option<u32>| v.map_or_else(|| "-".to_string(), |n| n.to_string());
+        out.push(format!("errors {}  quality {}%", num(rec.errors), num(rec.quality)));
+    }
+    out.push(format!("slot 1: {}", slot(&rec.slot1)));
+    if rec.slot2.tg.is_some() || rec.slot2.rid.is_some() {
+        out.push(format!("slot 2: {}", slot(&rec.slot2)));
+    }
+    out
+}
+
+/// Explain the first `blocks` records/skipped blocks of `path` into `out`.
+pub async fn explain(
+    path: &Path,
+    mut opts: ParseOptions,
+    blocks: usize,
+    out: &mut dyn Write,
+) -> Result<ExplainSummary, AppError> {
+    let io = |e: std::io::Error| AppError::IO(format!("explain: {}", e));
+    let format = formats::for_path(path, opts.format);
+    let info = format.info();
+    writeln!(out, "{}: {} format — {}", path.display(), info.name, info.description).map_err(io)?;
+    if info.name == "uniden" || path.is_dir() {
+        return Err(AppError::Other(format!("explain reads text logs, not recordings ({})", path.display())));
+    }
+
+    let file = std::fs::File::open(path).map_err(|e| AppError::IO(format!("open {}: {}", path.display(), e)))?;
+    let mut src = Source { lines: BufReader::new(file).lines(), next: 1 };
+
+    let (ntx, mut notes) = mpsc::unbounded_channel();
+    opts.notes = Some(ntx);
+    opts.follow = false;
+    let (tx, mut records) = mpsc::channel::<RadioRecord>(16);
+    let p = path.to_path_buf();
+    let parser = tokio::spawn(async move { input::stream_input(&p, opts, tx).await });
+
+    let mut summary = ExplainSummary::default();
+    let show = |src: &mut Source, line: usize, out: &mut dyn Write| -> Result<(), AppError> {
+        writeln!(out).map_err(io)?;
+        for (n, l) in src.block(line, info.multiline) {
+            writeln!(out, "{:>6} | {}", n, l).map_err(io)?;
+        }
+        Ok(())
+    };
+    while summary.records + summary.skipped < blocks {
+        let Some(note) = notes.recv().await else { break };
+        match note {
+            ParseNote::Record { line } => {
+                let Some(rec) = records.recv().await else { break };
+                show(&mut src, line, out)?;
+                for d in describe(&rec) {
+                    writeln!(out, "       -> {}", d).map_err(io)?;
+                }
+                summary.records += 1;
+            }
+            ParseNote::Skipped { line, reason } => {
+                show(&mut src, line, out)?;
+                writeln!(out, "       -> skipped: {}", reason).map_err(io)?;
+                summary.skipped += 1;
+            }
+            ParseNote::Ignored { line, reason } => {
+                writeln!(out, "       -> line {} ignored: {}", line, reason).map_err(io)?;
+            }
+        }
+    }
+    // Lines ignored inside the last record are already queued.
+    while let Ok(ParseNote::Ignored { line, reason }) = notes.try_recv() {
+        writeln!(out, "       -> line {} ignored: {}", line, reason).map_err(io)?;
+    }
+    parser.abort();
+    match parser.await {
+        Ok(res) => res?,
+        Err(e) if e.is_cancelled() => {}
+        Err(e) => return Err(AppError::Other(format!("explain join: {e}"))),
+    }
+    writeln!(out, "\n{} records, {} skipped", summary.records, summary.skipped).map_err(io)?;
+    Ok(summary)
+}
@@ -0,0 +1,248 @@
+// src/external_stt.rs
+//
+// STT backends declared in the config file, for engines without built-in
+// support (Vosk, a faster-whisper server, a hosted API behind a proxy):
+//
+//   [transcriber.vosk]
+//   command = ["vosk-transcriber", "-i", "{audio}", "-l", "{language}"]
+//
+//   [transcriber.asr]
+//   url = "http://127.0.0.1:9000/asr"
+//
+// Either way the call's recording is found like whisper's (the input's own
+// `audio_path`, else the Record directory index). A command gets the
+// recording's path and prints the transcript on standard output. An HTTP
+// backend (feature `stt-http`) receives the recording as the POST body,
+// with a `Content-Type` from the extension and `?language=` when a hint
+// applies, and answers with the transcript as plain text or as JSON with a
+// `text` field. The `[stt]` prompt and vocabulary (see `SttConfig::prompt`)
+// go in as `{prompt}` (empty without one) or `?prompt=`.
+
+use crate::config::{ExternalTranscriberConfig, LanguageHints, SttConfig};
+use crate::errors::AppError;
+use crate::freq::FreqTolerance;
+use crate::model::RadioRecord;
+use crate::transcriber::{AUDIO_EXTENSIONS, RecordFileIndex, Transcriber, choose_root};
+use crate::winpath;
+use log::debug;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+enum Backend {
+    /// Program followed by its arguments, placeholders unexpanded.
+    Command(Vec<String>),
+    #[cfg(feature = "stt-http")]
+    Http { client: reqwest::Client, url: reqwest::Url },
+}
+
+pub struct ExternalTranscriber {
+    name: String,
+    root: PathBuf,
+    backend: Backend,
+    languages: LanguageHints,
+    prompt: Option<String>,
+    audio: RecordFileIndex,
+}
+
+#[cfg(feature = "stt-http")]
+fn http_backend(name: &str, url: &str, timeout_s: Option<u64>) -> Result<Backend, AppError> {
+    let url = reqwest::Url::parse(url)
+        .map_err(|e| AppError::Parse(format!("[transcriber.{}] url '{}': {}", name, url, e)))?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(timeout_s.unwrap_or(120)))
+        .build()
+        .map_err(|e| AppError::Other(format!("http client: {}", e)))?;
+    Ok(Backend::Http { client, url })
+}
+
+#[cfg(not(feature = "stt-http"))]
+fn http_backend(name: &str, _url: &str, _timeout_s: Option<u64>) -> Result<Backend, AppError> {
+    Err(AppError::Other(format!(
+        "[transcriber.{}] url needs a build with the stt-http feature; use command instead",
+        name
+    )))
+}
+
+/// MIME type announced for a recording.
+#[cfg(feature = "stt-http")]
+fn content_type(audio: &Path) -> &'static str {
+    let ext = audio.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    match ext.as_str() {
+        "wav" => "audio/wav",
+        "mp3" => "audio/mpeg",
+        "flac" => "audio/flac",
+        "ogg" => "audio/ogg",
+        "m4a" => "audio/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The transcript in a response body: its `text` field if it is a JSON
+/// object, else the body itself.
+#[cfg(feature = "stt-http")]
+fn response_text(body: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(o)) => o.get("text").and_then(|t| t.as_str()).unwrap_or("").to_string(),
+        _ => body.to_string(),
+    }
+}
+
+/// `program` is a file, or (a bare name) found on `PATH`.
+fn program_exists(program: &str) -> bool {
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return path.is_file();
+    }
+    let names: Vec<String> = if cfg!(windows) && path.extension().is_none() {
+        ["exe", "cmd", "bat"].iter().map(|ext| format!("{}.{}", program, ext)).collect()
+    } else {
+        vec![program.to_string()]
+    };
+    std::env::var_os("PATH")
+        .is_some_and(|p| std::env::split_paths(&p).any(|dir| names.iter().any(|n| dir.join(n).is_file())))
+}
+
+impl ExternalTranscriber {
+    pub fn new(
+        name: &str,
+        cfg: &ExternalTranscriberConfig,
+        root: &Path,
+        stt: &SttConfig,
+        audio_extensions: Option<&[String]>,
+        freq_tolerance: FreqTolerance,
+    ) -> Result<Self, AppError> {
+        let backend = match (&cfg.command, &cfg.url) {
+            (Some(argv), None) if !argv.is_empty() && !argv[0].trim().is_empty() => Backend::Command(argv.clone()),
+            (Some(_), None) => {
+                return Err(AppError::Parse(format!("[transcriber.{}] command is empty", name)));
+            }
+            (None, Some(url)) => http_backend(name, url, cfg.timeout_s)?,
+            _ => {
+                return Err(AppError::Parse(format!("[transcriber.{}] needs exactly one of command or url", name)));
+            }
+        };
+        let audio = match audio_extensions {
+            Some(exts) => RecordFileIndex::new(exts),
+            None => RecordFileIndex::new(AUDIO_EXTENSIONS),
+        }
+        .with_freq_tolerance(freq_tolerance);
+        Ok(Self {
+            name: name.to_string(),
+            root: root.to_path_buf(),
+            backend,
+            languages: stt.language.clone(),
+            prompt: stt.prompt(),
+            audio,
+        })
+    }
+
+    fn run_command(&self, argv: &[String], audio: &Path, language: &str) -> Result<String, AppError> {
+        let path = audio.to_string_lossy();
+        let prompt = self.prompt.as_deref().unwrap_or("");
+        let mut args: Vec<String> = argv[1..]
+            .iter()
+            .map(|a| a.replace("{audio}", &path).replace("{language}", language).replace("{prompt}", prompt))
+            .collect();
+        if !argv[1..].iter().any(|a| a.contains("{audio}")) {
+            args.push(path.into_owned());
+        }
+        let out = Command::new(&argv[0])
+            .args(&args)
+            .output()
+            .map_err(|e| AppError::IO(format!("[transcriber.{}] run {}: {}", self.name, argv[0], e)))?;
+        if !out.status.success() {
+            return Err(AppError::Other(format!(
+                "[transcriber.{}] {} failed on {} ({}): {}",
+                self.name,
+                argv[0],
+                winpath::simplified(audio).display(),
+                out.status,
+                String::from_utf8_lossy(&out.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+    }
+
+    #[cfg(feature = "stt-http")]
+    fn post(
+        &self,
+        client: &reqwest::Client,
+        url: &reqwest::Url,
+        audio: &Path,
+        language: Option<&str>,
+    ) -> Result<String, AppError> {
+        let shown = winpath::simplified(audio);
+        let body = std::fs::read(audio).map_err(|e| AppError::IO(format!("read {}: {}", shown.display(), e)))?;
+        let mut url = url.clone();
+        if let Some(lang) = language {
+            url.query_pairs_mut().append_pair("language", lang);
+        }
+        if let Some(prompt) = &self.prompt {
+            url.query_pairs_mut().append_pair("prompt", prompt);
+        }
+        // Transcription runs on a blocking thread of the pipeline's runtime.
+        let handle = tokio::runtime::Handle::try_current()
+            .map_err(|e| AppError::Other(format!("[transcriber.{}] no runtime: {}", self.name, e)))?;
+        let request = client.post(url).header(reqwest::header::CONTENT_TYPE, content_type(audio)).body(body);
+        let text = handle
+            .block_on(async { request.send().await?.error_for_status()?.text().await })
+            .map_err(|e| AppError::Other(format!("[transcriber.{}] {}: {}", self.name, shown.display(), e)))?;
+        Ok(response_text(&text))
+    }
+}
+
+impl Transcriber for ExternalTranscriber {
+    fn transcribe(
+        &self,
+        rec: &RadioRecord,
+        record_dir: &Path,
+    ) -> Result<Option<String>, Option<AppError>> {
+        let audio = match &rec.audio_path {
+            Some(path) => path.clone(),
+            None => {
+                let root = choose_root(record_dir, &self.root).map_err(Some)?;
+                let Some(audio) = self.audio.find(rec, root).map_err(Some)? else {
+                    return Ok(None);
+                };
+                audio
+            }
+        };
+        let language = self.languages.for_record(rec);
+        let raw = match &self.backend {
+            Backend::Command(argv) => self.run_command(argv, &audio, language.unwrap_or("auto")),
+            #[cfg(feature = "stt-http")]
+            Backend::Http { client, url } => self.post(client, url, &audio, language),
+        }
+        .map_err(Some)?;
+        debug!(
+            "{}: rec#{} {} [{}]",
+            self.name,
+            rec.record_number,
+            winpath::simplified(&audio).display(),
+            language.unwrap_or("auto")
+        );
+        let text = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+        Ok(if text.is_empty() { None } else { Some(text) })
+    }
+
+    /// The command's program exists, or the endpoint answers at all (any
+    /// status will do; only the recording POST has to succeed).
+    fn health(&self) -> Result<(), AppError> {
+        match &self.backend {
+            Backend::Command(argv) if !program_exists(&argv[0]) => Err(AppError::Other(format!(
+                "[transcriber.{}] program '{}' not found",
+                self.name, argv[0]
+            ))),
+            Backend::Command(_) => Ok(()),
+            #[cfg(feature = "stt-http")]
+            Backend::Http { client, url } => {
+                let handle = tokio::runtime::Handle::try_current()
+                    .map_err(|e| AppError::Other(format!("[transcriber.{}] no runtime: {}", self.name, e)))?;
+                handle
+                    .block_on(client.get(url.clone()).send())
+                    .map(|_| ())
+                    .map_err(|e| AppError::Other(format!("[transcriber.{}] {} does not respond: {}", self.name, url, e)))
+            }
+        }
+    }
+}
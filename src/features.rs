@@ -0,0 +1,48 @@
+// src/features.rs
+//
+// Optional backends are gated behind cargo features so the default build
+// stays small. Every feature declared in Cargo.toml gets an entry here so
+// `--version` can report what this binary was built with.
+
+/// Cargo features compiled into this build.
+pub const COMPILED: &[&str] = &[
+    #[cfg(feature = "ffi")]
+    "ffi",
+    #[cfg(feature = "gui")]
+    "gui",
+    #[cfg(feature = "sink-postgres")]
+    "sink-postgres",
+    #[cfg(feature = "sink-elasticsearch")]
+    "sink-elasticsearch",
+    #[cfg(feature = "sink-sqlite")]
+    "sink-sqlite",
+    #[cfg(feature = "sink-template")]
+    "sink-template",
+    #[cfg(feature = "stt-whisper")]
+    "stt-whisper",
+    #[cfg(feature = "stt-segments")]
+    "stt-segments",
+    #[cfg(feature = "stt-http")]
+    "stt-http",
+    #[cfg(feature = "models-download")]
+    "models-download",
+    #[cfg(feature = "audio-fingerprint")]
+    "audio-fingerprint",
+    #[cfg(feature = "proptests")]
+    "proptests",
+];
+
+/// One-line version banner, e.g. `dsd_event_parser 0.1.0 (features: ffi)`.
+pub fn version_string() -> String {
+    let features = if COMPILED.is_empty() {
+        "none".to_string()
+    } else {
+        COMPILED.join(", ")
+    };
+    format!(
+        "{} {} (features: {})",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        features
+    )
+}
@@ -0,0 +1,198 @@
+// src/ffi.rs
+//
+// C ABI for reusing the parsers (and optionally the RLE stage) from C/C++
+// tooling. The matching declarations live in `include/callscribe.h`.
+
+use crate::model::RadioRecord;
+use crate::input::{self, ParseOptions};
+use crate::stats::PipelineStats;
+use crate::{rle_filter, tz};
+use std::ffi::{CStr, CString, c_char, c_int, c_void};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+pub const CALLSCRIBE_OK: c_int = 0;
+pub const CALLSCRIBE_STOPPED: c_int = 1;
+pub const CALLSCRIBE_ERR_ARG: c_int = -1;
+pub const CALLSCRIBE_ERR_PARSE: c_int = -2;
+pub const CALLSCRIBE_ERR_RUNTIME: c_int = -3;
+pub const CALLSCRIBE_ERR_PANIC: c_int = -4;
+
+/// One parsed record as seen from C. String fields are NUL-terminated and
+/// NULL when absent; all pointers are only valid for the duration of the
+/// callback invocation.
+#[repr(C)]
+pub struct CallscribeRecord {
+    pub record_number: u64,
+    /// Unix timestamp (seconds, UTC).
+    pub timestamp: i64,
+    /// Offset of the record's local time from UTC, in seconds east.
+    pub utc_offset: i32,
+    pub duration: u32,
+    pub frequency: *const c_char,
+    pub radio_type: *const c_char,
+    /// NAC or DCC, whichever the record has.
+    pub dcc: *const c_char,
+    pub slot1_tg: *const c_char,
+    pub slot1_rid: *const c_char,
+    pub slot2_tg: *const c_char,
+    pub slot2_rid: *const c_char,
+    /// `RadioRecord::record_id`, as written by the sinks.
+    pub record_id: *const c_char,
+    /// `CallType::as_str`.
+    pub call_type: *const c_char,
+    pub target_rid: *const c_char,
+    /// -1 when the log doesn't report it.
+    pub errors: i32,
+    /// Percent, -1 when the log doesn't report it.
+    pub quality: i32,
+    /// P25 NAC only.
+    pub nac: *const c_char,
+    /// DMR color code only.
+    pub color_code: *const c_char,
+}
+
+/// Return non-zero to stop parsing early.
+pub type CallscribeRecordCallback =
+    extern "C" fn(rec: *const CallscribeRecord, user_data: *mut c_void) -> c_int;
+
+/// Owns the C strings backing a `CallscribeRecord` while the callback runs.
+struct OwnedStrings {
+    fields: [Option<CString>; 12],
+}
+
+impl OwnedStrings {
+    fn from_record(r: &RadioRecord) -> Self {
+        let c = |s: &Option<String>| s.as_deref().and_then(|v| CString::new(v).ok());
+        Self {
+            fields: [
+                c(&r.frequency),
+                c(&r.radio_type),
+                c(&r.nac_or_dcc().map(str::to_string)),
+                c(&r.slot1.tg),
+                c(&r.slot1.rid),
+                c(&r.slot2.tg),
+                c(&r.slot2.rid),
+                c(&Some(r.record_id())),
+                c(&Some(r.call_type.as_str().to_string())),
+                c(&r.target_rid),
+                c(&r.nac),
+                c(&r.dcc),
+            ],
+        }
+    }
+
+    fn ptr(&self, i: usize) -> *const c_char {
+        self.fields[i].as_ref().map_or(ptr::null(), |s| s.as_ptr())
+    }
+}
+
+/// Parse `path` (SRT or event log, chosen by extension) and invoke `callback`
+/// once per record.
+///
+/// - `tz` is an optional IANA timezone name (NULL = local time).
+/// - `rle` non-zero runs records through the RLE compressor first.
+///
+/// Returns `CALLSCRIBE_OK`, `CALLSCRIBE_STOPPED` if the callback asked to
+/// stop, or a negative `CALLSCRIBE_ERR_*` code.
+///
+/// # Safety
+/// `path` must be a valid NUL-terminated string, `tz` must be NULL or a valid
+/// NUL-terminated string, and `callback` must be safe to call with `user_data`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn callscribe_parse_file(
+    path: *const c_char,
+    tz: *const c_char,
+    rle: c_int,
+    callback: Option<CallscribeRecordCallback>,
+    user_data: *mut c_void,
+) -> c_int {
+    let Some(callback) = callback else { return CALLSCRIBE_ERR_ARG; };
+    if path.is_null() {
+        return CALLSCRIBE_ERR_ARG;
+    }
+    let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else { return CALLSCRIBE_ERR_ARG; };
+    let tz_name = if tz.is_null() {
+        None
+    } else {
+        match unsafe { CStr::from_ptr(tz) }.to_str() {
+            Ok(s) => Some(s.to_string()),
+            Err(_) => return CALLSCRIBE_ERR_ARG,
+        }
+    };
+    let path = PathBuf::from(path);
+
+    catch_unwind(AssertUnwindSafe(|| {
+        parse_file_blocking(path, tz_name, rle != 0, callback, user_data)
+    }))
+    .unwrap_or(CALLSCRIBE_ERR_PANIC)
+}
+
+fn parse_file_blocking(
+    path: PathBuf,
+    tz_name: Option<String>,
+    rle: bool,
+    callback: CallscribeRecordCallback,
+    user_data: *mut c_void,
+) -> c_int {
+    let rt = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+        Ok(rt) => rt,
+        Err(_) => return CALLSCRIBE_ERR_RUNTIME,
+    };
+
+    rt.block_on(async move {
+        let opts = ParseOptions { tz_offset: tz::compute_tz_offset(&tz_name), ..Default::default() };
+
+        let (tx_parse, rx_parse) = mpsc::channel::<RadioRecord>(1024);
+        let producer = tokio::spawn(async move { input::stream_input(&path, opts, tx_parse).await });
+
+        let mut rx = if rle {
+            let (tx_rle, rx_rle) = mpsc::channel::<RadioRecord>(1024);
+            tokio::spawn(rle_filter::rle_compress_stream(rx_parse, tx_rle, Default::default(), Arc::new(PipelineStats::default()), None));
+            rx_rle
+        } else {
+            rx_parse
+        };
+
+        // The callback runs on this thread only; dropping `rx` on early stop
+        // closes the channels and lets the producer wind down.
+        let mut status = CALLSCRIBE_OK;
+        while let Some(r) = rx.recv().await {
+            let strings = OwnedStrings::from_record(&r);
+            let rec = CallscribeRecord {
+                record_number: r.record_number as u64,
+                timestamp: r.datetime.timestamp(),
+                utc_offset: r.datetime.offset().local_minus_utc(),
+                duration: r.duration,
+                frequency: strings.ptr(0),
+                radio_type: strings.ptr(1),
+                dcc: strings.ptr(2),
+                slot1_tg: strings.ptr(3),
+                slot1_rid: strings.ptr(4),
+                slot2_tg: strings.ptr(5),
+                slot2_rid: strings.ptr(6),
+                record_id: strings.ptr(7),
+                call_type: strings.ptr(8),
+                target_rid: strings.ptr(9),
+                errors: r.errors.map_or(-1, |e| e.min(i32::MAX as u32) as i32),
+                quality: r.quality.map_or(-1, |q| q as i32),
+                nac: strings.ptr(10),
+                color_code: strings.ptr(11),
+            };
+            if callback(&rec, user_data) != 0 {
+                status = CALLSCRIBE_STOPPED;
+                break;
+            }
+        }
+        drop(rx);
+
+        match producer.await {
+            Ok(Ok(())) => status,
+            Ok(Err(_)) => CALLSCRIBE_ERR_PARSE,
+            Err(_) => CALLSCRIBE_ERR_RUNTIME,
+        }
+    })
+}
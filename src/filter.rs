@@ -1,21 +1,33 @@
+use crate::budget::PipelineBudget;
+use crate::freq::FreqTolerance;
 use crate::model::RadioRecord;
+use crate::stats::PipelineStats;
 use tokio::sync::mpsc::{Receiver, Sender};
 use std::sync::Arc;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct FilterConfig {
     pub freqs: Vec<String>,
     pub rtypes: Vec<String>,
     pub rids: Vec<String>,
     pub tgs: Vec<String>,
+    /// P25 NACs; matched against `nac` only.
     pub nacs: Vec<String>,
+    /// DMR color codes; matched against `dcc` only.
+    pub dccs: Vec<String>,
+    /// Drop records reporting more decode errors than this.
+    pub max_errors: Option<u32>,
+    /// Drop records whose quality is below this percentage.
+    pub min_quality: Option<u32>,
+    /// How far a record's frequency may be from a `--freq` value.
+    pub freq_tolerance: FreqTolerance,
 }
 
 impl FilterConfig {
     pub fn accept(&self, r: &RadioRecord) -> bool {
         if !self.freqs.is_empty() {
             match &r.frequency {
-                Some(f) if self.freqs.iter().any(|q| q == f) => {}
+                Some(f) if self.freqs.iter().any(|q| self.freq_tolerance.matches(q, f)) => {}
                 _ => return false,
             }
         }
@@ -37,26 +49,55 @@ impl FilterConfig {
                 _ => return false,
             }
         }
+        // NACs are hex, written either case.
         if !self.nacs.is_empty() {
+            match &r.nac {
+                Some(n) if self.nacs.iter().any(|q| q.eq_ignore_ascii_case(n)) => {}
+                _ => return false,
+            }
+        }
+        if !self.dccs.is_empty() {
             match &r.dcc {
-                Some(d) if self.nacs.iter().any(|q| q == d) => {}
+                Some(d) if self.dccs.iter().any(|q| q == d) => {}
                 _ => return false,
             }
         }
+        // Records that don't report errors/quality pass.
+        if let (Some(max), Some(e)) = (self.max_errors, r.errors)
+            && e > max
+        {
+            return false;
+        }
+        if let (Some(min), Some(q)) = (self.min_quality, r.quality)
+            && q < min
+        {
+            return false;
+        }
         true
     }
 }
 
-/// Async stage: forwards only records that pass `cfg`.
+/// Async stage: forwards only records that pass `cfg`. With a budget, each
+/// record must get a permit before it is looked at.
 pub async fn filter_stream(
     cfg: Arc<FilterConfig>,
     mut rx: Receiver<RadioRecord>,
     tx: Sender<RadioRecord>,
+    stats: Arc<PipelineStats>,
+    budget: Option<Arc<PipelineBudget>>,
 ) {
-    while let Some(rec) = rx.recv().await {
+    let _timer = stats.filter.timer();
+    while let Some(rec) = stats.filter.recv(&mut rx).await {
+        stats.filter.inc_in();
+        if let Some(b) = &budget {
+            stats.filter.blocked_on(b.acquire()).await;
+        }
         if cfg.accept(&rec) {
             // Ignore send errors (downstream closed) → terminate.
-            if tx.send(rec).await.is_err() { break; }
+            if stats.filter.send(&tx, rec).await.is_err() { break; }
+            stats.filter.inc_out();
+        } else if let Some(b) = &budget {
+            b.release(1);
         }
     }
 }
@@ -0,0 +1,132 @@
+// src/fingerprint.rs
+//
+// Compact audio fingerprints for `--audio-dedup`, in the spirit of
+// chromaprint: the clip is cut into short overlapping frames, each frame's
+// energy is measured in 33 bands across the voice channel, and every frame
+// becomes 32 bits, one per pair of neighbouring bands: whether the energy
+// difference between them grew or shrank since the previous frame. The bits
+// survive level changes, resampling and lossy re-encoding, so two
+// recordings of the same transmission (a conventional channel and a trunk
+// trace of the same call) agree on most bits while unrelated audio agrees
+// on about half.
+//
+// Only WAV is decoded; other formats get no fingerprint.
+
+use crate::audio_prep;
+use crate::errors::AppError;
+use std::path::Path;
+
+/// Bands, log-spaced over the voice channel.
+const BANDS: usize = 33;
+/// Frequencies measured per band.
+const BAND_POINTS: usize = 4;
+const LOW_HZ: f32 = 300.0;
+const HIGH_HZ: f32 = 3400.0;
+/// Frame length and hop.
+const FRAME_MS: u32 = 128;
+const HOP_MS: u32 = 16;
+/// Recordings may start up to this far apart (in hops, about one second).
+const MAX_SHIFT: usize = 1000 / HOP_MS as usize;
+/// Frames two clips must overlap by to be compared at all.
+const MIN_OVERLAP: usize = 8;
+/// Share of differing bits below which two clips are the same audio.
+pub const DUPLICATE_BER: f32 = 0.25;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fingerprint {
+    frames: Vec<u32>,
+}
+
+/// Goertzel power of one frequency over a windowed frame.
+fn power(frame: &[f32], coeff: f32) -> f32 {
+    let (mut s1, mut s2) = (0.0f32, 0.0f32);
+    for &x in frame {
+        let s = x + coeff * s1 - s2;
+        s2 = s1;
+        s1 = s;
+    }
+    s1 * s1 + s2 * s2 - coeff * s1 * s2
+}
+
+impl Fingerprint {
+    /// Fingerprint of a recording; `None` for formats other than WAV and
+    /// for clips shorter than a couple of frames.
+    pub fn compute(path: &Path) -> Result<Option<Self>, AppError> {
+        let is_wav = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("wav"));
+        if !is_wav {
+            return Ok(None);
+        }
+        let (samples, rate) = audio_prep::read_mono(path)?;
+        Ok(Self::from_samples(&samples, rate))
+    }
+
+    pub fn from_samples(samples: &[f32], rate: u32) -> Option<Self> {
+        let frame_len = (rate * FRAME_MS / 1000) as usize;
+        let hop = (rate * HOP_MS / 1000) as usize;
+        if frame_len == 0 || hop == 0 || samples.len() < frame_len + hop {
+            return None;
+        }
+        let nyquist = rate as f32 / 2.0;
+        // Each band's energy is summed over a few frequencies spread across
+        // it, so a tone between two centres still counts.
+        let edge = |b: usize| LOW_HZ * (HIGH_HZ / LOW_HZ).powf(b as f32 / BANDS as f32);
+        let coeffs: Vec<[f32; BAND_POINTS]> = (0..BANDS)
+            .map(|b| {
+                std::array::from_fn(|k| {
+                    let f = edge(b) + (edge(b + 1) - edge(b)) * (k as f32 + 0.5) / BAND_POINTS as f32;
+                    2.0 * (2.0 * std::f32::consts::PI * f.min(nyquist) / rate as f32).cos()
+                })
+            })
+            .collect();
+        let hann: Vec<f32> = (0..frame_len)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / frame_len as f32).cos())
+            .collect();
+
+        let mut frame = vec![0.0f32; frame_len];
+        let mut prev: Option<[f32; BANDS - 1]> = None;
+        let mut frames = Vec::with_capacity(samples.len() / hop);
+        for start in (0..=samples.len() - frame_len).step_by(hop) {
+            for (i, x) in frame.iter_mut().enumerate() {
+                *x = samples[start + i] * hann[i];
+            }
+            let energy: Vec<f32> = coeffs
+                .iter()
+                .map(|band| band.iter().map(|c| power(&frame, *c)).sum::<f32>().max(1e-12).ln())
+                .collect();
+            let mut diff = [0.0f32; BANDS - 1];
+            for (b, d) in diff.iter_mut().enumerate() {
+                *d = energy[b] - energy[b + 1];
+            }
+            if let Some(p) = prev {
+                let bits = (0..BANDS - 1).fold(0u32, |acc, b| acc | (((diff[b] > p[b]) as u32) << b));
+                frames.push(bits);
+            }
+            prev = Some(diff);
+        }
+        Some(Self { frames })
+    }
+
+    /// Lowest share of differing bits over the alignments within about a
+    /// second of each other; `None` if the clips barely overlap.
+    pub fn bit_error_rate(&self, other: &Fingerprint) -> Option<f32> {
+        let (a, b) = (&self.frames, &other.frames);
+        let mut best: Option<f32> = None;
+        for shift in -(MAX_SHIFT as isize)..=MAX_SHIFT as isize {
+            let (a0, b0) = if shift >= 0 { (shift as usize, 0) } else { (0, (-shift) as usize) };
+            if a0 >= a.len() || b0 >= b.len() {
+                continue;
+            }
+            let n = (a.len() - a0).min(b.len() - b0);
+            // Compare over most of the shorter clip, not just a lucky corner.
+            if n < MIN_OVERLAP || n * 2 < a.len().min(b.len()) {
+                continue;
+            }
+            let errors: u32 = a[a0..a0 + n].iter().zip(&b[b0..b0 + n]).map(|(x, y)| (x ^ y).count_ones()).sum();
+            let ber = errors as f32 / (n * 32) as f32;
+            if best.is_none_or(|b| ber < b) {
+                best = Some(ber);
+            }
+        }
+        best
+    }
+}
@@ -0,0 +1,228 @@
+// src/formats.rs
+//
+// Input formats, by name. Each one can tell whether the start of a file is
+// its kind (`sniff`) and turn a file into records (`stream`). The built-in
+// parsers are registered here; a program using the library adds its own
+// with `register`, and `stream_input`, `callscribe formats`, `explain`,
+// `golden` and `[[input]] format = ...` then know it like any other.
+//
+// A parser is picked by the input's `format` when one is given, Uniden for
+// a directory, else by extension. When no format claims the extension, or
+// several do, the first 4 KiB of the file are sniffed by the candidates (all
+// formats, for an unknown extension) in registration order; SRT is the
+// fallback.
+
+use crate::errors::AppError;
+use crate::input::ParseOptions;
+use crate::model::RadioRecord;
+use crate::{analog_log, event_stream, srt_stream, uniden, unitrunker};
+use parking_lot::RwLock;
+use std::future::Future;
+use std::io::Read;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, LazyLock};
+use tokio::sync::mpsc::Sender;
+
+/// Bytes read from a file for sniffing.
+const SNIFF_BYTES: u64 = 4096;
+
+/// An input format, for `callscribe formats`.
+pub struct FormatInfo {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    pub description: &'static str,
+    pub sample: &'static str,
+    /// Records span several lines, separated by blank ones.
+    pub multiline: bool,
+}
+
+pub type ParseFuture<'a> = Pin<Box<dyn Future<Output = Result<(), AppError>> + Send + 'a>>;
+
+pub trait InputFormat: Send + Sync {
+    fn info(&self) -> &FormatInfo;
+
+    /// Whether `head`, the start of a file, is in this format.
+    fn sniff(&self, head: &[u8]) -> bool;
+
+    /// Parse `path` (or `opts.stream`) and send its records into `tx`.
+    fn stream<'a>(&'a self, path: &'a Path, opts: ParseOptions, tx: Sender<RadioRecord>) -> ParseFuture<'a>;
+}
+
+enum Sniff {
+    Text(fn(&str) -> bool),
+    Bytes(fn(&[u8]) -> bool),
+}
+
+struct BuiltIn {
+    info: FormatInfo,
+    sniff: Sniff,
+    stream: for<'a> fn(&'a Path, ParseOptions, Sender<RadioRecord>) -> ParseFuture<'a>,
+}
+
+impl InputFormat for BuiltIn {
+    fn info(&self) -> &FormatInfo {
+        &self.info
+    }
+
+    fn sniff(&self, head: &[u8]) -> bool {
+        match self.sniff {
+            Sniff::Text(f) => f(&String::from_utf8_lossy(head)),
+            Sniff::Bytes(f) => f(head),
+        }
+    }
+
+    fn stream<'a>(&'a self, path: &'a Path, opts: ParseOptions, tx: Sender<RadioRecord>) -> ParseFuture<'a> {
+        (self.stream)(path, opts, tx)
+    }
+}
+
+fn srt<'a>(path: &'a Path, opts: ParseOptions, tx: Sender<RadioRecord>) -> ParseFuture<'a> {
+    Box::pin(srt_stream::stream_file(path, opts, tx))
+}
+
+fn event<'a>(path: &'a Path, opts: ParseOptions, tx: Sender<RadioRecord>) -> ParseFuture<'a> {
+    Box::pin(event_stream::stream_file(path, opts, tx))
+}
+
+fn unitrunker<'a>(path: &'a Path, opts: ParseOptions, tx: Sender<RadioRecord>) -> ParseFuture<'a> {
+    Box::pin(unitrunker::stream_file(path, opts, tx))
+}
+
+fn analog<'a>(path: &'a Path, opts: ParseOptions, tx: Sender<RadioRecord>) -> ParseFuture<'a> {
+    Box::pin(analog_log::stream_file(path, opts, tx))
+}
+
+fn uniden<'a>(path: &'a Path, opts: ParseOptions, tx: Sender<RadioRecord>) -> ParseFuture<'a> {
+    Box::pin(uniden::stream_path(path, opts, tx))
+}
+
+fn built_in() -> Vec<Arc<dyn InputFormat>> {
+    let formats = [
+        BuiltIn {
+            info: FormatInfo {
+                name: "srt",
+                extensions: &["srt"],
+                description: "DSDPlus subtitle log: one numbered block per voice frame; consecutive blocks of a call are merged by RLE",
+                sample: "1\n00:00:00,000 --> 00:00:01,000\n2025/09/10 06:43:56\n153.450000 +P25p1 NAC=293\nTG=2 RID=4506",
+                multiline: true,
+            },
+            sniff: Sniff::Text(srt_stream::sniff),
+            stream: srt,
+        },
+        BuiltIn {
+            info: FormatInfo {
+                name: "event",
+                extensions: &["event"],
+                description: "DSDPlus event log: one line per call; only \"Group call;\" / \"Private call;\" / ... lines become records",
+                sample: "2025/09/09  18:39:20  Freq=153.450000  NAC=293  Group call; TG=2  RID=4506   Pri0  7s",
+                multiline: false,
+            },
+            sniff: Sniff::Text(event_stream::sniff),
+            stream: event,
+        },
+        BuiltIn {
+            info: FormatInfo {
+                name: "unitrunker",
+                extensions: &["csv", "tsv"],
+                description: "Unitrunker call log export: a header line, then one call per row; columns are matched by name",
+                sample: "Date,Time,Site,Group,Radio,Frequency,Duration,Type\n09/10/2025,06:43:56,001-002,2,4506,851.0125,7,Group",
+                multiline: false,
+            },
+            sniff: Sniff::Text(unitrunker::sniff),
+            stream: unitrunker,
+        },
+        BuiltIn {
+            info: FormatInfo {
+                name: "analog",
+                extensions: &["log"],
+                description: "rtl_airband / SDR++ scanner activity log: one line per squelch opening (or closing) with a timestamp and frequency",
+                sample: "Sep 10 06:43:56 pi rtl_airband[512]: Activity on 118.300 MHz\n[2025-09-10 06:44:10.120] (INFO) Scanner: Found signal at 155475000 Hz",
+                multiline: false,
+            },
+            sniff: Sniff::Text(analog_log::sniff),
+            stream: analog,
+        },
+        BuiltIn {
+            info: FormatInfo {
+                name: "uniden",
+                extensions: &["wav"],
+                description: "Uniden SDS100/SDS200 recording, or a directory of them: one call per WAV, from its INFO/unid metadata",
+                sample: "2025-09-10_06-43-56.wav  INAM=Dispatch  IART=Fire  unid: TGID:2 UID:4506 851.012500MHz P25",
+                multiline: false,
+            },
+            sniff: Sniff::Bytes(uniden::sniff),
+            stream: uniden,
+        },
+    ];
+    formats.into_iter().map(|f| Arc::new(f) as Arc<dyn InputFormat>).collect()
+}
+
+static REGISTRY: LazyLock<RwLock<Vec<Arc<dyn InputFormat>>>> = LazyLock::new(|| RwLock::new(built_in()));
+
+/// Add a format; one with the name of a registered format replaces it.
+pub fn register(format: Arc<dyn InputFormat>) {
+    let mut formats = REGISTRY.write();
+    let name = format.info().name;
+    match formats.iter().position(|f| f.info().name.eq_ignore_ascii_case(name)) {
+        Some(i) => formats[i] = format,
+        None => formats.push(format),
+    }
+}
+
+/// Every format, built-ins first.
+pub fn all() -> Vec<Arc<dyn InputFormat>> {
+    REGISTRY.read().clone()
+}
+
+/// The format called `name` (case-insensitive).
+pub fn named(name: &str) -> Option<Arc<dyn InputFormat>> {
+    REGISTRY.read().iter().find(|f| f.info().name.eq_ignore_ascii_case(name.trim())).cloned()
+}
+
+/// Names of every format, for messages.
+pub fn names() -> String {
+    all().iter().map(|f| f.info().name).collect::<Vec<_>>().join(", ")
+}
+
+/// The first bytes of `path`; empty when it can't be read (a socket, a
+/// device, a file not there yet).
+fn head(path: &Path) -> Vec<u8> {
+    let mut buf = Vec::new();
+    if let Ok(f) = std::fs::File::open(path) {
+        let _ = f.take(SNIFF_BYTES).read_to_end(&mut buf);
+    }
+    buf
+}
+
+/// The format that reads `path`: `format` if set, else Uniden for a
+/// directory, else by extension, sniffing the file when that doesn't decide.
+pub fn for_path(path: &Path, format: Option<&str>) -> Arc<dyn InputFormat> {
+    let formats = all();
+    let by_name = |name: &str| formats.iter().find(|f| f.info().name == name).cloned();
+    if let Some(f) = format.and_then(named) {
+        return f;
+    }
+    if path.is_dir()
+        && let Some(f) = by_name("uniden")
+    {
+        return f;
+    }
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let mut candidates: Vec<&Arc<dyn InputFormat>> =
+        formats.iter().filter(|f| f.info().extensions.iter().any(|x| x.eq_ignore_ascii_case(ext))).collect();
+    if candidates.len() == 1 {
+        return Arc::clone(candidates[0]);
+    }
+    let fallback = candidates.first().map(|f| Arc::clone(f)).or_else(|| by_name("srt"));
+    if candidates.is_empty() {
+        candidates = formats.iter().collect();
+    }
+    let head = head(path);
+    if !head.is_empty()
+        && let Some(f) = candidates.iter().find(|f| f.sniff(&head))
+    {
+        return Arc::clone(f);
+    }
+    fallback.unwrap_or_else(|| Arc::clone(&formats[0]))
+}
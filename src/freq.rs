@@ -0,0 +1,56 @@
+// src/freq.rs
+//
+// Frequency comparison. Logs and record file names write MHz with varying
+// precision (`153.45`, `153.450000`), and a receiver may sit slightly off
+// the channel after a retune. Filters, RLE and transcript lookup compare
+// frequencies as whole Hz, within `--freq-tolerance` (exact by default).
+
+use crate::errors::AppError;
+
+/// `153.45` (MHz) as Hz; `None` for anything that isn't a positive number.
+pub fn to_hz(mhz: &str) -> Option<u64> {
+    let v: f64 = mhz.trim().parse().ok()?;
+    (v.is_finite() && v > 0.0).then(|| (v * 1e6).round() as u64)
+}
+
+/// Largest difference, in Hz, at which two frequencies are the same.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FreqTolerance(pub u64);
+
+impl FreqTolerance {
+    /// `500`, `500Hz`, `1k`, `12.5kHz`, `0.01M`: Hz unless a unit is given.
+    pub fn parse(s: &str) -> Result<Self, AppError> {
+        let t = s.trim().to_ascii_lowercase();
+        let t = t.strip_suffix("hz").unwrap_or(&t);
+        let (num, mult) = if let Some(n) = t.strip_suffix('k') {
+            (n, 1e3)
+        } else if let Some(n) = t.strip_suffix('m') {
+            (n, 1e6)
+        } else {
+            (t, 1.0)
+        };
+        match num.trim().parse::<f64>() {
+            Ok(v) if v.is_finite() && v >= 0.0 => Ok(Self((v * mult).round() as u64)),
+            _ => Err(AppError::Parse(format!(
+                "invalid --freq-tolerance '{}': expected Hz or a value like 1k, 12.5kHz",
+                s
+            ))),
+        }
+    }
+
+    /// Numerically within tolerance; values that aren't numbers must be equal.
+    pub fn matches(self, a: &str, b: &str) -> bool {
+        match (to_hz(a), to_hz(b)) {
+            (Some(x), Some(y)) => x.abs_diff(y) <= self.0,
+            _ => a == b,
+        }
+    }
+
+    /// As `matches`, for optional fields: two missing values match.
+    pub fn matches_opt(self, a: Option<&str>, b: Option<&str>) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => self.matches(a, b),
+            (a, b) => a == b,
+        }
+    }
+}
@@ -0,0 +1,115 @@
+// src/golden.rs
+//
+// Golden files for the parsers. Each input fixture (`name.srt`,
+// `name.event`, ...) sits next to `name.srt.jsonl`: one `RadioRecord::to_json`
+// line per record the parser produced, before filtering or RLE. `check`
+// parses a fixture and compares it with its golden file; with `update` the
+// golden file is rewritten instead. `callscribe golden [DIR]` runs every
+// fixture in a directory (default `tests/fixtures`), so a new input format
+// comes with fixture pairs there.
+
+use crate::errors::AppError;
+use crate::formats;
+use crate::input::{self, ParseOptions};
+use crate::model::RadioRecord;
+use chrono::FixedOffset;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+pub const DEFAULT_DIR: &str = "tests/fixtures";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Parser output equals the golden file (this many records).
+    Match(usize),
+    /// First differing line (1-based); `None` where one side has ended.
+    Mismatch { line: usize, expected: Option<String>, got: Option<String> },
+    /// No golden file yet; run with `update` to create it.
+    Missing,
+    /// The golden file was (re)written with this many records.
+    Updated(usize),
+}
+
+impl Outcome {
+    pub fn ok(&self) -> bool {
+        matches!(self, Outcome::Match(_) | Outcome::Updated(_))
+    }
+}
+
+/// `name.srt.jsonl` next to `name.srt`; the full name keeps fixtures of
+/// different formats with the same stem apart.
+pub fn golden_path(input: &Path) -> PathBuf {
+    let mut name = input.file_name().unwrap_or_default().to_os_string();
+    name.push(".jsonl");
+    input.with_file_name(name)
+}
+
+/// Input fixtures in `dir` (files with an extension some parser handles),
+/// sorted by name.
+pub fn fixtures(dir: &Path) -> Result<Vec<PathBuf>, AppError> {
+    let entries = std::fs::read_dir(dir).map_err(|e| AppError::IO(format!("read {}: {}", dir.display(), e)))?;
+    let mut out: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .filter(|p| {
+            let ext = p.extension().and_then(|e| e.to_str()).unwrap_or("");
+            formats::all().iter().any(|f| f.info().extensions.iter().any(|x| x.eq_ignore_ascii_case(ext)))
+        })
+        .collect();
+    out.sort();
+    Ok(out)
+}
+
+/// Parse `path` as a normal run would (UTC, with extras) into JSON lines.
+pub async fn parse_to_jsonl(path: &Path) -> Result<Vec<String>, AppError> {
+    let opts = ParseOptions { tz_offset: FixedOffset::east_opt(0), extras: true, ..Default::default() };
+    let (tx, mut rx) = mpsc::channel::<RadioRecord>(64);
+    let p = path.to_path_buf();
+    let parser = tokio::spawn(async move { input::stream_input(&p, opts, tx).await });
+    let mut lines = Vec::new();
+    while let Some(rec) = rx.recv().await {
+        lines.push(rec.to_json().to_string());
+    }
+    parser.await.map_err(|e| AppError::Other(format!("golden join: {e}")))??;
+    Ok(lines)
+}
+
+/// Equal lines, or the same JSON written differently (hand-edited golden
+/// files needn't match serde_json's key order or spacing).
+fn same_record(expected: Option<&str>, got: Option<&str>) -> bool {
+    match (expected, got) {
+        (Some(e), Some(g)) => {
+            e == g
+                || serde_json::from_str::<serde_json::Value>(e)
+                    .is_ok_and(|v| serde_json::from_str::<serde_json::Value>(g).is_ok_and(|w| v == w))
+        }
+        (e, g) => e == g,
+    }
+}
+
+/// Compare `input`'s parse with its golden file, or rewrite the golden file
+/// when `update` is set.
+pub async fn check(input: &Path, update: bool) -> Result<Outcome, AppError> {
+    let got = parse_to_jsonl(input).await?;
+    let golden = golden_path(input);
+    if update {
+        let mut text = got.join("\n");
+        text.push('\n');
+        std::fs::write(&golden, text).map_err(|e| AppError::IO(format!("write {}: {}", golden.display(), e)))?;
+        return Ok(Outcome::Updated(got.len()));
+    }
+    let expected = match std::fs::read_to_string(&golden) {
+        Ok(t) => t,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Outcome::Missing),
+        Err(e) => return Err(AppError::IO(format!("read {}: {}", golden.display(), e))),
+    };
+    let expected: Vec<&str> = expected.lines().filter(|l| !l.trim().is_empty()).collect();
+    for i in 0..expected.len().max(got.len()) {
+        let (e, g) = (expected.get(i).copied(), got.get(i).map(String::as_str));
+        if !same_record(e, g) {
+            return Ok(Outcome::Mismatch { line: i + 1, expected: e.map(str::to_string), got: g.map(str::to_string) });
+        }
+    }
+    Ok(Outcome::Match(got.len()))
+}
@@ -0,0 +1,164 @@
+// src/grafana.rs
+//
+// `serve --grafana`: the endpoints of Grafana's JSON datasource plugin
+// (simpod-json-datasource; `/search` also serves the older SimpleJSON
+// plugin), so a Grafana install can chart scanner activity straight from
+// the same database or JSON lines file as the viewer. Point the datasource
+// at `http://127.0.0.1:8080/` and pick one of the metrics below per query:
+//
+//   calls_per_tg     timeseries, one series per talkgroup: calls per interval
+//   airtime_per_tg   timeseries, one series per talkgroup: seconds per interval
+//   recent_calls     table of the calls in the dashboard's time range, newest first
+//
+// Routes (all POST with a JSON body, as the plugin sends them):
+//
+//   /search, /metrics   the metric names
+//   /query              one result per target
+//   /annotations        none
+
+use crate::errors::AppError;
+use crate::serve::Call;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+pub const CALLS_PER_TG: &str = "calls_per_tg";
+pub const AIRTIME_PER_TG: &str = "airtime_per_tg";
+pub const RECENT_CALLS: &str = "recent_calls";
+
+const METRICS: [(&str, &str); 3] = [
+    (CALLS_PER_TG, "Calls per talkgroup"),
+    (AIRTIME_PER_TG, "Airtime per talkgroup (s)"),
+    (RECENT_CALLS, "Recent calls (table)"),
+];
+
+/// Bucket width when the query doesn't give one.
+const DEFAULT_INTERVAL_MS: i64 = 60_000;
+
+/// Rows in a `recent_calls` table unless `maxDataPoints` is smaller.
+const MAX_TABLE_ROWS: usize = 5000;
+
+/// Empty buckets are filled in up to this many per series; beyond that the
+/// series only has points where there were calls.
+const MAX_FILLED_BUCKETS: i64 = 10_000;
+
+#[derive(Debug, Deserialize)]
+pub struct Range {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Target {
+    #[serde(default)]
+    pub target: Option<String>,
+    #[serde(default)]
+    pub hide: bool,
+}
+
+/// The body of `POST /query`; fields the endpoints don't use are ignored.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryRequest {
+    pub range: Range,
+    #[serde(default)]
+    pub interval_ms: Option<i64>,
+    #[serde(default)]
+    pub max_data_points: Option<usize>,
+    #[serde(default)]
+    pub targets: Vec<Target>,
+}
+
+impl QueryRequest {
+    pub fn parse(body: &[u8]) -> Result<Self, AppError> {
+        serde_json::from_slice(body).map_err(|e| AppError::Parse(format!("grafana query: {}", e)))
+    }
+}
+
+/// `/search` (SimpleJSON): the metric names.
+pub fn search() -> Value {
+    json!(METRICS.iter().map(|(name, _)| name).collect::<Vec<_>>())
+}
+
+/// `/metrics` (JSON datasource): names with labels.
+pub fn metrics() -> Value {
+    json!(METRICS.iter().map(|(name, label)| json!({ "value": name, "label": label })).collect::<Vec<_>>())
+}
+
+/// Answer every visible target from `calls`, which are the calls in the
+/// query's range. Unknown metrics are an error, so a typo shows up in the
+/// panel rather than as an empty graph.
+pub fn query(req: &QueryRequest, calls: &[Call]) -> Result<Value, AppError> {
+    let dated: Vec<(i64, &Call)> = calls
+        .iter()
+        .filter_map(|c| DateTime::parse_from_rfc3339(&c.datetime).ok().map(|t| (t.timestamp_millis(), c)))
+        .collect();
+    let mut out = Vec::new();
+    for t in req.targets.iter().filter(|t| !t.hide) {
+        match t.target.as_deref().unwrap_or(CALLS_PER_TG) {
+            CALLS_PER_TG => out.extend(per_tg(req, &dated, |_| 1)),
+            AIRTIME_PER_TG => out.extend(per_tg(req, &dated, |c| c.duration)),
+            RECENT_CALLS => out.push(table(req, &dated)),
+            other => {
+                let known: Vec<&str> = METRICS.iter().map(|(n, _)| *n).collect();
+                return Err(AppError::Parse(format!("unknown metric '{}' (use {})", other, known.join(", "))));
+            }
+        }
+    }
+    Ok(Value::Array(out))
+}
+
+/// One series per talkgroup, `value(call)` summed per `intervalMs` bucket.
+fn per_tg(req: &QueryRequest, calls: &[(i64, &Call)], value: impl Fn(&Call) -> i64) -> Vec<Value> {
+    let interval = req.interval_ms.unwrap_or(DEFAULT_INTERVAL_MS).max(1000);
+    let bucket = |ms: i64| ms.div_euclid(interval) * interval;
+    let mut series: BTreeMap<&str, BTreeMap<i64, i64>> = BTreeMap::new();
+    for (ms, c) in calls {
+        let tg = if c.tg.is_empty() { "-" } else { c.tg.as_str() };
+        *series.entry(tg).or_default().entry(bucket(*ms)).or_default() += value(c);
+    }
+    let (first, last) = (bucket(req.range.from.timestamp_millis()), bucket(req.range.to.timestamp_millis()));
+    let fill = (last - first) / interval < MAX_FILLED_BUCKETS;
+    series
+        .into_iter()
+        .map(|(tg, mut points)| {
+            if fill {
+                let mut b = first;
+                while b <= last {
+                    points.entry(b).or_default();
+                    b += interval;
+                }
+            }
+            let datapoints: Vec<[i64; 2]> = points.into_iter().map(|(b, v)| [v, b]).collect();
+            json!({ "target": format!("TG {}", tg), "datapoints": datapoints })
+        })
+        .collect()
+}
+
+fn table(req: &QueryRequest, calls: &[(i64, &Call)]) -> Value {
+    let limit = req.max_data_points.unwrap_or(MAX_TABLE_ROWS).clamp(1, MAX_TABLE_ROWS);
+    let rows: Vec<Value> = calls
+        .iter()
+        .take(limit)
+        .map(|(ms, c)| {
+            json!([ms, c.frequency, c.tg, c.rid, c.duration, c.radio_type, c.encrypted, c.alert, c.text])
+        })
+        .collect();
+    let col = |text: &str, ty: &str| json!({ "text": text, "type": ty });
+    json!({
+        "type": "table",
+        "columns": [
+            col("Time", "time"),
+            col("Frequency", "string"),
+            col("TG", "string"),
+            col("RID", "string"),
+            col("Duration", "number"),
+            col("Type", "string"),
+            col("Encrypted", "boolean"),
+            col("Alert", "string"),
+            col("Transcript", "string"),
+        ],
+        "rows": rows,
+    })
+}
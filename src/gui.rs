@@ -0,0 +1,369 @@
+// src/gui.rs
+//
+// `callscribe gui` (feature `gui`): a small egui window for people who'd
+// rather not use a command line. Inputs are dropped onto the window or typed
+// in; the Record directory, time zone, talk group / radio / frequency
+// filters and the output format are set with a few fields. Run starts this
+// same executable with the matching arguments, so a conversion behaves
+// exactly as it would from the shell and the command is shown for reuse.
+// Its log is shown as it runs, with the share of inputs done, and the first
+// rows of the first CSV written are shown as a table when it has finished.
+//
+// A build with the feature opens the window when started without arguments
+// (double-clicked), too.
+
+use crate::errors::AppError;
+use crate::sink;
+use eframe::egui;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use tokio_util::compat::TokioAsyncReadCompatExt;
+
+/// Rows of the result shown in the preview.
+const PREVIEW_ROWS: usize = 200;
+/// Log lines kept.
+const LOG_LINES: usize = 500;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Csv,
+    Excel,
+    Influx,
+}
+
+impl Format {
+    fn label(self) -> &'static str {
+        match self {
+            Format::Csv => "CSV",
+            Format::Excel => "CSV for Excel",
+            Format::Influx => "InfluxDB line protocol",
+        }
+    }
+}
+
+struct Job {
+    child: Child,
+    inputs: Vec<PathBuf>,
+    format: Format,
+}
+
+struct Preview {
+    path: PathBuf,
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+struct App {
+    inputs: Vec<PathBuf>,
+    new_input: String,
+    record_dir: String,
+    transcripts: bool,
+    tz: String,
+    tgs: String,
+    rids: String,
+    freqs: String,
+    format: Format,
+    job: Option<Job>,
+    log: Arc<Mutex<Vec<String>>>,
+    /// Outcome of the last run.
+    status: String,
+    preview: Option<Result<Preview, String>>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self {
+            inputs: Vec::new(),
+            new_input: String::new(),
+            record_dir: String::new(),
+            transcripts: true,
+            tz: String::new(),
+            tgs: String::new(),
+            rids: String::new(),
+            freqs: String::new(),
+            format: Format::Csv,
+            job: None,
+            log: Arc::new(Mutex::new(Vec::new())),
+            status: String::new(),
+            preview: None,
+        }
+    }
+}
+
+/// Values of a filter field, separated by commas or spaces.
+fn values(field: &str) -> impl Iterator<Item = &str> {
+    field.split([',', ' ']).map(str::trim).filter(|v| !v.is_empty())
+}
+
+/// The first rows of a CSV file.
+async fn read_preview(path: &Path) -> Result<Preview, String> {
+    let file = tokio::fs::File::open(path).await.map_err(|e| format!("open {}: {}", path.display(), e))?;
+    let mut reader = csv_async::AsyncReaderBuilder::new().flexible(true).create_reader(file.compat());
+    let mut header: Vec<String> = reader.headers().await.map_err(|e| e.to_string())?.iter().map(String::from).collect();
+    if let Some(first) = header.first_mut() {
+        *first = first.trim_start_matches('\u{FEFF}').to_string();
+    }
+    let mut rows = Vec::new();
+    let mut record = csv_async::StringRecord::new();
+    while rows.len() < PREVIEW_ROWS && reader.read_record(&mut record).await.map_err(|e| e.to_string())? {
+        rows.push(record.iter().map(String::from).collect());
+    }
+    Ok(Preview { path: path.to_path_buf(), header, rows })
+}
+
+/// [`read_preview`] on a thread of its own, outside the window's runtime.
+fn load_preview(path: PathBuf) -> Result<Preview, String> {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().map_err(|e| e.to_string())?;
+        rt.block_on(read_preview(&path))
+    })
+    .join()
+    .map_err(|_| "preview failed".to_string())?
+}
+
+impl App {
+    fn arguments(&self) -> Vec<String> {
+        let mut args: Vec<String> = Vec::new();
+        let dir = self.record_dir.trim();
+        if !dir.is_empty() {
+            args.extend(["--record-dir".into(), dir.into()]);
+            if self.transcripts {
+                args.extend(["--transcriber".into(), "text".into()]);
+            }
+        }
+        if !self.tz.trim().is_empty() {
+            args.extend(["--tz".into(), self.tz.trim().into()]);
+        }
+        for (flag, field) in [("--tg", &self.tgs), ("--rid", &self.rids), ("--freq", &self.freqs)] {
+            for v in values(field) {
+                args.extend([flag.into(), v.into()]);
+            }
+        }
+        match self.format {
+            Format::Csv => {}
+            Format::Excel => args.push("--excel-compat".into()),
+            Format::Influx => args.extend(["--sink".into(), "influx".into()]),
+        }
+        args.extend(self.inputs.iter().map(|p| p.display().to_string()));
+        args
+    }
+
+    fn start(&mut self, ctx: &egui::Context) {
+        self.preview = None;
+        self.log.lock().unwrap().clear();
+        let exe = match std::env::current_exe() {
+            Ok(exe) => exe,
+            Err(e) => {
+                self.status = format!("can't find this program: {}", e);
+                return;
+            }
+        };
+        let args = self.arguments();
+        self.log.lock().unwrap().push(format!("> callscribe {}", args.join(" ")));
+        let child = Command::new(exe)
+            .args(&args)
+            .env("RUST_LOG_STYLE", "never")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        let mut child = match child {
+            Ok(c) => c,
+            Err(e) => {
+                self.status = format!("can't start: {}", e);
+                return;
+            }
+        };
+        // The summary goes to stdout and the log to stderr; show both.
+        let stdout = child.stdout.take().map(|s| Box::new(s) as Box<dyn std::io::Read + Send>);
+        let stderr = child.stderr.take().map(|s| Box::new(s) as Box<dyn std::io::Read + Send>);
+        for stream in [stdout, stderr].into_iter().flatten() {
+            let log = Arc::clone(&self.log);
+            let ctx = ctx.clone();
+            std::thread::spawn(move || {
+                for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                    let mut log = log.lock().unwrap();
+                    log.push(line);
+                    if log.len() > LOG_LINES {
+                        log.remove(0);
+                    }
+                    ctx.request_repaint();
+                }
+            });
+        }
+        self.status = "running".into();
+        self.job = Some(Job { child, inputs: self.inputs.clone(), format: self.format });
+    }
+
+    /// Check on the running conversion; load the preview when it has ended.
+    fn poll(&mut self) {
+        let Some(job) = self.job.as_mut() else { return; };
+        let status = match job.child.try_wait() {
+            Ok(Some(status)) => status,
+            Ok(None) => return,
+            Err(e) => {
+                self.status = format!("lost the conversion: {}", e);
+                self.job = None;
+                return;
+            }
+        };
+        let job = self.job.take().unwrap();
+        if !status.success() {
+            self.status = format!("failed ({})", status);
+            return;
+        }
+        self.status = "done".into();
+        if job.format != Format::Influx
+            && let Some(input) = job.inputs.first()
+        {
+            self.preview = Some(load_preview(sink::output_path(input, "csv")));
+        }
+    }
+
+    /// Inputs written so far, from the sinks' log lines.
+    fn progress(&self, job: &Job) -> f32 {
+        let log = self.log.lock().unwrap();
+        let done = log.iter().filter(|l| l.contains("CSV wrote") || l.contains("Line protocol wrote")).count();
+        done.min(job.inputs.len()) as f32 / job.inputs.len().max(1) as f32
+    }
+
+    fn options(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Inputs");
+        if self.inputs.is_empty() {
+            ui.label("Drop DSDPlus event logs or SRT files here, or add a path below.");
+        }
+        let mut remove = None;
+        for (i, p) in self.inputs.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("x").clicked() {
+                    remove = Some(i);
+                }
+                ui.monospace(p.display().to_string());
+            });
+        }
+        if let Some(i) = remove {
+            self.inputs.remove(i);
+        }
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.new_input);
+            if ui.button("Add").clicked() && !self.new_input.trim().is_empty() {
+                self.inputs.push(PathBuf::from(self.new_input.trim()));
+                self.new_input.clear();
+            }
+        });
+        ui.separator();
+        egui::Grid::new("options").num_columns(2).show(ui, |ui| {
+            ui.label("Record directory");
+            ui.text_edit_singleline(&mut self.record_dir);
+            ui.end_row();
+            ui.label("");
+            ui.checkbox(&mut self.transcripts, "Attach transcripts found there");
+            ui.end_row();
+            ui.label("Time zone (e.g. America/Chicago)");
+            ui.text_edit_singleline(&mut self.tz);
+            ui.end_row();
+            ui.label("Only talk groups");
+            ui.text_edit_singleline(&mut self.tgs);
+            ui.end_row();
+            ui.label("Only radio IDs");
+            ui.text_edit_singleline(&mut self.rids);
+            ui.end_row();
+            ui.label("Only frequencies");
+            ui.text_edit_singleline(&mut self.freqs);
+            ui.end_row();
+            ui.label("Output");
+            egui::ComboBox::from_id_salt("format").selected_text(self.format.label()).show_ui(ui, |ui| {
+                for f in [Format::Csv, Format::Excel, Format::Influx] {
+                    ui.selectable_value(&mut self.format, f, f.label());
+                }
+            });
+            ui.end_row();
+        });
+    }
+}
+
+impl eframe::App for App {
+    fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
+        let dropped: Vec<PathBuf> =
+            ui.ctx().input(|i| i.raw.dropped_files.iter().map(|f| f.path().to_path_buf()).collect());
+        for p in dropped {
+            if !self.inputs.contains(&p) {
+                self.inputs.push(p);
+            }
+        }
+        self.poll();
+
+        egui::Panel::top("options").show(ui, |ui| {
+            self.options(ui);
+            ui.separator();
+            ui.horizontal(|ui| {
+                if let Some(job) = &self.job {
+                    ui.spinner();
+                    ui.add(egui::ProgressBar::new(self.progress(job)).show_percentage());
+                    if ui.button("Stop").clicked()
+                        && let Some(job) = self.job.as_mut()
+                    {
+                        let _ = job.child.kill();
+                    }
+                } else {
+                    let ready = !self.inputs.is_empty();
+                    if ui.add_enabled(ready, egui::Button::new("Run")).clicked() {
+                        self.start(ui.ctx());
+                    }
+                    ui.label(&self.status);
+                }
+            });
+        });
+        egui::Panel::bottom("log").resizable(true).show(ui, |ui| {
+            egui::ScrollArea::vertical().max_height(160.0).stick_to_bottom(true).show(ui, |ui| {
+                for line in self.log.lock().unwrap().iter() {
+                    ui.monospace(line);
+                }
+            });
+        });
+        egui::CentralPanel::default().show(ui, |ui| match &self.preview {
+            None => {
+                ui.label("The first rows of the result appear here.");
+            }
+            Some(Err(e)) => {
+                ui.label(format!("No preview: {}", e));
+            }
+            Some(Ok(p)) => {
+                ui.label(format!("{} (first {} rows)", p.path.display(), p.rows.len()));
+                egui::ScrollArea::both().show(ui, |ui| {
+                    egui::Grid::new("preview").striped(true).show(ui, |ui| {
+                        for h in &p.header {
+                            ui.strong(h);
+                        }
+                        ui.end_row();
+                        for row in &p.rows {
+                            for cell in row {
+                                ui.label(cell);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            }
+        });
+        if self.job.is_some() {
+            // Notice the end of the run without waiting for input.
+            ui.ctx().request_repaint_after(std::time::Duration::from_millis(250));
+        }
+    }
+}
+
+/// Open the window; returns when it is closed.
+pub fn run() -> Result<(), AppError> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("Callscribe")
+            .with_inner_size([960.0, 720.0])
+            .with_drag_and_drop(true),
+        ..Default::default()
+    };
+    eframe::run_native("Callscribe", options, Box::new(|_cc| Ok(Box::new(App::default()))))
+        .map_err(|e| AppError::Other(format!("gui: {}", e)))
+}
@@ -0,0 +1,116 @@
+// src/influx_sink.rs
+//
+// InfluxDB line-protocol sink: one point per call in measurement `calls`,
+// tagged by talkgroup, frequency, radio type and call type, with
+// duration/rid/target_rid/errors/quality/id fields.
+// The `.lp` file can be loaded with `influx write --file` or tailed by Telegraf.
+
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use crate::output_file::{OutputFile, WriteMode};
+use crate::sink::{self, DurationFormat};
+use crate::stats::PipelineStats;
+use log::info;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc::Receiver;
+
+const MEASUREMENT: &str = "calls";
+
+#[derive(Clone, Debug, Default)]
+pub struct InfluxSinkOptions {
+    /// Flush whenever the input channel runs dry (follow mode).
+    pub flush_when_idle: bool,
+    /// Add a `duration_hms` string field (`--duration-format hms`).
+    pub duration: DurationFormat,
+    /// Temporary file and rename, or in place (follow mode); `--fsync`.
+    pub write_mode: WriteMode,
+}
+
+/// Tag keys/values: escape comma, equals and space.
+fn escape_tag(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, ',' | '=' | ' ') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// String field values: double-quoted, escape backslash and quote.
+fn quote_field(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Render one record as a line-protocol point (nanosecond timestamp).
+pub fn to_line_protocol(r: &RadioRecord, duration: DurationFormat) -> String {
+    let mut line = String::from(MEASUREMENT);
+    let tg = r.slot1.tg.as_ref().or(r.slot2.tg.as_ref());
+    let rid = r.slot1.rid.as_ref().or(r.slot2.rid.as_ref());
+    let radio_type = r.radio_type.as_deref().map(|t| t.trim_start_matches('+'));
+
+    // Empty tag values are not allowed; omit the tag instead.
+    let call = Some(r.call_type.as_str());
+    for (k, v) in [("tg", tg.map(String::as_str)), ("freq", r.frequency.as_deref()), ("type", radio_type), ("call", call)] {
+        if let Some(v) = v.filter(|v| !v.is_empty()) {
+            line.push(',');
+            line.push_str(k);
+            line.push('=');
+            line.push_str(&escape_tag(v));
+        }
+    }
+
+    line.push_str(&format!(" duration={}i", r.duration));
+    if duration == DurationFormat::Hms {
+        line.push_str(&format!(",duration_hms={}", quote_field(&sink::hms(r.duration))));
+    }
+    if let Some(rid) = rid {
+        line.push_str(&format!(",rid={}", quote_field(rid)));
+    }
+    for (k, v) in [("errors", r.errors), ("quality", r.quality)] {
+        if let Some(v) = v {
+            line.push_str(&format!(",{}={}i", k, v));
+        }
+    }
+    if let Some(t) = &r.target_rid {
+        line.push_str(&format!(",target_rid={}", quote_field(t)));
+    }
+    line.push_str(&format!(",id={}", quote_field(&r.record_id())));
+
+    let ns = r.datetime.timestamp_nanos_opt().unwrap_or(r.datetime.timestamp() * 1_000_000_000);
+    line.push_str(&format!(" {}\n", ns));
+    line
+}
+
+pub async fn write_influx_stream(
+    out_path: &Path,
+    mut rx: Receiver<RadioRecord>,
+    stats: Arc<PipelineStats>,
+    opts: &InfluxSinkOptions,
+) -> Result<Vec<PathBuf>, AppError> {
+    let _timer = stats.sink.timer();
+    let (out, file) = OutputFile::create(out_path, opts.write_mode).await?;
+    let mut w = BufWriter::new(file);
+    let mut count: usize = 0;
+
+    while let Some(r) = stats.sink.recv(&mut rx).await {
+        stats.sink.inc_in();
+        w.write_all(to_line_protocol(&r, opts.duration).as_bytes())
+            .await
+            .map_err(|e| AppError::IO(format!("lp write: {}", e)))?;
+        count += 1;
+        stats.sink.inc_out();
+
+        if opts.flush_when_idle && rx.is_empty() {
+            w.flush().await.map_err(|e| AppError::IO(format!("lp flush: {}", e)))?;
+        }
+    }
+
+    w.flush().await.map_err(|e| AppError::IO(format!("lp flush: {}", e)))?;
+    out.commit(w.into_inner()).await?;
+    info!("Line protocol wrote {} points to {}", count, out_path.display());
+    Ok(vec![out_path.to_path_buf()])
+}
@@ -0,0 +1,74 @@
+// src/init.rs
+//
+// `callscribe init [DIR]`: a starter setup in DIR (default: the current
+// directory), so a new install works without hunting for files:
+//
+//   callscribe.toml          configuration with every section commented out
+//   callscribe.cmd / .sh     launcher passing that file, Record/ and models/
+//                            (Windows: drop event logs onto it)
+//   ten-codes.toml           the ten-code dictionary, to edit for the
+//                            `expand` transcript post-processor
+//   Record/                  where DSDPlus (or a copy) keeps transcripts and
+//                            recordings
+//   models/                  speech-to-text model cache (`models download`)
+//
+// Existing files are kept unless `--force` is given.
+
+use crate::assets;
+use crate::errors::AppError;
+use crate::winpath;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const CONFIG: &str = "callscribe.toml";
+#[cfg(windows)]
+const LAUNCHER: &str = "callscribe.cmd";
+#[cfg(not(windows))]
+const LAUNCHER: &str = "callscribe.sh";
+const TEN_CODES: &str = "ten-codes.toml";
+const FOLDERS: [&str; 2] = ["Record", "models"];
+
+fn bundled(name: &str) -> Result<&'static str, AppError> {
+    assets::text(&format!("init/{}", name)).ok_or_else(|| AppError::Other(format!("init: {} is not bundled", name)))
+}
+
+/// Write `contents` to `path` unless it exists (and not `force`); whether it
+/// was written.
+fn write_file(path: &Path, contents: &str, force: bool) -> Result<bool, AppError> {
+    if path.exists() && !force {
+        return Ok(false);
+    }
+    std::fs::write(path, contents).map_err(|e| AppError::IO(format!("write {}: {}", path.display(), e)))?;
+    #[cfg(unix)]
+    if path.extension().is_some_and(|e| e == "sh") {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| AppError::IO(format!("chmod {}: {}", path.display(), e)))?;
+    }
+    Ok(true)
+}
+
+/// Set up `dir` for `exe`, the executable the launcher runs; reports each
+/// file and folder on `out`. Returns the launcher's path.
+pub fn init(dir: &Path, exe: &Path, force: bool, out: &mut impl Write) -> Result<PathBuf, AppError> {
+    let io = |e: std::io::Error| AppError::IO(format!("write: {}", e));
+    std::fs::create_dir_all(dir).map_err(|e| AppError::IO(format!("create {}: {}", dir.display(), e)))?;
+    let exe = winpath::simplified(exe).display().to_string();
+    let files = [
+        (CONFIG, bundled(CONFIG)?.to_string()),
+        (LAUNCHER, bundled(LAUNCHER)?.replace("{exe}", &exe)),
+        (TEN_CODES, assets::text(TEN_CODES).ok_or_else(|| AppError::Other(format!("init: {} is not bundled", TEN_CODES)))?.to_string()),
+    ];
+    for (name, contents) in &files {
+        let path = dir.join(name);
+        let state = if write_file(&path, contents, force)? { "wrote" } else { "kept existing" };
+        writeln!(out, "{:<14} {}", state, path.display()).map_err(io)?;
+    }
+    for name in FOLDERS {
+        let path = dir.join(name);
+        let state = if path.is_dir() { "kept existing" } else { "created" };
+        std::fs::create_dir_all(&path).map_err(|e| AppError::IO(format!("create {}: {}", path.display(), e)))?;
+        writeln!(out, "{:<14} {}{}", state, path.display(), std::path::MAIN_SEPARATOR).map_err(io)?;
+    }
+    Ok(dir.join(LAUNCHER))
+}
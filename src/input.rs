@@ -0,0 +1,417 @@
+// src/input.rs
+use crate::affiliation::SharedTracker;
+use crate::config::InputConfig;
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use crate::device_input;
+use crate::net_input::{self, ListenAddr};
+use crate::radio_type::RadioTypes;
+use crate::slot_policy::SlotPolicies;
+use crate::formats;
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use chrono_tz::Tz;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader, ReadBuf};
+use tokio::sync::mpsc::{Sender, UnboundedSender};
+use tokio::time::Sleep;
+
+/// Per-input knobs handed to every parser.
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    pub tz_offset: Option<FixedOffset>,
+    /// Date used for lines that carry only a time of day.
+    pub base_date: Option<NaiveDate>,
+    /// Keep reading past EOF, waiting for the file to grow (`--follow`).
+    pub follow: bool,
+    /// Where parsers report what they did with each block (`explain`).
+    pub notes: Option<ParseNotes>,
+    /// Collect unrecognised `KEY=VALUE` tokens into `RadioRecord::extras`.
+    pub extras: bool,
+    /// Bytes read from the input so far are added here, for progress display.
+    pub bytes_read: Option<Arc<AtomicU64>>,
+    /// Recover from malformed input instead of failing (`--lenient`):
+    /// invalid UTF-8 is replaced, and a block cut short resynchronises on
+    /// the next block header.
+    pub lenient: bool,
+    /// Recoveries made in lenient mode are counted here.
+    pub recoveries: Option<Arc<AtomicU64>>,
+    /// Record registrations/affiliations here and tag calls with the unit's
+    /// current talkgroup (`--affiliations`; event logs).
+    pub affiliations: Option<SharedTracker>,
+    /// Names radio types are normalized to (built-ins plus `[radio_type]`).
+    pub radio_types: Arc<RadioTypes>,
+    /// Slot of SRT detail lines without `Slot N`, by radio type
+    /// (built-ins plus `[slot_policy]`).
+    pub slot_policies: Arc<SlotPolicies>,
+    /// Read from a socket or device instead of the file (`--listen`, `--device`).
+    pub stream: Option<StreamSource>,
+    /// Parser to use regardless of the extension (a [`formats`] name).
+    pub format: Option<&'static str>,
+    /// Extras set on every record of this input (`[[input]]` `system`/`site`).
+    pub labels: Vec<(String, String)>,
+}
+
+impl ParseOptions {
+    pub(crate) fn note(&self, note: ParseNote) {
+        if let Some(tx) = &self.notes {
+            let _ = tx.send(note);
+        }
+    }
+
+    /// Last touches every parser gives a record it built: the radio type
+    /// normalized and this input's labels added.
+    pub(crate) fn finish(&self, rec: &mut RadioRecord) {
+        self.radio_types.apply(rec);
+        for (k, v) in &self.labels {
+            rec.extras.insert(k.clone(), v.clone());
+        }
+    }
+
+    /// Count one recovery from malformed input.
+    pub(crate) fn recovered(&self) {
+        if let Some(c) = &self.recoveries {
+            c.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// What a parser made of a block or line, for `callscribe explain`.
+#[derive(Clone, Debug)]
+pub enum ParseNote {
+    /// The next record sent downstream was built from input starting at `line`.
+    Record { line: usize },
+    /// Input at `line` did not produce a record.
+    Skipped { line: usize, reason: String },
+    /// A line inside the record just reported that contributed nothing.
+    Ignored { line: usize, reason: String },
+}
+
+pub type ParseNotes = UnboundedSender<ParseNote>;
+
+/// Input lines with 1-based line numbers. Lines are split on `\n` and lose
+/// a trailing `\r`. Invalid UTF-8 is an error, or with `--lenient` is
+/// replaced (and counted as a recovery).
+pub(crate) struct NumberedLines {
+    reader: BufReader<Box<dyn AsyncRead + Unpin + Send>>,
+    buf: Vec<u8>,
+    /// Lines handed back with `unread`, the next one last.
+    pending: Vec<String>,
+    lenient: bool,
+    recoveries: Option<Arc<AtomicU64>>,
+    /// Number of the line last returned.
+    pub line: usize,
+}
+
+impl NumberedLines {
+    pub(crate) fn new(r: Box<dyn AsyncRead + Unpin + Send>, opts: &ParseOptions) -> Self {
+        Self {
+            reader: BufReader::new(r),
+            buf: Vec::new(),
+            pending: Vec::new(),
+            lenient: opts.lenient,
+            recoveries: opts.recoveries.clone(),
+            line: 0,
+        }
+    }
+
+    pub(crate) async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        if let Some(l) = self.pending.pop() {
+            self.line += 1;
+            return Ok(Some(l));
+        }
+        self.buf.clear();
+        if self.reader.read_until(b'\n', &mut self.buf).await? == 0 {
+            return Ok(None);
+        }
+        if self.buf.last() == Some(&b'\n') {
+            self.buf.pop();
+            if self.buf.last() == Some(&b'\r') {
+                self.buf.pop();
+            }
+        }
+        self.line += 1;
+        let bytes = std::mem::take(&mut self.buf);
+        match String::from_utf8(bytes) {
+            Ok(l) => Ok(Some(l)),
+            Err(e) if self.lenient => {
+                if let Some(c) = &self.recoveries {
+                    c.fetch_add(1, Ordering::Relaxed);
+                }
+                Ok(Some(String::from_utf8_lossy(e.as_bytes()).into_owned()))
+            }
+            Err(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("line {}: stream did not contain valid UTF-8", self.line),
+            )),
+        }
+    }
+
+    /// Put `line` back; the next `next_line` returns it again.
+    pub(crate) fn unread(&mut self, line: String) {
+        self.pending.push(line);
+        self.line -= 1;
+    }
+}
+
+/// An input that isn't a file. It never ends, like a followed file.
+#[derive(Clone, Debug)]
+pub enum StreamSource {
+    /// `--listen tcp://...` / `udp://...`
+    Listen(ListenAddr),
+    /// `--device`: a named pipe, COM port, FIFO or tty.
+    Device(PathBuf),
+}
+
+/// One input file plus its optional overrides.
+#[derive(Clone, Debug, Default)]
+pub struct InputSpec {
+    pub path: PathBuf,
+    pub tz: Option<String>,
+    /// Read from this instead; `path` then only names the format and the
+    /// output.
+    pub stream: Option<StreamSource>,
+    /// From the config file's `[[input]]` entry for `path`; see
+    /// [`ParseOptions`] for `format` and `labels`.
+    pub format: Option<&'static str>,
+    pub labels: Vec<(String, String)>,
+    /// Record directory instead of `--record-dir`.
+    pub record_dir: Option<PathBuf>,
+}
+
+impl InputSpec {
+    /// Parse `PATH[:IANA_TZ]`. The suffix only counts as a timezone when it
+    /// actually names one, so Windows drive letters (`C:\...`) survive.
+    pub fn parse(s: &str) -> Self {
+        if let Some((path, tz)) = s.rsplit_once(':')
+            && !path.is_empty()
+            && tz.parse::<Tz>().is_ok()
+        {
+            return Self { path: PathBuf::from(path), tz: Some(tz.to_string()), ..Default::default() };
+        }
+        Self { path: PathBuf::from(s), ..Default::default() }
+    }
+
+    /// Whether the input comes with its recordings (Uniden), so that
+    /// transcribing it needs no Record directory.
+    pub fn brings_audio(&self) -> bool {
+        formats::for_path(&self.path, self.format).info().name == "uniden"
+    }
+
+    /// Fill in what the config file's `[[input]]` entry sets; a timezone
+    /// given on the command line wins.
+    pub fn with_config(mut self, c: &InputConfig) -> Result<Self, AppError> {
+        let err = |what: String| AppError::Parse(format!("[[input]] {}: {}", c.path.display(), what));
+        if let Some(name) = c.format.as_deref() {
+            let f = formats::named(name)
+                .ok_or_else(|| err(format!("unknown format '{}' (use {})", name, formats::names())))?;
+            self.format = Some(f.info().name);
+        }
+        if let Some(tz) = c.tz.as_deref() {
+            tz.parse::<Tz>().map_err(|_| err(format!("unknown timezone '{}'", tz)))?;
+            self.tz = self.tz.or_else(|| Some(tz.to_string()));
+        }
+        for (key, value) in [("system", &c.system), ("site", &c.site)] {
+            if let Some(v) = value {
+                self.labels.push((key.to_string(), v.clone()));
+            }
+        }
+        if c.record_dir.is_some() {
+            self.record_dir = c.record_dir.clone();
+        }
+        Ok(self)
+    }
+}
+
+/// Extract a `YYYYMMDD` / `YYYY-MM-DD` / `YYYY_MM_DD` date from a file name
+/// (e.g. `20250909_DSDPlus.event`, `DSDPlus-2025-09-09.srt`).
+pub fn date_from_filename(path: &Path) -> Option<NaiveDate> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"(?:^|\D)((?:19|20)\d{2})[-_]?(\d{2})[-_]?(\d{2})(?:\D|$)").unwrap());
+    let name = path.file_name()?.to_str()?;
+    let caps = re.captures(name)?;
+    NaiveDate::from_ymd_opt(caps[1].parse().ok()?, caps[2].parse().ok()?, caps[3].parse().ok()?)
+}
+
+/// Resolves time-only log lines against a base date, advancing the date when
+/// the clock wraps past midnight (a jump backwards of more than 12 hours).
+pub struct TimeOnlyClock {
+    date: NaiveDate,
+    last: Option<NaiveTime>,
+}
+
+impl TimeOnlyClock {
+    pub fn new(date: NaiveDate) -> Self {
+        Self { date, last: None }
+    }
+
+    pub fn resolve(&mut self, t: NaiveTime) -> NaiveDateTime {
+        if let Some(prev) = self.last
+            && (prev - t).num_hours() >= 12
+            && let Some(next) = self.date.succ_opt()
+        {
+            self.date = next;
+        }
+        self.last = Some(t);
+        self.date.and_time(t)
+    }
+}
+
+/// `(errors, quality)` from the signal tokens DSDPlus puts on some lines:
+/// error counts as `ERR=3` / `errs=3` / `Errors=3`, and quality in percent as
+/// `Q=85`, `Sync=85%` or `Quality=85.5%`. Later tokens win.
+pub(crate) fn signal_quality(s: &str) -> (Option<u32>, Option<u32>) {
+    let (mut errors, mut quality) = (None, None);
+    for tok in s.split_whitespace() {
+        let Some((k, v)) = tok.split_once('=') else { continue };
+        let v = v.trim_end_matches(['%', ',', ';']);
+        match k.to_ascii_lowercase().as_str() {
+            "err" | "errs" | "errors" => errors = v.parse::<u32>().ok().or(errors),
+            "q" | "sync" | "quality" => {
+                quality = v.parse::<f64>().ok().map(|q| q.round().clamp(0.0, 100.0) as u32).or(quality)
+            }
+            _ => {}
+        }
+    }
+    (errors, quality)
+}
+
+/// Whether `s` marks a call as encrypted: an `Encrypted`/`ENC` word, or an
+/// `ALG=`/`ALGID=` token naming an algorithm other than clear (P25 `0x80`).
+pub(crate) fn encrypted(s: &str) -> bool {
+    s.split(|c: char| c.is_whitespace() || c == ';' || c == ',').any(|tok| {
+        if let Some((k, v)) = tok.split_once('=') {
+            let k = k.to_ascii_lowercase();
+            if k == "alg" || k == "algid" {
+                let v = v.trim_start_matches("0x").trim_start_matches("0X");
+                return u32::from_str_radix(v, 16).is_ok_and(|a| a != 0 && a != 0x80);
+            }
+            return false;
+        }
+        matches!(tok.to_ascii_lowercase().as_str(), "encrypted" | "enc")
+    })
+}
+
+/// Keys the parsers map to record fields; any other `KEY=VALUE` token is an extra.
+const KNOWN_KEYS: &[&str] = &[
+    "freq", "nac", "dcc", "tg", "rid", "tgt", "slot", "err", "errs", "errors", "q", "sync", "quality",
+];
+
+/// Add the `KEY=VALUE` tokens of `s` that no parser understands to `extras`.
+/// The first value seen for a key is kept.
+pub(crate) fn collect_extras(s: &str, extras: &mut BTreeMap<String, String>) {
+    for tok in s.split_whitespace() {
+        let Some((k, v)) = tok.split_once('=') else { continue };
+        if k.is_empty() || v.is_empty() || !k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            continue;
+        }
+        if !KNOWN_KEYS.iter().any(|known| known.eq_ignore_ascii_case(k)) {
+            extras.entry(k.to_string()).or_insert_with(|| v.trim_end_matches([',', ';']).to_string());
+        }
+    }
+}
+
+/// Value of `RadioRecord::source` for records read from `path`: the file
+/// name, so ids don't depend on the directory a run was started from.
+pub(crate) fn source_name(path: &Path) -> Arc<str> {
+    path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into()
+}
+
+/// Pick a parser for `path` (see [`formats::for_path`]) and stream its
+/// records into `tx`.
+pub async fn stream_input(
+    path: &Path,
+    opts: ParseOptions,
+    tx: Sender<RadioRecord>,
+) -> Result<(), AppError> {
+    let format = formats::for_path(path, opts.format);
+    format.stream(path, opts, tx).await
+}
+
+/// How long a followed input sleeps at EOF before checking for new data.
+const FOLLOW_POLL: Duration = Duration::from_millis(500);
+
+/// `AsyncRead` adapter that never reports EOF: when the underlying file has
+/// no more data it sleeps and retries, like `tail -f`.
+pub struct FollowReader<R> {
+    inner: R,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl<R> FollowReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, sleep: None }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for FollowReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(s) = this.sleep.as_mut() {
+                ready!(s.as_mut().poll(cx));
+                this.sleep = None;
+            }
+            let before = buf.filled().len();
+            ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+            if buf.filled().len() > before {
+                return Poll::Ready(Ok(()));
+            }
+            this.sleep = Some(Box::pin(tokio::time::sleep(FOLLOW_POLL)));
+        }
+    }
+}
+
+/// `AsyncRead` adapter that adds every byte read to a shared counter.
+pub struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for CountingReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+        this.count.fetch_add((buf.filled().len() - before) as u64, Ordering::Relaxed);
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Open an input file for a parser, following it past EOF when requested;
+/// with `--listen` / `--device`, the socket or device instead.
+pub async fn open_input(
+    path: &Path,
+    opts: &ParseOptions,
+) -> Result<Box<dyn AsyncRead + Unpin + Send>, AppError> {
+    let reader: Box<dyn AsyncRead + Unpin + Send> = match &opts.stream {
+        Some(StreamSource::Listen(addr)) => net_input::open(addr).await?,
+        Some(StreamSource::Device(dev)) => device_input::open(dev).await?,
+        None => {
+            let file = File::open(path)
+                .await
+                .map_err(|e| AppError::IO(format!("open {}: {}", path.display(), e)))?;
+            if opts.follow { Box::new(FollowReader::new(file)) } else { Box::new(file) }
+        }
+    };
+    match &opts.bytes_read {
+        Some(count) => Ok(Box::new(CountingReader { inner: reader, count: Arc::clone(count) })),
+        None => Ok(reader),
+    }
+}
@@ -0,0 +1,187 @@
+// src/keyword_index.rs
+//
+// Optional pass-through stage that builds an inverted index over transcript
+// text (word -> records) and writes it next to the main output as a
+// tab-separated file, one word per line, so `grep -P '^water\t'` answers
+// "which calls mention water" without a database.
+//
+// The index is rewritten every minute while records arrive, so a followed
+// run has a current one on disk. With `--rotate hourly|daily` each period's
+// words go to an index named after that period's output file
+// (`<stem>-YYYYMMDD-HHMMSS.keywords.tsv`) and are then dropped, which keeps
+// a long-running daemon's index from growing without bound.
+
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use crate::rotation::{RotationPolicy, Rotator};
+use log::{debug, info, warn};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc::{Receiver, Sender};
+
+/// Words shorter than this are not indexed.
+const MIN_WORD_LEN: usize = 2;
+
+/// How often a changed index is rewritten while the stream runs.
+const FLUSH_EVERY: Duration = Duration::from_secs(60);
+
+/// Lowercased alphanumeric words of `text` (apostrophes dropped), deduplicated.
+pub fn words(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .map(|w| w.replace('\'', "").to_lowercase())
+        .filter(|w| w.chars().count() >= MIN_WORD_LEN)
+        .collect()
+}
+
+/// `<out stem>.keywords.tsv` next to the main output.
+pub fn index_path(out_path: &Path) -> PathBuf {
+    out_path.with_extension("keywords.tsv")
+}
+
+/// Write `index` to `path` through a temporary file, so a reader never sees
+/// half an index.
+async fn write_index(path: &Path, index: &BTreeMap<String, Vec<String>>) -> Result<(), AppError> {
+    let tmp = path.with_extension("tsv.tmp");
+    let file = tokio::fs::File::create(&tmp)
+        .await
+        .map_err(|e| AppError::IO(format!("open keyword index '{}': {}", tmp.display(), e)))?;
+    let mut w = BufWriter::new(file);
+    for (word, postings) in index {
+        let line = format!("{}\t{}\t{}\n", word, postings.len(), postings.join(" "));
+        w.write_all(line.as_bytes()).await?;
+    }
+    w.flush().await?;
+    drop(w);
+    tokio::fs::rename(&tmp, path)
+        .await
+        .map_err(|e| AppError::IO(format!("write keyword index '{}': {}", path.display(), e)))?;
+    debug!("keyword_index: {} words -> {}", index.len(), path.display());
+    Ok(())
+}
+
+/// Forward every record unchanged while indexing its transcript text. Line
+/// format: `word<TAB>count<TAB>record_id@datetime record_id@datetime ...`.
+/// `rotate` is the main output's `--rotate`; only the time-based policies
+/// split the index, a size-rotated output keeps one index for the run.
+pub async fn index_keywords_stream(
+    mut rx: Receiver<RadioRecord>,
+    tx: Sender<RadioRecord>,
+    out_path: PathBuf,
+    rotate: Option<RotationPolicy>,
+) -> Result<Vec<PathBuf>, AppError> {
+    let mut rotator = match rotate {
+        Some(policy @ (RotationPolicy::Hourly | RotationPolicy::Daily)) => Some(Rotator::new(&out_path, policy, None)),
+        _ => None,
+    };
+    let mut path = index_path(&out_path);
+    let mut written = Vec::new();
+    let mut index: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut dirty = false;
+    let mut flush = tokio::time::interval(FLUSH_EVERY);
+    flush.tick().await;
+
+    loop {
+        let rec = tokio::select! {
+            rec = rx.recv() => match rec {
+                Some(rec) => rec,
+                None => break,
+            },
+            _ = flush.tick() => {
+                if dirty {
+                    write_index(&path, &index).await?;
+                    dirty = false;
+                }
+                continue;
+            }
+        };
+        if let Some(r) = rotator.as_mut().filter(|r| r.needs_rotation(&rec)) {
+            if !index.is_empty() {
+                write_index(&path, &index).await?;
+                info!("Keyword index: {} words -> {}", index.len(), path.display());
+                index.clear();
+            }
+            path = index_path(&r.next_path(&rec));
+            dirty = false;
+        }
+
+        let texts = [rec.slot1.text.as_deref(), rec.slot2.text.as_deref()];
+        let mut seen = BTreeSet::new();
+        for text in texts.into_iter().flatten() {
+            seen.extend(words(text));
+        }
+        if !seen.is_empty() {
+            let posting = format!("{}@{}", rec.record_id(), rec.datetime.format("%Y-%m-%dT%H:%M:%S"));
+            for w in seen {
+                index.entry(w).or_default().push(posting.clone());
+            }
+            dirty = true;
+            if written.last() != Some(&path) {
+                written.push(path.clone());
+            }
+        }
+        if tx.send(rec).await.is_err() {
+            warn!("keyword_index: downstream closed");
+            break;
+        }
+    }
+
+    if rotator.is_none() || !index.is_empty() {
+        write_index(&path, &index).await?;
+        info!("Keyword index: {} words -> {}", index.len(), path.display());
+        if written.last() != Some(&path) {
+            written.push(path);
+        }
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, TimeZone};
+    use std::fs;
+    use tokio::sync::mpsc;
+
+    fn rec(day: u32, text: &str) -> RadioRecord {
+        let dt = FixedOffset::east_opt(0).unwrap().with_ymd_and_hms(2025, 9, day, 6, 0, 0).unwrap();
+        let mut r = RadioRecord::new(1, dt);
+        r.slot1.tg = Some("2".into());
+        r.slot1.text = Some(text.into());
+        r
+    }
+
+    #[test]
+    fn daily_rotation_splits_the_index_and_keys_by_record_id() {
+        let dir = std::env::temp_dir().join(format!("callscribe-keywords-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let calls = [rec(10, "Water main break"), rec(10, "water on Main"), rec(11, "Engine two")];
+        let ids: Vec<String> = calls.iter().map(RadioRecord::record_id).collect();
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let written = rt
+            .block_on(async {
+                let (tx_in, rx_in) = mpsc::channel(8);
+                let (tx_out, mut rx_out) = mpsc::channel(8);
+                for call in calls {
+                    tx_in.send(call).await.unwrap();
+                }
+                drop(tx_in);
+                let out = dir.join("capture.csv");
+                let (written, _) = tokio::join!(
+                    index_keywords_stream(rx_in, tx_out, out, Some(RotationPolicy::Daily)),
+                    async { while rx_out.recv().await.is_some() {} }
+                );
+                written
+            })
+            .unwrap();
+        let names: Vec<_> = written.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(names, ["capture-20250910-060000.keywords.tsv", "capture-20250911-060000.keywords.tsv"]);
+        let day10 = fs::read_to_string(&written[0]).unwrap();
+        let line = day10.lines().find(|l| l.starts_with("water\t")).expect("water");
+        assert_eq!(line, format!("water\t2\t{0}@2025-09-10T06:00:00 {1}@2025-09-10T06:00:00", ids[0], ids[1]));
+        assert!(!day10.contains("engine"));
+        assert!(fs::read_to_string(&written[1]).unwrap().starts_with("engine\t1\t"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
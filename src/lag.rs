@@ -0,0 +1,116 @@
+// src/lag.rs
+//
+// How far a followed pipeline is behind its input: the bytes the input files
+// have grown by that the parser hasn't read yet, and the calls RLE has
+// produced that transcription hasn't passed on. Checked every few seconds;
+// past `--lag-warn` bytes or `--lag-warn-calls` calls a warning is logged
+// (then every few minutes while it lasts, and once more on catching up), and
+// the figures go into the daemon's status line and the stats.
+//
+// With `--lag-shed-stt` the transcription stage stops transcribing calls
+// nobody is watching (no alert, not a `--stt-priority-tg`) while the
+// pipeline is behind, and forwards them without text until it is back under
+// half of both limits. `transcribe --in` fills them in later.
+
+use crate::stats::PipelineStats;
+use log::{info, warn};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+/// Default `--lag-warn`: 1 MiB of log is some minutes of a busy system.
+pub const DEFAULT_WARN_BYTES: u64 = 1 << 20;
+
+/// Default `--lag-warn-calls`.
+pub const DEFAULT_WARN_CALLS: u64 = 200;
+
+/// How often the input's size is checked.
+const CHECK_EVERY: Duration = Duration::from_secs(5);
+
+/// How often a lasting lag is logged again.
+const WARN_EVERY: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone)]
+pub struct LagOptions {
+    pub warn_bytes: u64,
+    pub warn_calls: u64,
+    /// Skip STT for unwatched calls while behind (`--lag-shed-stt`).
+    pub shed_stt: bool,
+}
+
+impl Default for LagOptions {
+    fn default() -> Self {
+        Self { warn_bytes: DEFAULT_WARN_BYTES, warn_calls: DEFAULT_WARN_CALLS, shed_stt: false }
+    }
+}
+
+/// Bytes behind and calls queued, right now.
+fn measure(files: &[PathBuf], stats: &PipelineStats) -> (u64, u64) {
+    let size: u64 = files.iter().filter_map(|p| std::fs::metadata(p).ok()).map(|m| m.len()).sum();
+    // A file truncated or replaced by a new one reads as caught up.
+    let bytes = size.saturating_sub(stats.input_read.load(Ordering::Relaxed));
+    let calls = stats.rle.records_out().saturating_sub(stats.transcribe.records_out());
+    (bytes, calls)
+}
+
+/// `512 KiB`, `1.5 MiB`.
+pub fn size(bytes: u64) -> String {
+    if bytes >= 1 << 20 {
+        format!("{:.1} MiB", bytes as f64 / (1 << 20) as f64)
+    } else {
+        format!("{} KiB", bytes / 1024)
+    }
+}
+
+/// Watch one pipeline reading `files` until it finishes.
+pub async fn monitor(name: String, files: Vec<PathBuf>, stats: Arc<PipelineStats>, opts: LagOptions) {
+    let mut interval = tokio::time::interval(CHECK_EVERY);
+    let mut behind_since: Option<Instant> = None;
+    let mut last_warn = Instant::now();
+    while stats.finished.get().is_none() {
+        interval.tick().await;
+        let (bytes, calls) = measure(&files, &stats);
+        stats.lag_bytes.store(bytes, Ordering::Relaxed);
+        stats.lag_calls.store(calls, Ordering::Relaxed);
+        stats.lag_peak_bytes.fetch_max(bytes, Ordering::Relaxed);
+        stats.lag_peak_calls.fetch_max(calls, Ordering::Relaxed);
+        let over = bytes > opts.warn_bytes || calls > opts.warn_calls;
+        let under_half = bytes <= opts.warn_bytes / 2 && calls <= opts.warn_calls / 2;
+        match behind_since {
+            None if over => {
+                let shed = if opts.shed_stt { "; skipping STT for unwatched calls" } else { "" };
+                warn!(
+                    "{}: falling behind the input: {} unread, {} calls waiting for transcription{}",
+                    name,
+                    size(bytes),
+                    calls,
+                    shed
+                );
+                behind_since = Some(Instant::now());
+                last_warn = Instant::now();
+                if opts.shed_stt {
+                    stats.shed_stt.store(true, Ordering::Relaxed);
+                }
+            }
+            Some(since) if under_half => {
+                let shed = stats.stt_shed.load(Ordering::Relaxed);
+                let shed = if opts.shed_stt { format!("; {} calls so far left untranscribed", shed) } else { String::new() };
+                info!("{}: caught up with the input after {}s{}", name, since.elapsed().as_secs(), shed);
+                behind_since = None;
+                stats.shed_stt.store(false, Ordering::Relaxed);
+            }
+            Some(since) if last_warn.elapsed() >= WARN_EVERY => {
+                warn!(
+                    "{}: still behind the input after {}s: {} unread, {} calls waiting",
+                    name,
+                    since.elapsed().as_secs(),
+                    size(bytes),
+                    calls
+                );
+                last_warn = Instant::now();
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// src/lib.rs
+//
+// Library surface shared by the `dsd_event_parser` binary and external
+// consumers (see the `ffi` feature for the C ABI).
+
+pub mod srt_stream;
+pub mod model;
+pub mod csv_sink;
+pub mod errors;
+pub mod transcriber;
+pub mod stt_registry;
+pub mod external_stt;
+pub mod postprocess;
+pub mod filter;
+pub mod alert;
+pub mod throttle;
+pub mod severity;
+pub mod freq;
+pub mod transcription_adder;
+pub mod rle_filter;
+pub mod renumber;
+pub mod replay;
+pub mod sort;
+pub mod event_stream;
+pub mod uniden;
+pub mod unitrunker;
+pub mod analog_log;
+pub mod affiliation;
+pub mod input;
+pub mod lag;
+pub mod formats;
+pub mod net_input;
+pub mod device_input;
+pub mod radio_type;
+pub mod slot_policy;
+pub mod tz;
+pub mod features;
+pub mod stats;
+pub mod manifest;
+pub mod summary;
+pub mod anomaly;
+pub mod processed;
+pub mod unit_registry;
+pub mod sdrtrunk;
+pub mod rotation;
+pub mod output_file;
+pub mod sink;
+pub mod retention;
+pub mod influx_sink;
+pub mod syslog_sink;
+pub mod keyword_index;
+pub mod audio_match;
+#[cfg(feature = "audio-fingerprint")]
+pub mod fingerprint;
+pub mod playlist;
+pub mod utilization;
+pub mod serve;
+pub mod grafana;
+pub mod models;
+pub mod config;
+pub mod budget;
+pub mod backfill;
+pub mod winpath;
+pub mod record_name;
+pub mod selftest;
+pub mod golden;
+pub mod explain;
+pub mod probe;
+pub mod assets;
+pub mod init;
+pub mod sd_notify;
+pub mod progress;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "gui")]
+pub mod gui;
+#[cfg(feature = "sink-postgres")]
+pub mod pg_sink;
+#[cfg(feature = "sink-template")]
+pub mod template_sink;
+#[cfg(feature = "sink-elasticsearch")]
+pub mod es_sink;
+#[cfg(feature = "sink-sqlite")]
+pub mod sqlite_sink;
+#[cfg(feature = "stt-whisper")]
+pub mod whisper;
+#[cfg(any(feature = "stt-whisper", feature = "stt-segments", feature = "audio-fingerprint"))]
+pub mod audio_prep;
+#[cfg(feature = "stt-segments")]
+pub mod segments;
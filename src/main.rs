@@ -1,24 +1,44 @@
 // src/main.rs
 
+// `run_options` is one `json!` object, deeper than the default limit.
+#![recursion_limit = "256"]
+
 mod cli;
-mod srt_stream;
-mod model;
-mod csv_sink;
-mod errors;
-mod transcriber;
-mod filter;
-mod transcription_adder;
-mod rle_filter;
-mod event_stream;
-
-use crate::errors::AppError;
-use chrono::{FixedOffset, Utc};
-use chrono_tz::Tz;
+
+use dsd_event_parser::budget::{self, MemoryBudget, PipelineBudget};
+use dsd_event_parser::config::{Aliases, Config, ProfileConfig};
+use dsd_event_parser::device_input;
+use dsd_event_parser::net_input::ListenAddr;
+use dsd_event_parser::radio_type::RadioTypes;
+use dsd_event_parser::slot_policy::SlotPolicies;
+use dsd_event_parser::errors::AppError;
+use dsd_event_parser::freq::FreqTolerance;
+use dsd_event_parser::formats;
+use dsd_event_parser::input::{InputSpec, ParseOptions, StreamSource};
+use dsd_event_parser::csv_sink::{self, CsvSinkOptions};
+use dsd_event_parser::influx_sink::InfluxSinkOptions;
+use dsd_event_parser::manifest::{self, InputReport, OutputFile, RunManifest};
+use dsd_event_parser::output_file::WriteMode;
+use dsd_event_parser::rotation::{self, RotationPolicy};
+use dsd_event_parser::postprocess::{PostProcessed, PostProcessor};
+use dsd_event_parser::retention::{self, RetentionPolicy};
+use dsd_event_parser::sink::{self, DurationFormat, SinkKind};
+use dsd_event_parser::stats::PipelineStats;
+use dsd_event_parser::stt_registry::{self, SharedTranscriber};
+use dsd_event_parser::syslog_sink::SyslogSinkOptions;
+use dsd_event_parser::{
+    affiliation, alert, audio_match, backfill, features, filter, input, keyword_index, lag, model, models, playlist, processed, progress,
+    rle_filter, renumber, replay, sd_notify, serve, severity, sort, summary, throttle, transcriber, transcription_adder, tz,
+    unit_registry, utilization,
+};
 use env_logger::Env;
-use log::{info, warn};
-use std::path::PathBuf;
+use log::{debug, info, warn};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
 
 fn setup_logging(level: &str) {
     let env = Env::default().filter_or(
@@ -35,150 +55,1615 @@ fn setup_logging(level: &str) {
     env_logger::Builder::from_env(env).init();
 }
 
-fn compute_tz_offset(args_tz: &Option<String>) -> Option<FixedOffset> {
-    if let Some(tzname) = args_tz.as_ref() {
-        match tzname.parse::<Tz>() {
-            Ok(tz) => {
-                // Map "now" in that time zone to a fixed offset
-                let now_utc = Utc::now();
-                let now_tz = now_utc.with_timezone(&tz);
-                let seconds = (now_tz.naive_local() - now_tz.naive_utc()).num_seconds();
-                FixedOffset::east_opt(seconds as i32)
-            }
-            Err(_) => {
-                warn!("Timezone parse failed; falling back to local");
-                None
-            }
+#[tokio::main(flavor = "multi_thread")]
+async fn main() -> Result<(), AppError> {
+    match cli::parse_command() {
+        cli::Command::Run(args) => run(*args).await,
+        cli::Command::Search(args) => search(args).await,
+        cli::Command::Anomalies(args) => anomalies(args).await,
+        cli::Command::Serve(args) => serve(args).await,
+        cli::Command::Models(args) => models_cmd(args).await,
+        cli::Command::Transcribe(args) => transcribe_cmd(args).await,
+        cli::Command::Selftest(args) => selftest(args).await,
+        cli::Command::Golden(args) => golden(args).await,
+        cli::Command::Formats => {
+            formats();
+            Ok(())
+        }
+        cli::Command::Explain(args) => explain(args).await,
+        cli::Command::Probe(args) => probe(args).await,
+        cli::Command::Init(args) => init(args),
+        cli::Command::Registry(args) => registry(args),
+        cli::Command::Maintain(args) => maintain(args).await,
+        cli::Command::Gui => gui(),
+        cli::Command::SystemdUnit(rest) => {
+            print!("{}", systemd_unit(&rest));
+            Ok(())
         }
+    }
+}
+
+#[cfg(feature = "gui")]
+fn gui() -> Result<(), AppError> {
+    setup_logging("warn");
+    dsd_event_parser::gui::run()
+}
+
+#[cfg(not(feature = "gui"))]
+fn gui() -> Result<(), AppError> {
+    Err(AppError::Other("gui needs a build with the gui feature".into()))
+}
+
+/// Quote one `ExecStart=` word for systemd: `%` and `$` are specifiers and
+/// variables there, and words with spaces need quotes.
+fn systemd_quote(arg: &str) -> String {
+    let a = arg.replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%").replace('$', "$$");
+    if a.is_empty() || a.contains(char::is_whitespace) {
+        format!("\"{}\"", a)
     } else {
-        None
+        a
     }
 }
 
-#[tokio::main(flavor = "multi_thread")]
-async fn main() -> Result<(), AppError> {
-    let args = cli::parse_cli();
+/// Unit file for `daemon ARGS...` run by the current executable.
+fn systemd_unit(args: &[String]) -> String {
+    let exe = std::env::current_exe().map_or_else(|_| "/usr/local/bin/dsd_event_parser".into(), |p| p.display().to_string());
+    let exec: Vec<String> = std::iter::once(exe.as_str())
+        .chain(std::iter::once("daemon"))
+        .chain(args.iter().map(String::as_str))
+        .map(systemd_quote)
+        .collect();
+    format!(
+        "# Install as /etc/systemd/system/callscribe.service, then\n\
+         #   systemctl daemon-reload && systemctl enable --now callscribe\n\
+         # Options can also go in /etc/callscribe/env as CALLSCRIBE_* variables.\n\
+         [Unit]\n\
+         Description=Callscribe DSDPlus call log converter\n\
+         After=network-online.target\n\
+         Wants=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=notify\n\
+         NotifyAccess=main\n\
+         ExecStart={}\n\
+         EnvironmentFile=-/etc/callscribe/env\n\
+         Restart=on-failure\n\
+         RestartSec=5\n\
+         WatchdogSec=120\n\
+         TimeoutStopSec=60\n\
+         \n\
+         [Install]\n\
+         WantedBy=multi-user.target\n",
+        exec.join(" ")
+    )
+}
+
+fn formats() {
+    for f in formats::all() {
+        let f = f.info();
+        println!("{} (.{})\n  {}\n", f.name, f.extensions.join(", ."), f.description);
+        for line in f.sample.lines() {
+            println!("    {}", line);
+        }
+        println!();
+    }
+}
+
+async fn explain(args: cli::ExplainArgs) -> Result<(), AppError> {
     setup_logging(&args.log_level);
-    info!("Starting: processing {} files", args.input_files.len());
-
-    // Build transcriber (only "text" is supported; uses incremental day-sharded index)
-    let transcriber: Option<Arc<dyn transcriber::Transcriber + Send + Sync>> =
-        match args.transcriber.as_str() {
-            "text" => {
-                if let Some(root) = args.record_dir.as_ref() {
-                    let t = transcriber::TextFileTranscriber::new_indexed(root)?;
-                    Some(Arc::new(t))
-                } else {
-                    warn!("--transcriber text used without --record-dir; no transcripts will be found");
-                    let t = transcriber::TextFileTranscriber::new();
-                    Some(Arc::new(t))
+    let opts = ParseOptions {
+        tz_offset: tz::compute_tz_offset(&args.tz),
+        base_date: if args.date_from_filename { input::date_from_filename(&args.input) } else { None },
+        ..Default::default()
+    };
+    let mut out = std::io::stdout().lock();
+    dsd_event_parser::explain::explain(&args.input, opts, args.blocks, &mut out).await?;
+    Ok(())
+}
+
+async fn probe(args: cli::ProbeArgs) -> Result<(), AppError> {
+    use dsd_event_parser::probe::ProbeOptions;
+    setup_logging(&args.log_level);
+    let config = Config::load_optional(args.config.as_deref())?;
+    let exts = |set: &Option<Vec<String>>, default: &[&str]| {
+        set.clone().unwrap_or_else(|| default.iter().map(|e| e.to_string()).collect())
+    };
+    let probe = ProbeOptions {
+        transcript_extensions: exts(&config.record.transcript_extensions, transcriber::TRANSCRIPT_EXTENSIONS),
+        audio_extensions: exts(&config.record.audio_extensions, transcriber::AUDIO_EXTENSIONS),
+        freq_tolerance: args.freq_tolerance.as_deref().map(FreqTolerance::parse).transpose()?.unwrap_or_default(),
+        audio_window: args.audio_window,
+        examples: args.examples,
+    };
+    transcriber::check_record_root(&args.record_dir, &probe.transcript_extensions)
+        .or_else(|_| transcriber::check_record_root(&args.record_dir, &probe.audio_extensions))?;
+    let opts = ParseOptions {
+        tz_offset: tz::compute_tz_offset(&args.tz),
+        base_date: if args.date_from_filename { input::date_from_filename(&args.input) } else { None },
+        ..Default::default()
+    };
+    let mut out = std::io::stdout().lock();
+    dsd_event_parser::probe::probe(&args.input, opts, &args.record_dir, &probe, &mut out).await?;
+    Ok(())
+}
+
+fn init(args: cli::InitArgs) -> Result<(), AppError> {
+    use std::io::Write;
+    setup_logging(&args.log_level);
+    let exe = std::env::current_exe().map_err(|e| AppError::IO(format!("init: this executable: {}", e)))?;
+    let mut out = std::io::stdout().lock();
+    let launcher = dsd_event_parser::init::init(&args.dir, &exe, args.force, &mut out)?;
+    writeln!(out, "\nEdit callscribe.toml as needed, then run {} with an event log or SRT file.", launcher.display())
+        .map_err(|e| AppError::IO(format!("write: {}", e)))
+}
+
+fn registry(args: cli::RegistryArgs) -> Result<(), AppError> {
+    use dsd_event_parser::sdrtrunk::{self, ExportFormat, ExportOptions};
+    use dsd_event_parser::unit_registry::{ShowOptions, UnitRegistry};
+    use std::io::Write;
+    setup_logging(&args.log_level);
+    if args.action != "show" && args.action != "export" {
+        return Err(AppError::Other(format!("unknown registry action '{}' (use show or export)", args.action)));
+    }
+    if !args.file.exists() {
+        return Err(AppError::Other(format!("no unit registry at {} (a run with --unit-registry creates it)", args.file.display())));
+    }
+    let opts = ShowOptions::parse(&args.kind, &args.sort, args.ids, args.limit)?;
+    let registry = UnitRegistry::load(&args.file)?;
+    if args.action == "show" {
+        return registry.show(&opts, &mut std::io::stdout().lock());
+    }
+    let config = Config::load_optional(args.config.as_deref())?;
+    let export = ExportOptions {
+        format: ExportFormat::parse(&args.format)?,
+        list: args.alias_list.clone(),
+        protocol: args.protocol.clone(),
+    };
+    match &args.out {
+        Some(path) => {
+            let io = |e: std::io::Error| AppError::IO(format!("write {}: {}", path.display(), e));
+            let mut out = std::io::BufWriter::new(std::fs::File::create(path).map_err(io)?);
+            let n = sdrtrunk::export(&registry, &opts, &config.alias, &export, &mut out)?;
+            out.flush().map_err(io)?;
+            info!("Wrote {} aliases to {}", n, path.display());
+        }
+        None => {
+            sdrtrunk::export(&registry, &opts, &config.alias, &export, &mut std::io::stdout().lock())?;
+        }
+    }
+    Ok(())
+}
+
+async fn golden(args: cli::GoldenArgs) -> Result<(), AppError> {
+    use dsd_event_parser::golden::{self, Outcome};
+    setup_logging(&args.log_level);
+    let fixtures = golden::fixtures(&args.dir)?;
+    let mut failed = 0;
+    for f in &fixtures {
+        let name = f.file_name().unwrap_or_default().to_string_lossy();
+        let outcome = golden::check(f, args.update).await?;
+        let detail = match &outcome {
+            Outcome::Match(n) => format!("{} records as expected", n),
+            Outcome::Updated(n) => format!("wrote {} records to {}", n, golden::golden_path(f).display()),
+            Outcome::Missing => format!("no {}; create it with --update-golden", golden::golden_path(f).display()),
+            Outcome::Mismatch { line, expected, got } => format!(
+                "record {} differs\n        expected: {}\n        got:      {}",
+                line,
+                expected.as_deref().unwrap_or("(end of file)"),
+                got.as_deref().unwrap_or("(no more records)")
+            ),
+        };
+        if !outcome.ok() {
+            failed += 1;
+        }
+        println!("{:4}  {}: {}", if outcome.ok() { "ok" } else { "FAIL" }, name, detail);
+    }
+    match failed {
+        0 => Ok(()),
+        n => Err(AppError::Other(format!("golden: {} of {} fixtures failed", n, fixtures.len()))),
+    }
+}
+
+async fn selftest(args: cli::SelftestArgs) -> Result<(), AppError> {
+    setup_logging(&args.log_level);
+    println!("{}", features::version_string());
+    let checks = dsd_event_parser::selftest::run(args.record_dir.as_deref()).await?;
+    for c in &checks {
+        println!("{:4}  {}: {}", if c.ok { "ok" } else { "FAIL" }, c.name, c.detail);
+    }
+    match checks.iter().filter(|c| !c.ok).count() {
+        0 => Ok(()),
+        n => Err(AppError::Other(format!("selftest: {} of {} checks failed", n, checks.len()))),
+    }
+}
+
+async fn transcribe_cmd(args: cli::TranscribeArgs) -> Result<(), AppError> {
+    setup_logging(&args.log_level);
+    let input = args
+        .input
+        .clone()
+        .ok_or_else(|| AppError::Other("transcribe needs --in <calls.csv|calls.db>".into()))?;
+    let record_dir = args
+        .record_dir
+        .clone()
+        .ok_or_else(|| AppError::Other("transcribe needs --record-dir".into()))?;
+    let config = Config::load_optional(args.config.as_deref())?;
+    let t = build_transcriber(&args.stt, Some(&record_dir), &config, FreqTolerance::default(), false)?
+        .ok_or_else(|| AppError::Other("transcribe needs a --transcriber other than none".into()))?;
+    let is_csv = input.extension().is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+    let s = if is_csv {
+        if !input.exists() {
+            return Err(AppError::IO(format!("no such file '{}'", input.display())));
+        }
+        backfill::backfill_csv(&input, args.out.as_deref(), t, record_dir, args.concurrency).await?
+    } else {
+        if args.out.is_some() {
+            return Err(AppError::Other("--out is for CSV files; a database is updated in place".into()));
+        }
+        backfill_db(&input, t, record_dir, args.concurrency).await?
+    };
+    info!(
+        "Backfill done: {} pending, {} transcribed, {} without transcript, {} errors",
+        s.pending, s.transcribed, s.missing, s.errors
+    );
+    Ok(())
+}
+
+#[cfg(feature = "sink-sqlite")]
+async fn backfill_db(
+    db: &Path,
+    t: SharedTranscriber,
+    record_dir: PathBuf,
+    concurrency: usize,
+) -> Result<backfill::BackfillSummary, AppError> {
+    backfill::backfill_sqlite(db, t, record_dir, concurrency).await
+}
+
+#[cfg(not(feature = "sink-sqlite"))]
+async fn backfill_db(
+    _db: &Path,
+    _t: SharedTranscriber,
+    _record_dir: PathBuf,
+    _concurrency: usize,
+) -> Result<backfill::BackfillSummary, AppError> {
+    Err(AppError::Other("transcribe on a database needs a build with the sink-sqlite feature".into()))
+}
+
+async fn models_cmd(args: cli::ModelsArgs) -> Result<(), AppError> {
+    setup_logging(&args.log_level);
+    let dir = args.dir.clone().unwrap_or_else(models::default_dir);
+    let named = || -> Result<&'static models::ModelInfo, AppError> {
+        let name = args
+            .name
+            .as_deref()
+            .ok_or_else(|| AppError::Other(format!("models {} needs a model name", args.action)))?;
+        models::find(name).ok_or_else(|| AppError::Other(format!("unknown model '{}' (see `models list`)", name)))
+    };
+    match args.action.as_str() {
+        "list" => {
+            println!("Model directory: {}", dir.display());
+            for m in models::KNOWN {
+                let state = if models::is_installed(&dir, m) { "installed" } else { "" };
+                println!("{:<8} {:<30} {:>6} MB  {}", m.backend.as_str(), m.name, m.size_mb, state);
+            }
+            Ok(())
+        }
+        "download" => {
+            let m = named()?;
+            let path = models::download(&dir, m).await?;
+            println!("{}", path.display());
+            Ok(())
+        }
+        "verify" => {
+            let selected: Vec<&models::ModelInfo> = match args.name {
+                Some(_) => vec![named()?],
+                None => models::KNOWN.iter().filter(|m| models::is_installed(&dir, m)).collect(),
+            };
+            let mut failed = 0;
+            for m in selected {
+                match models::verify(&dir, m)? {
+                    models::Verify::Ok { files } => println!("{}: OK ({} files)", m.name, files),
+                    models::Verify::NotInstalled => {
+                        failed += 1;
+                        println!("{}: not installed", m.name);
+                    }
+                    models::Verify::Mismatch(problems) => {
+                        failed += 1;
+                        println!("{}: FAILED", m.name);
+                        for p in problems {
+                            println!("  {}", p);
+                        }
+                    }
                 }
             }
-            "" => None,
-            _ => {
-                warn!(
-                    "Unknown transcriber '{}' — proceeding without transcription",
-                    args.transcriber
-                );
-                None
+            if failed > 0 {
+                return Err(AppError::Other(format!("{} model(s) failed verification", failed)));
             }
+            Ok(())
+        }
+        other => Err(AppError::Other(format!("unknown models action '{}' (list|download|verify)", other))),
+    }
+}
+
+async fn maintain(args: cli::MaintainArgs) -> Result<(), AppError> {
+    setup_logging(&args.log_level);
+    let config = Config::load_optional(args.config.as_deref())?;
+    let r = &args.retention;
+    let policy = RetentionPolicy::from_args(r.days, r.keep_alerts, r.archive, &config.retention)?
+        .ok_or_else(|| AppError::Other("maintain needs --retain-days (or [retention] days in --config)".into()))?;
+    let sink = maintained_sink(&args).await?;
+    retention::apply(&sink, &policy).await?;
+    Ok(())
+}
+
+/// The database `maintain` works on: `--pg-url`'s, else the SQLite `--out`.
+#[cfg_attr(not(all(feature = "sink-postgres", feature = "sink-sqlite")), allow(unused_variables))]
+async fn maintained_sink(args: &cli::MaintainArgs) -> Result<SinkKind, AppError> {
+    if let Some(url) = &args.pg_url {
+        #[cfg(feature = "sink-postgres")]
+        {
+            use dsd_event_parser::pg_sink::{PgSink, PgSinkOptions};
+            let opts = PgSinkOptions { url: url.clone(), table: args.pg_table.clone(), batch: 1, flush_when_idle: false };
+            return Ok(SinkKind::Postgres(PgSink::connect(opts).await?));
+        }
+        #[cfg(not(feature = "sink-postgres"))]
+        return Err(AppError::Other("maintain --pg-url needs a build with the sink-postgres feature".into()));
+    }
+    #[cfg(feature = "sink-sqlite")]
+    {
+        use dsd_event_parser::sqlite_sink::{SqliteSink, SqliteSinkOptions};
+        if !args.db.exists() {
+            return Err(AppError::IO(format!("no such database '{}'", args.db.display())));
+        }
+        let opts = SqliteSinkOptions { path: args.db.clone(), flush_when_idle: false };
+        Ok(SinkKind::Sqlite(SqliteSink::open(opts).await?))
+    }
+    #[cfg(not(feature = "sink-sqlite"))]
+    Err(AppError::Other("maintain on a SQLite database needs a build with the sink-sqlite feature".into()))
+}
+
+#[cfg(feature = "sink-sqlite")]
+async fn search(args: cli::SearchArgs) -> Result<(), AppError> {
+    setup_logging(&args.log_level);
+    let hits = dsd_event_parser::sqlite_sink::search(&args.db, &args.query, args.limit).await?;
+    for h in &hits {
+        println!(
+            "{}  {}  TG {}  RID {}  {}s  {}",
+            h.datetime,
+            h.frequency,
+            h.tg,
+            h.rid,
+            h.duration,
+            h.audio_path.as_deref().unwrap_or("-")
+        );
+        println!("    {}", h.text.trim());
+    }
+    info!("{} matches", hits.len());
+    Ok(())
+}
+
+#[cfg(not(feature = "sink-sqlite"))]
+async fn search(_args: cli::SearchArgs) -> Result<(), AppError> {
+    Err(AppError::Other("search needs a build with the sink-sqlite feature".into()))
+}
+
+#[cfg(feature = "sink-sqlite")]
+async fn anomalies(args: cli::AnomaliesArgs) -> Result<(), AppError> {
+    use dsd_event_parser::anomaly;
+    setup_logging(&args.log_level);
+    let counts = dsd_event_parser::sqlite_sink::hourly_counts(&args.db).await?;
+    let opts = anomaly::AnomalyOptions { sigma: args.sigma, min_calls: args.min_calls, days: args.days };
+    let found = anomaly::detect(&counts, &opts)?;
+    let report = anomaly::to_csv(&found);
+    match &args.report {
+        Some(path) => {
+            std::fs::write(path, report).map_err(|e| AppError::IO(format!("write {}: {}", path.display(), e)))?;
+            info!("{} anomalous hours -> {}", found.len(), path.display());
+        }
+        None => {
+            print!("{}", report);
+            info!("{} anomalous hours", found.len());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "sink-sqlite"))]
+async fn anomalies(_args: cli::AnomaliesArgs) -> Result<(), AppError> {
+    Err(AppError::Other("anomalies needs a build with the sink-sqlite feature".into()))
+}
+
+async fn serve(args: cli::ServeArgs) -> Result<(), AppError> {
+    setup_logging(&args.log_level);
+    let endpoints = serve::Endpoints { viewer: args.static_viewer, grafana: args.grafana };
+    if !endpoints.viewer && !endpoints.grafana {
+        return Err(AppError::Other(
+            "serve: nothing to serve; pass --static for the web viewer and/or --grafana for Grafana".into(),
+        ));
+    }
+    let source = serve::Source::open(&args.data).await?;
+    serve::serve(&args.listen, source, endpoints).await
+}
+
+async fn run(mut args: cli::CliArgs) -> Result<(), AppError> {
+    setup_logging(&args.log_level);
+    let mut config = Config::load_optional(args.config.as_deref())?;
+    if let Some(name) = args.profile.clone() {
+        let profile = config.use_profile(&name)?;
+        info!("Profile {}", name.trim());
+        apply_profile(&mut args, profile);
+    }
+    apply_env(&mut args);
+    if args.sink.is_empty() {
+        args.sink = "csv".into();
+    }
+    if args.daemon {
+        args.follow = true;
+        if args.sink.split(',').any(|s| s.trim() == "csv") && args.rotate.is_none() {
+            info!("--daemon: rotating CSV output daily (set --rotate to change)");
+            args.rotate = Some("daily".into());
+        }
+    }
+    let started = std::time::Instant::now();
+    let started_at = chrono::Local::now();
+    debug!("{}", features::version_string());
+    // Positional inputs use the global --tz; --input PATH[:TZ] may override it per file
+    let mut inputs: Vec<InputSpec> = args
+        .input_files
+        .iter()
+        .map(|p| InputSpec { path: p.clone(), ..Default::default() })
+        .chain(args.inputs.iter().map(|s| InputSpec::parse(s)))
+        .collect();
+    // [[input]] entries add settings to a command line input of the same
+    // path, or are inputs of their own.
+    for c in &config.input {
+        match inputs.iter().position(|s| s.path == c.path) {
+            Some(i) => inputs[i] = std::mem::take(&mut inputs[i]).with_config(c)?,
+            None => inputs.push(InputSpec { path: c.path.clone(), ..Default::default() }.with_config(c)?),
+        }
+    }
+    if !args.extras && inputs.iter().any(|s| !s.labels.is_empty()) {
+        info!("[[input]] system/site labels are written as extras; turning on --extras");
+        args.extras = true;
+    }
+    // The listener and the device are more inputs, named after the port /
+    // device and the format.
+    if args.listen.is_some() || args.device.is_some() {
+        let ext = match args.listen_format.trim().to_ascii_lowercase().as_str() {
+            f @ ("event" | "srt") => f.to_string(),
+            f => return Err(AppError::Other(format!("--listen-format: unknown format '{}' (use event, srt)", f))),
         };
+        if let Some(url) = args.listen.as_deref() {
+            let addr = ListenAddr::parse(url)?;
+            let path = PathBuf::from(format!("listen-{}.{}", addr.port(), ext));
+            inputs.push(InputSpec { path, stream: Some(StreamSource::Listen(addr)), ..Default::default() });
+        }
+        if let Some(dev) = args.device.clone() {
+            let path = PathBuf::from(format!("{}.{}", device_input::device_name(&dev), ext));
+            inputs.push(InputSpec { path, stream: Some(StreamSource::Device(dev)), ..Default::default() });
+        }
+        args.follow = true;
+    }
+    if args.sort && args.follow {
+        return Err(AppError::Other("--sort can't be combined with --follow/--daemon/--listen/--device: it reads a whole input before sorting, and a followed input never ends".into()));
+    }
+    let mut registry = if args.skip_processed {
+        if args.follow {
+            return Err(AppError::Other("--skip-processed can't be combined with --follow/--daemon/--listen/--device: a followed input never finishes".into()));
+        }
+        let path = args
+            .processed_state
+            .clone()
+            .unwrap_or_else(|| processed::Registry::default_path(inputs.first().map(|s| s.path.as_path())));
+        Some(processed::Registry::load(&path)?)
+    } else {
+        None
+    };
+    let mut fingerprints = HashMap::new();
+    if let Some(reg) = registry.as_mut() {
+        let mut done = HashSet::new();
+        for spec in &inputs {
+            match reg.check(&spec.path) {
+                Ok(processed::Check::Processed) => {
+                    done.insert(spec.path.clone());
+                }
+                Ok(processed::Check::Pending(f)) => {
+                    fingerprints.insert(spec.path.clone(), f);
+                }
+                // Left to the pipeline to report; not recorded.
+                Err(e) => warn!("--skip-processed: {}", e),
+            }
+        }
+        if !done.is_empty() {
+            info!("--skip-processed: {} of {} inputs already converted ({})", done.len(), inputs.len(), reg.path().display());
+        }
+        // A merge is one output: converted again in full if any file in it changed.
+        if !args.merge {
+            inputs.retain(|s| !done.contains(&s.path));
+        } else if done.len() == inputs.len() {
+            inputs.clear();
+        }
+    }
+    info!("Starting: processing {} files", inputs.len());
+
+    // Inputs without their own record_dir use --record-dir; when every input
+    // has one, the first stands in for it.
+    let record_dir = match &args.record_dir {
+        Some(dir) => Some(dir.clone()),
+        None if !inputs.is_empty() && inputs.iter().all(|s| s.record_dir.is_some()) => inputs[0].record_dir.clone(),
+        None => None,
+    };
+    let freq_tolerance = args.freq_tolerance.as_deref().map(FreqTolerance::parse).transpose()?.unwrap_or_default();
+    let radio_types = Arc::new(RadioTypes::new(&config.radio_type));
+    let slot_policies = Arc::new(SlotPolicies::new(&config.slot_policy, &radio_types)?);
+    let transcriber = if args.defer_transcription {
+        info!("Transcription deferred; fill it in later with `transcribe --in <output>`");
+        None
+    } else {
+        let bring_audio = !inputs.is_empty() && inputs.iter().all(InputSpec::brings_audio);
+        build_transcriber(&args.stt, record_dir.as_ref(), &config, freq_tolerance, bring_audio)?
+    };
+
+    let severity = severity::SeverityRules::new(&config.severity, &config.alias)?;
+    let severity = (!severity.is_empty()).then(|| Arc::new(severity));
+    if args.alert_severity.is_some() && severity.is_none() {
+        return Err(AppError::Other("--alert-severity needs [severity] rules in the config file".into()));
+    }
+    let sinks = build_sinks(&args, severity.is_some()).await?;
+    let r = &args.retention;
+    let retention = RetentionPolicy::from_args(r.days, r.keep_alerts, r.archive, &config.retention)?;
+    if r.days.is_some() && !sinks.iter().any(retention::applies) {
+        warn!("--retain-days only prunes the sqlite and postgres sinks; there is none in --sink {}", args.sink);
+    }
 
-    let tz_offset = compute_tz_offset(&args.tz);
+    let budget = args.max_memory.as_deref().map(MemoryBudget::parse).transpose()?.map(Arc::new);
+    if args.sort && budget.is_some() {
+        return Err(AppError::Other("--sort has its own memory limit (--sort-memory); drop --max-memory".into()));
+    }
+    let sort = if args.sort {
+        Some(sort::SortOptions::parse(args.sort_memory.as_deref(), args.sort_tmp.as_deref())?)
+    } else {
+        None
+    };
+    let renumber = renumber::Renumber::parse(&args.renumber)?;
+    let replay = args.replay_speed.as_deref().map(replay::ReplaySpeed::parse).transpose()?;
+    let units = args.unit_registry.as_deref().map(unit_registry::UnitRegistry::load).transpose()?.map(Arc::new);
+    if replay.is_some() && args.follow {
+        return Err(AppError::Other("--replay-speed replays a finished log; a followed input is live already".into()));
+    }
+    let channel_capacity = match &budget {
+        Some(b) => {
+            // Each RLE stage can pin one record while it waits for the next.
+            if b.permits() < 2 * inputs.len() {
+                return Err(AppError::Other(format!(
+                    "--max-memory {} is too small for {} inputs (need at least {} bytes)",
+                    args.max_memory.as_deref().unwrap_or_default(),
+                    inputs.len(),
+                    2 * inputs.len() as u64 * budget::RECORD_BYTES_ESTIMATE
+                )));
+            }
+            let optional = [
+                args.match_audio,
+                args.keyword_index,
+                severity.is_some(),
+                args.playlist.is_some(),
+                args.utilization.is_some(),
+                renumber != renumber::Renumber::Source,
+            ];
+            let unbudgeted = UNBUDGETED_CHANNELS + optional.iter().filter(|on| **on).count();
+            let cap = b.channel_capacity(inputs.len(), unbudgeted);
+            info!("Memory budget: {} records in flight, channel depth {}", b.permits(), cap);
+            cap
+        }
+        None => budget::DEFAULT_CHANNEL_CAPACITY,
+    };
+
+    // Set on SIGTERM/SIGINT in daemon and replay mode: inputs stop, the rest drains.
+    let (stop_tx, stop_rx) = watch::channel(false);
+    if args.daemon || replay.is_some() {
+        tokio::spawn(stop_on_signal(stop_tx));
+    }
+
+    let rle = rle_filter::RleOptions {
+        per_slot: args.rle_per_slot,
+        min_blocks: args.rle_min_blocks.unwrap_or(0),
+        run_details: args.run_details,
+        freq_tolerance,
+        ..Default::default()
+    };
+    let rle = match args.fill_forward.as_deref() {
+        Some(fields) => rle.with_fill_forward(fields)?,
+        None => rle,
+    };
+
+    let audio_matcher = |root: &Path| -> Result<Arc<audio_match::AudioMatcher>, AppError> {
+        let matcher = audio_match::AudioMatcher::new(root, config.record.audio_extensions.as_deref(), freq_tolerance)
+            .with_time_window(args.audio_window);
+        Ok(Arc::new(if args.audio_dedup { with_audio_dedup(matcher)? } else { matcher }))
+    };
+    let audio = match (args.match_audio, record_dir.as_ref()) {
+        (false, _) => None,
+        (true, None) => return Err(AppError::Other("--match-audio needs --record-dir".into())),
+        (true, Some(root)) => Some(audio_matcher(root)?),
+    };
+    if args.audio_dedup && audio.is_none() {
+        warn!("--audio-dedup has nothing to do without --match-audio");
+    }
+    let playlist = args.playlist.as_deref().map(playlist::PlaylistMode::parse).transpose()?;
+    let utilization = args.utilization.as_deref().map(utilization::UtilizationFormat::parse).transpose()?;
+    if playlist.is_some() && audio.is_none() {
+        warn!("--playlist without --match-audio: only calls that already have audio_path are listed");
+    }
+    let stt_priority = if args.stt_priority || !args.stt_priority_tgs.is_empty() {
+        if transcriber.is_none() {
+            warn!("--stt-priority has nothing to do without a transcriber");
+        }
+        let tgs = args
+            .stt_priority_tgs
+            .iter()
+            .map(|n| {
+                Aliases::resolve(&config.alias.tg, n)
+                    .ok_or_else(|| AppError::Parse(format!("--stt-priority-tg: '{}' is neither an ID nor a [alias.tg] name", n)))
+            })
+            .collect::<Result<_, _>>()?;
+        Some(Arc::new(transcription_adder::PriorityRules {
+            tgs,
+            concurrency: STT_CONCURRENCY,
+            lookahead: transcription_adder::PRIORITY_LOOKAHEAD,
+        }))
+    } else {
+        None
+    };
+    let watch = alert::WatchList::new(&args.watch_rids, &args.watch_tgs, &config.alias)?;
+    let alerts = if watch.is_empty() && args.alert_severity.is_none() {
+        if args.notify_exec.is_some() {
+            warn!("--notify-exec has nothing to do without --watch-rid/--watch-tg/--alert-severity");
+        }
+        None
+    } else {
+        let mut notifiers: Vec<Arc<dyn alert::Notifier>> = vec![Arc::new(alert::LogNotifier)];
+        if let Some(command) = args.notify_exec.clone() {
+            notifiers.push(Arc::new(alert::ExecNotifier { command }));
+        }
+        Some(alert::Alerting {
+            watch: Arc::new(watch),
+            min_severity: args.alert_severity,
+            notifiers: Arc::new(notifiers),
+            throttle: Arc::new(throttle::Throttle::new(&config.notify, &config.alias)?),
+        })
+    };
 
-    // Shared filter config
-    let cfg = Arc::new(filter::FilterConfig {
-        freqs: args.freqs.clone(),
-        rtypes: args.rtypes.clone(),
-        rids: args.rids.clone(),
-        tgs: args.tgs.clone(),
-        nacs: args.nacs.clone(),
+    // Settings shared by every pipeline
+    let shared = Arc::new(PipelineShared {
+        filter: Arc::new(filter::FilterConfig {
+            freqs: args.freqs.clone(),
+            rtypes: args.rtypes.iter().map(|t| radio_types.normalize(t)).collect(),
+            rids: args.rids.clone(),
+            tgs: args.tgs.clone(),
+            nacs: args.nacs.clone(),
+            dccs: args.dccs.clone(),
+            max_errors: args.max_errors,
+            min_quality: args.min_quality,
+            freq_tolerance,
+        }),
+        rle,
+        transcriber,
+        record_dir: record_dir.clone(),
+        sinks,
+        keyword_index: args.keyword_index,
+        rotate: args.rotate.as_deref().map(RotationPolicy::parse).transpose()?,
+        audio,
+        playlist,
+        utilization,
+        sort,
+        alerts,
+        severity,
+        stt_priority,
+        renumber,
+        record_numbers: renumber::counter(),
+        units: units.clone(),
+        replay,
+        budget,
+        channel_capacity,
+        shutdown: stop_rx,
     });
 
-    // Launch one pipeline per input file
-    let mut tasks = Vec::with_capacity(args.input_files.len());
-    for in_path in &args.input_files {
-        let in_path = in_path.clone();
-        let cfg = Arc::clone(&cfg);
-        let transcriber = transcriber.clone();
-        let record_dir = args.record_dir.clone();
-        let tz_offset = tz_offset;
+    // Launch one pipeline per input file; --merge reads them all, in order, into one
+    let groups: Vec<Vec<InputSpec>> = if args.merge && !inputs.is_empty() {
+        info!("--merge: {} files into one output", inputs.len());
+        vec![inputs]
+    } else {
+        inputs.into_iter().map(|spec| vec![spec]).collect()
+    };
+    // The table replaces per-pipeline info logs; warnings still go through.
+    let log_level = log::max_level();
+    let progress = args.progress && std::io::IsTerminal::is_terminal(&std::io::stderr());
+    if progress {
+        log::set_max_level(log_level.min(log::LevelFilter::Warn));
+    } else if args.progress {
+        debug!("--progress: stderr is not a terminal; logging instead");
+    }
+    // Pipelines of inputs with their own record_dir get their own transcriber
+    // and audio matcher, shared by inputs with the same directory.
+    let mut by_record_dir: HashMap<PathBuf, Arc<PipelineShared>> = HashMap::new();
+    if let Some(dir) = &record_dir {
+        by_record_dir.insert(dir.clone(), Arc::clone(&shared));
+    }
+    let lag = args.follow.then(|| -> Result<lag::LagOptions, AppError> {
+        let warn_bytes = match args.lag_warn.as_deref() {
+            Some(v) => rotation::parse_size(v)
+                .ok_or_else(|| AppError::Parse(format!("invalid --lag-warn '{}': expected a size like 512KB", v)))?,
+            None => lag::DEFAULT_WARN_BYTES,
+        };
+        Ok(lag::LagOptions {
+            warn_bytes,
+            warn_calls: args.lag_warn_calls.unwrap_or(lag::DEFAULT_WARN_CALLS),
+            shed_stt: args.lag_shed_stt,
+        })
+    });
+    let lag = lag.transpose()?;
+    let mut tasks = Vec::with_capacity(groups.len());
+    for mut group in groups {
+        let stats = Arc::new(PipelineStats::default());
+        let last = group.len() - 1;
+        let mut sources = Vec::with_capacity(group.len());
+        let tracker = args.affiliations.then(affiliation::SharedTracker::default);
+        for (i, spec) in group.iter().enumerate() {
+            let opts = ParseOptions {
+                tz_offset: tz::compute_tz_offset(&spec.tz.clone().or_else(|| args.tz.clone())),
+                base_date: if args.date_from_filename { input::date_from_filename(&spec.path) } else { None },
+                // Only the newest file of a merge can still be growing.
+                follow: args.follow && i == last,
+                notes: None,
+                extras: args.extras,
+                bytes_read: Some(Arc::clone(&stats.input_read)),
+                lenient: args.lenient,
+                recoveries: Some(Arc::clone(&stats.parse_recoveries)),
+                affiliations: tracker.clone(),
+                radio_types: Arc::clone(&radio_types),
+                slot_policies: Arc::clone(&slot_policies),
+                stream: spec.stream.clone(),
+                format: spec.format,
+                labels: spec.labels.clone(),
+            };
+            if args.date_from_filename && opts.base_date.is_none() {
+                warn!("--date-from-filename: no date found in {}", spec.path.display());
+            }
+            if let Ok(m) = std::fs::metadata(&spec.path) {
+                stats.input_size.fetch_add(m.len(), std::sync::atomic::Ordering::Relaxed);
+            }
+            sources.push((spec.path.clone(), opts));
+        }
+        let spec = group.remove(0);
+        if group.iter().any(|s| s.record_dir != spec.record_dir) {
+            warn!("--merge: using the record_dir of {} for all merged inputs", spec.path.display());
+        }
+        let merged: Vec<PathBuf> = group.into_iter().map(|s| s.path).collect();
+        let shared = match &spec.record_dir {
+            Some(dir) if !by_record_dir.contains_key(dir) => {
+                let transcriber = if shared.transcriber.is_some() {
+                    build_transcriber(&args.stt, Some(dir), &config, freq_tolerance, spec.brings_audio())?
+                } else {
+                    None
+                };
+                let audio = shared.audio.as_ref().map(|_| audio_matcher(dir)).transpose()?;
+                let s = Arc::new(PipelineShared { transcriber, audio, record_dir: Some(dir.clone()), ..(*shared).clone() });
+                by_record_dir.insert(dir.clone(), Arc::clone(&s));
+                s
+            }
+            Some(dir) => Arc::clone(&by_record_dir[dir]),
+            None => Arc::clone(&shared),
+        };
+        let out_path = sink::output_path(&spec.path, shared.sinks[0].extension());
+        if let Some(opts) = &lag {
+            let first = spec.stream.is_none().then_some(&spec.path);
+            let files = first.into_iter().chain(&merged).filter(|p| p.is_file()).cloned().collect();
+            tokio::spawn(lag::monitor(spec.path.display().to_string(), files, Arc::clone(&stats), opts.clone()));
+        }
 
+        let p_stats = Arc::clone(&stats);
         let t = tokio::spawn(async move {
-            if let Err(e) = run_pipeline(in_path, tz_offset, cfg, transcriber, record_dir).await {
+            let started = std::time::Instant::now();
+            let res = run_pipeline(sources, out_path, shared, Arc::clone(&p_stats)).await;
+            let _ = p_stats.finished.set(res.is_ok());
+            if let Err(e) = &res {
                 warn!("pipeline failed: {}", e);
             }
+            (res, started.elapsed())
         });
-        tasks.push(t);
+        tasks.push((spec, merged, stats, t));
     }
 
-    for t in tasks {
-        let _ = t.await;
+    let status = args.daemon.then(|| {
+        sd_notify::notify("READY=1");
+        tokio::spawn(daemon_status(tasks.iter().map(|(_, _, s, _)| Arc::clone(s)).collect()))
+    });
+
+    let table = progress.then(|| {
+        let rows = tasks.iter().map(|(spec, _, s, _)| (spec.path.display().to_string(), Arc::clone(s))).collect();
+        let (stop_tx, stop_rx) = watch::channel(false);
+        (stop_tx, tokio::spawn(progress::run(progress::ProgressTable::new(rows), stop_rx)))
+    });
+    let mut joined = Vec::with_capacity(tasks.len());
+    for (spec, merged, stats, t) in tasks {
+        joined.push((spec, merged, stats, t.await));
+    }
+    if let Some((stop_tx, table)) = table {
+        let _ = stop_tx.send(true);
+        let _ = table.await;
+        log::set_max_level(log_level);
     }
 
-    info!("Done.");
+    let mut reports = Vec::with_capacity(joined.len());
+    let mut summaries = Vec::new();
+    let mut outcomes = Vec::with_capacity(joined.len());
+    for (spec, merged, stats, res) in joined {
+        let (written, error, elapsed) = match res {
+            Ok((Ok(files), elapsed)) => (files, None, elapsed),
+            Ok((Err(e), elapsed)) => (vec![], Some(e.to_string()), elapsed),
+            Err(e) => (vec![], Some(format!("pipeline join: {e}")), started.elapsed()),
+        };
+        if let Some(reg) = registry.as_mut()
+            && error.is_none()
+        {
+            for path in std::iter::once(&spec.path).chain(&merged) {
+                if let Some(f) = fingerprints.remove(path) {
+                    reg.mark(path, f);
+                }
+            }
+        }
+        let suppressed = stats.rle_suppressed.load(std::sync::atomic::Ordering::Relaxed);
+        if suppressed > 0 {
+            info!("{}: RLE dropped {} runs shorter than {} blocks", spec.path.display(), suppressed, rle.min_blocks);
+        }
+        let snapshot = stats.snapshot();
+        if snapshot.parse_recoveries > 0 {
+            warn!("{}: recovered from {} malformed lines/blocks (--lenient)", spec.path.display(), snapshot.parse_recoveries);
+        }
+        // The progress table already showed each input's totals.
+        if progress {
+            debug!("Contents of {}: {}", spec.path.display(), snapshot.content.report());
+        } else {
+            info!("Contents of {}: {}", spec.path.display(), snapshot.content.report());
+        }
+        if snapshot.coverage.total.found + snapshot.coverage.total.missing > 0 {
+            if args.coverage_report {
+                info!("Transcripts for {}: {}", spec.path.display(), snapshot.coverage.report());
+            } else {
+                debug!("Transcripts for {}: {}", spec.path.display(), snapshot.coverage.report());
+            }
+        }
+        if args.stats_json.is_some() || args.encryption_report.is_some() {
+            summaries.push((spec.path.clone(), snapshot.clone()));
+        }
+        let report = snapshot.timing_report();
+        if args.timing {
+            info!("Timing for {}:\n{}", spec.path.display(), report);
+        } else {
+            debug!("Timing for {}:\n{}", spec.path.display(), report);
+        }
+        outcomes.push(summary::InputOutcome {
+            path: spec.path.clone(),
+            stages: snapshot.clone(),
+            elapsed,
+            error: error.clone(),
+        });
+        if let Some(path) = args.manifest.as_ref() {
+            let mut outputs = Vec::with_capacity(written.len());
+            for f in &written {
+                match OutputFile::describe(f) {
+                    Ok(o) => outputs.push(o),
+                    Err(e) => warn!("manifest {}: {}", path.display(), e),
+                }
+            }
+            reports.push(InputReport {
+                path: spec.path,
+                tz: spec.tz,
+                format: spec.format,
+                record_dir: spec.record_dir,
+                merged,
+                error,
+                stages: snapshot,
+                outputs,
+            });
+        }
+    }
+
+    if let Some(status) = status {
+        status.abort();
+    }
+
+    if let Some(reg) = registry.as_mut() {
+        reg.save()?;
+    }
+    if let Some(units) = &units {
+        units.save()?;
+        info!("Updated unit registry {}", units.path().display());
+    }
+    if let Some(policy) = &retention {
+        for s in &shared.sinks {
+            retention::apply(s, policy).await?;
+        }
+    }
+
+    if let Some(path) = args.stats_json.as_ref() {
+        let summary = summary::RunSummary::new(&summaries, shared.transcriber.is_some());
+        summary::write_summary(path, &summary)?;
+        info!("Wrote run statistics {}", path.display());
+    }
+    if let Some(path) = args.encryption_report.as_ref() {
+        let mut all = dsd_event_parser::stats::ContentStats::default();
+        for (_, s) in &summaries {
+            all.merge(&s.content);
+        }
+        summary::write_encryption_report(path, &all)?;
+        info!("Wrote encryption report {}", path.display());
+    }
+
+    if let Some(path) = args.manifest.as_ref() {
+        let mut m = RunManifest::new(run_options(&args));
+        m.started_at = started_at.to_rfc3339();
+        m.finished_at = chrono::Local::now().to_rfc3339();
+        m.wall_time_secs = started.elapsed().as_secs_f64();
+        m.complete = reports.iter().all(|r| r.error.is_none());
+        m.inputs = reports;
+        manifest::write_manifest(path, &m)?;
+        info!("Wrote run manifest {}", path.display());
+    }
+
+    // On stderr with the log, unless it has been turned down to errors only.
+    let table = summary::ExitTable(&outcomes);
+    if log_level >= log::LevelFilter::Warn {
+        eprint!("{}", table);
+    }
+    match table.failed() {
+        0 => info!("Done: {} inputs.", outcomes.len()),
+        n if args.exit_zero => warn!("Done: {} inputs, {} failed.", outcomes.len(), n),
+        n => return Err(AppError::Other(format!("{} of {} inputs failed", n, outcomes.len()))),
+    }
     Ok(())
 }
 
-async fn run_pipeline(
-    in_path: PathBuf,
-    tz_offset: Option<FixedOffset>,
-    cfg: Arc<filter::FilterConfig>,
-    transcriber: Option<Arc<dyn transcriber::Transcriber + Send + Sync>>,
+/// Fill in what `--profile` sets and the command line doesn't.
+fn apply_profile(args: &mut cli::CliArgs, p: ProfileConfig) {
+    if args.input_files.is_empty() && args.inputs.is_empty() {
+        args.input_files = p.inputs;
+    }
+    args.tz = args.tz.take().or(p.tz);
+    args.record_dir = args.record_dir.take().or(p.record_dir);
+    if args.sink.is_empty() {
+        args.sink = p.sink.unwrap_or_default();
+    }
+    args.out = args.out.take().or(p.out);
+    args.rotate = args.rotate.take().or(p.rotate);
+    let f = p.filter;
+    for (arg, value) in [
+        (&mut args.freqs, f.freq),
+        (&mut args.tgs, f.tg),
+        (&mut args.rids, f.rid),
+        (&mut args.rtypes, f.radio_type),
+        (&mut args.nacs, f.nac),
+        (&mut args.dccs, f.dcc),
+    ] {
+        if arg.is_empty() {
+            *arg = value;
+        }
+    }
+    args.max_errors = args.max_errors.or(f.max_errors);
+    args.min_quality = args.min_quality.or(f.min_quality);
+}
+
+/// Fill in what the environment sets and neither the command line nor
+/// `--profile` does.
+fn apply_env(args: &mut cli::CliArgs) {
+    let e = std::mem::take(&mut args.env);
+    args.tz = args.tz.take().or(e.tz);
+    args.record_dir = args.record_dir.take().or(e.record_dir);
+    if args.sink.is_empty() {
+        args.sink = e.sink;
+    }
+    args.out = args.out.take().or(e.out);
+    args.rotate = args.rotate.take().or(e.rotate);
+    args.max_errors = args.max_errors.or(e.max_errors);
+    args.min_quality = args.min_quality.or(e.min_quality);
+}
+
+/// The options of a run, for `--manifest` and the CSV `--preamble`.
+fn run_options(args: &cli::CliArgs) -> serde_json::Value {
+    serde_json::json!({
+        "profile": args.profile,
+        "config": args.config,
+        "sink": args.sink,
+        "out": args.out,
+        "follow": args.follow,
+        "rotate": args.rotate,
+        "rotate_keep": args.rotate_keep,
+        "csv_quote": args.csv_quote,
+        "csv_escape": args.csv_escape,
+        "csv_batch": args.csv_batch,
+        "excel_compat": args.excel_compat,
+        "tz": args.tz,
+        "date_from_filename": args.date_from_filename,
+        "record_dir": args.record_dir,
+        "transcriber": args.stt.transcriber,
+        "transcript_once": args.stt.transcript_once,
+        "stt_check": args.stt.check,
+        "stt_model": (args.stt.transcriber == "whisper").then_some(&args.stt.model),
+        "stt_device": (args.stt.transcriber == "whisper").then_some(&args.stt.device),
+        "stt_threads": args.stt.threads,
+        "stt_preprocess": args.stt.preprocess,
+        "stt_segments": args.stt.segments,
+        "freqs": args.freqs,
+        "rtypes": args.rtypes,
+        "rids": args.rids,
+        "tgs": args.tgs,
+        "nacs": args.nacs,
+        "dccs": args.dccs,
+        "watch_rids": args.watch_rids,
+        "watch_tgs": args.watch_tgs,
+        "alert_severity": args.alert_severity,
+        "max_errors": args.max_errors,
+        "min_quality": args.min_quality,
+        "extras": args.extras,
+        "affiliations": args.affiliations,
+        "fill_forward": args.fill_forward,
+        "rle_per_slot": args.rle_per_slot,
+        "rle_min_blocks": args.rle_min_blocks,
+        "renumber": args.renumber,
+        "replay_speed": args.replay_speed,
+        "run_details": args.run_details,
+        "merge": args.merge,
+        "sort": args.sort,
+        "stt_priority": args.stt_priority || !args.stt_priority_tgs.is_empty(),
+        "stt_priority_tgs": args.stt_priority_tgs,
+        "match_audio": args.match_audio,
+        "audio_dedup": args.audio_dedup,
+        "audio_window": args.audio_window,
+        "playlist": args.playlist,
+        "utilization": args.utilization,
+        "sort_memory": args.sort_memory,
+        "max_memory": args.max_memory,
+        "keyword_index": args.keyword_index,
+        "freq_tolerance": args.freq_tolerance,
+        "lenient": args.lenient,
+        "skip_processed": args.skip_processed,
+        "encryption_report": args.encryption_report,
+        "duration_format": args.duration_format,
+        "listen": args.listen,
+        "device": args.device,
+        "listen_format": (args.listen.is_some() || args.device.is_some()).then_some(&args.listen_format),
+    })
+}
+
+#[cfg(feature = "audio-fingerprint")]
+fn with_audio_dedup(matcher: audio_match::AudioMatcher) -> Result<audio_match::AudioMatcher, AppError> {
+    Ok(matcher.with_dedup())
+}
+
+#[cfg(not(feature = "audio-fingerprint"))]
+fn with_audio_dedup(_matcher: audio_match::AudioMatcher) -> Result<audio_match::AudioMatcher, AppError> {
+    Err(AppError::Other("--audio-dedup needs a build with the audio-fingerprint feature".into()))
+}
+
+/// `--stt-segments`: transcribe long recordings part by part through `t`.
+#[cfg(feature = "stt-segments")]
+fn segmented(
+    t: SharedTranscriber,
+    stt: &cli::SttArgs,
+    record_dir: Option<&PathBuf>,
+    config: &Config,
+    freq_tolerance: FreqTolerance,
+) -> Result<SharedTranscriber, AppError> {
+    use dsd_event_parser::segments;
+    if stt.transcriber == "text" {
+        return Err(AppError::Other("--stt-segments needs a transcriber that listens to the audio, not --transcriber text".into()));
+    }
+    if stt.segment_gap == 0 {
+        return Err(AppError::Other("--stt-segment-gap must be at least 1 ms".into()));
+    }
+    let opts = segments::SegmentOptions {
+        gap_ms: stt.segment_gap,
+        min_call_s: stt.segment_min,
+        out: stt.segments_out.clone(),
+        audio_extensions: config.record.audio_extensions.clone(),
+        freq_tolerance,
+    };
+    let root = record_dir.cloned().unwrap_or_default();
+    Ok(Arc::new(segments::Segmented::new(t, &root, opts)?))
+}
+
+#[cfg(not(feature = "stt-segments"))]
+fn segmented(
+    _t: SharedTranscriber,
+    _stt: &cli::SttArgs,
+    _record_dir: Option<&PathBuf>,
+    _config: &Config,
+    _freq_tolerance: FreqTolerance,
+) -> Result<SharedTranscriber, AppError> {
+    Err(AppError::Other("--stt-segments needs a build with the stt-segments feature".into()))
+}
+
+/// Build the transcriber selected with `--transcriber` (and the STT options).
+fn build_transcriber(
+    stt: &cli::SttArgs,
+    record_dir: Option<&PathBuf>,
+    config: &Config,
+    freq_tolerance: FreqTolerance,
+    inputs_bring_audio: bool,
+) -> Result<Option<SharedTranscriber>, AppError> {
+    let policy = stt_registry::HealthPolicy::parse(&stt.check)?;
+    let mut registry = stt_registry::Registry::new();
+    registry.register("text", |ctx| {
+        let mut t = if let Some(root) = ctx.record_dir {
+            transcriber::TextFileTranscriber::new_indexed(root)?
+        } else {
+            warn!("--transcriber text used without --record-dir; no transcripts will be found");
+            transcriber::TextFileTranscriber::new()
+        };
+        if let Some(exts) = &ctx.config.record.transcript_extensions {
+            t = t.with_extensions(exts);
+        }
+        Ok(Arc::new(t.with_freq_tolerance(ctx.freq_tolerance).with_once(stt.transcript_once)))
+    });
+    #[cfg(feature = "stt-whisper")]
+    registry.register("whisper", |ctx| {
+        use dsd_event_parser::whisper;
+        let root = ctx.record_dir.ok_or_else(|| {
+            AppError::Other("--transcriber whisper needs --record-dir (to find the audio)".into())
+        })?;
+        let device = whisper::SttDevice::parse(&stt.device)?;
+        let models_dir = stt.models_dir.clone().unwrap_or_else(models::default_dir);
+        let opts = whisper::WhisperOptions {
+            bin: stt.whisper_bin.clone(),
+            model: models::resolve(&models_dir, &stt.model)?,
+            device,
+            threads: stt.threads,
+            preprocess: stt.preprocess,
+            languages: ctx.config.stt.language.clone(),
+            prompt: ctx.config.stt.prompt(),
+            audio_extensions: ctx.config.record.audio_extensions.clone(),
+            freq_tolerance: ctx.freq_tolerance,
+        };
+        info!("Whisper: model {}, device {}", opts.model.display(), stt.device);
+        Ok(Arc::new(whisper::WhisperTranscriber::new(root, opts)?))
+    });
+    #[cfg(not(feature = "stt-whisper"))]
+    registry.register("whisper", |_| {
+        Err(AppError::Other("--transcriber whisper needs a build with the stt-whisper feature".into()))
+    });
+    registry.add_external(config)?;
+    let ctx = stt_registry::BuildContext { record_dir: record_dir.map(PathBuf::as_path), config, freq_tolerance };
+    let Some(mut t) = registry.build(&stt.transcriber, &ctx)? else {
+        return Ok(None);
+    };
+    let post = PostProcessor::new(&config.stt.postprocess)?;
+    if !post.is_empty() {
+        t = Arc::new(PostProcessed::new(t, post));
+    }
+    if stt.segments {
+        t = segmented(t, stt, record_dir, config, freq_tolerance)?;
+    }
+    if policy == stt_registry::HealthPolicy::Off {
+        return Ok(Some(t));
+    }
+    // Transcripts always come from the Record directory; recordings may
+    // come with the inputs instead.
+    let root_check = match record_dir {
+        Some(root) if stt.transcriber == "text" => match &config.record.transcript_extensions {
+            Some(exts) => transcriber::check_record_root(root, exts),
+            None => transcriber::check_record_root(root, transcriber::TRANSCRIPT_EXTENSIONS),
+        },
+        Some(root) if !inputs_bring_audio => match &config.record.audio_extensions {
+            Some(exts) => transcriber::check_record_root(root, exts),
+            None => transcriber::check_record_root(root, transcriber::AUDIO_EXTENSIONS),
+        },
+        _ => Ok(()),
+    };
+    match root_check.and_then(|()| tokio::task::block_in_place(|| t.health())) {
+        Ok(()) => Ok(Some(t)),
+        Err(AppError::IO(e) | AppError::Parse(e) | AppError::Other(e)) => {
+            if policy == stt_registry::HealthPolicy::Warn {
+                warn!("--transcriber {}: {}; continuing without transcription", stt.transcriber, e);
+                return Ok(None);
+            }
+            Err(AppError::Other(format!(
+                "--transcriber {}: {} (--stt-check warn runs without transcription instead)",
+                stt.transcriber, e
+            )))
+        }
+    }
+}
+
+/// Settings shared by every per-input pipeline.
+#[derive(Clone)]
+struct PipelineShared {
+    filter: Arc<filter::FilterConfig>,
+    rle: rle_filter::RleOptions,
+    transcriber: Option<SharedTranscriber>,
     record_dir: Option<PathBuf>,
-) -> Result<(), AppError> {
+    /// `--sink`, in the order listed.
+    sinks: Vec<SinkKind>,
+    keyword_index: bool,
+    /// `--rotate`, which splits the keyword index along with the CSV output.
+    rotate: Option<RotationPolicy>,
+    /// Look up each call's recording (`--match-audio`).
+    audio: Option<Arc<audio_match::AudioMatcher>>,
+    playlist: Option<playlist::PlaylistMode>,
+    /// Per-frequency busy seconds per minute (`--utilization`).
+    utilization: Option<utilization::UtilizationFormat>,
+    /// Sort each input by time before RLE (`--sort`).
+    sort: Option<sort::SortOptions>,
+    /// `--watch-rid`/`--watch-tg` and where their alerts go.
+    alerts: Option<alert::Alerting>,
+    /// `[severity]` rules, when the config file has any.
+    severity: Option<Arc<severity::SeverityRules>>,
+    /// `--stt-priority`: transcribe urgent calls first.
+    stt_priority: Option<Arc<transcription_adder::PriorityRules>>,
+    renumber: renumber::Renumber,
+    /// Next `record_number` for `--renumber global`.
+    record_numbers: Arc<std::sync::atomic::AtomicUsize>,
+    /// `--unit-registry`: every TG and RID heard, kept across runs.
+    units: Option<Arc<unit_registry::UnitRegistry>>,
+    /// `--replay-speed`: pace calls by their timestamps.
+    replay: Option<replay::ReplaySpeed>,
+    budget: Option<Arc<MemoryBudget>>,
+    /// Depth of every channel; smaller under `--max-memory`.
+    channel_capacity: usize,
+    /// Becomes `true` when inputs should stop being read.
+    shutdown: watch::Receiver<bool>,
+}
+
+/// Wait for SIGTERM (systemd stop) or Ctrl-C, then ask the pipelines to stop
+/// reading so everything already parsed is still written.
+async fn stop_on_signal(stop: watch::Sender<bool>) {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = term.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+            }
+            Err(e) => {
+                warn!("cannot watch for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+    info!("Stopping: finishing records already read");
+    sd_notify::notify("STOPPING=1");
+    let _ = stop.send(true);
+}
+
+/// How often the daemon logs its totals.
+const STATUS_LOG_EVERY: Duration = Duration::from_secs(300);
+
+/// Daemon heartbeat: systemd watchdog pings, `STATUS=` totals for
+/// `systemctl status`, and the same totals in the log every few minutes.
+async fn daemon_status(stats: Vec<Arc<PipelineStats>>) {
+    let watchdog = sd_notify::watchdog_interval();
+    let tick = watchdog.map_or(Duration::from_secs(30), |w| w.min(Duration::from_secs(30)));
+    let mut interval = tokio::time::interval(tick);
+    let mut last_log = std::time::Instant::now();
+    loop {
+        interval.tick().await;
+        let (mut parsed, mut written, mut transcripts, mut lag_bytes, mut lag_calls) = (0, 0, 0, 0, 0);
+        for s in &stats {
+            parsed += s.filter.records_in();
+            written += s.sink.records_out();
+            transcripts += s.transcripts_attached.load(std::sync::atomic::Ordering::Relaxed);
+            lag_bytes += s.lag_bytes.load(std::sync::atomic::Ordering::Relaxed);
+            lag_calls += s.lag_calls.load(std::sync::atomic::Ordering::Relaxed);
+        }
+        let mut line = format!("{} inputs, {} records read, {} written, {} transcripts", stats.len(), parsed, written, transcripts);
+        if lag_bytes > 0 || lag_calls > 0 {
+            line.push_str(&format!(", behind by {} and {} calls", lag::size(lag_bytes), lag_calls));
+        }
+        if watchdog.is_some() {
+            sd_notify::notify("WATCHDOG=1");
+        }
+        sd_notify::notify(&format!("STATUS={}", line));
+        if last_log.elapsed() >= STATUS_LOG_EVERY {
+            info!("{}", line);
+            last_log = std::time::Instant::now();
+        }
+    }
+}
+
+/// Transcriptions running at once per pipeline.
+const STT_CONCURRENCY: usize = 4;
+
+/// Recording lookups (`--match-audio`) running at once per pipeline.
+const AUDIO_CONCURRENCY: usize = 8;
+
+/// Channels outside the budgeted filter..transcribe span: parse output and
+/// transcribe output, plus one per optional stage after transcription.
+const UNBUDGETED_CHANNELS: usize = 2;
+
+/// An optional pass-through stage; resolves to the side files it wrote.
+type SideStage = JoinHandle<Result<Vec<PathBuf>, AppError>>;
+
+/// The sinks of `--sink`, a comma-separated list.
+async fn build_sinks(args: &cli::CliArgs, severity: bool) -> Result<Vec<SinkKind>, AppError> {
+    let mut sinks: Vec<SinkKind> = Vec::new();
+    for name in args.sink.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        if sinks.iter().any(|s| s.name() == name) {
+            return Err(AppError::Other(format!("--sink lists '{}' twice", name)));
+        }
+        let kind = build_sink(args, name, severity).await?;
+        if kind.writes_file()
+            && let Some(other) = sinks.iter().find(|s| s.writes_file() && s.extension() == kind.extension())
+        {
+            return Err(AppError::Other(format!(
+                "--sink {} and {} would both write <input>.{}; name the --template for another extension",
+                other.name(),
+                name,
+                kind.extension()
+            )));
+        }
+        sinks.push(kind);
+    }
+    if sinks.is_empty() {
+        return Err(AppError::Other(format!("--sink needs a sink (available: {})", SinkKind::available().join(", "))));
+    }
+    Ok(sinks)
+}
+
+async fn build_sink(args: &cli::CliArgs, name: &str, severity: bool) -> Result<SinkKind, AppError> {
+    let duration = DurationFormat::parse(&args.duration_format)?;
+    // A replayed run is watched as it is written, like a followed one.
+    let live = args.follow || args.replay_speed.is_some();
+    let write_mode = WriteMode { in_place: live, fsync: args.fsync };
+    match name {
+        "csv" => Ok(SinkKind::Csv(CsvSinkOptions {
+            rotate: args.rotate.as_deref().map(RotationPolicy::parse).transpose()?,
+            keep: args.rotate_keep,
+            batch: args.csv_batch,
+            flush_interval: args.csv_flush_ms.map(Duration::from_millis),
+            flush_when_idle: live,
+            extras: args.extras,
+            run_details: args.run_details,
+            affiliations: args.affiliations,
+            severity,
+            quote: args.csv_quote.as_deref().map(csv_sink::parse_quote_style).transpose()?.unwrap_or_default(),
+            escape: args.csv_escape.as_deref().map(csv_sink::parse_escape).transpose()?,
+            excel: args.excel_compat,
+            duration,
+            preamble: args.preamble.then(|| csv_sink::preamble(&run_options(args))),
+            write_mode,
+        })),
+        "influx" => Ok(SinkKind::Influx(InfluxSinkOptions { flush_when_idle: live, duration, write_mode })),
+        "syslog" => {
+            let addr = args
+                .syslog_addr
+                .as_deref()
+                .ok_or_else(|| AppError::Other("--sink syslog requires --syslog-addr".into()))?;
+            Ok(SinkKind::Syslog(SyslogSinkOptions { duration, ..SyslogSinkOptions::parse(addr, &args.syslog_facility)? }))
+        }
+        #[cfg(feature = "sink-postgres")]
+        "postgres" => {
+            let url = args
+                .pg_url
+                .clone()
+                .ok_or_else(|| AppError::Other("--sink postgres requires --pg-url".into()))?;
+            let opts = dsd_event_parser::pg_sink::PgSinkOptions {
+                url,
+                table: args.pg_table.clone(),
+                batch: args.pg_batch,
+                flush_when_idle: live,
+            };
+            Ok(SinkKind::Postgres(dsd_event_parser::pg_sink::PgSink::connect(opts).await?))
+        }
+        #[cfg(feature = "sink-elasticsearch")]
+        "elasticsearch" => {
+            let url = args
+                .es_url
+                .clone()
+                .ok_or_else(|| AppError::Other("--sink elasticsearch requires --es-url".into()))?;
+            let opts = dsd_event_parser::es_sink::EsSinkOptions {
+                url,
+                index_prefix: args.es_index.clone(),
+                api_key: args.es_api_key.clone(),
+                batch: args.es_batch,
+                retries: args.es_retries,
+                flush_when_idle: live,
+                duration,
+            };
+            Ok(SinkKind::Elasticsearch(dsd_event_parser::es_sink::EsSink::new(opts)?))
+        }
+        #[cfg(feature = "sink-sqlite")]
+        "sqlite" => {
+            let opts = dsd_event_parser::sqlite_sink::SqliteSinkOptions {
+                path: args.out.clone().unwrap_or_else(|| PathBuf::from("calls.db")),
+                flush_when_idle: live,
+            };
+            Ok(SinkKind::Sqlite(dsd_event_parser::sqlite_sink::SqliteSink::open(opts).await?))
+        }
+        #[cfg(feature = "sink-template")]
+        "template" => {
+            let path = args
+                .template
+                .clone()
+                .ok_or_else(|| AppError::Other("--sink template requires --template".into()))?;
+            let opts = dsd_event_parser::template_sink::TemplateSinkOptions {
+                path,
+                flush_when_idle: live,
+                write_mode,
+            };
+            Ok(SinkKind::Template(dsd_event_parser::template_sink::TemplateSink::load(opts)?))
+        }
+        other => Err(AppError::Other(format!(
+            "unknown or not compiled-in sink '{}' (available: {})",
+            other,
+            SinkKind::available().join(", ")
+        ))),
+    }
+}
+
+/// `inputs` are read one after another into the same stream: one file
+/// normally, all of them in order with `--merge`.
+async fn run_pipeline(
+    inputs: Vec<(PathBuf, ParseOptions)>,
+    out_path: PathBuf,
+    shared: Arc<PipelineShared>,
+    stats: Arc<PipelineStats>,
+) -> Result<Vec<PathBuf>, AppError> {
     use model::RadioRecord;
 
-    info!("Reading file {}", in_path.display());
+    // Sinks and logs name the pipeline after its first input.
+    let in_path = inputs[0].0.clone();
+    let affiliations = inputs[0].1.affiliations.clone();
+    let report_base = out_path.clone();
 
     // Channels:
-    // parse -> filter -> rle -> transcriber -> csv
-    let (tx_parse, rx_parse) = mpsc::channel::<RadioRecord>(1024);
-    let (tx_filt, rx_filt) = mpsc::channel::<RadioRecord>(1024);
-    let (tx_rle, rx_rle) = mpsc::channel::<RadioRecord>(1024);
-    let (tx_rows, rx_rows) = mpsc::channel::<RadioRecord>(1024);
+    // parse -> filter -> [sort] -> rle -> [replay] -> [alert] -> transcriber -> [severity] -> [alert] -> [optional stages] -> sink
+    let cap = shared.channel_capacity;
+    let (tx_parse, rx_parse) = mpsc::channel::<RadioRecord>(cap);
+    let (tx_filt, rx_filt) = mpsc::channel::<RadioRecord>(cap);
+    let (tx_rle, rx_rle) = mpsc::channel::<RadioRecord>(cap);
+    let (tx_trans, rx_trans) = mpsc::channel::<RadioRecord>(cap);
+    let budget = shared.budget.clone().map(|b| Arc::new(PipelineBudget::new(b)));
 
     // 1) Parser (producer)
-    let p_in = in_path.clone();
+    let mut stop = shared.shutdown.clone();
     let producer = tokio::spawn(async move {
-        match p_in.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase().as_str() {
-            "event" => event_stream::stream_file(&p_in, tz_offset, tx_parse).await,
-            "srt"   => srt_stream::stream_file(&p_in, tz_offset, tx_parse).await,
-            _       => {
-                // Heuristic: *.event often lacks blocks; default to event parser, else SRT
-                // If you prefer strictness, return an error instead.
-                if p_in.file_name().and_then(|n| n.to_str()).unwrap_or("").to_ascii_lowercase().ends_with(".event") {
-                    event_stream::stream_file(&p_in, tz_offset, tx_parse).await
-                } else {
-                    srt_stream::stream_file(&p_in, tz_offset, tx_parse).await
-                }
+        // Dropping the parser closes its channel; downstream stages then drain.
+        for (path, opts) in inputs {
+            info!("Reading file {}", path.display());
+            tokio::select! {
+                res = input::stream_input(&path, opts, tx_parse.clone()) => res?,
+                _ = stop.wait_for(|s| *s) => break,
             }
         }
+        Ok(())
     });
 
     // 2) Filter (drop non-matching)
-    let f_cfg = Arc::clone(&cfg);
+    let f_cfg = Arc::clone(&shared.filter);
+    let f_stats = Arc::clone(&stats);
+    let f_budget = budget.clone();
     let filter_task = tokio::spawn(async move {
-        filter::filter_stream(f_cfg, rx_parse, tx_filt).await;
+        filter::filter_stream(f_cfg, rx_parse, tx_filt, f_stats, f_budget).await;
         Ok::<_, AppError>(())
     });
 
+    // --sort: hold everything back and order it by time before RLE.
+    let mut rx_filt = rx_filt;
+    let mut sort_task = None;
+    if let Some(opts) = &shared.sort {
+        let (tx, rx) = mpsc::channel::<RadioRecord>(cap);
+        sort_task = Some(tokio::spawn(sort::sort_stream(rx_filt, tx, opts.clone())));
+        rx_filt = rx;
+    }
+
     // 3) RLE compressor (collapse adjacent identical radio-info into a single record w/ duration)
+    let r_opts = shared.rle;
+    let r_stats = Arc::clone(&stats);
+    let r_budget = budget.clone();
     let rle_task = tokio::spawn(async move {
-        rle_filter::rle_compress_stream(rx_filt, tx_rle).await;
+        rle_filter::rle_compress_stream(rx_filt, tx_rle, r_opts, r_stats, r_budget).await;
         Ok::<_, AppError>(())
     });
 
-    // 4) Transcription adder (enrich first record in a run; concurrency bound = 4)
-    let t_record_dir = record_dir.clone();
-    let t_transcriber = transcriber.clone();
+    // Watchlist alerts, ahead of transcription so notifications aren't held up
+    // by STT; a severity threshold has to wait for the transcript and score.
+    let mut rx_rle = rx_rle;
+    let mut extra_tasks: Vec<(&str, SideStage)> = Vec::new();
+    if let Some(speed) = shared.replay {
+        let (tx, rx) = mpsc::channel::<RadioRecord>(cap);
+        let task = replay::replay_stream(rx_rle, tx, speed, shared.shutdown.clone());
+        extra_tasks.push(("replay", tokio::spawn(task)));
+        rx_rle = rx;
+    }
+    let late_alerts = shared.alerts.as_ref().filter(|a| a.min_severity.is_some());
+    if let Some(alerting) = shared.alerts.as_ref().filter(|a| a.min_severity.is_none()) {
+        let (tx, rx) = mpsc::channel::<RadioRecord>(cap);
+        let task = alert::alert_stream(rx_rle, tx, alerting.clone());
+        extra_tasks.push(("alert", tokio::spawn(task)));
+        rx_rle = rx;
+    }
+
+    // 4) Transcription adder (enrich first record in a run; concurrency bound = STT_CONCURRENCY)
+    let t_record_dir = shared.record_dir.clone();
+    let t_transcriber = shared.transcriber.clone();
+    let t_stats = Arc::clone(&stats);
+    let t_priority = shared.stt_priority.clone();
     let trans_task = tokio::spawn(async move {
-        transcription_adder::add_transcriptions(rx_rle, tx_rows, t_record_dir, t_transcriber, 4).await
+        match (t_priority, t_record_dir, t_transcriber) {
+            (Some(rules), Some(dir), Some(t)) => {
+                transcription_adder::add_transcriptions_by_priority(rx_rle, tx_trans, dir, t, rules, t_stats, budget)
+                    .await
+            }
+            (_, dir, t) => {
+                transcription_adder::add_transcriptions(rx_rle, tx_trans, dir, t, STT_CONCURRENCY, t_stats, budget)
+                    .await
+            }
+        }
     });
 
-    // 5) CSV sink (one CSV per input file)
-    let out_path = in_path.with_extension("csv");
-    let sink = tokio::spawn(async move { csv_sink::write_csv_stream(out_path.as_path(), rx_rows).await });
+    // 5) Optional pass-through stages; each may write side outputs.
+    let mut rx_rows = rx_trans;
+    if let Some(rules) = &shared.severity {
+        let (tx, rx) = mpsc::channel::<RadioRecord>(cap);
+        extra_tasks.push(("severity", tokio::spawn(severity::score_stream(rx_rows, tx, Arc::clone(rules)))));
+        rx_rows = rx;
+    }
+    if let Some(alerting) = late_alerts {
+        let (tx, rx) = mpsc::channel::<RadioRecord>(cap);
+        extra_tasks.push(("alert", tokio::spawn(alert::alert_stream(rx_rows, tx, alerting.clone()))));
+        rx_rows = rx;
+    }
+    let numbers = match shared.renumber {
+        renumber::Renumber::Source => None,
+        renumber::Renumber::Sequential => Some(renumber::counter()),
+        renumber::Renumber::Global => Some(Arc::clone(&shared.record_numbers)),
+    };
+    if let Some(next) = numbers {
+        let (tx, rx) = mpsc::channel::<RadioRecord>(cap);
+        extra_tasks.push(("renumber", tokio::spawn(renumber::renumber_stream(rx_rows, tx, next))));
+        rx_rows = rx;
+    }
+    if let Some(matcher) = &shared.audio {
+        let (tx, rx) = mpsc::channel::<RadioRecord>(cap);
+        let task = audio_match::match_audio_stream(rx_rows, tx, Arc::clone(matcher), AUDIO_CONCURRENCY);
+        extra_tasks.push(("audio match", tokio::spawn(task)));
+        rx_rows = rx;
+    }
+    if let Some(units) = &shared.units {
+        let (tx, rx) = mpsc::channel::<RadioRecord>(cap);
+        extra_tasks.push(("unit registry", tokio::spawn(unit_registry::observe_stream(rx_rows, tx, Arc::clone(units)))));
+        rx_rows = rx;
+    }
+    if shared.keyword_index {
+        let (tx, rx) = mpsc::channel::<RadioRecord>(cap);
+        let task = keyword_index::index_keywords_stream(rx_rows, tx, out_path.clone(), shared.rotate);
+        extra_tasks.push(("keyword index", tokio::spawn(task)));
+        rx_rows = rx;
+    }
+    if let Some(mode) = shared.playlist {
+        let (tx, rx) = mpsc::channel::<RadioRecord>(cap);
+        let task = playlist::write_playlists_stream(rx_rows, tx, out_path.clone(), mode);
+        extra_tasks.push(("playlist", tokio::spawn(task)));
+        rx_rows = rx;
+    }
+    if let Some(format) = shared.utilization {
+        let (tx, rx) = mpsc::channel::<RadioRecord>(cap);
+        let task = utilization::write_utilization_stream(rx_rows, tx, out_path.clone(), format);
+        extra_tasks.push(("utilization", tokio::spawn(task)));
+        rx_rows = rx;
+    }
+
+    // 6) Sinks (CSV: one file per input)
+    let s_stats = Arc::clone(&stats);
+    let s_shared = Arc::clone(&shared);
+    let s_in = in_path.clone();
+    let sink = tokio::spawn(async move {
+        sink::run_sinks(&s_shared.sinks, &s_in, rx_rows, s_stats, cap).await
+    });
 
     // Join all
     let p_res = producer
@@ -187,6 +1672,10 @@ async fn run_pipeline(
     let f_res = filter_task
         .await
         .unwrap_or_else(|e| Err(AppError::IO(format!("filter join: {e}"))));
+    let sort_res = match sort_task {
+        Some(t) => t.await.unwrap_or_else(|e| Err(AppError::IO(format!("sort join: {e}")))),
+        None => Ok(()),
+    };
     let rle_res = rle_task
         .await
         .unwrap_or_else(|e| Err(AppError::IO(format!("rle join: {e}"))));
@@ -196,13 +1685,56 @@ async fn run_pipeline(
     let s_res = sink
         .await
         .unwrap_or_else(|e| Err(AppError::IO(format!("sink join: {e}"))));
+    let mut x_res = Vec::with_capacity(extra_tasks.len());
+    for (name, task) in extra_tasks {
+        x_res.push(task.await.unwrap_or_else(|e| Err(AppError::IO(format!("{name} join: {e}")))));
+    }
 
     p_res?;
     f_res?;
+    sort_res?;
     rle_res?;
     t_res?;
-    s_res?;
+    let mut written = s_res?;
+    for r in x_res {
+        written.extend(r?);
+    }
+    if let Some(tracker) = &affiliations {
+        written.extend(affiliation::write_report(&report_base, tracker).await?);
+    }
 
     info!("Finished {}", in_path.display());
-    Ok(())
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_wins_over_the_environment_and_the_command_line_over_both() {
+        let mut args = cli::CliArgs { tz: Some("Europe/Berlin".into()), ..Default::default() };
+        args.env = cli::ProfileEnv {
+            tz: Some("UTC".into()),
+            record_dir: Some("/env/Record".into()),
+            sink: "sqlite".into(),
+            out: Some("env.db".into()),
+            rotate: Some("hourly".into()),
+            ..Default::default()
+        };
+        let profile = ProfileConfig {
+            tz: Some("America/Chicago".into()),
+            record_dir: Some("county/Record".into()),
+            sink: Some("csv,sqlite".into()),
+            ..Default::default()
+        };
+        apply_profile(&mut args, profile);
+        apply_env(&mut args);
+        assert_eq!(args.tz.as_deref(), Some("Europe/Berlin"));
+        assert_eq!(args.record_dir, Some(PathBuf::from("county/Record")));
+        assert_eq!(args.sink, "csv,sqlite");
+        // What the profile leaves unset still comes from the environment.
+        assert_eq!(args.out, Some(PathBuf::from("env.db")));
+        assert_eq!(args.rotate.as_deref(), Some("hourly"));
+    }
 }
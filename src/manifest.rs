@@ -0,0 +1,92 @@
+// src/manifest.rs
+//
+// Machine-readable run manifest (`--manifest run.json`): what was read, with
+// which options, how many records each stage saw, and what was written.
+
+use crate::errors::AppError;
+use crate::features;
+use crate::model::SCHEMA_VERSION;
+use crate::stats::StatsSnapshot;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize)]
+pub struct RunManifest {
+    pub tool: &'static str,
+    pub version: &'static str,
+    /// [`SCHEMA_VERSION`] of the records written.
+    pub schema_version: u32,
+    pub features: Vec<&'static str>,
+    pub started_at: String,
+    pub finished_at: String,
+    pub wall_time_secs: f64,
+    /// True only if every input finished without error.
+    pub complete: bool,
+    pub options: serde_json::Value,
+    pub inputs: Vec<InputReport>,
+}
+
+impl RunManifest {
+    pub fn new(options: serde_json::Value) -> Self {
+        Self {
+            tool: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            schema_version: SCHEMA_VERSION,
+            features: features::COMPILED.to_vec(),
+            started_at: String::new(),
+            finished_at: String::new(),
+            wall_time_secs: 0.0,
+            complete: false,
+            options,
+            inputs: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct InputReport {
+    pub path: PathBuf,
+    pub tz: Option<String>,
+    /// Set by the config file's `[[input]]` entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record_dir: Option<PathBuf>,
+    /// Later files read into the same output after `path` (`--merge`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub merged: Vec<PathBuf>,
+    pub error: Option<String>,
+    pub stages: StatsSnapshot,
+    pub outputs: Vec<OutputFile>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutputFile {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+impl OutputFile {
+    /// Size and SHA-256 of a finished output file.
+    pub fn describe(path: &Path) -> Result<Self, AppError> {
+        let mut file = std::fs::File::open(path)
+            .map_err(|e| AppError::IO(format!("checksum {}: {}", path.display(), e)))?;
+        let mut hasher = Sha256::new();
+        let bytes = std::io::copy(&mut file, &mut hasher)
+            .map_err(|e| AppError::IO(format!("checksum {}: {}", path.display(), e)))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            bytes,
+            sha256: hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect(),
+        })
+    }
+}
+
+pub fn write_manifest(path: &Path, manifest: &RunManifest) -> Result<(), AppError> {
+    let json = serde_json::to_string_pretty(manifest)
+        .map_err(|e| AppError::Other(format!("manifest encode: {}", e)))?;
+    std::fs::write(path, json)
+        .map_err(|e| AppError::IO(format!("write manifest {}: {}", path.display(), e)))
+}
@@ -1,18 +1,353 @@
-#[derive(Clone, Debug)]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Version of the record layout in outputs: CSV columns and JSON documents
+/// (`schema_version`). Bumped when a field is renamed, removed or changes
+/// meaning; new optional fields don't bump it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Kind of call, from the words DSDPlus logs with it ("Group call",
+/// "Private call", "Emergency", "Data").
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CallType {
+    #[default]
+    Unknown,
+    Group,
+    /// Unit-to-unit call; the called unit goes in `target_rid`.
+    Private,
+    Data,
+    Emergency,
+}
+
+impl CallType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CallType::Unknown => "unknown",
+            CallType::Group => "group",
+            CallType::Private => "private",
+            CallType::Data => "data",
+            CallType::Emergency => "emergency",
+        }
+    }
+
+    /// The inverse of [`as_str`](Self::as_str), e.g. for a CSV column.
+    pub fn from_name(s: &str) -> Option<CallType> {
+        [CallType::Unknown, CallType::Group, CallType::Private, CallType::Data, CallType::Emergency]
+            .into_iter()
+            .find(|t| t.as_str().eq_ignore_ascii_case(s.trim()))
+    }
+
+    /// Classify a log line or block. An emergency flag wins over the call it
+    /// is raised on; `None` when the text names no call type.
+    pub fn detect(text: &str) -> Option<CallType> {
+        let t = text.to_ascii_lowercase();
+        let word = |w: &str| t.split(|c: char| !c.is_ascii_alphanumeric()).any(|x| x == w);
+        if word("emergency") || word("emerg") {
+            Some(CallType::Emergency)
+        } else if t.contains("private call") || t.contains("unit to unit") || word("pc") || word("uu") {
+            Some(CallType::Private)
+        } else if t.contains("data call") || word("data") {
+            Some(CallType::Data)
+        } else if t.contains("group call") || word("gc") {
+            Some(CallType::Group)
+        } else {
+            None
+        }
+    }
+}
+
+/// Where an RLE run starts and ends in the raw log (`--run-details`); the
+/// start time is the record's own `datetime`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunSpan {
+    /// The parser's `record_number` for the first block, kept when
+    /// `--renumber` replaces the record's own.
+    pub first_record: usize,
+    pub last_record: usize,
+    pub end_datetime: chrono::DateTime<chrono::FixedOffset>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct SlotData {
     pub tg: Option<String>,
     pub rid: Option<String>,
     pub text: Option<String>,
 }
 
-#[derive(Clone, Debug)]
+/// One call record. Every parser builds records through `RadioRecord::new`
+/// so fields a format doesn't carry are defaulted identically everywhere.
+/// The serde derives are for `--sort` spill files; outputs use `to_json`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RadioRecord {
     pub record_number: usize,
     pub datetime: chrono::DateTime<chrono::FixedOffset>,
     pub frequency: Option<String>,
     pub radio_type: Option<String>,
-    pub dcc: Option<String>, // NAC or DCC
+    /// P25 Network Access Code (`NAC=`).
+    pub nac: Option<String>,
+    /// DMR color code (`DCC=`).
+    pub dcc: Option<String>,
     pub slot1: SlotData,
     pub slot2: SlotData,
-    pub duration: u32
-}
\ No newline at end of file
+    pub call_type: CallType,
+    /// DSDPlus flagged the call encrypted (`Encrypted`, `ALG=` other than clear).
+    pub encrypted: bool,
+    /// Called unit of a private call (DSDPlus logs it as `TG=`/`TGT=`).
+    pub target_rid: Option<String>,
+    /// Decode errors DSDPlus reported for the call (`ERR=`, `errs=`).
+    pub errors: Option<u32>,
+    /// Sync/signal quality in percent (`Q=`, `Sync=`, `Quality=`).
+    pub quality: Option<u32>,
+    /// `KEY=VALUE` tokens the parser has no field for (with `--extras`).
+    pub extras: BTreeMap<String, String>,
+    /// Length in blocks/seconds; 1 when the source doesn't report it.
+    pub duration: u32,
+    /// Input blocks/lines RLE merged into this record.
+    pub block_count: u32,
+    /// Last block of the run, tracked by RLE with `--run-details`.
+    pub run: Option<RunSpan>,
+    /// Matched recording, when one is known.
+    pub audio_path: Option<PathBuf>,
+    /// File name of the input the record was parsed from.
+    pub source: Option<Arc<str>>,
+    /// Why the call is on the watchlist (`RID 4506 (Engine 5)`), set by the
+    /// alert stage.
+    pub alert: Option<String>,
+    /// Triage score from the config file's `[severity]` rules, set by the
+    /// severity stage.
+    pub severity: Option<u32>,
+    /// Talkgroup the calling unit was affiliated to when the call started
+    /// (`--affiliations`, event logs only).
+    pub affiliated_tg: Option<String>,
+}
+
+impl RadioRecord {
+    pub fn new(record_number: usize, datetime: chrono::DateTime<chrono::FixedOffset>) -> Self {
+        Self {
+            record_number,
+            datetime,
+            frequency: None,
+            radio_type: None,
+            nac: None,
+            dcc: None,
+            slot1: SlotData::default(),
+            slot2: SlotData::default(),
+            call_type: CallType::Unknown,
+            encrypted: false,
+            target_rid: None,
+            errors: None,
+            quality: None,
+            extras: BTreeMap::new(),
+            duration: 1,
+            block_count: 1,
+            run: None,
+            audio_path: None,
+            source: None,
+            alert: None,
+            severity: None,
+            affiliated_tg: None,
+        }
+    }
+
+    /// Deterministic id for correlating one call across outputs: a
+    /// UUID-formatted (version 8) SHA-256 of datetime, frequency, both
+    /// slots' TG/RID and the source file name. Re-running the same input
+    /// gives the same ids.
+    pub fn record_id(&self) -> String {
+        let opt = |v: &Option<String>| v.clone().unwrap_or_default();
+        let key = [
+            self.datetime.to_rfc3339(),
+            opt(&self.frequency),
+            opt(&self.slot1.tg),
+            opt(&self.slot1.rid),
+            opt(&self.slot2.tg),
+            opt(&self.slot2.rid),
+            self.source.as_deref().unwrap_or_default().to_string(),
+        ];
+        let mut b: [u8; 16] = Sha256::digest(key.join("\x1f")).as_slice()[..16].try_into().unwrap_or_default();
+        b[6] = (b[6] & 0x0f) | 0x80;
+        b[8] = (b[8] & 0x3f) | 0x80;
+        let hex: String = b.iter().map(|x| format!("{:02x}", x)).collect();
+        format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+    }
+
+    /// A private call's TG= value is the called unit, not a talkgroup: move
+    /// it to `target_rid` (unless a target was logged explicitly).
+    pub fn map_private_target(&mut self) {
+        if self.call_type != CallType::Private {
+            return;
+        }
+        for slot in [&mut self.slot1, &mut self.slot2] {
+            if let Some(tg) = slot.tg.take() {
+                self.target_rid.get_or_insert(tg);
+            }
+        }
+    }
+
+    /// NAC or DCC, whichever the record has: the single `dcc` column older
+    /// outputs carried, kept for compatibility.
+    pub fn nac_or_dcc(&self) -> Option<&str> {
+        self.nac.as_deref().or(self.dcc.as_deref())
+    }
+
+    /// Set from a legacy combined NAC/DCC value: NAC on P25, DCC otherwise.
+    pub fn set_nac_or_dcc(&mut self, v: Option<String>) {
+        if self.radio_type.as_deref().is_some_and(|t| t.trim_start_matches('+').starts_with("P25")) {
+            self.nac = v;
+        } else {
+            self.dcc = v;
+        }
+    }
+
+    /// JSON document shared by the JSON-based sinks. `radio_type` is emitted
+    /// without DSDPlus's leading '+'.
+    pub fn to_json(&self) -> serde_json::Value {
+        let slot = |s: &SlotData| serde_json::json!({ "tg": s.tg, "rid": s.rid, "text": s.text });
+        let mut doc = serde_json::json!({
+            "schema_version": SCHEMA_VERSION,
+            "record_id": self.record_id(),
+            "record_number": self.record_number,
+            "datetime": self.datetime.to_rfc3339(),
+            "duration": self.duration,
+            "frequency": self.frequency,
+            "radio_type": self.radio_type.as_deref().map(|t| t.trim_start_matches('+')),
+            "dcc": self.nac_or_dcc(),
+            "nac": self.nac,
+            "color_code": self.dcc,
+            "call_type": self.call_type.as_str(),
+            "target_rid": self.target_rid,
+            "errors": self.errors,
+            "quality": self.quality,
+            "slot1": slot(&self.slot1),
+            "slot2": slot(&self.slot2),
+            "audio_path": self.audio_path,
+        });
+        if !self.extras.is_empty() {
+            doc["extras"] = serde_json::json!(self.extras);
+        }
+        if self.encrypted {
+            doc["encrypted"] = serde_json::json!(true);
+        }
+        if let Some(alert) = &self.alert {
+            doc["alert"] = serde_json::json!(alert);
+        }
+        if let Some(severity) = self.severity {
+            doc["severity"] = serde_json::json!(severity);
+        }
+        if let Some(tg) = &self.affiliated_tg {
+            doc["affiliated_tg"] = serde_json::json!(tg);
+        }
+        if let Some(run) = &self.run {
+            doc["run"] = serde_json::json!({
+                "first_record": run.first_record,
+                "last_record": run.last_record,
+                "block_count": self.block_count,
+                "end_datetime": run.end_datetime.to_rfc3339(),
+            });
+        }
+        doc
+    }
+
+    /// `extras` as `KEY=VALUE` pairs joined by spaces, for flat outputs.
+    pub fn extras_string(&self) -> String {
+        self.extras.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats;
+    use crate::input::{self, ParseOptions};
+    use chrono::{FixedOffset, TimeZone};
+    use std::collections::BTreeSet;
+
+    /// Keys `to_json` writes only when the field is set.
+    const OPTIONAL_KEYS: &[&str] = &["extras", "encrypted", "alert", "severity", "affiliated_tg", "run"];
+
+    fn keys(doc: &serde_json::Value) -> BTreeSet<String> {
+        doc.as_object().expect("object").keys().cloned().collect()
+    }
+
+    /// Every record `format`'s `callscribe formats` sample turns into.
+    fn parse_sample(format: &str) -> Vec<RadioRecord> {
+        let f = formats::named(format).expect("format");
+        let info = f.info();
+        let name = format!("callscribe-model-{}-{}.{}", std::process::id(), format, info.extensions[0]);
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, info.sample).expect("write sample");
+        let opts = ParseOptions {
+            tz_offset: FixedOffset::east_opt(0),
+            base_date: chrono::NaiveDate::from_ymd_opt(2025, 9, 10),
+            format: Some(info.name),
+            ..Default::default()
+        };
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().expect("runtime");
+        let records = rt.block_on(async {
+            let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+            let collect = async {
+                let mut out = Vec::new();
+                while let Some(rec) = rx.recv().await {
+                    out.push(rec);
+                }
+                out
+            };
+            let (parsed, records) = tokio::join!(input::stream_input(&path, opts, tx), collect);
+            parsed.expect("parse sample");
+            records
+        });
+        let _ = std::fs::remove_file(&path);
+        records
+    }
+
+    #[test]
+    fn new_defaults() {
+        let dt = FixedOffset::east_opt(0).unwrap().with_ymd_and_hms(2025, 9, 10, 6, 43, 56).unwrap();
+        let rec = RadioRecord::new(7, dt);
+        assert_eq!((rec.record_number, rec.duration, rec.block_count), (7, 1, 1));
+        assert_eq!(rec.call_type, CallType::Unknown);
+        assert!(rec.audio_path.is_none() && rec.run.is_none() && rec.source.is_none());
+        assert!(rec.to_json().as_object().unwrap().keys().all(|k| !OPTIONAL_KEYS.contains(&k.as_str())));
+    }
+
+    /// Every text parser builds the same shape of record: the keys of a
+    /// default record, plus only the optional ones, with a time, frequency
+    /// and source filled in. (Uniden needs real WAV files; its records come
+    /// through `RadioRecord::new` like the rest.)
+    #[test]
+    fn parsers_produce_the_same_shape() {
+        let dt = FixedOffset::east_opt(0).unwrap().with_ymd_and_hms(2025, 9, 10, 0, 0, 0).unwrap();
+        let base = keys(&RadioRecord::new(1, dt).to_json());
+        let samples = [("srt", "2025-09-10"), ("event", "2025-09-09"), ("unitrunker", "2025-09-10"), ("analog", "2025-09-10")];
+        for (format, day) in samples {
+            let records = parse_sample(format);
+            assert!(!records.is_empty(), "{}: no records from its sample", format);
+            for rec in &records {
+                let doc = rec.to_json();
+                let got = keys(&doc);
+                assert!(got.is_superset(&base), "{}: missing {:?}", format, base.difference(&got).collect::<Vec<_>>());
+                let extra: Vec<_> = got.difference(&base).filter(|k| !OPTIONAL_KEYS.contains(&k.as_str())).collect();
+                assert!(extra.is_empty(), "{}: unexpected keys {:?}", format, extra);
+                assert!(rec.frequency.is_some(), "{}: no frequency", format);
+                assert!(rec.source.is_some(), "{}: no source", format);
+                assert!(rec.duration >= 1, "{}: duration 0", format);
+                assert_eq!(doc["datetime"].as_str().map(|d| &d[..10]), Some(day), "{}: datetime", format);
+            }
+        }
+    }
+
+    #[test]
+    fn srt_and_event_agree_on_one_call() {
+        let srt = &parse_sample("srt")[0];
+        let event = &parse_sample("event")[0];
+        for rec in [srt, event] {
+            assert_eq!(rec.frequency.as_deref(), Some("153.450000"));
+            assert_eq!((rec.slot1.tg.as_deref(), rec.slot1.rid.as_deref()), (Some("2"), Some("4506")));
+            assert_eq!(rec.nac.as_deref(), Some("293"));
+        }
+        assert_eq!(event.call_type, CallType::Group);
+    }
+}
@@ -0,0 +1,329 @@
+// src/models.rs
+//
+// Speech-to-text model cache used by the `models` subcommand. Known whisper
+// (ggml) and vosk models are downloaded into one per-user directory; next to
+// each model a `<name>.sha256` file in `sha256sum` format records the
+// checksum of every file so `models verify` can detect truncated or
+// corrupted downloads later.
+
+use crate::errors::AppError;
+use crate::manifest::OutputFile;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Single ggml `.bin` file for whisper.cpp.
+    Whisper,
+    /// Zip archive unpacked into a directory.
+    Vosk,
+}
+
+impl Backend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Backend::Whisper => "whisper",
+            Backend::Vosk => "vosk",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ModelInfo {
+    pub name: &'static str,
+    pub backend: Backend,
+    pub url: &'static str,
+    /// Approximate download size, for the listing only.
+    pub size_mb: u32,
+}
+
+macro_rules! whisper {
+    ($name:literal, $mb:literal) => {
+        ModelInfo { name: $name, backend: Backend::Whisper, url: concat!("https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-", $name, ".bin"), size_mb: $mb }
+    };
+}
+
+macro_rules! vosk {
+    ($name:literal, $mb:literal) => {
+        ModelInfo { name: $name, backend: Backend::Vosk, url: concat!("https://alphacephei.com/vosk/models/", $name, ".zip"), size_mb: $mb }
+    };
+}
+
+/// Models `models download` knows how to fetch.
+pub const KNOWN: &[ModelInfo] = &[
+    whisper!("tiny", 75),
+    whisper!("tiny.en", 75),
+    whisper!("base", 142),
+    whisper!("base.en", 142),
+    whisper!("small", 466),
+    whisper!("small.en", 466),
+    whisper!("medium", 1500),
+    whisper!("medium.en", 1500),
+    whisper!("large-v3", 2900),
+    whisper!("large-v3-turbo", 1500),
+    vosk!("vosk-model-small-en-us-0.15", 40),
+    vosk!("vosk-model-en-us-0.22", 1800),
+    vosk!("vosk-model-small-es-0.42", 39),
+];
+
+pub fn find(name: &str) -> Option<&'static ModelInfo> {
+    KNOWN.iter().find(|m| m.name == name)
+}
+
+/// Cache directory: `$CALLSCRIBE_MODELS_DIR`, else the platform's per-user
+/// data directory (`$XDG_DATA_HOME` / `~/.local/share`, `~/Library/Application
+/// Support`, `%LOCALAPPDATA%`) + `callscribe/models`.
+pub fn default_dir() -> PathBuf {
+    if let Some(d) = std::env::var_os("CALLSCRIBE_MODELS_DIR") {
+        return PathBuf::from(d);
+    }
+    let base = if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|h| PathBuf::from(h).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))
+    };
+    base.unwrap_or_else(|| PathBuf::from(".")).join("callscribe").join("models")
+}
+
+/// Where `model` lives inside `dir`: the `.bin` file for whisper, the
+/// unpacked directory for vosk.
+pub fn model_path(dir: &Path, model: &ModelInfo) -> PathBuf {
+    match model.backend {
+        Backend::Whisper => dir.join(format!("ggml-{}.bin", model.name)),
+        Backend::Vosk => dir.join(model.name),
+    }
+}
+
+/// Where to fetch `model` from: its upstream URL, or the same file name
+/// under `$CALLSCRIBE_MODELS_MIRROR` for offline/internal mirrors.
+pub fn download_url(model: &ModelInfo) -> String {
+    match std::env::var("CALLSCRIBE_MODELS_MIRROR") {
+        Ok(base) if !base.is_empty() => {
+            let file = model.url.rsplit('/').next().unwrap_or(model.url);
+            format!("{}/{}", base.trim_end_matches('/'), file)
+        }
+        _ => model.url.to_string(),
+    }
+}
+
+fn checksum_path(dir: &Path, model: &ModelInfo) -> PathBuf {
+    dir.join(format!("{}.sha256", model.name))
+}
+
+/// Resolve a `--stt-model`-style argument: an existing path is used as-is,
+/// otherwise it is looked up by name in the cache.
+pub fn resolve(dir: &Path, name_or_path: &str) -> Result<PathBuf, AppError> {
+    let p = PathBuf::from(name_or_path);
+    if p.exists() {
+        return Ok(p);
+    }
+    let model = find(name_or_path)
+        .ok_or_else(|| AppError::Other(format!("unknown model '{}' (see `models list`)", name_or_path)))?;
+    let path = model_path(dir, model);
+    if !path.exists() {
+        return Err(AppError::Other(format!(
+            "model '{}' is not installed in {}; run `models download {}`",
+            model.name,
+            dir.display(),
+            model.name
+        )));
+    }
+    Ok(path)
+}
+
+pub fn is_installed(dir: &Path, model: &ModelInfo) -> bool {
+    model_path(dir, model).exists() && checksum_path(dir, model).exists()
+}
+
+/// Relative path -> sha256 for every file of an installed model, sorted.
+fn hash_files(dir: &Path, model: &ModelInfo) -> Result<Vec<(String, String)>, AppError> {
+    let root = model_path(dir, model);
+    let mut out = Vec::new();
+    if root.is_file() {
+        out.push((root.file_name().unwrap_or_default().to_string_lossy().into_owned(), OutputFile::describe(&root)?.sha256));
+        return Ok(out);
+    }
+    for entry in walkdir::WalkDir::new(&root).sort_by_file_name() {
+        let entry = entry.map_err(|e| AppError::IO(format!("walk {}: {}", root.display(), e)))?;
+        if entry.file_type().is_file() {
+            let rel = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+            out.push((rel.to_string_lossy().replace('\\', "/"), OutputFile::describe(entry.path())?.sha256));
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(feature = "models-download")]
+fn write_checksums(dir: &Path, model: &ModelInfo) -> Result<(), AppError> {
+    let body: String = hash_files(dir, model)?
+        .iter()
+        .map(|(rel, sum)| format!("{}  {}\n", sum, rel))
+        .collect();
+    let path = checksum_path(dir, model);
+    std::fs::write(&path, body).map_err(|e| AppError::IO(format!("write {}: {}", path.display(), e)))
+}
+
+/// Outcome of verifying one model.
+#[derive(Debug)]
+pub enum Verify {
+    Ok { files: usize },
+    NotInstalled,
+    /// Files whose checksum differs or that are missing/unexpected.
+    Mismatch(Vec<String>),
+}
+
+/// Recompute checksums of an installed model and compare with the ones
+/// recorded at download time.
+pub fn verify(dir: &Path, model: &ModelInfo) -> Result<Verify, AppError> {
+    if !is_installed(dir, model) {
+        return Ok(Verify::NotInstalled);
+    }
+    let recorded = std::fs::read_to_string(checksum_path(dir, model))?;
+    let mut expected: Vec<(String, String)> = recorded
+        .lines()
+        .filter_map(|l| l.split_once("  "))
+        .map(|(sum, rel)| (rel.to_string(), sum.to_string()))
+        .collect();
+    expected.sort();
+    let actual = hash_files(dir, model)?;
+
+    let mut bad = Vec::new();
+    for (rel, sum) in &expected {
+        match actual.iter().find(|(r, _)| r == rel) {
+            Some((_, s)) if s == sum => {}
+            Some(_) => bad.push(format!("{}: checksum mismatch", rel)),
+            None => bad.push(format!("{}: missing", rel)),
+        }
+    }
+    for (rel, _) in actual.iter().filter(|(r, _)| !expected.iter().any(|(e, _)| e == r)) {
+        bad.push(format!("{}: not in checksum list", rel));
+    }
+    Ok(if bad.is_empty() { Verify::Ok { files: expected.len() } } else { Verify::Mismatch(bad) })
+}
+
+/// Download `model` into `dir` (feature `models-download`). The file is
+/// streamed to a `.part` file and only moved into place once complete; when
+/// the server advertises the payload's SHA-256 (Hugging Face answers with
+/// `X-Linked-Etag` on the redirect to its LFS storage), the download is
+/// checked against it.
+#[cfg(feature = "models-download")]
+pub async fn download(dir: &Path, model: &ModelInfo) -> Result<PathBuf, AppError> {
+    use log::info;
+    use sha2::{Digest, Sha256};
+    use tokio::io::AsyncWriteExt;
+
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|e| AppError::IO(format!("create {}: {}", dir.display(), e)))?;
+    let part = dir.join(format!("{}.part", model.name));
+    let url = download_url(model);
+
+    let no_redirect = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| AppError::Other(format!("http client: {}", e)))?;
+    let advertised = match no_redirect.head(&url).send().await {
+        Ok(head) => head
+            .headers()
+            .get("x-linked-etag")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim_matches('"').to_ascii_lowercase())
+            .filter(|v| v.len() == 64 && v.chars().all(|c| c.is_ascii_hexdigit())),
+        Err(_) => None,
+    };
+
+    let mut resp = reqwest::get(&url)
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| AppError::IO(format!("download {}: {}", url, e)))?;
+    let total = resp.content_length();
+    info!("Downloading {} ({} MB) from {}", model.name, total.map_or(model.size_mb as u64, |b| b >> 20), url);
+
+    let mut file = tokio::fs::File::create(&part)
+        .await
+        .map_err(|e| AppError::IO(format!("create {}: {}", part.display(), e)))?;
+    let mut hasher = Sha256::new();
+    let mut done: u64 = 0;
+    let mut next_report: u64 = 0;
+    while let Some(chunk) = resp
+        .chunk()
+        .await
+        .map_err(|e| AppError::IO(format!("download {}: {}", url, e)))?
+    {
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+        done += chunk.len() as u64;
+        if done >= next_report {
+            info!("  {} MB{}", done >> 20, total.map_or(String::new(), |t| format!(" / {} MB", t >> 20)));
+            next_report = done + (64 << 20);
+        }
+    }
+    file.flush().await?;
+    drop(file);
+
+    if let Some(t) = total
+        && t != done
+    {
+        let _ = tokio::fs::remove_file(&part).await;
+        return Err(AppError::IO(format!("download {}: got {} of {} bytes", model.name, done, t)));
+    }
+    let sum: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    if let Some(expected) = advertised.as_deref()
+        && expected != sum
+    {
+        let _ = tokio::fs::remove_file(&part).await;
+        return Err(AppError::IO(format!("download {}: sha256 {} does not match server's {}", model.name, sum, expected)));
+    }
+
+    let dest = model_path(dir, model);
+    match model.backend {
+        Backend::Whisper => tokio::fs::rename(&part, &dest).await?,
+        Backend::Vosk => {
+            let (part2, dir2) = (part.clone(), dir.to_path_buf());
+            tokio::task::spawn_blocking(move || unzip(&part2, &dir2))
+                .await
+                .map_err(|e| AppError::Other(format!("unzip join: {e}")))??;
+            tokio::fs::remove_file(&part).await?;
+            if !dest.is_dir() {
+                return Err(AppError::IO(format!("{}: archive did not contain {}/", url, model.name)));
+            }
+        }
+    }
+    let dir2 = dir.to_path_buf();
+    let model2 = *model;
+    tokio::task::spawn_blocking(move || write_checksums(&dir2, &model2))
+        .await
+        .map_err(|e| AppError::Other(format!("checksum join: {e}")))??;
+    info!("Installed {} -> {}", model.name, dest.display());
+    Ok(dest)
+}
+
+#[cfg(feature = "models-download")]
+fn unzip(archive: &Path, into: &Path) -> Result<(), AppError> {
+    let file = std::fs::File::open(archive)?;
+    let mut zip = zip::ZipArchive::new(file).map_err(|e| AppError::IO(format!("open {}: {}", archive.display(), e)))?;
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).map_err(|e| AppError::IO(format!("read {}: {}", archive.display(), e)))?;
+        // enclosed_name() rejects absolute paths and `..` components.
+        let Some(rel) = entry.enclosed_name() else { continue };
+        let out = into.join(rel);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&out)?;
+        } else {
+            if let Some(parent) = out.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut f = std::fs::File::create(&out)?;
+            std::io::copy(&mut entry, &mut f)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "models-download"))]
+pub async fn download(_dir: &Path, _model: &ModelInfo) -> Result<PathBuf, AppError> {
+    Err(AppError::Other("downloading models needs a build with the models-download feature".into()))
+}
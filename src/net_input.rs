@@ -0,0 +1,137 @@
+// src/net_input.rs
+//
+// `--listen tcp://0.0.0.0:9000` / `udp://0.0.0.0:9000`: read DSDPlus event
+// or SRT lines sent over the network, e.g. by
+//
+//   tail -F CC-DSDPlus.event | nc callscribe-host 9000
+//
+// on the capture machine, so that Callscribe can run elsewhere. The
+// listener is an input like any other: its text goes to the parser named
+// by `--listen-format` and never ends, as with `--follow`.
+//
+// TCP serves one sender at a time; a second connection waits until the
+// first closes, so SRT blocks from two senders can't interleave. A UDP
+// datagram holds whole lines. Either way a line cut short by a connection
+// closing or a datagram ending is terminated, so the next one starts clean.
+
+use crate::errors::AppError;
+use log::{info, warn};
+use std::net::SocketAddr;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio::net::{TcpListener, UdpSocket};
+
+/// Bytes buffered between the socket and the parser.
+const PIPE_CAPACITY: usize = 64 * 1024;
+const MAX_DATAGRAM: usize = 64 * 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    Udp(SocketAddr),
+}
+
+impl ListenAddr {
+    pub fn parse(s: &str) -> Result<Self, AppError> {
+        let err = || AppError::Parse(format!("--listen '{}': use tcp://HOST:PORT or udp://HOST:PORT", s));
+        let (scheme, addr) = s.trim().split_once("://").ok_or_else(err)?;
+        let addr: SocketAddr = addr.trim_end_matches('/').parse().map_err(|_| err())?;
+        match scheme.to_ascii_lowercase().as_str() {
+            "tcp" => Ok(ListenAddr::Tcp(addr)),
+            "udp" => Ok(ListenAddr::Udp(addr)),
+            _ => Err(err()),
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        match self {
+            ListenAddr::Tcp(a) | ListenAddr::Udp(a) => a.port(),
+        }
+    }
+}
+
+impl std::fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListenAddr::Tcp(a) => write!(f, "tcp://{}", a),
+            ListenAddr::Udp(a) => write!(f, "udp://{}", a),
+        }
+    }
+}
+
+/// Write `bytes` and, if they don't end a line, a newline. False once the
+/// parser is gone.
+async fn forward(pipe: &mut DuplexStream, bytes: &[u8]) -> bool {
+    if pipe.write_all(bytes).await.is_err() {
+        return false;
+    }
+    if bytes.last().is_some_and(|b| *b != b'\n') {
+        return pipe.write_all(b"\n").await.is_ok();
+    }
+    true
+}
+
+async fn serve_tcp(listener: TcpListener, mut pipe: DuplexStream) {
+    let mut buf = vec![0u8; PIPE_CAPACITY];
+    loop {
+        let (mut conn, peer) = match listener.accept().await {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("--listen: accept: {}", e);
+                continue;
+            }
+        };
+        info!("--listen: {} connected", peer);
+        let mut last = b'\n';
+        loop {
+            match conn.read(&mut buf).await {
+                Ok(0) => break,
+                Ok(n) => {
+                    last = buf[n - 1];
+                    if pipe.write_all(&buf[..n]).await.is_err() {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    warn!("--listen: {}: {}", peer, e);
+                    break;
+                }
+            }
+        }
+        if last != b'\n' && pipe.write_all(b"\n").await.is_err() {
+            return;
+        }
+        info!("--listen: {} disconnected", peer);
+    }
+}
+
+async fn serve_udp(socket: UdpSocket, mut pipe: DuplexStream) {
+    let mut buf = vec![0u8; MAX_DATAGRAM];
+    loop {
+        match socket.recv_from(&mut buf).await {
+            Ok((n, _)) => {
+                if !forward(&mut pipe, &buf[..n]).await {
+                    return;
+                }
+            }
+            Err(e) => warn!("--listen: receive: {}", e),
+        }
+    }
+}
+
+/// Bind `addr` and return the text received on it as one endless stream.
+pub async fn open(addr: &ListenAddr) -> Result<Box<dyn AsyncRead + Unpin + Send>, AppError> {
+    let (reader, pipe) = tokio::io::duplex(PIPE_CAPACITY);
+    let bind_err = |e: std::io::Error| AppError::IO(format!("--listen {}: {}", addr, e));
+    match addr {
+        ListenAddr::Tcp(a) => {
+            let listener = TcpListener::bind(a).await.map_err(bind_err)?;
+            tokio::spawn(serve_tcp(listener, pipe));
+        }
+        ListenAddr::Udp(a) => {
+            let socket = UdpSocket::bind(a).await.map_err(bind_err)?;
+            tokio::spawn(serve_udp(socket, pipe));
+        }
+    }
+    info!("Listening on {}", addr);
+    Ok(Box::new(reader))
+}
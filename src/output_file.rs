@@ -0,0 +1,86 @@
+// src/output_file.rs
+//
+// File sinks write under a temporary name next to the target
+// (`calls.csv.part`) and rename it over the target only once the stream has
+// ended, so a run that is killed or fails part-way leaves the `.part` file
+// behind instead of a truncated `calls.csv` that looks like a finished
+// conversion. With `--fsync` the file (and, on Unix, its directory) is
+// flushed to disk around the rename, for archival jobs that must survive a
+// power cut as well.
+//
+// Follow mode writes in place: its output is never complete and is meant to
+// be read while it grows.
+
+use crate::errors::AppError;
+use log::debug;
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+
+/// How a file sink creates its files.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WriteMode {
+    /// Write the target directly (follow mode).
+    pub in_place: bool,
+    /// `sync_all` the file before the rename, and its directory after.
+    pub fsync: bool,
+}
+
+/// An output file being written; [`OutputFile::commit`] puts it in place.
+pub struct OutputFile {
+    target: PathBuf,
+    /// The temporary name, unless writing in place.
+    part: Option<PathBuf>,
+    fsync: bool,
+}
+
+/// `calls.csv` -> `calls.csv.part`.
+pub fn part_path(target: &Path) -> PathBuf {
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    target.with_file_name(name)
+}
+
+fn io_err(what: &str, path: &Path, e: std::io::Error) -> AppError {
+    AppError::IO(format!("{} '{}': {}", what, path.display(), e))
+}
+
+impl OutputFile {
+    pub async fn create(target: &Path, mode: WriteMode) -> Result<(Self, File), AppError> {
+        let part = (!mode.in_place).then(|| part_path(target));
+        let path = part.as_deref().unwrap_or(target);
+        let file = File::create(path).await.map_err(|e| io_err("open out", path, e))?;
+        Ok((Self { target: target.to_path_buf(), part, fsync: mode.fsync }, file))
+    }
+
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// Finish the file: `file` must already be flushed.
+    pub async fn commit(self, file: File) -> Result<(), AppError> {
+        let written = self.part.as_deref().unwrap_or(&self.target);
+        if self.fsync {
+            file.sync_all().await.map_err(|e| io_err("fsync", written, e))?;
+        }
+        drop(file);
+        if let Some(part) = &self.part {
+            tokio::fs::rename(part, &self.target)
+                .await
+                .map_err(|e| io_err(&format!("rename {} to", part.display()), &self.target, e))?;
+            debug!("renamed {} to {}", part.display(), self.target.display());
+        }
+        #[cfg(unix)]
+        if self.fsync
+            && let Some(dir) = self.target.parent()
+        {
+            let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+            File::open(dir)
+                .await
+                .map_err(|e| io_err("open dir", dir, e))?
+                .sync_all()
+                .await
+                .map_err(|e| io_err("fsync", dir, e))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+// src/pg_sink.rs
+//
+// PostgreSQL sink (feature `sink-postgres`). Rows are batch-inserted and
+// upserted on (datetime, frequency, tg, rid), so several receivers or repeated
+// runs over the same logs can feed one calls table without duplicates.
+// `--retain-days` prunes it (see `retention.rs`).
+
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use crate::retention::{Pruned, RetentionPolicy};
+use crate::stats::PipelineStats;
+use log::{debug, info};
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::{Postgres, QueryBuilder};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::mpsc::Receiver;
+
+#[derive(Clone, Debug)]
+pub struct PgSinkOptions {
+    pub url: String,
+    pub table: String,
+    /// Rows per INSERT statement.
+    pub batch: usize,
+    /// Also flush a partial batch whenever the input goes idle (follow mode).
+    pub flush_when_idle: bool,
+}
+
+impl PgSinkOptions {
+    /// Table names are interpolated into SQL, so only plain identifiers are allowed.
+    pub fn validate(&self) -> Result<(), AppError> {
+        let ok = !self.table.is_empty()
+            && self.table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !self.table.starts_with(|c: char| c.is_ascii_digit());
+        if !ok {
+            return Err(AppError::Other(format!(
+                "invalid --pg-table '{}': use letters, digits and '_'",
+                self.table
+            )));
+        }
+        if self.batch == 0 {
+            return Err(AppError::Other("--pg-batch must be at least 1".into()));
+        }
+        let max = max_batch();
+        if self.batch > max {
+            return Err(AppError::Other(format!(
+                "--pg-batch {} is too large: at {} parameters a row, PostgreSQL takes at most {} rows per INSERT",
+                self.batch,
+                COLUMNS.split(',').count(),
+                max
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Conflict key; absent values are stored as '' because key columns are NOT NULL.
+fn upsert_key(r: &RadioRecord) -> (i64, String, String, String) {
+    let tg = r.slot1.tg.as_ref().or(r.slot2.tg.as_ref()).cloned().unwrap_or_default();
+    let rid = r.slot1.rid.as_ref().or(r.slot2.rid.as_ref()).cloned().unwrap_or_default();
+    (r.datetime.timestamp(), r.frequency.clone().unwrap_or_default(), tg, rid)
+}
+
+/// Every column, in the order rows are written.
+const COLUMNS: &str = "datetime, frequency, tg, rid, record_number, duration, radio_type, dcc, \
+    slot1_tg, slot1_rid, slot1_text, slot2_tg, slot2_rid, slot2_text, audio_path, source, record_id, \
+    call_type, target_rid, errors, quality, extras, nac, color_code, alert, encrypted, severity";
+
+/// PostgreSQL's limit on bind parameters in one statement.
+const MAX_PARAMETERS: usize = 65_535;
+
+/// Most rows one INSERT can bind, one parameter per column each.
+fn max_batch() -> usize {
+    MAX_PARAMETERS / COLUMNS.split(',').count()
+}
+
+async fn ensure_table(pool: &PgPool, table: &str) -> Result<(), AppError> {
+    let ddl = format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+            datetime      TIMESTAMPTZ NOT NULL,
+            frequency     TEXT NOT NULL DEFAULT '',
+            tg            TEXT NOT NULL DEFAULT '',
+            rid           TEXT NOT NULL DEFAULT '',
+            record_number BIGINT,
+            duration      INTEGER,
+            radio_type    TEXT,
+            dcc           TEXT,
+            slot1_tg      TEXT,
+            slot1_rid     TEXT,
+            slot1_text    TEXT,
+            slot2_tg      TEXT,
+            slot2_rid     TEXT,
+            slot2_text    TEXT,
+            audio_path    TEXT,
+            source        TEXT,
+            record_id     TEXT,
+            call_type     TEXT,
+            target_rid    TEXT,
+            errors        INTEGER,
+            quality       INTEGER,
+            extras        TEXT,
+            nac           TEXT,
+            color_code    TEXT,
+            alert         TEXT,
+            encrypted     BOOLEAN,
+            severity      INTEGER,
+            PRIMARY KEY (datetime, frequency, tg, rid)
+        )"
+    );
+    // The ALTERs upgrade tables created before these columns existed.
+    let mut ddls = vec![ddl];
+    let added = [
+        ("record_id", "TEXT"),
+        ("call_type", "TEXT"),
+        ("target_rid", "TEXT"),
+        ("errors", "INTEGER"),
+        ("quality", "INTEGER"),
+        ("extras", "TEXT"),
+        ("nac", "TEXT"),
+        ("color_code", "TEXT"),
+        ("alert", "TEXT"),
+        ("encrypted", "BOOLEAN"),
+        ("severity", "INTEGER"),
+    ];
+    ddls.extend(added.map(|(c, ty)| format!("ALTER TABLE {table} ADD COLUMN IF NOT EXISTS {c} {ty}")));
+    ddls.push(format!("CREATE INDEX IF NOT EXISTS {table}_record_id ON {table} (record_id)"));
+    for ddl in ddls {
+        sqlx::query(&ddl)
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::IO(format!("postgres create table {}: {}", table, e)))?;
+    }
+    Ok(())
+}
+
+async fn flush_batch(
+    pool: &PgPool,
+    table: &str,
+    source: &str,
+    batch: &mut Vec<RadioRecord>,
+) -> Result<(), AppError> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+
+    // A single INSERT .. ON CONFLICT may not touch the same key twice, so
+    // collapse duplicates within the batch first (last one wins).
+    let mut by_key: HashMap<(i64, String, String, String), usize> = HashMap::new();
+    let mut rows: Vec<&RadioRecord> = Vec::with_capacity(batch.len());
+    for r in batch.iter() {
+        match by_key.get(&upsert_key(r)) {
+            Some(&i) => rows[i] = r,
+            None => {
+                by_key.insert(upsert_key(r), rows.len());
+                rows.push(r);
+            }
+        }
+    }
+
+    let mut qb: QueryBuilder<Postgres> = QueryBuilder::new(format!("INSERT INTO {table} ({COLUMNS}) "));
+    qb.push_values(rows, |mut b, r| {
+        let (_, freq, tg, rid) = upsert_key(r);
+        b.push_bind(r.datetime)
+            .push_bind(freq)
+            .push_bind(tg)
+            .push_bind(rid)
+            .push_bind(r.record_number as i64)
+            .push_bind(r.duration as i32)
+            .push_bind(r.radio_type.clone())
+            .push_bind(r.nac_or_dcc().map(str::to_string))
+            .push_bind(r.slot1.tg.clone())
+            .push_bind(r.slot1.rid.clone())
+            .push_bind(r.slot1.text.clone())
+            .push_bind(r.slot2.tg.clone())
+            .push_bind(r.slot2.rid.clone())
+            .push_bind(r.slot2.text.clone())
+            .push_bind(r.audio_path.as_ref().map(|p| p.display().to_string()))
+            .push_bind(source.to_string())
+            .push_bind(r.record_id())
+            .push_bind(r.call_type.as_str())
+            .push_bind(r.target_rid.clone())
+            .push_bind(r.errors.map(|e| e as i32))
+            .push_bind(r.quality.map(|q| q as i32))
+            .push_bind((!r.extras.is_empty()).then(|| serde_json::json!(r.extras).to_string()))
+            .push_bind(r.nac.clone())
+            .push_bind(r.dcc.clone())
+            .push_bind(r.alert.clone())
+            .push_bind(r.encrypted)
+            .push_bind(r.severity.map(|s| s as i32));
+    });
+    // Re-runs may have gained transcripts/audio or a longer run; never lose data.
+    qb.push(format!(
+        " ON CONFLICT (datetime, frequency, tg, rid) DO UPDATE SET \
+         duration   = GREATEST({table}.duration, EXCLUDED.duration), \
+         radio_type = COALESCE(EXCLUDED.radio_type, {table}.radio_type), \
+         dcc        = COALESCE(EXCLUDED.dcc, {table}.dcc), \
+         slot1_text = COALESCE(EXCLUDED.slot1_text, {table}.slot1_text), \
+         slot2_text = COALESCE(EXCLUDED.slot2_text, {table}.slot2_text), \
+         audio_path = COALESCE(EXCLUDED.audio_path, {table}.audio_path), \
+         record_id  = COALESCE({table}.record_id, EXCLUDED.record_id), \
+         call_type  = EXCLUDED.call_type, \
+         target_rid = COALESCE(EXCLUDED.target_rid, {table}.target_rid), \
+         errors     = COALESCE(EXCLUDED.errors, {table}.errors), \
+         quality    = COALESCE(EXCLUDED.quality, {table}.quality), \
+         extras     = COALESCE(EXCLUDED.extras, {table}.extras), \
+         nac        = COALESCE(EXCLUDED.nac, {table}.nac), \
+         color_code = COALESCE(EXCLUDED.color_code, {table}.color_code), \
+         alert      = COALESCE(EXCLUDED.alert, {table}.alert), \
+         encrypted  = COALESCE(EXCLUDED.encrypted, {table}.encrypted), \
+         severity   = COALESCE(EXCLUDED.severity, {table}.severity)"
+    ));
+
+    qb.build()
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::IO(format!("postgres upsert: {}", e)))?;
+    debug!("pg_sink: upserted {} rows", batch.len());
+    batch.clear();
+    Ok(())
+}
+
+/// Connection pool shared by every pipeline; the table is created once here
+/// rather than racing `CREATE TABLE` from concurrent pipelines.
+#[derive(Clone, Debug)]
+pub struct PgSink {
+    pool: PgPool,
+    opts: PgSinkOptions,
+}
+
+impl PgSink {
+    pub async fn connect(opts: PgSinkOptions) -> Result<Self, AppError> {
+        opts.validate()?;
+        let pool = PgPoolOptions::new()
+            .max_connections(4)
+            .connect(&opts.url)
+            .await
+            .map_err(|e| AppError::IO(format!("postgres connect: {}", e)))?;
+        ensure_table(&pool, &opts.table).await?;
+        Ok(Self { pool, opts })
+    }
+
+    /// Delete the calls `policy` has expired, or with `archive` move them
+    /// into `<table>_archive` (created like the table), in one transaction.
+    pub async fn prune(&self, policy: &RetentionPolicy) -> Result<Pruned, AppError> {
+        let err = |e: sqlx::Error| AppError::IO(format!("postgres retention: {}", e));
+        let table = &self.opts.table;
+        let expired = if policy.keep_alerts { "datetime < $1 AND COALESCE(alert, '') = ''" } else { "datetime < $1" };
+        let cutoff = policy.cutoff();
+        let archive = format!("{}_archive", table);
+        if policy.archive {
+            ensure_table(&self.pool, &archive).await?;
+        }
+        let mut pruned = Pruned::default();
+        let mut tx = self.pool.begin().await.map_err(err)?;
+        if policy.archive {
+            let copy = format!(
+                "INSERT INTO {archive} ({COLUMNS}) SELECT {COLUMNS} FROM {table} WHERE {expired} ON CONFLICT DO NOTHING"
+            );
+            pruned.archived = sqlx::query(&copy).bind(cutoff).execute(&mut *tx).await.map_err(err)?.rows_affected();
+        }
+        pruned.deleted = sqlx::query(&format!("DELETE FROM {table} WHERE {expired}"))
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await
+            .map_err(err)?
+            .rows_affected();
+        tx.commit().await.map_err(err)?;
+        Ok(pruned)
+    }
+}
+
+pub async fn write_pg_stream(
+    sink: &PgSink,
+    source: &Path,
+    mut rx: Receiver<RadioRecord>,
+    stats: Arc<PipelineStats>,
+) -> Result<(), AppError> {
+    let _timer = stats.sink.timer();
+    let (pool, opts) = (&sink.pool, &sink.opts);
+    let source = source.display().to_string();
+    let mut batch: Vec<RadioRecord> = Vec::with_capacity(opts.batch);
+    let mut count: usize = 0;
+
+    while let Some(r) = stats.sink.recv(&mut rx).await {
+        stats.sink.inc_in();
+        batch.push(r);
+        // Flush on a full batch, or when the stream goes idle (follow mode).
+        if batch.len() >= opts.batch || (opts.flush_when_idle && rx.is_empty()) {
+            let n = batch.len();
+            flush_batch(pool, &opts.table, &source, &mut batch).await?;
+            count += n;
+            stats.sink.add_out(n as u64);
+        }
+    }
+    let n = batch.len();
+    flush_batch(pool, &opts.table, &source, &mut batch).await?;
+    count += n;
+    stats.sink.add_out(n as u64);
+
+    info!("Postgres upserted {} rows into {}", count, opts.table);
+    Ok(())
+}
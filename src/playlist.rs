@@ -0,0 +1,106 @@
+// src/playlist.rs
+//
+// `--playlist`: extended M3U (`.m3u8`) playlists of the calls' recordings,
+// in time order, next to the main output: one for the whole output, or one
+// per talkgroup (`<out stem>.tg<TG>.m3u8`). Calls without `audio_path` are
+// left out, so this goes with `--match-audio`.
+
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use log::{info, warn};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc::{Receiver, Sender};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaylistMode {
+    /// `<out stem>.m3u8`
+    Output,
+    /// `<out stem>.tg<TG>.m3u8`, calls without a TG in `<out stem>.tg-.m3u8`
+    PerTg,
+}
+
+impl PlaylistMode {
+    pub fn parse(s: &str) -> Result<Self, AppError> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "output" => Ok(PlaylistMode::Output),
+            "tg" => Ok(PlaylistMode::PerTg),
+            _ => Err(AppError::Parse(format!("unknown --playlist '{}' (use output, tg)", s))),
+        }
+    }
+}
+
+pub fn playlist_path(out_path: &Path, tg: Option<&str>) -> PathBuf {
+    match tg {
+        None => out_path.with_extension("m3u8"),
+        Some(tg) => out_path.with_extension(format!("tg{}.m3u8", tg)),
+    }
+}
+
+struct Entry {
+    datetime: chrono::DateTime<chrono::FixedOffset>,
+    line: String,
+}
+
+/// `#EXTINF` line plus the recording's absolute path.
+fn entry(rec: &RadioRecord, audio: &Path) -> Entry {
+    let tg = rec.slot1.tg.as_deref().or(rec.slot2.tg.as_deref()).unwrap_or("-");
+    let rid = rec.slot1.rid.as_deref().or(rec.slot2.rid.as_deref()).unwrap_or("-");
+    let title = format!(
+        "{} TG {} RID {} {}",
+        rec.datetime.format("%Y-%m-%d %H:%M:%S"),
+        tg,
+        rid,
+        rec.frequency.as_deref().unwrap_or("-")
+    );
+    let path = std::path::absolute(audio).unwrap_or_else(|_| audio.to_path_buf());
+    Entry { datetime: rec.datetime, line: format!("#EXTINF:{},{}\n{}\n", rec.duration, title, path.display()) }
+}
+
+/// Forward every record unchanged; write the playlists once the stream ends.
+pub async fn write_playlists_stream(
+    mut rx: Receiver<RadioRecord>,
+    tx: Sender<RadioRecord>,
+    out_path: PathBuf,
+    mode: PlaylistMode,
+) -> Result<Vec<PathBuf>, AppError> {
+    let mut lists: BTreeMap<Option<String>, Vec<Entry>> = BTreeMap::new();
+    // Calls sharing a recording (`--audio-dedup`) are listed once.
+    let mut listed: HashSet<(Option<String>, PathBuf)> = HashSet::new();
+    while let Some(rec) = rx.recv().await {
+        if let Some(audio) = &rec.audio_path {
+            let key = match mode {
+                PlaylistMode::Output => None,
+                PlaylistMode::PerTg => {
+                    Some(rec.slot1.tg.clone().or_else(|| rec.slot2.tg.clone()).unwrap_or_else(|| "-".into()))
+                }
+            };
+            if listed.insert((key.clone(), audio.clone())) {
+                lists.entry(key).or_default().push(entry(&rec, audio));
+            }
+        }
+        if tx.send(rec).await.is_err() {
+            warn!("playlist: downstream closed");
+            break;
+        }
+    }
+
+    let mut written = Vec::with_capacity(lists.len());
+    for (tg, mut entries) in lists {
+        entries.sort_by_key(|e| e.datetime);
+        let path = playlist_path(&out_path, tg.as_deref());
+        let file = tokio::fs::File::create(&path)
+            .await
+            .map_err(|e| AppError::IO(format!("open playlist '{}': {}", path.display(), e)))?;
+        let mut w = BufWriter::new(file);
+        w.write_all(b"#EXTM3U\n").await?;
+        for e in &entries {
+            w.write_all(e.line.as_bytes()).await?;
+        }
+        w.flush().await?;
+        info!("Playlist: {} calls -> {}", entries.len(), path.display());
+        written.push(path);
+    }
+    Ok(written)
+}
@@ -0,0 +1,493 @@
+// src/postprocess.rs
+//
+// `[[stt.postprocess]]`: an ordered chain of rewrites applied to every
+// transcript a transcriber returns, before the transcription stage attaches
+// it and the sinks see it (deferred transcription with `transcribe` goes
+// through the same chain).
+//
+// ```toml
+// [[stt.postprocess]]
+// kind = "regex"
+// pattern = "(?i)\\bten[- ]four\\b"
+// replace = "10-4"
+//
+// [[stt.postprocess]]
+// kind = "case"
+// mode = "sentence"         # lower, upper or sentence
+//
+// [[stt.postprocess]]
+// kind = "numbers"          # "five one two" -> "512", "twenty five" -> "25"
+//
+// [[stt.postprocess]]
+// kind = "callsigns"        # "kilo alpha one two" -> "KA12", "w1abc" -> "W1ABC"
+// ```
+//
+// Steps run in the order given, each on the previous one's output: the
+// regex above has to see "ten four" before `numbers` makes it "104", and
+// `case` comes before `callsigns` so it can't lower-case them again.
+//
+// `expand` annotates rather than rewrites, for readers who don't speak
+// radio: "10-50" becomes "10-50 [accident]", "ten four" "ten four
+// [acknowledged]" and "kilo alpha bravo" "kilo alpha bravo [KAB]". Its
+// dictionary is the bundled ten-codes (`assets/ten-codes.toml`), then a
+// `dictionary = "FILE"` of the same form (relative to the config file),
+// then inline `codes = { "Code 3" = "lights and siren" }`, each adding to
+// and replacing the ones before; `builtin = false` starts empty and
+// `phonetic = false` leaves spelled-out letters alone. It belongs ahead of
+// `numbers` and `callsigns`, which would change what it looks for.
+// `numbers` converts runs of number words; a lone "one" or "oh" is left
+// alone ("that one"), and consecutive numbers are written one after another
+// the way radio traffic reads them out ("one twenty five" -> "125").
+// `callsigns` joins runs of at least two NATO phonetic letters, with any
+// digits among them, and upper-cases amateur-style callsigns. Regex
+// replacements use the `regex` crate's syntax (`$1` for groups) and are
+// checked when the run starts.
+
+use crate::assets;
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use crate::transcriber::Transcriber;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+
+/// One `[[stt.postprocess]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum PostProcessConfig {
+    Case { mode: String },
+    Numbers,
+    Callsigns,
+    Regex { pattern: String, replace: String },
+    Expand {
+        #[serde(default)]
+        dictionary: Option<PathBuf>,
+        #[serde(default)]
+        codes: BTreeMap<String, String>,
+        #[serde(default = "yes")]
+        builtin: bool,
+        #[serde(default = "yes")]
+        phonetic: bool,
+    },
+}
+
+fn yes() -> bool {
+    true
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CaseMode {
+    Lower,
+    Upper,
+    Sentence,
+}
+
+#[derive(Debug)]
+enum Step {
+    Case(CaseMode),
+    Numbers,
+    Callsigns,
+    Regex(Regex, String),
+    Expand(Expansions),
+}
+
+/// The `expand` dictionary, compiled: one capture group per way of saying
+/// an entry, `meanings` in group order.
+#[derive(Debug)]
+struct Expansions {
+    codes: Option<Regex>,
+    meanings: Vec<String>,
+    phonetic: bool,
+}
+
+/// The compiled chain.
+#[derive(Debug)]
+pub struct PostProcessor {
+    steps: Vec<Step>,
+}
+
+static WORD: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[A-Za-z0-9]+").unwrap());
+static AMATEUR_CALLSIGN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(?i:[a-z]{1,2}[0-9][a-z]{1,4})\b").unwrap());
+
+impl PostProcessor {
+    pub fn new(config: &[PostProcessConfig]) -> Result<Self, AppError> {
+        let steps = config
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                Ok(match c {
+                    PostProcessConfig::Case { mode } => Step::Case(match mode.trim().to_ascii_lowercase().as_str() {
+                        "lower" => CaseMode::Lower,
+                        "upper" => CaseMode::Upper,
+                        "sentence" => CaseMode::Sentence,
+                        _ => {
+                            return Err(AppError::Parse(format!(
+                                "[[stt.postprocess]] #{}: invalid case mode '{}': expected lower, upper or sentence",
+                                i + 1,
+                                mode
+                            )));
+                        }
+                    }),
+                    PostProcessConfig::Numbers => Step::Numbers,
+                    PostProcessConfig::Callsigns => Step::Callsigns,
+                    PostProcessConfig::Regex { pattern, replace } => {
+                        let re = Regex::new(pattern).map_err(|e| {
+                            AppError::Parse(format!("[[stt.postprocess]] #{}: pattern '{}': {}", i + 1, pattern, e))
+                        })?;
+                        Step::Regex(re, replace.clone())
+                    }
+                    PostProcessConfig::Expand { dictionary, codes, builtin, phonetic } => {
+                        let at = |e: AppError| match e {
+                            AppError::IO(m) | AppError::Parse(m) | AppError::Other(m) => {
+                                AppError::Parse(format!("[[stt.postprocess]] #{}: {}", i + 1, m))
+                            }
+                        };
+                        Step::Expand(Expansions::new(dictionary.as_deref(), codes, *builtin, *phonetic).map_err(at)?)
+                    }
+                })
+            })
+            .collect::<Result<_, AppError>>()?;
+        Ok(Self { steps })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut out = text.to_string();
+        for step in &self.steps {
+            out = match step {
+                Step::Case(mode) => case(&out, *mode),
+                Step::Numbers => replace_runs(&out, |w| number_word(w).is_some(), numbers),
+                Step::Callsigns => {
+                    let joined = replace_runs(&out, |w| phonetic(w).is_some() || digit(w).is_some(), callsign);
+                    AMATEUR_CALLSIGN.replace_all(&joined, |c: &regex::Captures| c[0].to_uppercase()).into_owned()
+                }
+                Step::Regex(re, replace) => re.replace_all(&out, replace.as_str()).into_owned(),
+                Step::Expand(e) => e.apply(&out),
+            };
+        }
+        out
+    }
+}
+
+fn case(text: &str, mode: CaseMode) -> String {
+    match mode {
+        CaseMode::Lower => text.to_lowercase(),
+        CaseMode::Upper => text.to_uppercase(),
+        CaseMode::Sentence => {
+            let mut out = String::with_capacity(text.len());
+            let mut start = true;
+            for c in text.to_lowercase().chars() {
+                if start && c.is_alphabetic() {
+                    out.extend(c.to_uppercase());
+                    start = false;
+                } else {
+                    out.push(c);
+                }
+                if matches!(c, '.' | '!' | '?') {
+                    start = true;
+                }
+            }
+            out
+        }
+    }
+}
+
+/// Replace every maximal run of words matching `member`, separated only by
+/// spaces or hyphens, with what `convert` makes of it (`None` keeps it).
+fn replace_runs(text: &str, member: impl Fn(&str) -> bool, convert: impl Fn(&[&str]) -> Option<String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+    let mut run: Vec<regex::Match> = Vec::new();
+    let flush = |run: &mut Vec<regex::Match>, out: &mut String, copied: &mut usize| {
+        if let (Some(first), Some(last)) = (run.first(), run.last()) {
+            let words: Vec<&str> = run.iter().map(|m| m.as_str()).collect();
+            if let Some(s) = convert(&words) {
+                out.push_str(&text[*copied..first.start()]);
+                out.push_str(&s);
+                *copied = last.end();
+            }
+        }
+        run.clear();
+    };
+    for m in WORD.find_iter(text) {
+        let joined = run.last().is_some_and(|prev| text[prev.end()..m.start()].chars().all(|c| c == ' ' || c == '-'));
+        if !member(m.as_str()) || !joined {
+            flush(&mut run, &mut out, &mut copied);
+        }
+        if member(m.as_str()) {
+            run.push(m);
+        }
+    }
+    flush(&mut run, &mut out, &mut copied);
+    out.push_str(&text[copied..]);
+    out
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum NumberWord {
+    /// 0-9, and "oh" for zero.
+    Unit(u64),
+    /// 10-19.
+    Teen(u64),
+    /// 20, 30, ... 90.
+    Tens(u64),
+    Hundred,
+    Thousand,
+}
+
+fn number_word(w: &str) -> Option<NumberWord> {
+    use NumberWord::*;
+    Some(match w.to_ascii_lowercase().as_str() {
+        "zero" | "oh" => Unit(0),
+        "one" => Unit(1),
+        "two" => Unit(2),
+        "three" => Unit(3),
+        "four" => Unit(4),
+        "five" => Unit(5),
+        "six" => Unit(6),
+        "seven" => Unit(7),
+        "eight" => Unit(8),
+        "nine" | "niner" => Unit(9),
+        "ten" => Teen(10),
+        "eleven" => Teen(11),
+        "twelve" => Teen(12),
+        "thirteen" => Teen(13),
+        "fourteen" => Teen(14),
+        "fifteen" => Teen(15),
+        "sixteen" => Teen(16),
+        "seventeen" => Teen(17),
+        "eighteen" => Teen(18),
+        "nineteen" => Teen(19),
+        "twenty" => Tens(20),
+        "thirty" => Tens(30),
+        "forty" => Tens(40),
+        "fifty" => Tens(50),
+        "sixty" => Tens(60),
+        "seventy" => Tens(70),
+        "eighty" => Tens(80),
+        "ninety" => Tens(90),
+        "hundred" => Hundred,
+        "thousand" => Thousand,
+        _ => return None,
+    })
+}
+
+/// Below 100: a unit, a teen, or tens with an optional unit.
+fn small(words: &[NumberWord], i: &mut usize) -> Option<u64> {
+    match *words.get(*i)? {
+        NumberWord::Unit(v) | NumberWord::Teen(v) => {
+            *i += 1;
+            Some(v)
+        }
+        NumberWord::Tens(v) => {
+            *i += 1;
+            if let Some(NumberWord::Unit(u)) = words.get(*i).copied().filter(|w| *w != NumberWord::Unit(0)) {
+                *i += 1;
+                return Some(v + u);
+            }
+            Some(v)
+        }
+        _ => None,
+    }
+}
+
+/// Below 1000: `small`, optionally times a hundred plus another `small`.
+fn hundreds(words: &[NumberWord], i: &mut usize) -> Option<u64> {
+    let v = small(words, i)?;
+    if words.get(*i) != Some(&NumberWord::Hundred) {
+        return Some(v);
+    }
+    *i += 1;
+    Some(v * 100 + small(words, i).unwrap_or(0))
+}
+
+/// One number, up to the thousands.
+fn number(words: &[NumberWord], i: &mut usize) -> Option<u64> {
+    let v = hundreds(words, i)?;
+    if words.get(*i) != Some(&NumberWord::Thousand) {
+        return Some(v);
+    }
+    *i += 1;
+    Some(v * 1000 + hundreds(words, i).unwrap_or(0))
+}
+
+fn numbers(run: &[&str]) -> Option<String> {
+    if let [w] = run
+        && matches!(w.to_ascii_lowercase().as_str(), "one" | "oh")
+    {
+        return None;
+    }
+    let words: Vec<NumberWord> = run.iter().map(|w| number_word(w)).collect::<Option<_>>()?;
+    let mut i = 0;
+    let mut out = String::new();
+    while i < words.len() {
+        out.push_str(&number(&words, &mut i)?.to_string());
+    }
+    Some(out)
+}
+
+fn phonetic(w: &str) -> Option<char> {
+    const ALPHABET: [&str; 26] = [
+        "alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel", "india", "juliet", "kilo", "lima",
+        "mike", "november", "oscar", "papa", "quebec", "romeo", "sierra", "tango", "uniform", "victor", "whiskey",
+        "xray", "yankee", "zulu",
+    ];
+    let w = w.to_ascii_lowercase();
+    let w = match w.as_str() {
+        "alfa" => "alpha",
+        "juliett" => "juliet",
+        "whisky" => "whiskey",
+        other => other,
+    };
+    ALPHABET.iter().position(|a| *a == w).map(|i| (b'A' + i as u8) as char)
+}
+
+/// A digit word or the digits themselves.
+fn digit(w: &str) -> Option<String> {
+    if w.chars().all(|c| c.is_ascii_digit()) {
+        return Some(w.to_string());
+    }
+    match number_word(w)? {
+        NumberWord::Unit(v) => Some(v.to_string()),
+        _ => None,
+    }
+}
+
+fn callsign(run: &[&str]) -> Option<String> {
+    if run.iter().filter(|w| phonetic(w).is_some()).count() < 2 {
+        return None;
+    }
+    // Digits ahead of the first letter stay apart ("unit 5 alpha bravo").
+    let first = run.iter().position(|w| phonetic(w).is_some())?;
+    let mut out = run[..first].join(" ");
+    if !out.is_empty() {
+        out.push(' ');
+    }
+    for w in &run[first..] {
+        match phonetic(w) {
+            Some(c) => out.push(c),
+            None => out.push_str(&digit(w)?),
+        }
+    }
+    Some(out)
+}
+
+fn parse_dictionary(text: &str, what: &str) -> Result<BTreeMap<String, String>, AppError> {
+    toml::from_str(text).map_err(|e| AppError::Parse(format!("dictionary {}: {}", what, e)))
+}
+
+/// Below 100 in words, as a pattern: "twenty[- ]three".
+fn spoken(n: u64) -> Option<String> {
+    const UNITS: [&str; 10] = ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
+    const TEENS: [&str; 10] =
+        ["ten", "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen"];
+    const TENS: [&str; 8] = ["twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+    Some(match n {
+        0..=9 => UNITS[n as usize].to_string(),
+        10..=19 => TEENS[n as usize - 10].to_string(),
+        20..=99 if n.is_multiple_of(10) => TENS[n as usize / 10 - 2].to_string(),
+        20..=99 => format!("{}[- ]{}", TENS[n as usize / 10 - 2], UNITS[n as usize % 10]),
+        _ => return None,
+    })
+}
+
+/// Ways `key` may appear: as written, with a space or a dash between its
+/// parts, and for a ten-code also "ten-50" and "ten fifty".
+fn key_patterns(key: &str) -> Vec<String> {
+    let Some(n) = key.strip_prefix("10-").and_then(|n| n.parse::<u64>().ok()) else {
+        let parts: Vec<String> = key.split([' ', '-']).filter(|p| !p.is_empty()).map(regex::escape).collect();
+        return vec![parts.join("[- ]")];
+    };
+    let mut patterns = vec![format!("(?:10|ten)[- ]{}", n)];
+    patterns.extend(spoken(n).map(|words| format!("ten[- ]{}", words)));
+    patterns
+}
+
+impl Expansions {
+    fn new(dictionary: Option<&Path>, codes: &BTreeMap<String, String>, builtin: bool, phonetic: bool) -> Result<Self, AppError> {
+        let mut all = BTreeMap::new();
+        if builtin {
+            let text = assets::text("ten-codes.toml").ok_or_else(|| AppError::Other("ten-codes.toml is not bundled".into()))?;
+            all.extend(parse_dictionary(text, "(built-in)")?);
+        }
+        if let Some(path) = dictionary {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| AppError::IO(format!("dictionary {}: {}", path.display(), e)))?;
+            all.extend(parse_dictionary(&text, &path.display().to_string())?);
+        }
+        all.extend(codes.clone());
+        let mut alternatives: Vec<(String, &String)> = all
+            .iter()
+            .filter(|(k, m)| !k.trim().is_empty() && !m.is_empty())
+            .flat_map(|(k, m)| key_patterns(k).into_iter().map(move |p| (p, m)))
+            .collect();
+        // Longest first, so "10-20" can't be taken for "10-2".
+        alternatives.sort_by_key(|(p, _)| std::cmp::Reverse(p.len()));
+        let codes = if alternatives.is_empty() {
+            None
+        } else {
+            let groups: Vec<String> = alternatives.iter().map(|(p, _)| format!("({})", p)).collect();
+            let re = Regex::new(&format!(r"(?i)\b(?:{})\b", groups.join("|")))
+                .map_err(|e| AppError::Parse(format!("dictionary: {}", e)))?;
+            Some(re)
+        };
+        Ok(Self { codes, meanings: alternatives.into_iter().map(|(_, m)| m.clone()).collect(), phonetic })
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len() + 32);
+        let mut copied = 0;
+        if let Some(re) = &self.codes {
+            for caps in re.captures_iter(text) {
+                let whole = caps.get(0).unwrap();
+                out.push_str(&text[copied..whole.end()]);
+                copied = whole.end();
+                // Already annotated (a transcript run through twice).
+                if text[copied..].starts_with(" [") {
+                    continue;
+                }
+                if let Some(g) = (1..caps.len()).find(|g| caps.get(*g).is_some()) {
+                    out.push_str(&format!(" [{}]", self.meanings[g - 1]));
+                }
+            }
+        }
+        out.push_str(&text[copied..]);
+        if !self.phonetic {
+            return out;
+        }
+        let spelled = |run: &[&str]| {
+            let first = run.iter().position(|w| phonetic(w).is_some())?;
+            let letters = callsign(&run[first..])?;
+            Some(format!("{} [{}]", run.join(" "), letters))
+        };
+        replace_runs(&out, |w| phonetic(w).is_some() || digit(w).is_some(), spelled)
+    }
+}
+
+/// A transcriber whose transcripts go through a [`PostProcessor`].
+pub struct PostProcessed<T: ?Sized> {
+    inner: Arc<T>,
+    post: PostProcessor,
+}
+
+impl<T: Transcriber + ?Sized> PostProcessed<T> {
+    pub fn new(inner: Arc<T>, post: PostProcessor) -> Self {
+        Self { inner, post }
+    }
+}
+
+impl<T: Transcriber + ?Sized> Transcriber for PostProcessed<T> {
+    fn transcribe(&self, rec: &RadioRecord, record_dir: &Path) -> Result<Option<String>, Option<AppError>> {
+        Ok(self.inner.transcribe(rec, record_dir)?.map(|text| self.post.apply(&text)))
+    }
+
+    fn health(&self) -> Result<(), AppError> {
+        self.inner.health()
+    }
+}
@@ -0,0 +1,178 @@
+// src/probe.rs
+//
+// `callscribe probe --record-dir <DIR> --input <FILE>`: before a long run,
+// estimate how many calls will find a transcript or recording. The input is
+// parsed and run-length compressed as in a normal run, and each call is
+// looked up in the Record directory the way the transcribers and
+// `--match-audio` do. The report has one line per day: its calls, whether
+// the day's folder exists and how many files it holds, and the share of
+// calls with a transcript and with audio; then examples of calls without
+// one, with the file name prefix they were looked for under.
+
+use crate::errors::AppError;
+use crate::freq::FreqTolerance;
+use crate::input::{self, ParseOptions};
+use crate::model::RadioRecord;
+use crate::rle_filter::{self, RleOptions};
+use crate::stats::PipelineStats;
+use crate::transcriber::RecordFileIndex;
+use crate::winpath;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+pub struct ProbeOptions {
+    pub transcript_extensions: Vec<String>,
+    pub audio_extensions: Vec<String>,
+    pub freq_tolerance: FreqTolerance,
+    /// `--audio-window`, as for `--match-audio`.
+    pub audio_window: u32,
+    /// Unmatched calls to list.
+    pub examples: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProbeSummary {
+    pub calls: usize,
+    pub transcripts: usize,
+    pub audio: usize,
+}
+
+#[derive(Default)]
+struct Day {
+    calls: usize,
+    transcripts: usize,
+    audio: usize,
+    /// Files in the day's folder; `None` when it doesn't exist.
+    files: Option<usize>,
+    folder: String,
+}
+
+fn share(n: usize, of: usize) -> String {
+    if of == 0 {
+        return "-".into();
+    }
+    format!("{}/{} ({:.0}%)", n, of, n as f64 * 100.0 / of as f64)
+}
+
+/// Files directly in `dir`, or `None` if it is not a directory.
+fn count_files(dir: &Path) -> Option<usize> {
+    let entries = std::fs::read_dir(winpath::extended(dir)).ok()?;
+    Some(entries.flatten().filter(|e| e.file_type().is_ok_and(|t| t.is_file())).count())
+}
+
+/// The `HHMMSS_FREQ` a Record file for `rec` starts with.
+fn name_prefix(rec: &RadioRecord) -> String {
+    format!("{}_{}", rec.datetime.format("%H%M%S"), rec.frequency.as_deref().unwrap_or("?"))
+}
+
+fn call(rec: &RadioRecord) -> String {
+    let tg = rec.slot1.tg.as_deref().or(rec.target_rid.as_deref()).unwrap_or("-");
+    format!(
+        "{}  {:>11}  TG {:<6} RID {:<8}",
+        rec.datetime.format("%Y-%m-%d %H:%M:%S"),
+        rec.frequency.as_deref().unwrap_or("-"),
+        tg,
+        rec.slot1.rid.as_deref().unwrap_or("-")
+    )
+}
+
+pub async fn probe(
+    path: &Path,
+    opts: ParseOptions,
+    record_dir: &Path,
+    probe: &ProbeOptions,
+    out: &mut impl Write,
+) -> Result<ProbeSummary, AppError> {
+    let io = |e: std::io::Error| AppError::IO(format!("write: {}", e));
+    let transcripts = RecordFileIndex::new(&probe.transcript_extensions).with_freq_tolerance(probe.freq_tolerance);
+    let audio = RecordFileIndex::new(&probe.audio_extensions)
+        .with_freq_tolerance(probe.freq_tolerance)
+        .with_time_window(probe.audio_window);
+
+    let (tx_parse, rx_parse) = mpsc::channel::<RadioRecord>(1024);
+    let (tx_rle, mut rx) = mpsc::channel::<RadioRecord>(1024);
+    let owned = path.to_path_buf();
+    let producer = tokio::spawn(async move { input::stream_input(&owned, opts, tx_parse).await });
+    let rle = RleOptions { freq_tolerance: probe.freq_tolerance, ..Default::default() };
+    tokio::spawn(rle_filter::rle_compress_stream(rx_parse, tx_rle, rle, Arc::new(PipelineStats::default()), None));
+
+    let mut days: BTreeMap<String, Day> = BTreeMap::new();
+    let mut unmatched: Vec<(String, String)> = Vec::new();
+    let mut total = ProbeSummary::default();
+    while let Some(rec) = rx.recv().await {
+        let day = days.entry(rec.datetime.format("%Y-%m-%d").to_string()).or_insert_with(|| {
+            let dir = transcripts.day_dir(&rec, record_dir);
+            Day {
+                files: dir.as_deref().and_then(count_files),
+                folder: dir.map_or_else(String::new, |d| winpath::simplified(&d).display().to_string()),
+                ..Default::default()
+            }
+        });
+        let t = transcripts.find(&rec, record_dir)?.is_some();
+        let a = rec.audio_path.is_some() || audio.find(&rec, record_dir)?.is_some();
+        day.calls += 1;
+        day.transcripts += t as usize;
+        day.audio += a as usize;
+        total.calls += 1;
+        total.transcripts += t as usize;
+        total.audio += a as usize;
+        if !t && !a && unmatched.len() < probe.examples {
+            let why = match day.files {
+                None => "no day folder".to_string(),
+                Some(_) => format!("no {}_* file", name_prefix(&rec)),
+            };
+            unmatched.push((call(&rec), why));
+        }
+    }
+    producer.await.map_err(|e| AppError::Other(format!("probe: parser: {}", e)))??;
+
+    writeln!(
+        out,
+        "{} against {} ({:?} layout)\n",
+        winpath::simplified(path).display(),
+        winpath::simplified(record_dir).display(),
+        transcripts.layout(record_dir)
+    )
+    .map_err(io)?;
+    writeln!(out, "{:<10}  {:>6}  {:>6}  {:<16}  {:<16}  folder", "day", "calls", "files", "transcripts", "audio").map_err(io)?;
+    for (name, d) in &days {
+        let (files, folder) = match d.files {
+            Some(n) => (n.to_string(), d.folder.clone()),
+            None => ("-".to_string(), format!("{} (missing)", d.folder)),
+        };
+        writeln!(
+            out,
+            "{:<10}  {:>6}  {:>6}  {:<16}  {:<16}  {}",
+            name,
+            d.calls,
+            files,
+            share(d.transcripts, d.calls),
+            share(d.audio, d.calls),
+            folder
+        )
+        .map_err(io)?;
+    }
+    let with_folder = days.values().filter(|d| d.files.is_some()).count();
+    writeln!(
+        out,
+        "{:<10}  {:>6}  {:>6}  {:<16}  {:<16}  {} of {} days",
+        "all",
+        total.calls,
+        "",
+        share(total.transcripts, total.calls),
+        share(total.audio, total.calls),
+        with_folder,
+        days.len()
+    )
+    .map_err(io)?;
+    if !unmatched.is_empty() {
+        writeln!(out, "\nCalls with neither a transcript nor audio, e.g.:").map_err(io)?;
+        for (c, why) in &unmatched {
+            writeln!(out, "  {}  {}", c, why).map_err(io)?;
+        }
+    }
+    Ok(total)
+}
@@ -0,0 +1,146 @@
+// src/processed.rs
+//
+// `--skip-processed`: a small JSON state file listing the input files that
+// were already converted completely, so a cron job pointed at a log
+// directory only converts files that are new or changed since the last run.
+//
+// A file counts as processed when its path, size and modification time
+// match an entry. When only the mtime differs (a copy, a `touch`), the
+// SHA-256 of the contents decides, and the entry is brought up to date.
+// Entries are written only for inputs whose pipeline finished without error.
+
+use crate::errors::AppError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// State file name when `--processed-state` isn't given, in the directory
+/// of the first input.
+pub const DEFAULT_STATE_FILE: &str = ".callscribe-processed.json";
+
+/// What an input looked like when it was read.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    pub size: u64,
+    /// Modification time, nanoseconds since the Unix epoch.
+    pub mtime_ns: u64,
+    pub sha256: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Entry {
+    #[serde(flatten)]
+    fingerprint: Fingerprint,
+    processed_at: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    files: BTreeMap<PathBuf, Entry>,
+}
+
+/// Whether an input needs converting, with the fingerprint to record.
+pub enum Check {
+    Processed,
+    Pending(Fingerprint),
+}
+
+pub struct Registry {
+    path: PathBuf,
+    state: State,
+    dirty: bool,
+}
+
+fn io_err(path: &Path) -> impl Fn(std::io::Error) -> AppError + '_ {
+    move |e| AppError::IO(format!("processed state {}: {}", path.display(), e))
+}
+
+/// Entries are keyed by absolute path, so runs from another directory agree.
+fn key(input: &Path) -> PathBuf {
+    std::path::absolute(input).unwrap_or_else(|_| input.to_path_buf())
+}
+
+fn sha256(path: &Path) -> Result<String, AppError> {
+    let mut file = std::fs::File::open(path).map_err(io_err(path))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(io_err(path))?;
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+impl Registry {
+    /// The default state file for these inputs.
+    pub fn default_path(first_input: Option<&Path>) -> PathBuf {
+        first_input
+            .and_then(Path::parent)
+            .map(|dir| dir.join(DEFAULT_STATE_FILE))
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_STATE_FILE))
+    }
+
+    /// Load `path`; a missing file is an empty registry.
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let state = match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| AppError::Parse(format!("processed state {}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => State::default(),
+            Err(e) => return Err(io_err(path)(e)),
+        };
+        Ok(Self { path: path.to_path_buf(), state, dirty: false })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Compare `input` with its entry. Hashes the file unless size and mtime
+    /// already match.
+    pub fn check(&mut self, input: &Path) -> Result<Check, AppError> {
+        let meta = std::fs::metadata(input).map_err(io_err(input))?;
+        let mtime_ns = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        let key = key(input);
+        if let Some(e) = self.state.files.get(&key)
+            && e.fingerprint.size == meta.len()
+            && e.fingerprint.mtime_ns == mtime_ns
+        {
+            return Ok(Check::Processed);
+        }
+        let fingerprint = Fingerprint { size: meta.len(), mtime_ns, sha256: sha256(input)? };
+        if let Some(e) = self.state.files.get_mut(&key)
+            && e.fingerprint.size == fingerprint.size
+            && e.fingerprint.sha256 == fingerprint.sha256
+        {
+            e.fingerprint.mtime_ns = mtime_ns;
+            self.dirty = true;
+            return Ok(Check::Processed);
+        }
+        Ok(Check::Pending(fingerprint))
+    }
+
+    /// Record a completely converted input with the fingerprint taken before it was read.
+    pub fn mark(&mut self, input: &Path, fingerprint: Fingerprint) {
+        let entry = Entry { fingerprint, processed_at: chrono::Local::now().to_rfc3339() };
+        self.state.files.insert(key(input), entry);
+        self.dirty = true;
+    }
+
+    /// Write the state file if anything changed (via a temp file and rename,
+    /// so an interrupted write leaves the old state).
+    pub fn save(&mut self) -> Result<(), AppError> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let json = serde_json::to_vec_pretty(&self.state)
+            .map_err(|e| AppError::Other(format!("processed state {}: {}", self.path.display(), e)))?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, json).map_err(io_err(&tmp))?;
+        std::fs::rename(&tmp, &self.path).map_err(io_err(&self.path))?;
+        self.dirty = false;
+        Ok(())
+    }
+}
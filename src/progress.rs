@@ -0,0 +1,117 @@
+// src/progress.rs
+//
+// `--progress`: one row per pipeline (input, stage, records read, rows
+// written, ETA), redrawn in place on the terminal while many inputs are
+// processed at once, instead of their interleaved log lines.
+
+use crate::stats::PipelineStats;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+const REFRESH: Duration = Duration::from_millis(500);
+const NAME_WIDTH: usize = 32;
+
+pub struct ProgressTable {
+    rows: Vec<(String, Arc<PipelineStats>)>,
+    started: Instant,
+    /// Lines drawn last time, to move the cursor back over.
+    drawn: usize,
+}
+
+/// Where a pipeline is: the earliest stage still working.
+fn stage(s: &PipelineStats) -> String {
+    match s.finished.get() {
+        Some(true) => return "done".into(),
+        Some(false) => return "failed".into(),
+        None => {}
+    }
+    let (read, size) = (s.input_read.load(Ordering::Relaxed), s.input_size.load(Ordering::Relaxed));
+    if read < size {
+        format!("parsing {:>3}%", read * 100 / size)
+    } else if s.transcribe.records_out() < s.rle.records_out() {
+        "transcribing".into()
+    } else {
+        "writing".into()
+    }
+}
+
+/// Remaining parse time at the rate so far; later stages aren't estimated.
+fn eta(s: &PipelineStats, elapsed: Duration) -> String {
+    if s.finished.get().is_some() {
+        return "-".into();
+    }
+    let (read, size) = (s.input_read.load(Ordering::Relaxed), s.input_size.load(Ordering::Relaxed));
+    if read == 0 || read >= size {
+        return "?".into();
+    }
+    let secs = (elapsed.as_secs_f64() * (size - read) as f64 / read as f64).round() as u64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// `name` cut to `NAME_WIDTH` characters, keeping its end.
+fn fit(name: &str) -> String {
+    let n = name.chars().count();
+    if n <= NAME_WIDTH {
+        name.to_string()
+    } else {
+        let tail: String = name.chars().skip(n - (NAME_WIDTH - 1)).collect();
+        format!("…{}", tail)
+    }
+}
+
+impl ProgressTable {
+    pub fn new(rows: Vec<(String, Arc<PipelineStats>)>) -> Self {
+        Self { rows, started: Instant::now(), drawn: 0 }
+    }
+
+    pub fn render(&self) -> Vec<String> {
+        let elapsed = self.started.elapsed();
+        let mut lines = vec![format!(
+            "{:<w$} {:<12} {:>10} {:>10} {:>7}",
+            "file", "stage", "records", "rows", "eta",
+            w = NAME_WIDTH
+        )];
+        for (name, s) in &self.rows {
+            lines.push(format!(
+                "{:<w$} {:<12} {:>10} {:>10} {:>7}",
+                fit(name),
+                stage(s),
+                s.filter.records_in(),
+                s.sink.records_out(),
+                eta(s, elapsed),
+                w = NAME_WIDTH
+            ));
+        }
+        lines
+    }
+
+    /// Draw over the previous table on stderr.
+    pub fn draw(&mut self) {
+        let lines = self.render();
+        let mut err = std::io::stderr().lock();
+        if self.drawn > 0 {
+            let _ = write!(err, "\x1b[{}A", self.drawn);
+        }
+        for l in &lines {
+            let _ = writeln!(err, "\x1b[2K{}", l);
+        }
+        let _ = err.flush();
+        self.drawn = lines.len();
+    }
+}
+
+/// Redraw `table` until `stop` turns true, then draw it a last time.
+pub async fn run(mut table: ProgressTable, mut stop: watch::Receiver<bool>) {
+    let mut interval = tokio::time::interval(REFRESH);
+    loop {
+        table.draw();
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = stop.wait_for(|s| *s) => break,
+        }
+    }
+    table.draw();
+}
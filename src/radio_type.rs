@@ -0,0 +1,90 @@
+// src/radio_type.rs
+//
+// One name per radio type. DSDPlus spells the same mode several ways
+// depending on version and log (`+P25p1`, `P25 Phase 1`, `+IDAS VOICE`), so
+// the parsers pass every type through this table before a record leaves
+// them, and `--rtype` values go through it too. Lookups ignore case, a
+// leading `+`, spaces, dashes and underscores; types not in the table keep
+// their spelling (minus the `+`).
+//
+// The config file's `[radio_type]` table adds entries or replaces built-in
+// ones, keyed the same way:
+//
+// ```toml
+// [radio_type]
+// "NXDN48" = "NXDN"
+// "P25 Phase 2" = "P25"
+// ```
+
+use crate::model::RadioRecord;
+use std::collections::{BTreeMap, HashMap};
+
+/// Built-in spellings and the name they are reported as.
+const BUILT_IN: &[(&str, &str)] = &[
+    ("P25p1", "P25p1"),
+    ("P25 Phase 1", "P25p1"),
+    ("P25 Phase I", "P25p1"),
+    ("P25ph1", "P25p1"),
+    ("P25p2", "P25p2"),
+    ("P25 Phase 2", "P25p2"),
+    ("P25 Phase II", "P25p2"),
+    ("P25ph2", "P25p2"),
+    ("DMR", "DMR"),
+    ("DMR Voice", "DMR"),
+    ("NXDN", "NXDN"),
+    ("NXDN Voice", "NXDN"),
+    ("IDAS", "NXDN"),
+    ("IDAS Voice", "NXDN"),
+    ("dPMR", "dPMR"),
+    ("dPMR Voice", "dPMR"),
+    ("ProVoice", "ProVoice"),
+    ("D-STAR", "D-STAR"),
+    ("YSF", "YSF"),
+];
+
+/// The lookup key for a spelling.
+fn key(s: &str) -> String {
+    s.trim()
+        .trim_start_matches('+')
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct RadioTypes {
+    names: HashMap<String, String>,
+}
+
+impl Default for RadioTypes {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new())
+    }
+}
+
+impl RadioTypes {
+    /// The built-in table with `overrides` (spelling -> name) on top.
+    pub fn new(overrides: &BTreeMap<String, String>) -> Self {
+        let names = BUILT_IN
+            .iter()
+            .map(|&(from, to)| (key(from), to.to_string()))
+            .chain(overrides.iter().map(|(from, to)| (key(from), to.trim().to_string())))
+            .collect();
+        Self { names }
+    }
+
+    pub fn normalize(&self, s: &str) -> String {
+        self.names
+            .get(&key(s))
+            .cloned()
+            .unwrap_or_else(|| s.trim().trim_start_matches('+').to_string())
+    }
+
+    /// Normalize the record's type in place.
+    pub fn apply(&self, rec: &mut RadioRecord) {
+        if let Some(t) = rec.radio_type.as_mut() {
+            *t = self.normalize(t);
+        }
+    }
+}
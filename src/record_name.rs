@@ -0,0 +1,191 @@
+// src/record_name.rs
+//
+// Grammar for DSDPlus record file names (transcripts and audio share them):
+//
+//   [YYYYMMDD_]HHMMSS_FREQ[_CHAN]_PROTO[_]_CALL_TG_RID
+//
+//   064356_153.450000_004_P25__GC_2_4506     P25 group call, channel 004
+//   064410_154.100000_DMR_GC_10_100          DMR group call, TG 10, RID 100
+//   064500_154.100000_DMR_PC_100_200         DMR private call 200 -> 100
+//   070102_452.950000_NXDN_GC_1_32           NXDN group call
+//
+// Numbers before the protocol token (channel/slot/sequence counters) are
+// never taken as TG or RID. Names without a recognised protocol fall back to
+// "first two numbers after the frequency are TG and RID".
+
+pub use crate::model::CallType;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordName {
+    /// YYYYMMDD, when the name starts with it (flat Record folders).
+    pub day: Option<u32>,
+    /// HHMMSS
+    pub time: u32,
+    /// As written; callers normalise.
+    pub freq: String,
+    /// Canonical protocol name, `None` when not recognised.
+    pub protocol: Option<&'static str>,
+    /// `Group` or `Private`; for private calls `tg` holds the called unit.
+    pub call_type: Option<CallType>,
+    pub tg: Option<u32>,
+    pub rid: Option<u32>,
+}
+
+/// Protocol tokens DSDPlus writes, and the name they are reported as.
+const PROTOCOLS: &[(&str, &str)] = &[
+    ("P25", "P25"),
+    ("P25p1", "P25"),
+    ("P25p2", "P25p2"),
+    ("DMR", "DMR"),
+    ("NXDN", "NXDN"),
+    ("NXDN48", "NXDN"),
+    ("NXDN96", "NXDN"),
+    ("dPMR", "dPMR"),
+    ("ProVoice", "ProVoice"),
+    ("YSF", "YSF"),
+    ("DSTAR", "D-STAR"),
+];
+
+fn protocol(tok: &str) -> Option<&'static str> {
+    PROTOCOLS.iter().find(|(t, _)| t.eq_ignore_ascii_case(tok)).map(|(_, name)| *name)
+}
+
+fn call_type(tok: &str) -> Option<CallType> {
+    match tok.to_ascii_uppercase().as_str() {
+        "GC" | "GROUP" => Some(CallType::Group),
+        "PC" | "PRIVATE" | "UU" => Some(CallType::Private),
+        _ => None,
+    }
+}
+
+/// `TG1234` / `RID5678` style tokens.
+fn prefixed(tok: &str, prefix: &str) -> Option<u32> {
+    match tok.get(..prefix.len()) {
+        Some(p) if p.eq_ignore_ascii_case(prefix) => tok[prefix.len()..].parse().ok(),
+        _ => None,
+    }
+}
+
+/// Parse a file stem (no extension).
+pub fn parse(stem: &str) -> Option<RecordName> {
+    let mut parts: Vec<&str> = stem.split('_').collect();
+    let day = match parts.first() {
+        Some(p) if p.len() == 8 && parts.len() > 2 => {
+            let d = p.parse::<u32>().ok()?;
+            parts.remove(0);
+            Some(d)
+        }
+        _ => None,
+    };
+    if parts.len() < 2 {
+        return None;
+    }
+    let time = parts[0].parse::<u32>().ok()?;
+    let freq = parts[1].to_string();
+    // Empty tokens come from "__" separators.
+    let rest: Vec<&str> = parts[2..].iter().copied().filter(|t| !t.is_empty()).collect();
+
+    let mut name = RecordName { day, time, freq, protocol: None, call_type: None, tg: None, rid: None };
+    match rest.iter().position(|t| protocol(t).is_some()) {
+        Some(p) => {
+            name.protocol = protocol(rest[p]);
+            let mut tail = rest[p + 1..].iter().copied().peekable();
+            if let Some(ct) = tail.peek().and_then(|t| call_type(t)) {
+                name.call_type = Some(ct);
+                tail.next();
+            }
+            // Positional TG then RID; `TG…` / `RID…` tokens say which they are.
+            for tok in tail {
+                if let Some(n) = prefixed(tok, "RID") {
+                    name.rid = Some(n);
+                } else if let Some(n) = prefixed(tok, "TG") {
+                    name.tg = Some(n);
+                } else if let Ok(n) = tok.parse::<u32>() {
+                    if name.tg.is_none() {
+                        name.tg = Some(n);
+                    } else if name.rid.is_none() {
+                        name.rid = Some(n);
+                    }
+                }
+            }
+        }
+        None => {
+            let mut nums = rest.iter().filter_map(|t| t.parse::<u32>().ok());
+            name.tg = nums.next();
+            name.rid = nums.next();
+        }
+    }
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn p25_group_call_with_channel() {
+        let n = parse("064356_153.450000_004_P25__GC_2_4506").unwrap();
+        assert_eq!(
+            n,
+            RecordName {
+                day: None,
+                time: 64356,
+                freq: "153.450000".into(),
+                protocol: Some("P25"),
+                call_type: Some(CallType::Group),
+                tg: Some(2),
+                rid: Some(4506),
+            }
+        );
+    }
+
+    #[test]
+    fn dmr_group_call() {
+        let n = parse("064410_154.100000_DMR_GC_10_100").unwrap();
+        assert_eq!((n.time, n.freq.as_str()), (64410, "154.100000"));
+        assert_eq!((n.protocol, n.call_type), (Some("DMR"), Some(CallType::Group)));
+        assert_eq!((n.tg, n.rid), (Some(10), Some(100)));
+    }
+
+    #[test]
+    fn dmr_private_call() {
+        let n = parse("064500_154.100000_DMR_PC_100_200").unwrap();
+        assert_eq!((n.protocol, n.call_type), (Some("DMR"), Some(CallType::Private)));
+        assert_eq!((n.tg, n.rid), (Some(100), Some(200)));
+    }
+
+    #[test]
+    fn nxdn_group_call() {
+        let n = parse("070102_452.950000_NXDN_GC_1_32").unwrap();
+        assert_eq!((n.protocol, n.call_type), (Some("NXDN"), Some(CallType::Group)));
+        assert_eq!((n.tg, n.rid), (Some(1), Some(32)));
+        assert_eq!(parse("070102_452.950000_NXDN48_GC_1_32").unwrap().protocol, Some("NXDN"));
+    }
+
+    #[test]
+    fn flat_folder_day_prefix() {
+        let n = parse("20250910_064410_154.100000_DMR_GC_10_100").unwrap();
+        assert_eq!((n.day, n.time), (Some(20250910), 64410));
+        assert_eq!((n.tg, n.rid), (Some(10), Some(100)));
+    }
+
+    #[test]
+    fn prefixed_ids() {
+        let n = parse("064410_154.100000_DMR_GC_RID100_TG10").unwrap();
+        assert_eq!((n.tg, n.rid), (Some(10), Some(100)));
+    }
+
+    #[test]
+    fn unknown_protocol_falls_back_to_numbers() {
+        let n = parse("064410_154.100000_TETRA_10_100").unwrap();
+        assert_eq!((n.protocol, n.call_type), (None, None));
+        assert_eq!((n.tg, n.rid), (Some(10), Some(100)));
+    }
+
+    #[test]
+    fn non_matching_names() {
+        assert_eq!(parse("notes"), None);
+        assert_eq!(parse("readme_first"), None);
+        assert_eq!(parse(""), None);
+    }
+}
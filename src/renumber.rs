@@ -0,0 +1,63 @@
+// src/renumber.rs
+//
+// `--renumber`: what `record_number` means in the output. Parsers number
+// records their own way (an SRT block's index, an event log's call count,
+// a recording's position in its folder), and every file of a merge starts
+// over, so the numbers can repeat within one output. This pass-through
+// stage, ahead of the other optional stages, replaces them:
+//
+//   source      the parser's numbers, unchanged (default)
+//   sequential  1, 2, 3, ... in each output
+//   global      1, 2, 3, ... across all outputs of the run
+//
+// The raw log position stays in the `first_record` / `last_record` columns
+// of `--run-details`.
+
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use log::warn;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::mpsc::{Receiver, Sender};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Renumber {
+    #[default]
+    Source,
+    Sequential,
+    Global,
+}
+
+impl Renumber {
+    pub fn parse(s: &str) -> Result<Self, AppError> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "source" => Ok(Renumber::Source),
+            "sequential" => Ok(Renumber::Sequential),
+            "global" => Ok(Renumber::Global),
+            _ => Err(AppError::Parse(format!("invalid --renumber '{}': expected source, sequential or global", s))),
+        }
+    }
+}
+
+/// Number hand-out shared by the pipelines of a `global` run, or owned by
+/// one pipeline for `sequential`.
+pub fn counter() -> Arc<AtomicUsize> {
+    Arc::new(AtomicUsize::new(1))
+}
+
+/// Give every record the next number from `next`.
+pub async fn renumber_stream(
+    mut rx: Receiver<RadioRecord>,
+    tx: Sender<RadioRecord>,
+    next: Arc<AtomicUsize>,
+) -> Result<Vec<PathBuf>, AppError> {
+    while let Some(mut rec) = rx.recv().await {
+        rec.record_number = next.fetch_add(1, Ordering::Relaxed);
+        if tx.send(rec).await.is_err() {
+            warn!("renumber: downstream closed");
+            break;
+        }
+    }
+    Ok(vec![])
+}
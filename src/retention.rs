@@ -0,0 +1,98 @@
+// src/retention.rs
+//
+// Record-level retention for the database sinks. Rows of calls older than
+// `--retain-days` are deleted from the SQLite / PostgreSQL table at the end
+// of a run, or by `callscribe maintain`; with `--retain-archive` they are
+// first copied into `<table>_archive` (same columns, in the same database),
+// and with `--retain-alerts` rows with an alert stay in the table for good.
+// File sinks keep their own history (`--rotate-keep`) and are left alone.
+
+use crate::config::RetentionConfig;
+use crate::errors::AppError;
+use crate::sink::SinkKind;
+use chrono::{DateTime, Utc};
+use log::info;
+
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    /// Rows of calls started more than this many days ago go.
+    pub days: u32,
+    /// Keep rows with an alert, however old.
+    pub keep_alerts: bool,
+    /// Move rows into `<table>_archive` instead of dropping them.
+    pub archive: bool,
+}
+
+/// Rows moved by one pass.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pruned {
+    pub archived: u64,
+    pub deleted: u64,
+}
+
+impl RetentionPolicy {
+    /// The policy of the `--retain-*` options over `[retention]`; `None`
+    /// when neither gives a number of days.
+    pub fn from_args(
+        days: Option<u32>,
+        keep_alerts: bool,
+        archive: bool,
+        config: &RetentionConfig,
+    ) -> Result<Option<Self>, AppError> {
+        let Some(days) = days.or(config.days) else {
+            return Ok(None);
+        };
+        if days == 0 {
+            return Err(AppError::Other("--retain-days must be at least 1".into()));
+        }
+        Ok(Some(Self {
+            days,
+            keep_alerts: keep_alerts || config.keep_alerts,
+            archive: archive || config.archive,
+        }))
+    }
+
+    /// Calls before this go.
+    pub fn cutoff(&self) -> DateTime<Utc> {
+        Utc::now() - chrono::Duration::days(i64::from(self.days))
+    }
+
+    /// `older than 90 days (alerts kept)`.
+    pub fn describe(&self) -> String {
+        format!("older than {} days{}", self.days, if self.keep_alerts { " (alerts kept)" } else { "" })
+    }
+}
+
+/// Whether `sink` is one retention applies to.
+pub fn applies(sink: &SinkKind) -> bool {
+    match sink {
+        #[cfg(feature = "sink-postgres")]
+        SinkKind::Postgres(_) => true,
+        #[cfg(feature = "sink-sqlite")]
+        SinkKind::Sqlite(_) => true,
+        _ => false,
+    }
+}
+
+/// Prune `sink` by `policy`; `None` for sinks without a table.
+pub async fn apply(sink: &SinkKind, policy: &RetentionPolicy) -> Result<Option<Pruned>, AppError> {
+    let pruned = match sink {
+        #[cfg(feature = "sink-postgres")]
+        SinkKind::Postgres(pg) => Some(pg.prune(policy).await?),
+        #[cfg(feature = "sink-sqlite")]
+        SinkKind::Sqlite(db) => Some(db.prune(policy).await?),
+        _ => None,
+    };
+    if let Some(p) = &pruned {
+        report(sink.name(), policy, p);
+    }
+    Ok(pruned)
+}
+
+pub fn report(sink: &str, policy: &RetentionPolicy, pruned: &Pruned) {
+    if policy.archive {
+        info!("Retention ({}): archived {} rows {}", sink, pruned.archived, policy.describe());
+    } else {
+        info!("Retention ({}): deleted {} rows {}", sink, pruned.deleted, policy.describe());
+    }
+}
@@ -1,40 +1,194 @@
 // src/rle_filter.rs
-use crate::model::RadioRecord;
+use crate::freq::FreqTolerance;
+use crate::model::{RadioRecord, RunSpan, SlotData};
+use crate::budget::PipelineBudget;
+use crate::errors::AppError;
+use crate::stats::PipelineStats;
 use log::{trace, warn};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use chrono::Duration;
 use tokio::sync::mpsc::{Receiver, Sender};
 
+/// Knobs for `rle_compress_stream`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RleOptions {
+    /// A block without a RID continues a run that has one (`--fill-forward rid`).
+    pub fill_rid: bool,
+    /// Likewise for a missing TG (`--fill-forward tg`).
+    pub fill_tg: bool,
+    /// Compress DMR slot 1 and slot 2 traffic as separate streams
+    /// (`--rle-per-slot`).
+    pub per_slot: bool,
+    /// Drop runs of fewer blocks than this (`--rle-min-blocks`): squelch
+    /// flaps and falsing. 0 and 1 keep everything.
+    pub min_blocks: u32,
+    /// Report each run's last record number and timestamp (`--run-details`).
+    pub run_details: bool,
+    /// Frequencies this close count as the same channel (`--freq-tolerance`).
+    pub freq_tolerance: FreqTolerance,
+}
+
+impl RleOptions {
+    /// Parse a `--fill-forward` list such as `rid` or `rid,tg`.
+    pub fn with_fill_forward(mut self, fields: &str) -> Result<Self, AppError> {
+        for f in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            match f.to_ascii_lowercase().as_str() {
+                "rid" => self.fill_rid = true,
+                "tg" => self.fill_tg = true,
+                _ => return Err(AppError::Other(format!("--fill-forward: unknown field '{}' (use rid, tg)", f))),
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// `next` matches the run's value, or is missing and `fill` lets it take the
+/// run's.
+fn same_value(run: &Option<String>, next: &Option<String>, fill: bool) -> bool {
+    run == next || (fill && next.is_none())
+}
+
+/// Longest silence between the end of a run in one file and its next block in
+/// the following file (`--merge`) for the two to still be one call.
+const MAX_BOUNDARY_GAP: Duration = Duration::seconds(5);
+
+fn no_ids(r: &RadioRecord) -> bool {
+    [&r.slot1.tg, &r.slot1.rid, &r.slot2.tg, &r.slot2.rid].iter().all(|v| v.is_none())
+}
+
 /// Two records are the "same identity" if their radio-defining fields match.
-/// Time does NOT factor into identity; the stream order defines runs.
-fn same_identity(a: &RadioRecord, b: &RadioRecord) -> bool {
-    // Frequency, radio type, and DCC/NAC
-    if a.frequency != b.frequency { return false; }
+/// Time does NOT factor into identity; the stream order defines runs. The
+/// exceptions are a file boundary in merge mode, where the next file has to
+/// continue the run within `MAX_BOUNDARY_GAP`, and records without any
+/// TG/RID (analog channels, where the frequency is all there is), which must
+/// start within `MAX_BOUNDARY_GAP` of the run's end.
+fn same_identity(a: &RadioRecord, b: &RadioRecord, opts: RleOptions) -> bool {
+    if a.source != b.source {
+        let end = a.run.as_ref().map_or(a.datetime, |s| s.end_datetime);
+        let gap = b.datetime.signed_duration_since(end);
+        if gap < Duration::zero() || gap > MAX_BOUNDARY_GAP { return false; }
+    }
+    if no_ids(a) && no_ids(b) {
+        let end = a.datetime + Duration::seconds(a.duration as i64);
+        if b.datetime.signed_duration_since(end) > MAX_BOUNDARY_GAP { return false; }
+    }
+
+    // Frequency, radio type, NAC and DCC
+    if !opts.freq_tolerance.matches_opt(a.frequency.as_deref(), b.frequency.as_deref()) { return false; }
     if a.radio_type != b.radio_type { return false; }
+    if a.nac != b.nac { return false; }
     if a.dcc != b.dcc { return false; }
+    if a.call_type != b.call_type { return false; }
+    if a.target_rid != b.target_rid { return false; }
 
     // Slot 1 TG/RID
-    if a.slot1.tg != b.slot1.tg { return false; }
-    if a.slot1.rid != b.slot1.rid { return false; }
+    if !same_value(&a.slot1.tg, &b.slot1.tg, opts.fill_tg) { return false; }
+    if !same_value(&a.slot1.rid, &b.slot1.rid, opts.fill_rid) { return false; }
 
     // Slot 2 TG/RID
-    if a.slot2.tg != b.slot2.tg { return false; }
-    if a.slot2.rid != b.slot2.rid { return false; }
+    if !same_value(&a.slot2.tg, &b.slot2.tg, opts.fill_tg) { return false; }
+    if !same_value(&a.slot2.rid, &b.slot2.rid, opts.fill_rid) { return false; }
 
     true
 }
 
+/// Fold `next`, which has the run's identity, into `run`.
+fn absorb(run: &mut RadioRecord, mut next: RadioRecord, budget: &Option<Arc<PipelineBudget>>) {
+    // Extend the current run by one block (one second equivalent)
+    run.duration = run.duration.saturating_add(1);
+    run.block_count = run.block_count.saturating_add(next.block_count);
+    if let (Some(span), Some(last)) = (run.run.as_mut(), next.run.take()) {
+        span.last_record = last.last_record;
+        span.end_datetime = last.end_datetime;
+    }
+    // Errors add up over the call; quality is its worst block.
+    if let Some(e) = next.errors {
+        run.errors = Some(run.errors.unwrap_or(0).saturating_add(e));
+    }
+    if let Some(q) = next.quality {
+        run.quality = Some(run.quality.map_or(q, |r| r.min(q)));
+    }
+    run.encrypted |= next.encrypted;
+    for (k, v) in std::mem::take(&mut next.extras) {
+        run.extras.entry(k).or_insert(v);
+    }
+    // The merged block no longer exists on its own.
+    if let Some(b) = budget {
+        b.release(1);
+    }
+
+    // Keep the *first* record's timestamp/ID and text, per your spec.
+    // If you ever want to fill missing text from later blocks, you can opt-in:
+    // if run.slot1.text.is_none() { run.slot1.text = next.slot1.text.clone(); }
+    // if run.slot2.text.is_none() { run.slot2.text = next.slot2.text.clone(); }
+
+    trace!("RLE: extended run rec#{} to {} blocks", run.record_number, run.duration);
+}
+
+/// Send a finished run on, or drop it if it is shorter than `min_blocks`.
+/// `Err` once downstream has closed.
+async fn finish(
+    mut run: RadioRecord,
+    tx: &Sender<RadioRecord>,
+    opts: RleOptions,
+    stats: &PipelineStats,
+    budget: &Option<Arc<PipelineBudget>>,
+) -> Result<(), ()> {
+    if run.block_count < opts.min_blocks {
+        trace!("RLE: suppressed rec#{} ({} blocks)", run.record_number, run.block_count);
+        stats.rle_suppressed.fetch_add(1, Ordering::Relaxed);
+        if let Some(b) = budget {
+            b.release(1);
+        }
+        return Ok(());
+    }
+    // The span is always tracked (merge mode needs the run's end time) but
+    // only reported on request.
+    if !opts.run_details {
+        run.run = None;
+    }
+    if let Ok(mut c) = stats.content.lock() {
+        c.record(&run);
+    }
+    stats.rle.send(tx, run).await.map_err(|_| ())?;
+    stats.rle.inc_out();
+    Ok(())
+}
+
 /// Async stage that run-length compresses adjacent records by "radio identity".
 /// - Preserves the first record_number and datetime of the run.
 /// - Accumulates `duration` in **blocks** (1 per input record), regardless of
 ///   absolute datetime gaps or duplicates.
-/// - Any change in identity starts a new run.
-pub async fn rle_compress_stream(mut rx: Receiver<RadioRecord>, tx: Sender<RadioRecord>) {
+/// - Sums `errors` and keeps the lowest `quality` of the run.
+/// - With fill-forward, blocks missing a RID/TG continue the run.
+/// - Any change in identity starts a new run, as does a gap at a file
+///   boundary when several files are merged into one stream.
+/// - Runs shorter than `min_blocks` are dropped and counted.
+/// - With `run_details`, records the run's last record number and time.
+/// - With `per_slot`, records carrying both slots are split in two and each
+///   slot is compressed on its own; runs come out in start-time order.
+pub async fn rle_compress_stream(
+    mut rx: Receiver<RadioRecord>,
+    tx: Sender<RadioRecord>,
+    opts: RleOptions,
+    stats: Arc<PipelineStats>,
+    budget: Option<Arc<PipelineBudget>>,
+) {
+    if opts.per_slot {
+        return rle_per_slot(rx, tx, opts, stats, budget).await;
+    }
+    let _timer = stats.rle.timer();
     let mut cur: Option<RadioRecord> = None;
 
-    while let Some(mut next) = rx.recv().await {
+    while let Some(mut next) = stats.rle.recv(&mut rx).await {
+        stats.rle.inc_in();
         // Each parsed SRT block contributes at least 1s of duration.
         if next.duration == 0 {
             next.duration = 1;
         }
+        let n = next.record_number;
+        next.run.get_or_insert(RunSpan { first_record: n, last_record: n, end_datetime: next.datetime });
 
         match &mut cur {
             None => {
@@ -42,23 +196,14 @@ pub async fn rle_compress_stream(mut rx: Receiver<RadioRecord>, tx: Sender<Radio
                 cur = Some(next);
             }
             Some(run) => {
-                if same_identity(run, &next) {
-                    // Extend the current run by one block (one second equivalent)
-                    run.duration = run.duration.saturating_add(1);
-
-                    // Keep the *first* record's timestamp/ID and text, per your spec.
-                    // If you ever want to fill missing text from later blocks, you can opt-in:
-                    // if run.slot1.text.is_none() { run.slot1.text = next.slot1.text.clone(); }
-                    // if run.slot2.text.is_none() { run.slot2.text = next.slot2.text.clone(); }
-
-                    trace!("RLE: extended run rec#{} to {} blocks", run.record_number, run.duration);
+                if same_identity(run, &next, opts) {
+                    absorb(run, next, &budget);
                 } else {
                     // Identity changed → flush current run and start a new one
-                    if tx.send(run.clone()).await.is_err() {
+                    if finish(std::mem::replace(run, next), &tx, opts, &stats, &budget).await.is_err() {
                         warn!("rle_filter: downstream closed on flush; aborting");
                         return;
                     }
-                    cur = Some(next);
                 }
             }
         }
@@ -66,6 +211,74 @@ pub async fn rle_compress_stream(mut rx: Receiver<RadioRecord>, tx: Sender<Radio
 
     // Flush trailing run (if any)
     if let Some(run) = cur {
-        let _ = tx.send(run).await;
+        let _ = finish(run, &tx, opts, &stats, &budget).await;
+    }
+}
+
+fn slot_empty(s: &SlotData) -> bool {
+    s.tg.is_none() && s.rid.is_none() && s.text.is_none()
+}
+
+/// A record with traffic on both slots as one record per slot.
+fn split_slots(r: RadioRecord) -> (RadioRecord, Option<RadioRecord>) {
+    if slot_empty(&r.slot1) || slot_empty(&r.slot2) {
+        return (r, None);
+    }
+    let mut second = r.clone();
+    second.slot1 = SlotData::default();
+    let mut first = r;
+    first.slot2 = SlotData::default();
+    (first, Some(second))
+}
+
+/// Slot-aware RLE: one open run per slot. A finished run is held back until
+/// no open run started before it, so output stays in start-time order.
+async fn rle_per_slot(
+    mut rx: Receiver<RadioRecord>,
+    tx: Sender<RadioRecord>,
+    opts: RleOptions,
+    stats: Arc<PipelineStats>,
+    budget: Option<Arc<PipelineBudget>>,
+) {
+    let _timer = stats.rle.timer();
+    let mut open: [Option<RadioRecord>; 2] = [None, None];
+    let mut done: Vec<RadioRecord> = Vec::new();
+
+    while let Some(mut rec) = stats.rle.recv(&mut rx).await {
+        stats.rle.inc_in();
+        if rec.duration == 0 {
+            rec.duration = 1;
+        }
+        let n = rec.record_number;
+        rec.run.get_or_insert(RunSpan { first_record: n, last_record: n, end_datetime: rec.datetime });
+        let (first, second) = split_slots(rec);
+        if let (Some(_), Some(b)) = (&second, &budget) {
+            b.charge();
+        }
+        for part in std::iter::once(first).chain(second) {
+            let lane = usize::from(slot_empty(&part.slot1) && !slot_empty(&part.slot2));
+            match &mut open[lane] {
+                Some(run) if same_identity(run, &part, opts) => absorb(run, part, &budget),
+                slot => done.extend(slot.replace(part)),
+            }
+        }
+
+        let horizon = open.iter().flatten().map(|r| r.datetime).min();
+        done.sort_by_key(|r| (r.datetime, r.record_number));
+        let ready = done.iter().take_while(|r| horizon.is_none_or(|h| r.datetime <= h)).count();
+        for run in done.drain(..ready) {
+            if finish(run, &tx, opts, &stats, &budget).await.is_err() {
+                warn!("rle_filter: downstream closed on flush; aborting");
+                return;
+            }
+        }
+    }
+
+    done.extend(open.into_iter().flatten());
+    done.sort_by_key(|r| (r.datetime, r.record_number));
+    for run in done {
+        if finish(run, &tx, opts, &stats, &budget).await.is_err() {
+            break;
+        }
     }
 }
@@ -0,0 +1,155 @@
+// src/rotation.rs
+//
+// Output rotation for long-running (--follow) pipelines: start a new file per
+// hour/day of record time or once a size threshold is crossed, optionally
+// keeping only the newest N files.
+
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use log::{debug, warn};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RotationPolicy {
+    Hourly,
+    Daily,
+    /// Rotate once roughly this many bytes have been written.
+    Size(u64),
+}
+
+impl RotationPolicy {
+    /// Accepts `hourly`, `daily`, or a size such as `100MB`, `512KB`, `1GB`.
+    pub fn parse(s: &str) -> Result<Self, AppError> {
+        let t = s.trim().to_ascii_lowercase();
+        match t.as_str() {
+            "hourly" => return Ok(Self::Hourly),
+            "daily" => return Ok(Self::Daily),
+            _ => {}
+        }
+        match parse_size(&t) {
+            Some(n) => Ok(Self::Size(n)),
+            None => Err(AppError::Parse(format!(
+                "invalid rotation '{}': expected hourly, daily, or a size like 100MB",
+                s
+            ))),
+        }
+    }
+}
+
+/// Positive byte count such as `4096`, `512KB`, `100MB`, `1GB` (case-insensitive, binary units).
+pub fn parse_size(s: &str) -> Option<u64> {
+    let t = s.trim().to_ascii_lowercase();
+    let (num, mult) = if let Some(n) = t.strip_suffix("gb") {
+        (n, 1u64 << 30)
+    } else if let Some(n) = t.strip_suffix("mb") {
+        (n, 1u64 << 20)
+    } else if let Some(n) = t.strip_suffix("kb") {
+        (n, 1u64 << 10)
+    } else {
+        (t.as_str(), 1)
+    };
+    match num.trim().parse::<u64>() {
+        Ok(n) if n > 0 => Some(n.saturating_mul(mult)),
+        _ => None,
+    }
+}
+
+/// Tracks the current output file of a rotating sink.
+pub struct Rotator {
+    base: PathBuf,
+    policy: RotationPolicy,
+    keep: Option<usize>,
+    bucket: Option<String>,
+    bytes: u64,
+}
+
+impl Rotator {
+    /// `base` is the un-rotated output path (e.g. `capture.csv`); rotated files
+    /// are written next to it as `capture-YYYYMMDD-HHMMSS.csv`.
+    pub fn new(base: &Path, policy: RotationPolicy, keep: Option<usize>) -> Self {
+        Self { base: base.to_path_buf(), policy, keep, bucket: None, bytes: 0 }
+    }
+
+    fn bucket_of(&self, rec: &RadioRecord) -> Option<String> {
+        match self.policy {
+            RotationPolicy::Hourly => Some(rec.datetime.format("%Y%m%d%H").to_string()),
+            RotationPolicy::Daily => Some(rec.datetime.format("%Y%m%d").to_string()),
+            RotationPolicy::Size(_) => None,
+        }
+    }
+
+    /// True if `rec` should go to a fresh file (always true before the first file).
+    pub fn needs_rotation(&self, rec: &RadioRecord) -> bool {
+        if self.bucket.is_none() && self.bytes == 0 {
+            return true;
+        }
+        match self.policy {
+            RotationPolicy::Size(limit) => self.bytes >= limit,
+            _ => self.bucket_of(rec) != self.bucket,
+        }
+    }
+
+    pub fn add_bytes(&mut self, n: u64) {
+        self.bytes = self.bytes.saturating_add(n);
+    }
+
+    /// Choose the path for a new file starting with `rec` and reset counters.
+    pub fn next_path(&mut self, rec: &RadioRecord) -> PathBuf {
+        self.bucket = self.bucket_of(rec);
+        // Non-zero so a size policy never re-rotates before the first write.
+        self.bytes = 1;
+
+        let stem = self.base.file_stem().and_then(|s| s.to_str()).unwrap_or("out");
+        let ext = self.base.extension().and_then(|s| s.to_str()).unwrap_or("csv");
+        let ts = rec.datetime.format("%Y%m%d-%H%M%S");
+        let mut candidate = self.base.with_file_name(format!("{}-{}.{}", stem, ts, ext));
+        let mut n = 1;
+        while candidate.exists() {
+            candidate = self.base.with_file_name(format!("{}-{}-{}.{}", stem, ts, n, ext));
+            n += 1;
+        }
+        candidate
+    }
+
+    /// Delete the oldest rotated files beyond the retention count.
+    pub async fn prune(&self) -> Result<(), AppError> {
+        let Some(keep) = self.keep else { return Ok(()); };
+        let dir = match self.base.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let stem = self.base.file_stem().and_then(|s| s.to_str()).unwrap_or("out");
+        let ext = self.base.extension().and_then(|s| s.to_str()).unwrap_or("csv");
+        let re = Regex::new(&format!(
+            r"^{}-\d{{8}}-\d{{6}}(-\d+)?\.{}$",
+            regex::escape(stem),
+            regex::escape(ext)
+        ))
+        .map_err(|e| AppError::Other(format!("rotation pattern: {}", e)))?;
+
+        let mut rotated = Vec::new();
+        let mut rd = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = rd.next_entry().await? {
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else { continue; };
+            if !re.is_match(name) {
+                continue;
+            }
+            let modified = entry.metadata().await.and_then(|m| m.modified()).ok();
+            rotated.push((modified, entry.path()));
+        }
+        if rotated.len() <= keep {
+            return Ok(());
+        }
+        rotated.sort();
+        let excess = rotated.len() - keep;
+        for (_, path) in rotated.into_iter().take(excess) {
+            debug!("rotation: removing {}", path.display());
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                warn!("rotation: could not remove {}: {}", path.display(), e);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+// src/sd_notify.rs
+//
+// Minimal systemd notification protocol (sd_notify(3)) for `--daemon`:
+// readiness, status text, watchdog pings and shutdown. Messages go to the
+// datagram socket named by $NOTIFY_SOCKET; without it (not started by
+// systemd, or not Type=notify) every call is a no-op.
+
+use std::time::Duration;
+
+/// Send `state` (e.g. `READY=1`, `STATUS=...`). Returns whether a message
+/// was delivered.
+pub fn notify(state: &str) -> bool {
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return false;
+    };
+    send(&path, state).is_ok()
+}
+
+#[cfg(unix)]
+fn send(path: &std::ffi::OsStr, state: &str) -> std::io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let sock = UnixDatagram::unbound()?;
+    // "@name" is a Linux abstract-namespace socket.
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::ffi::OsStrExt;
+        if let Some(name) = path.as_bytes().strip_prefix(b"@") {
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            sock.send_to_addr(state.as_bytes(), &addr)?;
+            return Ok(());
+        }
+    }
+    sock.send_to(state.as_bytes(), std::path::Path::new(path))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send(_path: &std::ffi::OsStr, _state: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "sd_notify needs a Unix socket"))
+}
+
+/// How often to send `WATCHDOG=1`: half of systemd's `WatchdogSec=`, when a
+/// watchdog is configured for this process.
+pub fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Some(pid) = std::env::var("WATCHDOG_PID").ok().and_then(|p| p.parse::<u32>().ok())
+        && pid != std::process::id()
+    {
+        return None;
+    }
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
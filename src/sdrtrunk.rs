@@ -0,0 +1,183 @@
+// src/sdrtrunk.rs
+//
+// `registry export`: the talk groups and radios of a `--unit-registry` file,
+// named from `[alias]`, as an SDRTrunk playlist (one `<alias>` per ID, in
+// one alias list) or as a CSV alias list, so what DSDPlus has turned up can
+// go straight into an SDRTrunk setup. IDs with an alias that haven't been
+// heard yet are exported too, unless `--limit` cuts the list short.
+//
+// SDRTrunk wants a protocol for each ID; it comes from the radio type of the
+// entry's latest call (P25 is APCO25, DMR and NXDN keep their names), else
+// `--protocol`. IDs without an alias are named `TG 2` / `Radio 4506`.
+
+use crate::config::Aliases;
+use crate::errors::AppError;
+use crate::unit_registry::{Kind, ShowOptions, Sighting, UnitRegistry};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Default `--alias-list`: the alias list an SDRTrunk channel is set to.
+pub const DEFAULT_LIST: &str = "Callscribe";
+
+/// Default `--protocol`.
+pub const DEFAULT_PROTOCOL: &str = "APCO25";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// SDRTrunk playlist XML.
+    Playlist,
+    /// `list,group,name,protocol,type,value,calls,airtime_s,first_seen,last_seen`
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn parse(s: &str) -> Result<Self, AppError> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "sdrtrunk" | "playlist" | "xml" => Ok(ExportFormat::Playlist),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(AppError::Parse(format!("invalid --format '{}': expected sdrtrunk or csv", s))),
+        }
+    }
+}
+
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub list: String,
+    pub protocol: String,
+}
+
+/// One alias to write.
+struct Entry<'a> {
+    kind: Kind,
+    id: String,
+    name: String,
+    protocol: &'a str,
+    sighting: Option<Sighting>,
+}
+
+/// SDRTrunk's protocol for a radio type; `None` for types it doesn't decode.
+fn protocol_of(radio_type: &str) -> Option<&'static str> {
+    match radio_type {
+        t if t.starts_with("P25") => Some("APCO25"),
+        "DMR" => Some("DMR"),
+        "NXDN" => Some("NXDN"),
+        _ => None,
+    }
+}
+
+/// The alias group and `<id type>` of a talk group or radio.
+fn group(kind: Kind) -> &'static str {
+    if kind == Kind::Rid { "Radios" } else { "Talkgroups" }
+}
+
+fn id_type(kind: Kind) -> &'static str {
+    if kind == Kind::Rid { "radio" } else { "talkgroup" }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn entries<'a>(
+    registry: &UnitRegistry,
+    show: &ShowOptions,
+    aliases: &Aliases,
+    opts: &'a ExportOptions,
+) -> Vec<Entry<'a>> {
+    let mut out = Vec::new();
+    for (kind, names, unnamed) in [(Kind::Tg, &aliases.tg, "TG"), (Kind::Rid, &aliases.rid, "Radio")] {
+        if show.kind != Kind::All && show.kind != kind {
+            continue;
+        }
+        let heard = registry.rows(kind, show);
+        let not_heard: BTreeMap<&String, &String> = if show.limit > 0 {
+            BTreeMap::new()
+        } else {
+            names
+                .iter()
+                .filter(|(id, _)| show.ids.is_empty() || show.ids.contains(id))
+                .filter(|(id, _)| !heard.iter().any(|(h, _)| h == *id))
+                .collect()
+        };
+        let heard = heard.into_iter().map(|(id, s)| (id, Some(s)));
+        for (id, sighting) in heard.chain(not_heard.into_keys().map(|id| (id.clone(), None))) {
+            let protocol = sighting
+                .as_ref()
+                .and_then(|s| s.last_radio_type.as_deref())
+                .and_then(protocol_of)
+                .unwrap_or(&opts.protocol);
+            let name = names.get(&id).cloned().unwrap_or_else(|| format!("{} {}", unnamed, id));
+            out.push(Entry { kind, id, name, protocol, sighting });
+        }
+    }
+    out
+}
+
+/// Write the talk groups and radios `show` picks as `opts.format`; returns
+/// how many were written.
+pub fn export(
+    registry: &UnitRegistry,
+    show: &ShowOptions,
+    aliases: &Aliases,
+    opts: &ExportOptions,
+    out: &mut impl Write,
+) -> Result<usize, AppError> {
+    let io = |e: std::io::Error| AppError::IO(format!("write: {}", e));
+    let entries = entries(registry, show, aliases, opts);
+    match opts.format {
+        ExportFormat::Playlist => {
+            writeln!(out, r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#).map_err(io)?;
+            writeln!(out, "<!-- {} aliases from {} -->", entries.len(), registry.path().display()).map_err(io)?;
+            writeln!(out, r#"<playlist version="4">"#).map_err(io)?;
+            for e in &entries {
+                writeln!(
+                    out,
+                    r#"  <alias name="{}" list="{}" group="{}" color="0" iconName="No Icon">"#,
+                    xml_escape(&e.name),
+                    xml_escape(&opts.list),
+                    group(e.kind)
+                )
+                .map_err(io)?;
+                writeln!(out, r#"    <id type="{}" value="{}" protocol="{}"/>"#, id_type(e.kind), xml_escape(&e.id), xml_escape(e.protocol))
+                    .map_err(io)?;
+                writeln!(out, "  </alias>").map_err(io)?;
+            }
+            writeln!(out, "</playlist>").map_err(io)?;
+        }
+        ExportFormat::Csv => {
+            writeln!(out, "list,group,name,protocol,type,value,calls,airtime_s,first_seen,last_seen").map_err(io)?;
+            for e in &entries {
+                let (calls, airtime, first, last) = match &e.sighting {
+                    Some(s) => {
+                        (s.calls.to_string(), s.airtime_s.to_string(), s.first_seen.to_rfc3339(), s.last_seen.to_rfc3339())
+                    }
+                    None => Default::default(),
+                };
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{},{},{}",
+                    csv_field(&opts.list),
+                    group(e.kind),
+                    csv_field(&e.name),
+                    e.protocol,
+                    id_type(e.kind),
+                    csv_field(&e.id),
+                    calls,
+                    airtime,
+                    first,
+                    last
+                )
+                .map_err(io)?;
+            }
+        }
+    }
+    Ok(entries.len())
+}
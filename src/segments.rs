@@ -0,0 +1,267 @@
+// src/segments.rs
+//
+// `--stt-segments` (feature `stt-segments`): a long recording, such as an RLE
+// run whose transmissions DSDPlus recorded back to back, is split at its
+// pauses and each part is transcribed on its own, so the transcript says who
+// spoke when instead of running several transmissions together. A pause is
+// `--stt-segment-gap` ms of audio below the silence level `--stt-preprocess`
+// trims at; parts shorter than 300 ms (key-up clicks) are dropped.
+//
+// Speakers are guessed from level alone: parts within 4 dB of each other are
+// taken for the same radio, the way a dispatcher's console and a portable in
+// the field come in at clearly different levels. It is a hint, not speaker
+// recognition; two units at the same distance share a number.
+//
+// The transcript becomes a JSON array in the text field,
+// `[{"start":0.0,"end":4.2,"speaker":1,"text":"..."}]`, offsets in seconds
+// from the start of the recording. With `--stt-segments-out FILE` the text
+// field keeps the parts' plain text, joined, and FILE gets one JSON line per
+// segmented call (`record_id`, `datetime`, `tg`, `rid`, `audio`, `segments`).
+// Recordings shorter than `--stt-segment-min` seconds, non-WAV files and
+// recordings without a pause go to the transcriber whole, as without the
+// option.
+
+use crate::audio_prep::{self, PAD_MS, SILENCE_DBFS, TARGET_RATE};
+use crate::errors::AppError;
+use crate::freq::FreqTolerance;
+use crate::model::RadioRecord;
+use crate::stt_registry::SharedTranscriber;
+use crate::transcriber::{AUDIO_EXTENSIONS, RecordFileIndex, Transcriber, choose_root};
+use crate::winpath;
+use log::debug;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Parts shorter than this are clicks and squelch bursts, not speech.
+const MIN_SEGMENT_MS: usize = 300;
+
+/// Parts whose levels are within this many dB get the same speaker.
+const SPEAKER_DB: f32 = 4.0;
+
+#[derive(Debug, Clone)]
+pub struct SegmentOptions {
+    /// `--stt-segment-gap`: the shortest pause split at.
+    pub gap_ms: u32,
+    /// `--stt-segment-min`: recordings shorter than this go through whole.
+    pub min_call_s: u32,
+    /// `--stt-segments-out`: segments go here, plain text to the record.
+    pub out: Option<PathBuf>,
+    pub audio_extensions: Option<Vec<String>>,
+    pub freq_tolerance: FreqTolerance,
+}
+
+/// One transcribed part of a recording.
+#[derive(Debug, Clone, Serialize)]
+pub struct Segment {
+    /// Seconds from the start of the recording.
+    pub start: f64,
+    pub end: f64,
+    /// 1 for the first level heard, 2 for the next, ...
+    pub speaker: u32,
+    pub text: String,
+}
+
+/// A stretch of speech: sample range and RMS level in dBFS.
+struct Region {
+    start: usize,
+    end: usize,
+    level_db: f32,
+}
+
+fn rms_db(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return f32::NEG_INFINITY;
+    }
+    let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt();
+    20.0 * rms.max(f32::EPSILON).log10()
+}
+
+/// The speech regions of `samples`: runs of 20 ms frames above the silence
+/// level, merged across pauses shorter than `gap_ms`, padded by `PAD_MS`.
+fn regions(samples: &[f32], rate: u32, gap_ms: u32) -> Vec<Region> {
+    let frame = (rate as usize / 50).max(1);
+    let frame_ms = 20;
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (i, chunk) in samples.chunks(frame).enumerate() {
+        if rms_db(chunk) < SILENCE_DBFS {
+            continue;
+        }
+        match runs.last_mut() {
+            Some((_, end)) if (i - *end) * frame_ms < gap_ms as usize => *end = i + 1,
+            _ => runs.push((i, i + 1)),
+        }
+    }
+    let pad = rate as usize * PAD_MS / 1000;
+    let mut out: Vec<Region> = Vec::new();
+    for (first, last) in runs {
+        if (last - first) * frame_ms < MIN_SEGMENT_MS {
+            continue;
+        }
+        let loud = &samples[first * frame..(last * frame).min(samples.len())];
+        let floor = out.last().map_or(0, |r| r.end);
+        out.push(Region {
+            start: (first * frame).saturating_sub(pad).max(floor),
+            end: (last * frame + pad).min(samples.len()),
+            level_db: rms_db(loud),
+        });
+    }
+    out
+}
+
+/// Number the regions' speakers by level, in order of first appearance.
+fn speakers(regions: &[Region]) -> Vec<u32> {
+    let mut levels: Vec<f32> = Vec::new();
+    regions
+        .iter()
+        .map(|r| {
+            let known = levels.iter().position(|l| (l - r.level_db).abs() <= SPEAKER_DB);
+            let n = known.unwrap_or_else(|| {
+                levels.push(r.level_db);
+                levels.len() - 1
+            });
+            n as u32 + 1
+        })
+        .collect()
+}
+
+fn seconds(sample: usize, rate: u32) -> f64 {
+    (sample as f64 / rate.max(1) as f64 * 100.0).round() / 100.0
+}
+
+/// Transcribes long recordings part by part through `inner`.
+pub struct Segmented {
+    inner: SharedTranscriber,
+    root: PathBuf,
+    audio: RecordFileIndex,
+    opts: SegmentOptions,
+    out: Option<Mutex<BufWriter<File>>>,
+}
+
+impl Segmented {
+    pub fn new(inner: SharedTranscriber, root: &Path, opts: SegmentOptions) -> Result<Self, AppError> {
+        let audio = match &opts.audio_extensions {
+            Some(exts) => RecordFileIndex::new(exts),
+            None => RecordFileIndex::new(AUDIO_EXTENSIONS),
+        }
+        .with_freq_tolerance(opts.freq_tolerance);
+        let out = match &opts.out {
+            Some(path) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| AppError::IO(format!("open {}: {}", path.display(), e)))?;
+                Some(Mutex::new(BufWriter::new(file)))
+            }
+            None => None,
+        };
+        Ok(Self { inner, root: root.to_path_buf(), audio, opts, out })
+    }
+
+    /// The call's recording, when it is a WAV file.
+    fn recording(&self, rec: &RadioRecord, record_dir: &Path) -> Result<Option<PathBuf>, AppError> {
+        let audio = match &rec.audio_path {
+            Some(path) => Some(path.clone()),
+            None => self.audio.find(rec, choose_root(record_dir, &self.root)?)?,
+        };
+        Ok(audio.filter(|p| p.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("wav"))))
+    }
+
+    /// Transcribe each speech region of `audio`; `None` when it is too short
+    /// or has no pause to split at.
+    fn segments(
+        &self,
+        rec: &RadioRecord,
+        record_dir: &Path,
+        audio: &Path,
+    ) -> Result<Option<Vec<Segment>>, Option<AppError>> {
+        let (mono, rate) = audio_prep::read_mono(audio).map_err(Some)?;
+        if mono.len() < self.opts.min_call_s as usize * rate as usize {
+            return Ok(None);
+        }
+        let regions = regions(&mono, rate, self.opts.gap_ms);
+        if regions.len() < 2 {
+            return Ok(None);
+        }
+        let speakers = speakers(&regions);
+        let mut out = Vec::new();
+        for (region, speaker) in regions.iter().zip(speakers) {
+            let part = audio_prep::resample(&mono[region.start..region.end], rate, TARGET_RATE);
+            let prepared = audio_prep::write_temporary(&part).map_err(Some)?;
+            let mut part_rec = rec.clone();
+            part_rec.audio_path = Some(prepared.path.clone());
+            let Some(text) = self.inner.transcribe(&part_rec, record_dir)? else { continue };
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            out.push(Segment {
+                start: seconds(region.start, rate),
+                end: seconds(region.end, rate),
+                speaker,
+                text: text.to_string(),
+            });
+        }
+        debug!(
+            "segments: rec#{} {}: {} parts, {} with speech, {} speakers",
+            rec.record_number,
+            winpath::simplified(audio).display(),
+            regions.len(),
+            out.len(),
+            out.iter().map(|s| s.speaker).max().unwrap_or(0)
+        );
+        Ok(Some(out))
+    }
+
+    fn write(
+        &self,
+        out: &Mutex<BufWriter<File>>,
+        rec: &RadioRecord,
+        audio: &Path,
+        segments: &[Segment],
+    ) -> Result<(), AppError> {
+        let line = serde_json::json!({
+            "record_id": rec.record_id(),
+            "datetime": rec.datetime.to_rfc3339(),
+            "tg": rec.slot1.tg.as_ref().or(rec.slot2.tg.as_ref()),
+            "rid": rec.slot1.rid.as_ref().or(rec.slot2.rid.as_ref()),
+            "audio": winpath::simplified(audio),
+            "segments": segments,
+        });
+        let path = self.opts.out.as_deref().unwrap_or(Path::new(""));
+        let mut out = out.lock();
+        writeln!(out, "{}", line)
+            .and_then(|()| out.flush())
+            .map_err(|e| AppError::IO(format!("write {}: {}", path.display(), e)))
+    }
+}
+
+impl Transcriber for Segmented {
+    fn transcribe(&self, rec: &RadioRecord, record_dir: &Path) -> Result<Option<String>, Option<AppError>> {
+        let Some(audio) = self.recording(rec, record_dir).map_err(Some)? else {
+            return self.inner.transcribe(rec, record_dir);
+        };
+        let Some(segments) = self.segments(rec, record_dir, &audio)? else {
+            return self.inner.transcribe(rec, record_dir);
+        };
+        if segments.is_empty() {
+            return Ok(None);
+        }
+        match &self.out {
+            Some(out) => {
+                self.write(out, rec, &audio, &segments).map_err(Some)?;
+                Ok(Some(segments.iter().map(|s| s.text.as_str()).collect::<Vec<_>>().join(" ")))
+            }
+            None => serde_json::to_string(&segments)
+                .map(Some)
+                .map_err(|e| Some(AppError::Other(format!("segments: {}", e)))),
+        }
+    }
+
+    fn health(&self) -> Result<(), AppError> {
+        self.inner.health()
+    }
+}
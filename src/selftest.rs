@@ -0,0 +1,222 @@
+// src/selftest.rs
+//
+// `callscribe selftest`: run the bundled fixtures (`fixtures/selftest/`)
+// through parse -> filter -> RLE -> text transcriber -> CSV in a scratch
+// directory, then read the CSV back and compare it with the expected rows.
+// With `--record-dir` it also checks that the user's Record directory has a
+// recognisable layout and file names.
+
+use crate::csv_sink::{self, CsvSinkOptions};
+use crate::errors::AppError;
+use crate::filter::{self, FilterConfig};
+use crate::input::{self, ParseOptions};
+use crate::model::RadioRecord;
+use crate::stats::PipelineStats;
+use crate::transcriber::{self, RecordLayout, TextFileTranscriber, Transcriber};
+use crate::{record_name, rle_filter, transcription_adder};
+use chrono::FixedOffset;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_util::compat::TokioAsyncReadCompatExt;
+
+const SAMPLE_SRT: &str = include_str!("../fixtures/selftest/sample.srt");
+const SAMPLE_EVENT: &str = include_str!("../fixtures/selftest/sample.event");
+const TRANSCRIPTS: &[(&str, &str)] = &[
+    (
+        "064356_153.450000_004_P25__GC_2_4506.txt",
+        include_str!("../fixtures/selftest/Record/20250910/064356_153.450000_004_P25__GC_2_4506.txt"),
+    ),
+    (
+        "064410_154.100000_DMR_GC_10_100.txt",
+        include_str!("../fixtures/selftest/Record/20250910/064410_154.100000_DMR_GC_10_100.txt"),
+    ),
+];
+
+/// Columns checked per expected row: CSV header name and value. A value
+/// ending in `…` only has to be a prefix.
+type Row = &'static [(&'static str, &'static str)];
+
+const EXPECT_SRT: &[Row] = &[
+    &[
+        ("record_number", "1"),
+        ("datetime", "2025-09-10 06:43:56"),
+        ("duration", "2"),
+        ("frequency", "153.450000"),
+        ("radio_type", "P25p1"),
+        ("dcc", "293"),
+        ("nac", "293"),
+        ("slot1_tg", "2"),
+        ("slot1_rid", "4506"),
+        ("slot1_text", "Water main break…"),
+    ],
+    &[
+        ("record_number", "3"),
+        ("duration", "1"),
+        ("radio_type", "DMR"),
+        ("slot1_tg", "10"),
+        ("slot1_rid", "100"),
+        ("slot1_text", "Copy, water crew…"),
+        ("slot2_tg", "20"),
+        ("slot2_rid", "200"),
+    ],
+];
+
+const EXPECT_EVENT: &[Row] = &[
+    &[("datetime", "2025-09-09 18:39:20"), ("duration", "7"), ("slot1_tg", "2"), ("slot1_rid", "4506")],
+    &[("slot1_tg", "19")],
+    &[("slot1_tg", "20")],
+    &[("slot1_tg", "21")],
+    &[("datetime", "2025-09-09 22:39:20"), ("slot1_tg", "22")],
+];
+
+/// Outcome of one check.
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, res: Result<String, String>) -> Self {
+        match res {
+            Ok(detail) => Self { name: name.into(), ok: true, detail },
+            Err(detail) => Self { name: name.into(), ok: false, detail },
+        }
+    }
+}
+
+/// Scratch directory, removed on drop.
+struct Scratch(PathBuf);
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn io(e: std::io::Error) -> AppError {
+    AppError::IO(format!("selftest scratch dir: {}", e))
+}
+
+/// Run every check. `Err` only for problems with the test setup itself.
+pub async fn run(record_dir: Option<&Path>) -> Result<Vec<Check>, AppError> {
+    let scratch = Scratch(std::env::temp_dir().join(format!("callscribe-selftest-{}", std::process::id())));
+    let day = scratch.0.join("Record").join("20250910");
+    std::fs::create_dir_all(&day).map_err(io)?;
+    for (name, text) in TRANSCRIPTS {
+        std::fs::write(day.join(name), text).map_err(io)?;
+    }
+    let srt = scratch.0.join("sample.srt");
+    let event = scratch.0.join("sample.event");
+    std::fs::write(&srt, SAMPLE_SRT).map_err(io)?;
+    std::fs::write(&event, SAMPLE_EVENT).map_err(io)?;
+
+    let record = scratch.0.join("Record");
+    let mut checks = Vec::new();
+    for (input, expect) in [(&srt, EXPECT_SRT), (&event, EXPECT_EVENT)] {
+        let name = format!("pipeline {}", input.file_name().and_then(|n| n.to_str()).unwrap_or_default());
+        let res = match pipeline(input, &record).await {
+            Ok(csv) => compare(&csv, expect).await,
+            Err(e) => Err(e.to_string()),
+        };
+        checks.push(Check::new(name, res));
+    }
+    if let Some(dir) = record_dir {
+        checks.push(Check::new(format!("record dir {}", dir.display()), check_record_dir(dir)));
+    }
+    Ok(checks)
+}
+
+/// The production stages, wired as in `run_pipeline`, into a CSV next to `input`.
+async fn pipeline(input: &Path, record_dir: &Path) -> Result<PathBuf, AppError> {
+    let out = input.with_extension("csv");
+    let stats = Arc::new(PipelineStats::default());
+    let filter_cfg = Arc::new(FilterConfig::default());
+    let transcriber: Arc<dyn Transcriber + Send + Sync> = Arc::new(TextFileTranscriber::new_indexed(record_dir)?);
+    let opts = ParseOptions { tz_offset: FixedOffset::east_opt(0), ..Default::default() };
+
+    let (tx_parse, rx_parse) = mpsc::channel::<RadioRecord>(64);
+    let (tx_filt, rx_filt) = mpsc::channel::<RadioRecord>(64);
+    let (tx_rle, rx_rle) = mpsc::channel::<RadioRecord>(64);
+    let (tx_trans, rx_trans) = mpsc::channel::<RadioRecord>(64);
+    let p_in = input.to_path_buf();
+    let producer = tokio::spawn(async move { input::stream_input(&p_in, opts, tx_parse).await });
+    tokio::spawn(filter::filter_stream(filter_cfg, rx_parse, tx_filt, Arc::clone(&stats), None));
+    tokio::spawn(rle_filter::rle_compress_stream(rx_filt, tx_rle, Default::default(), Arc::clone(&stats), None));
+    let trans = tokio::spawn(transcription_adder::add_transcriptions(
+        rx_rle,
+        tx_trans,
+        Some(record_dir.to_path_buf()),
+        Some(transcriber),
+        2,
+        Arc::clone(&stats),
+        None,
+    ));
+    let opts = CsvSinkOptions::default();
+    csv_sink::write_csv_stream(&out, rx_trans, stats, opts).await?;
+
+    let join = |e: tokio::task::JoinError| AppError::Other(format!("selftest join: {e}"));
+    producer.await.map_err(join)??;
+    trans.await.map_err(join)??;
+    Ok(out)
+}
+
+async fn compare(csv: &Path, expect: &[Row]) -> Result<String, String> {
+    let file = tokio::fs::File::open(csv).await.map_err(|e| format!("open {}: {}", csv.display(), e))?;
+    let mut reader = csv_async::AsyncReader::from_reader(file.compat());
+    let header = reader.headers().await.map_err(|e| e.to_string())?.clone();
+    let mut rows = Vec::new();
+    let mut record = csv_async::StringRecord::new();
+    while reader.read_record(&mut record).await.map_err(|e| e.to_string())? {
+        rows.push(record.clone());
+    }
+    if rows.len() != expect.len() {
+        return Err(format!("expected {} rows, got {}", expect.len(), rows.len()));
+    }
+    for (i, (row, want)) in rows.iter().zip(expect).enumerate() {
+        for (col, value) in want.iter() {
+            let idx = header.iter().position(|h| h == *col).ok_or_else(|| format!("no column {}", col))?;
+            let got = row.get(idx).unwrap_or_default();
+            let ok = match value.strip_suffix('…') {
+                Some(prefix) => got.starts_with(prefix),
+                None => got == *value,
+            };
+            if !ok {
+                return Err(format!("row {}: {} is {:?}, expected {:?}", i + 1, col, got, value));
+            }
+        }
+    }
+    Ok(format!("{} rows as expected", rows.len()))
+}
+
+/// Layout plus how many transcript files follow the record-name grammar.
+fn check_record_dir(dir: &Path) -> Result<String, String> {
+    if !dir.is_dir() {
+        return Err("not a directory".into());
+    }
+    let layout = RecordLayout::detect(dir, transcriber::TRANSCRIPT_EXTENSIONS);
+    let (mut total, mut parsed, mut sample_bad) = (0usize, 0usize, None);
+    for entry in walkdir::WalkDir::new(dir).max_depth(4).into_iter().flatten() {
+        let Some(name) = entry.file_name().to_str() else { continue };
+        let Some((stem, ext)) = name.rsplit_once('.') else { continue };
+        if !entry.file_type().is_file()
+            || !transcriber::TRANSCRIPT_EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext))
+        {
+            continue;
+        }
+        total += 1;
+        if record_name::parse(stem).is_some() {
+            parsed += 1;
+        } else if sample_bad.is_none() {
+            sample_bad = Some(name.to_string());
+        }
+    }
+    match (total, sample_bad) {
+        (0, _) => Err(format!("{:?} layout, but no transcript files found", layout)),
+        (_, Some(bad)) if parsed == 0 => Err(format!("{:?} layout; no file names recognised (e.g. {})", layout, bad)),
+        (_, Some(bad)) => Ok(format!("{:?} layout; {} of {} files recognised (not e.g. {})", layout, parsed, total, bad)),
+        (_, None) => Ok(format!("{:?} layout; all {} transcript files recognised", layout, total)),
+    }
+}
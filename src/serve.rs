@@ -0,0 +1,461 @@
+// src/serve.rs
+//
+// `serve --static`: a small web viewer for a run's results on localhost, for
+// people who would rather not use the command line. It serves one bundled
+// page (assets/viewer.html: table, search, audio playback) and a JSON API
+// over a database written by `--sink sqlite` or a JSON lines file of
+// records (one `RadioRecord::to_json` object per line, like the golden
+// files). Recordings are requested by call ID, never by path, so only files
+// named in the data can be read through it.
+//
+//   GET /                          the viewer
+//   GET /api/calls?q=&limit=&offset=   {"total": N, "calls": [...]}, newest first
+//   GET /audio/<id>                the call's recording
+//
+// `serve --grafana` adds Grafana JSON datasource routes (see `grafana.rs`);
+// both can be served at once.
+
+use crate::assets;
+use crate::errors::AppError;
+use crate::grafana;
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Default `--listen`.
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+
+/// Largest request head and body read; the viewer only sends short GETs and
+/// Grafana small JSON queries.
+const MAX_REQUEST: usize = 16 * 1024;
+const MAX_BODY: usize = 1024 * 1024;
+
+/// Calls per page unless `limit` says otherwise, and the most it may ask for.
+const PAGE: usize = 100;
+const MAX_PAGE: usize = 1000;
+
+/// One row of the viewer's table.
+#[derive(Debug, Clone, Serialize)]
+pub struct Call {
+    /// Database row ID, or line number in a JSON lines file.
+    pub id: u64,
+    pub datetime: String,
+    pub frequency: String,
+    pub tg: String,
+    pub rid: String,
+    pub duration: i64,
+    pub radio_type: Option<String>,
+    pub text: String,
+    pub alert: Option<String>,
+    pub encrypted: bool,
+    /// Whether `/audio/<id>` has a recording.
+    pub audio: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Page {
+    pub total: u64,
+    pub calls: Vec<Call>,
+}
+
+/// Where the calls come from.
+pub enum Source {
+    /// Re-read on every request, so a file still being written shows new calls.
+    Jsonl(PathBuf),
+    #[cfg(feature = "sink-sqlite")]
+    Sqlite(sqlx::SqlitePool),
+}
+
+impl Source {
+    /// `.db`/`.sqlite`/`.sqlite3` files are databases, anything else JSON lines.
+    pub async fn open(path: &Path) -> Result<Self, AppError> {
+        if !path.exists() {
+            return Err(AppError::IO(format!("no such file '{}'", path.display())));
+        }
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+        if matches!(ext.as_str(), "db" | "sqlite" | "sqlite3") {
+            #[cfg(feature = "sink-sqlite")]
+            return Ok(Source::Sqlite(crate::sqlite_sink::open_db(path, false).await?));
+            #[cfg(not(feature = "sink-sqlite"))]
+            return Err(AppError::Other("serving a database needs a build with the sink-sqlite feature".into()));
+        }
+        Ok(Source::Jsonl(path.to_path_buf()))
+    }
+
+    async fn page(&self, filter: &str, limit: usize, offset: usize) -> Result<Page, AppError> {
+        match self {
+            Source::Jsonl(path) => {
+                let (matched, _) = read_jsonl(path, filter).await?;
+                let total = matched.len() as u64;
+                let calls = matched.into_iter().rev().skip(offset).take(limit).collect();
+                Ok(Page { total, calls })
+            }
+            #[cfg(feature = "sink-sqlite")]
+            Source::Sqlite(pool) => {
+                let (total, calls) = crate::sqlite_sink::browse(pool, filter, limit, offset).await?;
+                Ok(Page { total, calls })
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Source::Jsonl(path) => path.display().to_string(),
+            #[cfg(feature = "sink-sqlite")]
+            Source::Sqlite(_) => "database".into(),
+        }
+    }
+
+    /// Calls starting in `from..=to`, newest first.
+    async fn between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Call>, AppError> {
+        match self {
+            Source::Jsonl(path) => {
+                let (calls, _) = read_jsonl(path, "").await?;
+                Ok(calls
+                    .into_iter()
+                    .rev()
+                    .filter(|c| {
+                        DateTime::parse_from_rfc3339(&c.datetime).is_ok_and(|t| t >= from && t <= to)
+                    })
+                    .collect())
+            }
+            #[cfg(feature = "sink-sqlite")]
+            Source::Sqlite(pool) => crate::sqlite_sink::between(pool, from.timestamp(), to.timestamp()).await,
+        }
+    }
+
+    async fn audio_path(&self, id: u64) -> Result<Option<PathBuf>, AppError> {
+        match self {
+            Source::Jsonl(path) => Ok(read_jsonl(path, "").await?.1.into_iter().nth(id as usize - 1).flatten()),
+            #[cfg(feature = "sink-sqlite")]
+            Source::Sqlite(pool) => crate::sqlite_sink::audio_path(pool, id).await,
+        }
+    }
+}
+
+/// A record's JSON (see `RadioRecord::to_json`) as a table row.
+fn call_from_json(id: u64, doc: &serde_json::Value) -> Call {
+    let s = |v: &serde_json::Value| v.as_str().unwrap_or_default().to_string();
+    let either = |key: &str| {
+        let one = s(&doc["slot1"][key]);
+        if one.is_empty() { s(&doc["slot2"][key]) } else { one }
+    };
+    let text = format!("{} {}", s(&doc["slot1"]["text"]), s(&doc["slot2"]["text"])).trim().to_string();
+    Call {
+        id,
+        datetime: s(&doc["datetime"]),
+        frequency: s(&doc["frequency"]),
+        tg: either("tg"),
+        rid: either("rid"),
+        duration: doc["duration"].as_i64().unwrap_or(0),
+        radio_type: doc["radio_type"].as_str().map(str::to_string),
+        text,
+        alert: doc["alert"].as_str().map(str::to_string),
+        encrypted: doc["encrypted"].as_bool().unwrap_or(false),
+        audio: doc["audio_path"].is_string(),
+    }
+}
+
+fn matches(c: &Call, filter: &str) -> bool {
+    filter.is_empty()
+        || [&c.datetime, &c.frequency, &c.tg, &c.rid, &c.text, c.alert.as_ref().unwrap_or(&String::new())]
+            .iter()
+            .any(|f| f.to_lowercase().contains(filter))
+}
+
+/// The calls in `path` matching `filter`, in file order, and every line's
+/// audio path (by line, so IDs stay line numbers). Lines that aren't records
+/// are skipped.
+async fn read_jsonl(path: &Path, filter: &str) -> Result<(Vec<Call>, Vec<Option<PathBuf>>), AppError> {
+    let body = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| AppError::IO(format!("read '{}': {}", path.display(), e)))?;
+    let filter = filter.to_lowercase();
+    let (mut calls, mut audio) = (Vec::new(), Vec::new());
+    for (i, line) in body.lines().enumerate() {
+        let doc: serde_json::Value = match serde_json::from_str(line) {
+            Ok(doc) => doc,
+            Err(e) => {
+                if !line.trim().is_empty() {
+                    debug!("serve: {}:{}: {}", path.display(), i + 1, e);
+                }
+                audio.push(None);
+                continue;
+            }
+        };
+        audio.push(doc["audio_path"].as_str().map(PathBuf::from));
+        let call = call_from_json(i as u64 + 1, &doc);
+        if matches(&call, &filter) {
+            calls.push(call);
+        }
+    }
+    Ok((calls, audio))
+}
+
+/// `%XX` and `+` decoding for query values.
+fn decode(s: &str) -> String {
+    let b = s.as_bytes();
+    let mut out = Vec::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        match b[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < b.len() => {
+                let hex = std::str::from_utf8(&b[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(v) => {
+                        out.push(v);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            c => out.push(c),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn query_param(query: &str, key: &str) -> Option<String> {
+    query.split('&').find_map(|kv| {
+        let (k, v) = kv.split_once('=').unwrap_or((kv, ""));
+        (k == key).then(|| decode(v))
+    })
+}
+
+fn audio_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase().as_str() {
+        "wav" => "audio/wav",
+        "mp3" => "audio/mpeg",
+        "flac" => "audio/flac",
+        "ogg" => "audio/ogg",
+        "m4a" => "audio/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: Vec<u8>) -> Self {
+        Self { status: "200 OK", content_type, body }
+    }
+
+    fn error(status: &'static str, msg: &str) -> Self {
+        Self { status, content_type: "text/plain; charset=utf-8", body: format!("{}\n", msg).into_bytes() }
+    }
+}
+
+fn json_response(v: &impl Serialize) -> Response {
+    Response::ok("application/json", serde_json::to_vec(v).unwrap_or_default())
+}
+
+fn server_error(e: AppError) -> Response {
+    warn!("serve: {}", e);
+    Response::error("500 Internal Server Error", &e.to_string())
+}
+
+/// What `serve` exposes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Endpoints {
+    /// `--static`: the viewer, `/api/calls` and `/audio/<id>`.
+    pub viewer: bool,
+    /// `--grafana`: the JSON datasource routes (see `grafana.rs`).
+    pub grafana: bool,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    body: Vec<u8>,
+}
+
+async fn viewer_route(source: &Source, req: &Request) -> Option<Response> {
+    let resp = match req.path.as_str() {
+        "/" | "/index.html" => Response::ok("text/html; charset=utf-8", assets::text("viewer.html").unwrap_or_default().as_bytes().to_vec()),
+        "/api/calls" => {
+            let filter = query_param(&req.query, "q").unwrap_or_default();
+            let number = |key, default| query_param(&req.query, key).and_then(|v| v.parse().ok()).unwrap_or(default);
+            let limit = number("limit", PAGE).clamp(1, MAX_PAGE);
+            match source.page(filter.trim(), limit, number("offset", 0)).await {
+                Ok(page) => json_response(&page),
+                Err(e) => server_error(e),
+            }
+        }
+        path => {
+            let id = path.strip_prefix("/audio/")?.parse::<u64>().ok().filter(|id| *id > 0)?;
+            match source.audio_path(id).await {
+                Ok(Some(p)) => match tokio::fs::read(&p).await {
+                    Ok(body) => Response::ok(audio_type(&p), body),
+                    Err(e) => {
+                        debug!("serve: audio {}: {}", p.display(), e);
+                        Response::error("404 Not Found", "recording missing")
+                    }
+                },
+                Ok(None) => Response::error("404 Not Found", "no recording for this call"),
+                Err(e) => server_error(e),
+            }
+        }
+    };
+    Some(if req.method == "GET" || req.method == "HEAD" {
+        resp
+    } else {
+        Response::error("405 Method Not Allowed", "only GET is supported here")
+    })
+}
+
+async fn grafana_route(source: &Source, req: &Request) -> Option<Response> {
+    let resp = match req.path.as_str() {
+        "/search" => json_response(&grafana::search()),
+        "/metrics" => json_response(&grafana::metrics()),
+        "/annotations" | "/metric-payload-options" | "/tag-keys" | "/tag-values" | "/variable" => {
+            json_response(&serde_json::json!([]))
+        }
+        "/query" => {
+            let q = match grafana::QueryRequest::parse(&req.body) {
+                Ok(q) => q,
+                Err(e) => return Some(Response::error("400 Bad Request", &e.to_string())),
+            };
+            let answer = match source.between(q.range.from, q.range.to).await {
+                Ok(calls) => grafana::query(&q, &calls),
+                Err(e) => return Some(server_error(e)),
+            };
+            match answer {
+                Ok(v) => json_response(&v),
+                Err(e) => Response::error("400 Bad Request", &e.to_string()),
+            }
+        }
+        _ => return None,
+    };
+    Some(resp)
+}
+
+async fn route(server: &Server, req: &Request) -> Response {
+    if server.endpoints.grafana
+        && let Some(resp) = grafana_route(&server.source, req).await
+    {
+        return resp;
+    }
+    if server.endpoints.viewer
+        && let Some(resp) = viewer_route(&server.source, req).await
+    {
+        return resp;
+    }
+    // The datasource's "Save & test" is a GET of the base URL; with the
+    // viewer on, that's the viewer page.
+    if server.endpoints.grafana && req.path == "/" {
+        return Response::ok("text/plain; charset=utf-8", b"OK\n".to_vec());
+    }
+    Response::error("404 Not Found", "not found")
+}
+
+struct Server {
+    source: Source,
+    endpoints: Endpoints,
+}
+
+/// Request head and, for POSTs, the `Content-Length` body.
+async fn read_request(sock: &mut TcpStream) -> std::io::Result<Option<Request>> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i + 4;
+        }
+        if buf.len() > MAX_REQUEST {
+            return Ok(None);
+        }
+        let n = sock.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    let head = String::from_utf8_lossy(&buf[..head_end]).into_owned();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or("/");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let length = lines
+        .filter_map(|l| l.split_once(':'))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    if length > MAX_BODY {
+        return Ok(None);
+    }
+    let mut body = buf[head_end..].to_vec();
+    while body.len() < length {
+        let n = sock.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(length);
+    Ok(Some(Request { method, path: path.to_string(), query: query.to_string(), body }))
+}
+
+/// One request per connection; the viewer's and Grafana's requests are few and small.
+async fn handle(mut sock: TcpStream, server: Arc<Server>) -> std::io::Result<()> {
+    let Some(req) = read_request(&mut sock).await? else {
+        return Ok(());
+    };
+    let resp = route(&server, &req).await;
+    debug!("serve: {} {} -> {}", req.method, req.path, resp.status);
+
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        resp.status,
+        resp.content_type,
+        resp.body.len()
+    );
+    sock.write_all(header.as_bytes()).await?;
+    if req.method != "HEAD" {
+        sock.write_all(&resp.body).await?;
+    }
+    sock.shutdown().await
+}
+
+/// Serve `endpoints` on `listen` until the process is stopped.
+pub async fn serve(listen: &str, source: Source, endpoints: Endpoints) -> Result<(), AppError> {
+    let listener = TcpListener::bind(listen)
+        .await
+        .map_err(|e| AppError::IO(format!("listen on {}: {}", listen, e)))?;
+    let addr = listener.local_addr().map_err(|e| AppError::IO(format!("listen on {}: {}", listen, e)))?;
+    if !addr.ip().is_loopback() {
+        warn!("serve: listening on {}, reachable from other machines; there is no authentication", addr);
+    }
+    if endpoints.viewer {
+        info!("Viewer at http://{}/", addr);
+    }
+    if endpoints.grafana {
+        info!("Grafana JSON datasource at http://{}/", addr);
+    }
+    info!("Serving {} (Ctrl-C to stop)", source.describe());
+    let server = Arc::new(Server { source, endpoints });
+    loop {
+        match listener.accept().await {
+            Ok((sock, peer)) => {
+                let server = Arc::clone(&server);
+                tokio::spawn(async move {
+                    if let Err(e) = handle(sock, server).await {
+                        debug!("serve: {}: {}", peer, e);
+                    }
+                });
+            }
+            Err(e) => warn!("serve: accept: {}", e),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+// src/severity.rs
+//
+// Triage score for each call (`severity`), from the config file's
+// `[severity]` rules, added up:
+//
+//   emergency   points for a call DSDPlus flagged as an emergency
+//   tg, rid     points per talk group / radio (IDs or `[alias]` names); a
+//               call on two listed TGs scores the higher one
+//   keywords    points per word or phrase in the transcript, matched
+//               case-insensitively as whole words, each counted once
+//   duration    points by call length: `{ 30 = 5, 120 = 15 }` gives 5 from
+//               30 s and 15 from two minutes
+//
+// Calls that match no rule score 0. The stage runs after transcription, so
+// keywords can count; with `--alert-severity N` the alert stage moves after
+// it too, and calls scoring N or more are alerted like watched ones.
+
+use crate::config::{Aliases, SeverityConfig};
+use crate::errors::AppError;
+use crate::model::{CallType, RadioRecord};
+use log::{info, warn};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::mpsc::{Receiver, Sender};
+
+/// `[severity]`, resolved and compiled.
+#[derive(Debug, Clone, Default)]
+pub struct SeverityRules {
+    emergency: u32,
+    tgs: BTreeMap<String, u32>,
+    rids: BTreeMap<String, u32>,
+    keywords: Vec<(Regex, u32)>,
+    /// (minimum seconds, points), shortest first.
+    durations: Vec<(u32, u32)>,
+}
+
+impl SeverityRules {
+    /// From `[severity]`; an unknown alias name or a duration that isn't a
+    /// number of seconds is an error.
+    pub fn new(cfg: &SeverityConfig, aliases: &Aliases) -> Result<Self, AppError> {
+        let resolve = |kind: &str, table: &BTreeMap<String, u32>, names: &BTreeMap<String, String>| {
+            table
+                .iter()
+                .map(|(name, points)| {
+                    let id = Aliases::resolve(names, name).ok_or_else(|| {
+                        AppError::Parse(format!("[severity] {}: '{}' is neither an ID nor a [alias.{}] name", kind, name, kind))
+                    })?;
+                    Ok((id, *points))
+                })
+                .collect::<Result<BTreeMap<_, _>, AppError>>()
+        };
+        let keywords = cfg
+            .keywords
+            .iter()
+            .filter(|(phrase, _)| !phrase.trim().is_empty())
+            .map(|(phrase, points)| {
+                let words: Vec<String> = phrase.split_whitespace().map(regex::escape).collect();
+                let re = Regex::new(&format!(r"(?i)\b{}\b", words.join(r"[\s-]+")))
+                    .map_err(|e| AppError::Parse(format!("[severity] keywords: '{}': {}", phrase, e)))?;
+                Ok((re, *points))
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+        let mut durations = cfg
+            .duration
+            .iter()
+            .map(|(secs, points)| {
+                let secs = secs.trim().parse::<u32>().map_err(|_| {
+                    AppError::Parse(format!("[severity] duration: '{}' is not a number of seconds", secs))
+                })?;
+                Ok((secs, *points))
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+        durations.sort_unstable();
+        Ok(Self {
+            emergency: cfg.emergency,
+            tgs: resolve("tg", &cfg.tg, &aliases.tg)?,
+            rids: resolve("rid", &cfg.rid, &aliases.rid)?,
+            keywords,
+            durations,
+        })
+    }
+
+    /// No rule can score a call.
+    pub fn is_empty(&self) -> bool {
+        self.emergency == 0
+            && self.tgs.values().all(|p| *p == 0)
+            && self.rids.values().all(|p| *p == 0)
+            && self.keywords.iter().all(|(_, p)| *p == 0)
+            && self.durations.iter().all(|(_, p)| *p == 0)
+    }
+
+    pub fn score(&self, rec: &RadioRecord) -> u32 {
+        let mut score = 0;
+        if rec.call_type == CallType::Emergency {
+            score += self.emergency;
+        }
+        let best = |table: &BTreeMap<String, u32>, ids: [Option<&String>; 2]| {
+            ids.into_iter().flatten().filter_map(|id| table.get(id)).copied().max().unwrap_or(0)
+        };
+        score += best(&self.tgs, [rec.slot1.tg.as_ref(), rec.slot2.tg.as_ref()]);
+        score += best(&self.rids, [rec.slot1.rid.as_ref(), rec.slot2.rid.as_ref()]);
+        let texts = [rec.slot1.text.as_deref(), rec.slot2.text.as_deref()];
+        for (re, points) in &self.keywords {
+            if texts.iter().flatten().any(|t| re.is_match(t)) {
+                score += points;
+            }
+        }
+        if let Some((_, points)) = self.durations.iter().rev().find(|(secs, _)| rec.duration >= *secs) {
+            score += points;
+        }
+        score
+    }
+}
+
+/// Forward every record with its `severity` set.
+pub async fn score_stream(
+    mut rx: Receiver<RadioRecord>,
+    tx: Sender<RadioRecord>,
+    rules: Arc<SeverityRules>,
+) -> Result<Vec<PathBuf>, AppError> {
+    let (mut calls, mut scored, mut highest) = (0usize, 0usize, 0u32);
+    while let Some(mut rec) = rx.recv().await {
+        let score = rules.score(&rec);
+        calls += 1;
+        if score > 0 {
+            scored += 1;
+            highest = highest.max(score);
+        }
+        rec.severity = Some(score);
+        if tx.send(rec).await.is_err() {
+            warn!("severity: downstream closed");
+            break;
+        }
+    }
+    info!("Severity: {} of {} calls scored, highest {}", scored, calls, highest);
+    Ok(vec![])
+}
@@ -0,0 +1,266 @@
+// src/sink.rs
+//
+// Output selection. Each sink is an `async fn` consuming the record channel;
+// `run_sink` dispatches to the one chosen with `--sink`.
+//
+// `--sink csv,influx,syslog` writes several outputs in one pass: `run_sinks`
+// puts a broadcast stage in front of them that hands every record to each
+// sink's own channel and task. A slow sink holds the others back once its
+// channel is full, so none of them misses records; one that fails is dropped
+// from the broadcast and its error reported when the rest have finished.
+// The sink counters (`--timing`, `--progress`) follow the first sink listed;
+// each sink's own count goes to `PipelineStats::sinks`, for the exit table
+// and the manifest.
+
+use crate::csv_sink::{self, CsvSinkOptions};
+use crate::errors::AppError;
+use crate::influx_sink::{self, InfluxSinkOptions};
+use crate::model::RadioRecord;
+use crate::stats::PipelineStats;
+use crate::syslog_sink::{self, SyslogSinkOptions};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use log::warn;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+#[cfg(feature = "sink-postgres")]
+use crate::pg_sink::{self, PgSink};
+#[cfg(feature = "sink-elasticsearch")]
+use crate::es_sink::{self, EsSink};
+#[cfg(feature = "sink-sqlite")]
+use crate::sqlite_sink::{self, SqliteSink};
+#[cfg(feature = "sink-template")]
+use crate::template_sink::{self, TemplateSink};
+
+/// How sinks write a call's duration (`--duration-format`). Text outputs
+/// (the CSV column, syslog messages) use it for `duration`, keeping raw
+/// seconds in `duration_s`; outputs with typed fields (InfluxDB,
+/// Elasticsearch) keep `duration` in seconds and add `duration_hms`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DurationFormat {
+    /// `157`
+    #[default]
+    Seconds,
+    /// `00:02:37`
+    Hms,
+}
+
+impl DurationFormat {
+    pub fn parse(s: &str) -> Result<Self, AppError> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "seconds" | "s" => Ok(DurationFormat::Seconds),
+            "hms" => Ok(DurationFormat::Hms),
+            _ => Err(AppError::Parse(format!("invalid --duration-format '{}': expected seconds or hms", s))),
+        }
+    }
+
+    pub fn format(self, seconds: u32) -> String {
+        match self {
+            DurationFormat::Seconds => seconds.to_string(),
+            DurationFormat::Hms => hms(seconds),
+        }
+    }
+}
+
+/// `HH:MM:SS`; hours keep counting past 99.
+pub fn hms(seconds: u32) -> String {
+    format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+#[derive(Clone, Debug)]
+pub enum SinkKind {
+    /// One CSV per input, next to it (default).
+    Csv(CsvSinkOptions),
+    /// InfluxDB line protocol, one `.lp` file per input.
+    Influx(InfluxSinkOptions),
+    /// RFC 5424 messages to a syslog collector.
+    Syslog(SyslogSinkOptions),
+    #[cfg(feature = "sink-postgres")]
+    Postgres(PgSink),
+    #[cfg(feature = "sink-elasticsearch")]
+    Elasticsearch(EsSink),
+    /// One SQLite database (with FTS5 transcript index) for all inputs.
+    #[cfg(feature = "sink-sqlite")]
+    Sqlite(SqliteSink),
+    /// Text rendered through a Tera template, one file per input.
+    #[cfg(feature = "sink-template")]
+    Template(TemplateSink),
+}
+
+impl SinkKind {
+    /// Names accepted by `--sink` in this build.
+    pub fn available() -> Vec<&'static str> {
+        vec![
+            "csv",
+            "influx",
+            "syslog",
+            #[cfg(feature = "sink-postgres")]
+            "postgres",
+            #[cfg(feature = "sink-elasticsearch")]
+            "elasticsearch",
+            #[cfg(feature = "sink-sqlite")]
+            "sqlite",
+            #[cfg(feature = "sink-template")]
+            "template",
+        ]
+    }
+
+    /// The `--sink` name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SinkKind::Csv(_) => "csv",
+            SinkKind::Influx(_) => "influx",
+            SinkKind::Syslog(_) => "syslog",
+            #[cfg(feature = "sink-postgres")]
+            SinkKind::Postgres(_) => "postgres",
+            #[cfg(feature = "sink-elasticsearch")]
+            SinkKind::Elasticsearch(_) => "elasticsearch",
+            #[cfg(feature = "sink-sqlite")]
+            SinkKind::Sqlite(_) => "sqlite",
+            #[cfg(feature = "sink-template")]
+            SinkKind::Template(_) => "template",
+        }
+    }
+
+    /// Whether the sink writes a file next to each input (`output_path`).
+    pub fn writes_file(&self) -> bool {
+        match self {
+            SinkKind::Csv(_) | SinkKind::Influx(_) => true,
+            #[cfg(feature = "sink-template")]
+            SinkKind::Template(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Extension of the per-input output file this sink writes next to each
+    /// input (ignored by non-file sinks).
+    pub fn extension(&self) -> &str {
+        match self {
+            SinkKind::Csv(_) => "csv",
+            SinkKind::Influx(_) => "lp",
+            SinkKind::Syslog(_) => "csv",
+            #[cfg(feature = "sink-postgres")]
+            SinkKind::Postgres(_) => "csv",
+            #[cfg(feature = "sink-elasticsearch")]
+            SinkKind::Elasticsearch(_) => "csv",
+            #[cfg(feature = "sink-sqlite")]
+            SinkKind::Sqlite(_) => "csv",
+            #[cfg(feature = "sink-template")]
+            SinkKind::Template(t) => t.extension(),
+        }
+    }
+}
+
+/// Where a file sink writes for `input`: next to it, with the sink's
+/// `extension`.
+pub fn output_path(input: &Path, extension: &str) -> PathBuf {
+    let out = input.with_extension(extension);
+    // A CSV input (Unitrunker) mustn't be overwritten by its own output.
+    if out == input { input.with_extension(format!("calls.{}", extension)) } else { out }
+}
+
+/// Hand every record to each of `txs`, in order, dropping those whose sink
+/// has stopped.
+async fn broadcast(mut rx: Receiver<RadioRecord>, mut txs: Vec<(&'static str, Sender<RadioRecord>)>) {
+    while let Some(rec) = rx.recv().await {
+        let mut i = 0;
+        while i < txs.len() {
+            if txs[i].1.send(rec.clone()).await.is_err() {
+                warn!("sink {} stopped; still writing the others", txs[i].0);
+                txs.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        if txs.is_empty() {
+            break;
+        }
+    }
+}
+
+/// Run the sinks in `kinds` on the same records (see the module comment).
+/// Returns the files they wrote.
+pub async fn run_sinks(
+    kinds: &[SinkKind],
+    in_path: &Path,
+    rx: Receiver<RadioRecord>,
+    stats: Arc<PipelineStats>,
+    capacity: usize,
+) -> Result<Vec<PathBuf>, AppError> {
+    if let [kind] = kinds {
+        return run_sink(kind, in_path, &output_path(in_path, kind.extension()), rx, stats).await;
+    }
+    let mut txs = Vec::with_capacity(kinds.len());
+    let mut tasks = Vec::with_capacity(kinds.len());
+    for (i, kind) in kinds.iter().enumerate() {
+        let (tx, rx) = mpsc::channel::<RadioRecord>(capacity);
+        txs.push((kind.name(), tx));
+        let kind = kind.clone();
+        let in_path = in_path.to_path_buf();
+        let own = (i > 0).then(|| Arc::new(PipelineStats::default()));
+        if let Ok(mut sinks) = stats.sinks.lock() {
+            sinks.push((kind.name(), own.clone()));
+        }
+        let stats = own.unwrap_or_else(|| Arc::clone(&stats));
+        tasks.push(tokio::spawn(async move {
+            run_sink(&kind, &in_path, &output_path(&in_path, kind.extension()), rx, stats).await
+        }));
+    }
+    broadcast(rx, txs).await;
+
+    let mut written = Vec::new();
+    let mut failed = None;
+    for (kind, task) in kinds.iter().zip(tasks) {
+        match task.await.unwrap_or_else(|e| Err(AppError::IO(format!("sink {} join: {e}", kind.name())))) {
+            Ok(files) => written.extend(files),
+            Err(e) => {
+                warn!("sink {} failed: {}", kind.name(), e);
+                failed.get_or_insert(e);
+            }
+        }
+    }
+    match failed {
+        Some(e) => Err(e),
+        None => Ok(written),
+    }
+}
+
+/// Drain `rx` into the selected sink. Returns the files written (empty for
+/// non-file sinks).
+#[cfg_attr(
+    not(any(feature = "sink-postgres", feature = "sink-elasticsearch", feature = "sink-sqlite")),
+    allow(unused_variables)
+)]
+pub async fn run_sink(
+    kind: &SinkKind,
+    in_path: &Path,
+    out_path: &Path,
+    rx: Receiver<RadioRecord>,
+    stats: Arc<PipelineStats>,
+) -> Result<Vec<PathBuf>, AppError> {
+    match kind {
+        SinkKind::Csv(opts) => csv_sink::write_csv_stream(out_path, rx, stats, opts.clone()).await,
+        SinkKind::Influx(opts) => influx_sink::write_influx_stream(out_path, rx, stats, opts).await,
+        SinkKind::Syslog(opts) => {
+            syslog_sink::write_syslog_stream(opts, rx, stats).await?;
+            Ok(vec![])
+        }
+        #[cfg(feature = "sink-postgres")]
+        SinkKind::Postgres(opts) => {
+            pg_sink::write_pg_stream(opts, in_path, rx, stats).await?;
+            Ok(vec![])
+        }
+        #[cfg(feature = "sink-elasticsearch")]
+        SinkKind::Elasticsearch(es) => {
+            es_sink::write_es_stream(es, in_path, rx, stats).await?;
+            Ok(vec![])
+        }
+        #[cfg(feature = "sink-sqlite")]
+        SinkKind::Sqlite(db) => {
+            sqlite_sink::write_sqlite_stream(db, in_path, rx, stats).await?;
+            Ok(vec![])
+        }
+        #[cfg(feature = "sink-template")]
+        SinkKind::Template(t) => template_sink::write_template_stream(t, out_path, rx, stats).await,
+    }
+}
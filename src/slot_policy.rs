@@ -0,0 +1,253 @@
+// src/slot_policy.rs
+//
+// Which slot an SRT detail line without a `Slot N` prefix (`TG=2 RID=4506`,
+// `RID=4506`) fills, by radio type:
+//
+//   slot1       everything goes to slot 1, the first value of each field
+//               kept: the mode has one voice channel, and a repeated line
+//               (late entry, a talker change) is the same call
+//   by_line     each line is one slot's call: its TG and RID go together to
+//               the first slot that has neither of them yet
+//   first_free  each TG and each RID goes on its own to the first slot
+//               without one (the parser's original rule)
+//
+// P25 Phase 1, NXDN, dPMR, ProVoice, D-STAR and YSF use `slot1`; DMR and
+// P25 Phase 2, which carry two slots, `by_line`; other types `first_free`.
+// The config file's `[slot_policy]` table sets others, keyed by radio type
+// as `[radio_type]` normalizes it:
+//
+// ```toml
+// [slot_policy]
+// "NXDN" = "by_line"
+// "TETRA" = "slot1"
+// ```
+
+use crate::errors::AppError;
+use crate::model::SlotData;
+use crate::radio_type::RadioTypes;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlotPolicy {
+    Slot1,
+    ByLine,
+    FirstFree,
+}
+
+impl SlotPolicy {
+    pub fn parse(s: &str) -> Result<Self, AppError> {
+        match s.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "slot1" | "slot_1" => Ok(SlotPolicy::Slot1),
+            "by_line" => Ok(SlotPolicy::ByLine),
+            "first_free" => Ok(SlotPolicy::FirstFree),
+            _ => Err(AppError::Parse(format!("slot policy '{}': expected slot1, by_line or first_free", s))),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SlotPolicy::Slot1 => "slot1",
+            SlotPolicy::ByLine => "by_line",
+            SlotPolicy::FirstFree => "first_free",
+        }
+    }
+
+    /// Put a line's `tg`/`rid` into a slot. Returns the values that found no
+    /// place (a slot already had them), for `explain`.
+    pub fn assign(
+        self,
+        slot1: &mut SlotData,
+        slot2: &mut SlotData,
+        tg: Option<String>,
+        rid: Option<String>,
+    ) -> Vec<String> {
+        let mut dropped = Vec::new();
+        match self {
+            SlotPolicy::Slot1 => {
+                fill(&mut slot1.tg, tg, "TG", &mut dropped);
+                fill(&mut slot1.rid, rid, "RID", &mut dropped);
+            }
+            SlotPolicy::ByLine => {
+                let free = |s: &SlotData| (tg.is_none() || s.tg.is_none()) && (rid.is_none() || s.rid.is_none());
+                let slot = if free(slot1) {
+                    Some(slot1)
+                } else if free(slot2) {
+                    Some(slot2)
+                } else {
+                    None
+                };
+                match slot {
+                    Some(s) => {
+                        fill(&mut s.tg, tg, "TG", &mut dropped);
+                        fill(&mut s.rid, rid, "RID", &mut dropped);
+                    }
+                    None => {
+                        dropped.extend(tg.map(|v| format!("TG={}", v)));
+                        dropped.extend(rid.map(|v| format!("RID={}", v)));
+                    }
+                }
+            }
+            SlotPolicy::FirstFree => {
+                if let Some(v) = tg {
+                    if slot1.tg.is_none() {
+                        slot1.tg = Some(v);
+                    } else {
+                        fill(&mut slot2.tg, Some(v), "TG", &mut dropped);
+                    }
+                }
+                if let Some(v) = rid {
+                    if slot1.rid.is_none() {
+                        slot1.rid = Some(v);
+                    } else {
+                        fill(&mut slot2.rid, Some(v), "RID", &mut dropped);
+                    }
+                }
+            }
+        }
+        dropped
+    }
+}
+
+fn fill(field: &mut Option<String>, value: Option<String>, name: &str, dropped: &mut Vec<String>) {
+    if let Some(v) = value {
+        if field.is_none() {
+            *field = Some(v);
+        } else {
+            dropped.push(format!("{}={}", name, v));
+        }
+    }
+}
+
+/// Built-in policies, by normalized radio type.
+const BUILT_IN: &[(&str, SlotPolicy)] = &[
+    ("P25p1", SlotPolicy::Slot1),
+    ("NXDN", SlotPolicy::Slot1),
+    ("dPMR", SlotPolicy::Slot1),
+    ("ProVoice", SlotPolicy::Slot1),
+    ("D-STAR", SlotPolicy::Slot1),
+    ("YSF", SlotPolicy::Slot1),
+    ("DMR", SlotPolicy::ByLine),
+    ("P25p2", SlotPolicy::ByLine),
+];
+
+#[derive(Debug, Clone)]
+pub struct SlotPolicies {
+    by_type: HashMap<String, SlotPolicy>,
+    radio_types: RadioTypes,
+}
+
+impl Default for SlotPolicies {
+    fn default() -> Self {
+        Self {
+            by_type: BUILT_IN.iter().map(|&(t, p)| (t.to_string(), p)).collect(),
+            radio_types: RadioTypes::default(),
+        }
+    }
+}
+
+impl SlotPolicies {
+    /// The built-in table with `[slot_policy]` on top; its keys and the
+    /// types looked up later go through `radio_types` first.
+    pub fn new(overrides: &BTreeMap<String, String>, radio_types: &RadioTypes) -> Result<Self, AppError> {
+        let mut by_type: HashMap<String, SlotPolicy> =
+            BUILT_IN.iter().map(|&(t, p)| (radio_types.normalize(t), p)).collect();
+        for (rtype, policy) in overrides {
+            let policy = SlotPolicy::parse(policy).map_err(|_| {
+                AppError::Parse(format!("[slot_policy] {}: '{}' is not slot1, by_line or first_free", rtype, policy))
+            })?;
+            by_type.insert(radio_types.normalize(rtype), policy);
+        }
+        Ok(Self { by_type, radio_types: radio_types.clone() })
+    }
+
+    /// The policy for a block of this radio type (as logged).
+    pub fn for_type(&self, rtype: Option<&str>) -> SlotPolicy {
+        rtype
+            .and_then(|t| self.by_type.get(&self.radio_types.normalize(t)))
+            .copied()
+            .unwrap_or(SlotPolicy::FirstFree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(v: &str) -> Option<String> {
+        Some(v.to_string())
+    }
+
+    /// Feed `lines` of `(tg, rid)` through `policy`; the two slots and what
+    /// was dropped.
+    fn run(policy: SlotPolicy, lines: &[(Option<&str>, Option<&str>)]) -> (SlotData, SlotData, Vec<String>) {
+        let (mut slot1, mut slot2) = (SlotData::default(), SlotData::default());
+        let mut dropped = Vec::new();
+        for (tg, rid) in lines {
+            dropped.extend(policy.assign(&mut slot1, &mut slot2, tg.map(str::to_string), rid.map(str::to_string)));
+        }
+        (slot1, slot2, dropped)
+    }
+
+    #[test]
+    fn built_in_policies_by_radio_type() {
+        let policies = SlotPolicies::default();
+        assert_eq!(policies.for_type(Some("+P25p1")), SlotPolicy::Slot1);
+        assert_eq!(policies.for_type(Some("NXDN")), SlotPolicy::Slot1);
+        assert_eq!(policies.for_type(Some("+DMR")), SlotPolicy::ByLine);
+        assert_eq!(policies.for_type(Some("P25p2")), SlotPolicy::ByLine);
+        assert_eq!(policies.for_type(Some("TETRA")), SlotPolicy::FirstFree);
+        assert_eq!(policies.for_type(None), SlotPolicy::FirstFree);
+    }
+
+    #[test]
+    fn p25_repeated_line_stays_in_slot1() {
+        let (slot1, slot2, dropped) =
+            run(SlotPolicy::Slot1, &[(Some("2"), Some("4506")), (Some("2"), Some("4507"))]);
+        assert_eq!((slot1.tg, slot1.rid), (s("2"), s("4506")));
+        assert_eq!((slot2.tg, slot2.rid), (None, None));
+        assert_eq!(dropped, ["TG=2", "RID=4507"]);
+    }
+
+    #[test]
+    fn nxdn_split_lines_fill_slot1() {
+        let (slot1, slot2, dropped) = run(SlotPolicy::Slot1, &[(Some("10"), None), (None, Some("100"))]);
+        assert_eq!((slot1.tg, slot1.rid), (s("10"), s("100")));
+        assert_eq!((slot2.tg, slot2.rid), (None, None));
+        assert!(dropped.is_empty());
+    }
+
+    #[test]
+    fn dmr_lines_keep_tg_and_rid_together() {
+        let (slot1, slot2, dropped) = run(
+            SlotPolicy::ByLine,
+            &[(Some("10"), Some("100")), (Some("20"), Some("200")), (Some("30"), Some("300"))],
+        );
+        assert_eq!((slot1.tg, slot1.rid), (s("10"), s("100")));
+        assert_eq!((slot2.tg, slot2.rid), (s("20"), s("200")));
+        assert_eq!(dropped, ["TG=30", "RID=300"]);
+    }
+
+    #[test]
+    fn dmr_rid_only_line_completes_its_slot() {
+        let (slot1, slot2, _) = run(SlotPolicy::ByLine, &[(Some("10"), None), (None, Some("100"))]);
+        assert_eq!((slot1.tg, slot1.rid), (s("10"), s("100")));
+        assert_eq!((slot2.tg, slot2.rid), (None, None));
+    }
+
+    #[test]
+    fn first_free_splits_fields() {
+        let (slot1, slot2, _) = run(SlotPolicy::FirstFree, &[(Some("10"), None), (Some("20"), Some("100"))]);
+        assert_eq!((slot1.tg, slot1.rid), (s("10"), s("100")));
+        assert_eq!((slot2.tg, slot2.rid), (s("20"), None));
+    }
+
+    #[test]
+    fn overrides_replace_built_ins() {
+        let overrides = BTreeMap::from([("NXDN".to_string(), "by-line".to_string())]);
+        let policies = SlotPolicies::new(&overrides, &RadioTypes::default()).unwrap();
+        assert_eq!(policies.for_type(Some("NXDN")), SlotPolicy::ByLine);
+        assert_eq!(policies.for_type(Some("P25p1")), SlotPolicy::Slot1);
+        let bad = BTreeMap::from([("NXDN".to_string(), "both".to_string())]);
+        assert!(SlotPolicies::new(&bad, &RadioTypes::default()).is_err());
+    }
+}
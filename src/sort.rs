@@ -0,0 +1,250 @@
+// src/sort.rs
+//
+// `--sort`: a second pass for inputs that are badly out of order. Every
+// record that passed the filter is buffered, the whole input is sorted by
+// timestamp, and only then does RLE (and everything after it) see it. Ties
+// keep their input order, so blocks logged in the same second stay in
+// sequence.
+//
+// Inputs larger than `--sort-memory` are sorted externally: each full buffer
+// is sorted and spilled to a run file (JSON lines) under `--sort-tmp`, and
+// the runs are merged back in timestamp order, no more than `MAX_FAN_IN` at
+// a time, so a 20 GB log sorts in a few hundred MB.
+
+use crate::budget::RECORD_BYTES_ESTIMATE;
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use crate::rotation::parse_size;
+use chrono::{DateTime, FixedOffset};
+use log::{debug, info, warn};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::mpsc::{Receiver, Sender};
+
+/// Run files open at once while merging.
+const MAX_FAN_IN: usize = 64;
+
+/// Default `--sort-memory`.
+pub const DEFAULT_MEMORY: &str = "256MB";
+
+/// Distinguishes the scratch directories of concurrent pipelines.
+static SCRATCH_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Debug)]
+pub struct SortOptions {
+    /// Records sorted in memory before a run is spilled.
+    pub chunk_records: usize,
+    /// Where run files go (`--sort-tmp`).
+    pub temp_dir: PathBuf,
+}
+
+impl SortOptions {
+    /// From `--sort-memory` (e.g. `512MB`; default [`DEFAULT_MEMORY`]) and `--sort-tmp`.
+    pub fn parse(memory: Option<&str>, temp_dir: Option<&Path>) -> Result<Self, AppError> {
+        let memory = memory.unwrap_or(DEFAULT_MEMORY);
+        let bytes = parse_size(memory)
+            .ok_or_else(|| AppError::Parse(format!("invalid --sort-memory '{}': expected a size like 512MB", memory)))?;
+        Ok(Self {
+            chunk_records: Self::records_for(bytes),
+            temp_dir: temp_dir.map_or_else(std::env::temp_dir, Path::to_path_buf),
+        })
+    }
+
+    fn records_for(bytes: u64) -> usize {
+        (bytes / RECORD_BYTES_ESTIMATE).max(1) as usize
+    }
+}
+
+/// Scratch directory for one pipeline's run files, removed on drop.
+struct Scratch {
+    dir: PathBuf,
+    runs: usize,
+}
+
+impl Scratch {
+    fn create(parent: &Path) -> Result<Self, AppError> {
+        let n = SCRATCH_SEQ.fetch_add(1, Ordering::Relaxed);
+        let dir = parent.join(format!("callscribe-sort-{}-{}", std::process::id(), n));
+        std::fs::create_dir_all(&dir).map_err(|e| AppError::IO(format!("--sort-tmp {}: {}", dir.display(), e)))?;
+        Ok(Self { dir, runs: 0 })
+    }
+
+    fn next_path(&mut self) -> PathBuf {
+        self.runs += 1;
+        self.dir.join(format!("run-{:06}.jsonl", self.runs))
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn io_err(path: &Path) -> impl Fn(std::io::Error) -> AppError + '_ {
+    move |e| AppError::IO(format!("sort run {}: {}", path.display(), e))
+}
+
+fn write_run(path: &Path, recs: &[RadioRecord]) -> Result<(), AppError> {
+    let mut w = BufWriter::new(File::create(path).map_err(io_err(path))?);
+    for r in recs {
+        serde_json::to_writer(&mut w, r).map_err(|e| AppError::IO(format!("sort run {}: {}", path.display(), e)))?;
+        w.write_all(b"\n").map_err(io_err(path))?;
+    }
+    w.flush().map_err(io_err(path))
+}
+
+/// Reads one run file back, record by record.
+struct RunReader {
+    path: PathBuf,
+    lines: Lines<BufReader<File>>,
+}
+
+impl RunReader {
+    fn open(path: &Path) -> Result<Self, AppError> {
+        let file = File::open(path).map_err(io_err(path))?;
+        Ok(Self { path: path.to_path_buf(), lines: BufReader::new(file).lines() })
+    }
+
+    fn next(&mut self) -> Result<Option<RadioRecord>, AppError> {
+        match self.lines.next() {
+            None => Ok(None),
+            Some(line) => {
+                let line = line.map_err(io_err(&self.path))?;
+                serde_json::from_str(&line)
+                    .map(Some)
+                    .map_err(|e| AppError::Parse(format!("sort run {}: {}", self.path.display(), e)))
+            }
+        }
+    }
+}
+
+/// K-way merge of sorted run files into `emit`, earliest first; on equal
+/// times the earlier run wins, which keeps input order. Stops early when
+/// `emit` returns false.
+fn merge(runs: &[PathBuf], mut emit: impl FnMut(RadioRecord) -> Result<bool, AppError>) -> Result<(), AppError> {
+    let mut readers = runs.iter().map(|p| RunReader::open(p)).collect::<Result<Vec<_>, _>>()?;
+    let mut heads: Vec<Option<RadioRecord>> = Vec::with_capacity(readers.len());
+    let mut heap: BinaryHeap<Reverse<(DateTime<FixedOffset>, usize)>> = BinaryHeap::new();
+    for (i, r) in readers.iter_mut().enumerate() {
+        let head = r.next()?;
+        if let Some(rec) = &head {
+            heap.push(Reverse((rec.datetime, i)));
+        }
+        heads.push(head);
+    }
+    while let Some(Reverse((_, i))) = heap.pop() {
+        let Some(rec) = heads[i].take() else { continue };
+        heads[i] = readers[i].next()?;
+        if let Some(next) = &heads[i] {
+            heap.push(Reverse((next.datetime, i)));
+        }
+        if !emit(rec)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Merge groups of `MAX_FAN_IN` runs into longer runs until one pass can
+/// merge the rest.
+fn reduce_runs(scratch: &mut Scratch, mut runs: Vec<PathBuf>) -> Result<Vec<PathBuf>, AppError> {
+    while runs.len() > MAX_FAN_IN {
+        let mut merged = Vec::with_capacity(runs.len().div_ceil(MAX_FAN_IN));
+        for group in runs.chunks(MAX_FAN_IN) {
+            let path = scratch.next_path();
+            let mut w = BufWriter::new(File::create(&path).map_err(io_err(&path))?);
+            merge(group, |rec| {
+                serde_json::to_writer(&mut w, &rec)
+                    .map_err(|e| AppError::IO(format!("sort run {}: {}", path.display(), e)))?;
+                w.write_all(b"\n").map_err(io_err(&path))?;
+                Ok(true)
+            })?;
+            w.flush().map_err(io_err(&path))?;
+            for p in group {
+                let _ = std::fs::remove_file(p);
+            }
+            merged.push(path);
+        }
+        debug!("sort: merged {} runs into {}", runs.len(), merged.len());
+        runs = merged;
+    }
+    Ok(runs)
+}
+
+/// Collect the whole stream, then forward it ordered by datetime, spilling
+/// to disk once more than `opts.chunk_records` records are buffered.
+pub async fn sort_stream(
+    mut rx: Receiver<RadioRecord>,
+    tx: Sender<RadioRecord>,
+    opts: SortOptions,
+) -> Result<(), AppError> {
+    let join = |e: tokio::task::JoinError| AppError::Other(format!("sort join: {e}"));
+    let mut buf = Vec::new();
+    let mut scratch: Option<Scratch> = None;
+    let mut runs = Vec::new();
+    let (mut total, mut moved) = (0usize, 0usize);
+    let mut last = None;
+    while let Some(rec) = rx.recv().await {
+        total += 1;
+        if last.is_some_and(|t| rec.datetime < t) {
+            moved += 1;
+        }
+        last = Some(rec.datetime);
+        buf.push(rec);
+        if buf.len() >= opts.chunk_records {
+            let mut s = match scratch.take() {
+                Some(s) => s,
+                None => Scratch::create(&opts.temp_dir)?,
+            };
+            let path = s.next_path();
+            let mut chunk = std::mem::take(&mut buf);
+            let p = path.clone();
+            tokio::task::spawn_blocking(move || {
+                chunk.sort_by_key(|r| r.datetime);
+                write_run(&p, &chunk)
+            })
+            .await
+            .map_err(join)??;
+            runs.push(path);
+            scratch = Some(s);
+        }
+    }
+    info!("Sorting {} records ({} were behind their predecessor)", total, moved);
+
+    let Some(mut scratch) = scratch else {
+        buf.sort_by_key(|r| r.datetime);
+        for rec in buf {
+            if tx.send(rec).await.is_err() {
+                warn!("sort: downstream closed");
+                break;
+            }
+        }
+        return Ok(());
+    };
+
+    if !buf.is_empty() {
+        buf.sort_by_key(|r| r.datetime);
+        let path = scratch.next_path();
+        write_run(&path, &buf)?;
+        runs.push(path);
+    }
+    drop(buf);
+    info!("sort: {} runs spilled to {}", runs.len(), scratch.dir.display());
+    tokio::task::spawn_blocking(move || {
+        let runs = reduce_runs(&mut scratch, runs)?;
+        merge(&runs, |rec| {
+            if tx.blocking_send(rec).is_err() {
+                warn!("sort: downstream closed");
+                return Ok(false);
+            }
+            Ok(true)
+        })
+    })
+    .await
+    .map_err(join)?
+}
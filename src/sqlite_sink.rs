@@ -0,0 +1,488 @@
+// src/sqlite_sink.rs
+//
+// SQLite sink (feature `sink-sqlite`). Every input is upserted into one
+// database file (`--out`, default `calls.db`) keyed on
+// (datetime, frequency, tg, rid), with an FTS5 index over transcript text
+// kept in sync by triggers so `callscribe search` can query it.
+// `--retain-days` prunes it (see `retention.rs`).
+
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use crate::retention::{Pruned, RetentionPolicy};
+use crate::serve::Call;
+use crate::stats::PipelineStats;
+use chrono::NaiveDateTime;
+use log::{debug, info};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
+
+/// SQLite's limit on bound variables in one statement (since 3.32).
+const MAX_VARIABLES: usize = 32_766;
+
+/// Rows per INSERT statement, one bind per entry of `COLUMNS` each.
+const BATCH: usize = 500;
+const _: () = assert!(BATCH * column_count(COLUMNS) <= MAX_VARIABLES, "BATCH rows bind more than SQLite takes");
+
+/// Entries of a comma-separated column list.
+const fn column_count(cols: &str) -> usize {
+    let bytes = cols.as_bytes();
+    let (mut n, mut i) = (1, 0);
+    while i < bytes.len() {
+        if bytes[i] == b',' {
+            n += 1;
+        }
+        i += 1;
+    }
+    n
+}
+
+#[derive(Clone, Debug)]
+pub struct SqliteSinkOptions {
+    pub path: PathBuf,
+    /// Also flush a partial batch whenever the input goes idle (follow mode).
+    pub flush_when_idle: bool,
+}
+
+const SCHEMA: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS calls (
+        id            INTEGER PRIMARY KEY,
+        datetime      TEXT NOT NULL,
+        ts            INTEGER NOT NULL,
+        frequency     TEXT NOT NULL DEFAULT '',
+        tg            TEXT NOT NULL DEFAULT '',
+        rid           TEXT NOT NULL DEFAULT '',
+        record_number INTEGER,
+        duration      INTEGER,
+        radio_type    TEXT,
+        dcc           TEXT,
+        slot1_tg      TEXT,
+        slot1_rid     TEXT,
+        slot1_text    TEXT,
+        slot2_tg      TEXT,
+        slot2_rid     TEXT,
+        slot2_text    TEXT,
+        audio_path    TEXT,
+        source        TEXT,
+        record_id     TEXT,
+        call_type     TEXT,
+        target_rid    TEXT,
+        errors        INTEGER,
+        quality       INTEGER,
+        extras        TEXT,
+        nac           TEXT,
+        color_code    TEXT,
+        alert         TEXT,
+        encrypted     INTEGER,
+        severity      INTEGER,
+        UNIQUE (ts, frequency, tg, rid)
+    )",
+    "CREATE VIRTUAL TABLE IF NOT EXISTS calls_fts USING fts5(
+        slot1_text, slot2_text, content='calls', content_rowid='id'
+    )",
+    "CREATE TRIGGER IF NOT EXISTS calls_ai AFTER INSERT ON calls BEGIN
+        INSERT INTO calls_fts(rowid, slot1_text, slot2_text) VALUES (new.id, new.slot1_text, new.slot2_text);
+    END",
+    "CREATE TRIGGER IF NOT EXISTS calls_ad AFTER DELETE ON calls BEGIN
+        INSERT INTO calls_fts(calls_fts, rowid, slot1_text, slot2_text) VALUES ('delete', old.id, old.slot1_text, old.slot2_text);
+    END",
+    "CREATE TRIGGER IF NOT EXISTS calls_au AFTER UPDATE ON calls BEGIN
+        INSERT INTO calls_fts(calls_fts, rowid, slot1_text, slot2_text) VALUES ('delete', old.id, old.slot1_text, old.slot2_text);
+        INSERT INTO calls_fts(rowid, slot1_text, slot2_text) VALUES (new.id, new.slot1_text, new.slot2_text);
+    END",
+];
+
+/// Columns added to `calls` after its first release, with their types.
+const ADDED_COLUMNS: &[(&str, &str)] = &[
+    ("record_id", "TEXT"),
+    ("call_type", "TEXT"),
+    ("target_rid", "TEXT"),
+    ("errors", "INTEGER"),
+    ("quality", "INTEGER"),
+    ("extras", "TEXT"),
+    ("nac", "TEXT"),
+    ("color_code", "TEXT"),
+    ("alert", "TEXT"),
+    ("encrypted", "INTEGER"),
+    ("severity", "INTEGER"),
+];
+
+/// Every column of `calls` but `id`, in the order rows are written.
+const COLUMNS: &str = "datetime, ts, frequency, tg, rid, record_number, duration, radio_type, dcc, \
+    slot1_tg, slot1_rid, slot1_text, slot2_tg, slot2_rid, slot2_text, audio_path, source, record_id, \
+    call_type, target_rid, errors, quality, extras, nac, color_code, alert, encrypted, severity";
+
+/// Open (creating if needed) the database at `path` with the calls schema.
+/// A single connection serialises writers from concurrent pipelines.
+pub async fn open_db(path: &Path, create: bool) -> Result<SqlitePool, AppError> {
+    let opts = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(create)
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(Duration::from_secs(30));
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(opts)
+        .await
+        .map_err(|e| AppError::IO(format!("sqlite open '{}': {}", path.display(), e)))?;
+    for ddl in SCHEMA {
+        sqlx::query(ddl)
+            .execute(&pool)
+            .await
+            .map_err(|e| AppError::IO(format!("sqlite schema: {}", e)))?;
+    }
+    // Databases written before these columns existed.
+    add_columns(&pool, "calls").await?;
+    sqlx::query("CREATE INDEX IF NOT EXISTS calls_record_id ON calls (record_id)")
+        .execute(&pool)
+        .await
+        .map_err(|e| AppError::IO(format!("sqlite schema: {}", e)))?;
+    Ok(pool)
+}
+
+/// Add the `ADDED_COLUMNS` that `table` lacks.
+async fn add_columns(pool: &SqlitePool, table: &str) -> Result<(), AppError> {
+    let cols: Vec<String> = sqlx::query(&format!("SELECT name FROM pragma_table_info('{}')", table))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| AppError::IO(format!("sqlite schema: {}", e)))?
+        .iter()
+        .map(|c| c.get(0))
+        .collect();
+    for (c, ty) in ADDED_COLUMNS.iter().filter(|(c, _)| !cols.iter().any(|have| have == c)) {
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, c, ty))
+            .execute(pool)
+            .await
+            .map_err(|e| AppError::IO(format!("sqlite schema: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Database handle shared by every pipeline.
+#[derive(Clone, Debug)]
+pub struct SqliteSink {
+    pool: SqlitePool,
+    opts: SqliteSinkOptions,
+}
+
+impl SqliteSink {
+    pub async fn open(opts: SqliteSinkOptions) -> Result<Self, AppError> {
+        let pool = open_db(&opts.path, true).await?;
+        Ok(Self { pool, opts })
+    }
+
+    pub async fn prune(&self, policy: &RetentionPolicy) -> Result<Pruned, AppError> {
+        prune(&self.pool, policy).await
+    }
+}
+
+async fn flush_batch(pool: &SqlitePool, source: &str, batch: &mut Vec<RadioRecord>) -> Result<(), AppError> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+    let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new(format!("INSERT INTO calls ({COLUMNS}) "));
+    qb.push_values(batch.iter(), |mut b, r| {
+        let tg = r.slot1.tg.as_ref().or(r.slot2.tg.as_ref()).cloned().unwrap_or_default();
+        let rid = r.slot1.rid.as_ref().or(r.slot2.rid.as_ref()).cloned().unwrap_or_default();
+        b.push_bind(r.datetime.to_rfc3339())
+            .push_bind(r.datetime.timestamp())
+            .push_bind(r.frequency.clone().unwrap_or_default())
+            .push_bind(tg)
+            .push_bind(rid)
+            .push_bind(r.record_number as i64)
+            .push_bind(r.duration as i64)
+            .push_bind(r.radio_type.clone())
+            .push_bind(r.nac_or_dcc().map(str::to_string))
+            .push_bind(r.slot1.tg.clone())
+            .push_bind(r.slot1.rid.clone())
+            .push_bind(r.slot1.text.clone())
+            .push_bind(r.slot2.tg.clone())
+            .push_bind(r.slot2.rid.clone())
+            .push_bind(r.slot2.text.clone())
+            .push_bind(r.audio_path.as_ref().map(|p| p.display().to_string()))
+            .push_bind(source.to_string())
+            .push_bind(r.record_id())
+            .push_bind(r.call_type.as_str())
+            .push_bind(r.target_rid.clone())
+            .push_bind(r.errors.map(i64::from))
+            .push_bind(r.quality.map(i64::from))
+            .push_bind((!r.extras.is_empty()).then(|| serde_json::json!(r.extras).to_string()))
+            .push_bind(r.nac.clone())
+            .push_bind(r.dcc.clone())
+            .push_bind(r.alert.clone())
+            .push_bind(r.encrypted)
+            .push_bind(r.severity.map(i64::from));
+    });
+    // Same merge rules as the PostgreSQL sink: never lose data on re-runs.
+    qb.push(
+        " ON CONFLICT (ts, frequency, tg, rid) DO UPDATE SET \
+         duration   = MAX(calls.duration, excluded.duration), \
+         radio_type = COALESCE(excluded.radio_type, calls.radio_type), \
+         dcc        = COALESCE(excluded.dcc, calls.dcc), \
+         slot1_text = COALESCE(excluded.slot1_text, calls.slot1_text), \
+         slot2_text = COALESCE(excluded.slot2_text, calls.slot2_text), \
+         audio_path = COALESCE(excluded.audio_path, calls.audio_path), \
+         record_id  = COALESCE(calls.record_id, excluded.record_id), \
+         call_type  = excluded.call_type, \
+         target_rid = COALESCE(excluded.target_rid, calls.target_rid), \
+         errors     = COALESCE(excluded.errors, calls.errors), \
+         quality    = COALESCE(excluded.quality, calls.quality), \
+         extras     = COALESCE(excluded.extras, calls.extras), \
+         nac        = COALESCE(excluded.nac, calls.nac), \
+         color_code = COALESCE(excluded.color_code, calls.color_code), \
+         alert      = COALESCE(excluded.alert, calls.alert), \
+         encrypted  = COALESCE(excluded.encrypted, calls.encrypted), \
+         severity   = COALESCE(excluded.severity, calls.severity)",
+    );
+    qb.build()
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::IO(format!("sqlite upsert: {}", e)))?;
+    debug!("sqlite_sink: upserted {} rows", batch.len());
+    batch.clear();
+    Ok(())
+}
+
+pub async fn write_sqlite_stream(
+    sink: &SqliteSink,
+    source: &Path,
+    mut rx: Receiver<RadioRecord>,
+    stats: Arc<PipelineStats>,
+) -> Result<(), AppError> {
+    let _timer = stats.sink.timer();
+    let source = source.display().to_string();
+    let mut batch: Vec<RadioRecord> = Vec::with_capacity(BATCH);
+    let mut count: usize = 0;
+
+    while let Some(r) = stats.sink.recv(&mut rx).await {
+        stats.sink.inc_in();
+        batch.push(r);
+        if batch.len() >= BATCH || (sink.opts.flush_when_idle && rx.is_empty()) {
+            let n = batch.len();
+            flush_batch(&sink.pool, &source, &mut batch).await?;
+            count += n;
+            stats.sink.add_out(n as u64);
+        }
+    }
+    let n = batch.len();
+    flush_batch(&sink.pool, &source, &mut batch).await?;
+    count += n;
+    stats.sink.add_out(n as u64);
+
+    info!("SQLite upserted {} rows into {}", count, sink.opts.path.display());
+    Ok(())
+}
+
+/// Delete the calls `policy` has expired, or with `archive` move them into
+/// `calls_archive` (the `calls` schema without the FTS index: archived calls
+/// aren't searched), in one transaction. A call already archived by an
+/// earlier pass (the same log imported again) is just deleted.
+pub async fn prune(pool: &SqlitePool, policy: &RetentionPolicy) -> Result<Pruned, AppError> {
+    let err = |e: sqlx::Error| AppError::IO(format!("sqlite retention: {}", e));
+    let expired = if policy.keep_alerts { "ts < ? AND COALESCE(alert, '') = ''" } else { "ts < ?" };
+    let cutoff = policy.cutoff().timestamp();
+    if policy.archive {
+        sqlx::query(&SCHEMA[0].replacen("calls", "calls_archive", 1))
+            .execute(pool)
+            .await
+            .map_err(err)?;
+        add_columns(pool, "calls_archive").await?;
+    }
+    let mut pruned = Pruned::default();
+    let mut tx = pool.begin().await.map_err(err)?;
+    if policy.archive {
+        let copy = format!("INSERT OR IGNORE INTO calls_archive ({COLUMNS}) SELECT {COLUMNS} FROM calls WHERE {expired}");
+        pruned.archived = sqlx::query(&copy).bind(cutoff).execute(&mut *tx).await.map_err(err)?.rows_affected();
+    }
+    pruned.deleted = sqlx::query(&format!("DELETE FROM calls WHERE {expired}"))
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await
+        .map_err(err)?
+        .rows_affected();
+    tx.commit().await.map_err(err)?;
+    Ok(pruned)
+}
+
+/// One row returned by [`search`].
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub datetime: String,
+    pub frequency: String,
+    pub tg: String,
+    pub rid: String,
+    pub duration: i64,
+    pub text: String,
+    pub audio_path: Option<String>,
+}
+
+/// Full-text query (FTS5 syntax: `water main`, `"water main"`, `water OR fire`)
+/// over transcripts in a database written by this sink, oldest first.
+pub async fn search(db: &Path, query: &str, limit: usize) -> Result<Vec<SearchHit>, AppError> {
+    if !db.exists() {
+        return Err(AppError::IO(format!("no such database '{}'", db.display())));
+    }
+    let pool = open_db(db, false).await?;
+    let rows = sqlx::query(
+        "SELECT c.datetime, c.frequency, c.tg, c.rid, c.duration, \
+                TRIM(COALESCE(c.slot1_text, '') || ' ' || COALESCE(c.slot2_text, '')) AS text, c.audio_path \
+         FROM calls_fts JOIN calls c ON c.id = calls_fts.rowid \
+         WHERE calls_fts MATCH ?1 ORDER BY c.ts, c.id LIMIT ?2",
+    )
+    .bind(query)
+    .bind(limit as i64)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| AppError::Other(format!("search '{}': {}", query, e)))?;
+
+    Ok(rows
+        .iter()
+        .map(|r| SearchHit {
+            datetime: r.get(0),
+            frequency: r.get(1),
+            tg: r.get(2),
+            rid: r.get(3),
+            duration: r.get::<Option<i64>, _>(4).unwrap_or(0),
+            text: r.get(5),
+            audio_path: r.get(6),
+        })
+        .collect())
+}
+
+/// Calls per talkgroup and wall-clock hour, for `anomalies`.
+pub async fn hourly_counts(db: &Path) -> Result<Vec<(String, NaiveDateTime, u64)>, AppError> {
+    if !db.exists() {
+        return Err(AppError::IO(format!("no such database '{}'", db.display())));
+    }
+    let pool = open_db(db, false).await?;
+    let rows = sqlx::query("SELECT tg, substr(datetime, 1, 13), COUNT(*) FROM calls GROUP BY 1, 2")
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| AppError::Other(format!("hourly counts: {}", e)))?;
+    Ok(rows
+        .iter()
+        .filter_map(|r| {
+            let hour = crate::anomaly::parse_hour(&r.get::<String, _>(1))?;
+            Some((r.get(0), hour, r.get::<i64, _>(2) as u64))
+        })
+        .collect())
+}
+
+/// Rows that have no transcript yet, oldest first, rebuilt as records so a
+/// [`Transcriber`](crate::transcriber::Transcriber) can look them up.
+pub async fn untranscribed(pool: &SqlitePool) -> Result<Vec<(i64, RadioRecord)>, AppError> {
+    let rows = sqlx::query(
+        "SELECT id, datetime, record_number, duration, frequency, radio_type, dcc, \
+                slot1_tg, slot1_rid, slot2_tg, slot2_rid, audio_path \
+         FROM calls WHERE slot1_text IS NULL AND slot2_text IS NULL ORDER BY ts, id",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::IO(format!("sqlite select: {}", e)))?;
+
+    let mut out = Vec::with_capacity(rows.len());
+    for r in &rows {
+        let id: i64 = r.get(0);
+        let dt_s: String = r.get(1);
+        let datetime = chrono::DateTime::parse_from_rfc3339(&dt_s)
+            .map_err(|e| AppError::Parse(format!("row {}: datetime '{}': {}", id, dt_s, e)))?;
+        let freq: String = r.get(4);
+        let mut rec = RadioRecord {
+            duration: r.get::<Option<i64>, _>(3).unwrap_or(1) as u32,
+            frequency: (!freq.is_empty()).then_some(freq),
+            radio_type: r.get(5),
+            slot1: crate::model::SlotData { tg: r.get(7), rid: r.get(8), text: None },
+            slot2: crate::model::SlotData { tg: r.get(9), rid: r.get(10), text: None },
+            audio_path: r.get::<Option<String>, _>(11).map(PathBuf::from),
+            ..RadioRecord::new(r.get::<Option<i64>, _>(2).unwrap_or(0) as usize, datetime)
+        };
+        rec.set_nac_or_dcc(r.get(6));
+        out.push((id, rec));
+    }
+    Ok(out)
+}
+
+/// Store a backfilled transcript; the FTS index follows via trigger.
+pub async fn set_transcript(pool: &SqlitePool, id: i64, text: &str) -> Result<(), AppError> {
+    sqlx::query("UPDATE calls SET slot1_text = ?1 WHERE id = ?2")
+        .bind(text)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::IO(format!("sqlite update row {}: {}", id, e)))?;
+    Ok(())
+}
+
+/// Columns read into a [`Call`] by [`call_row`].
+const CALL_COLUMNS: &str = "id, datetime, frequency, tg, rid, duration, radio_type, \
+     TRIM(COALESCE(slot1_text, '') || ' ' || COALESCE(slot2_text, '')), alert, encrypted, audio_path";
+
+fn call_row(r: &sqlx::sqlite::SqliteRow) -> Call {
+    Call {
+        id: r.get::<i64, _>(0) as u64,
+        datetime: r.get(1),
+        frequency: r.get(2),
+        tg: r.get(3),
+        rid: r.get(4),
+        duration: r.get::<Option<i64>, _>(5).unwrap_or(0),
+        radio_type: r.get(6),
+        text: r.get::<String, _>(7).trim().to_string(),
+        alert: r.get(8),
+        encrypted: r.get::<Option<bool>, _>(9).unwrap_or(false),
+        audio: r.get::<Option<String>, _>(10).is_some(),
+    }
+}
+
+/// A page of calls for `serve --static`, newest first, and how many match in
+/// all. `filter` is a case-insensitive substring of the time, frequency,
+/// talkgroup, radio ID, alert or transcript; empty matches everything.
+pub async fn browse(pool: &SqlitePool, filter: &str, limit: usize, offset: usize) -> Result<(u64, Vec<Call>), AppError> {
+    const WHERE: &str = "WHERE ?1 = '' OR instr(lower(datetime || ' ' || frequency || ' ' || tg || ' ' || rid || ' ' || \
+         COALESCE(alert, '') || ' ' || COALESCE(slot1_text, '') || ' ' || COALESCE(slot2_text, '')), lower(?1)) > 0";
+    let err = |e: sqlx::Error| AppError::IO(format!("sqlite select: {}", e));
+    let total: i64 = sqlx::query(&format!("SELECT COUNT(*) FROM calls {}", WHERE))
+        .bind(filter)
+        .fetch_one(pool)
+        .await
+        .map_err(err)?
+        .get(0);
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM calls {} ORDER BY ts DESC, id DESC LIMIT ?2 OFFSET ?3",
+        CALL_COLUMNS, WHERE
+    ))
+    .bind(filter)
+    .bind(limit as i64)
+    .bind(offset as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(err)?;
+    Ok((total as u64, rows.iter().map(call_row).collect()))
+}
+
+/// Calls starting in `from..=to` (Unix seconds), newest first, for the
+/// Grafana endpoints of `serve`.
+pub async fn between(pool: &SqlitePool, from: i64, to: i64) -> Result<Vec<Call>, AppError> {
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM calls WHERE ts BETWEEN ?1 AND ?2 ORDER BY ts DESC, id DESC",
+        CALL_COLUMNS
+    ))
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::IO(format!("sqlite select: {}", e)))?;
+    Ok(rows.iter().map(call_row).collect())
+}
+
+/// The recording stored for row `id`, if any.
+pub async fn audio_path(pool: &SqlitePool, id: u64) -> Result<Option<PathBuf>, AppError> {
+    let row = sqlx::query("SELECT audio_path FROM calls WHERE id = ?1")
+        .bind(id as i64)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AppError::IO(format!("sqlite select: {}", e)))?;
+    Ok(row.and_then(|r| r.get::<Option<String>, _>(0)).map(PathBuf::from))
+}
@@ -1,14 +1,15 @@
 use crate::errors::AppError;
-use crate::model::{RadioRecord, SlotData};
-use chrono::{DateTime, FixedOffset, Local, TimeZone};
+use crate::input::{self, NumberedLines, ParseNote, ParseOptions, TimeOnlyClock};
+use crate::model::{CallType, RadioRecord, SlotData};
+use chrono::{DateTime, FixedOffset, Local, NaiveTime, TimeZone};
 use log::{debug, trace, warn};
+use std::collections::BTreeMap;
 use std::path::Path;
-use tokio::fs::File;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 
 #[inline]
-fn strip_bom<'a>(s: &'a str) -> &'a str {
+fn strip_bom(s: &str) -> &str {
     s.strip_prefix('\u{FEFF}').unwrap_or(s)
 }
 
@@ -32,7 +33,7 @@ fn apply_tz(
 }
 
 #[inline]
-fn parse_freq_type_dcc(line: &str) -> (Option<String>, Option<String>, Option<String>) {
+fn parse_freq_type_dcc(line: &str) -> (Option<String>, Option<String>, Option<String>, Option<String>) {
     let s = strip_bom(line.trim());
     let mut it = s.split_whitespace().peekable();
 
@@ -44,7 +45,7 @@ fn parse_freq_type_dcc(line: &str) -> (Option<String>, Option<String>, Option<St
 
     let mut rtype: Option<String> = None;
     let mut parts: Vec<String> = Vec::new();
-    while let Some(tok) = it.next() {
+    for tok in it.by_ref() {
         if tok.starts_with('+') {
             parts.push(tok.to_string());
             break;
@@ -62,22 +63,13 @@ fn parse_freq_type_dcc(line: &str) -> (Option<String>, Option<String>, Option<St
         rtype = Some(guarded);
     }
 
-    let mut dcc_or_nac: Option<String> = None;
-    if let Some(i) = s.find("DCC=") {
-        let val = s[i + 4..].split_whitespace().next().unwrap_or("").to_string();
-        if !val.is_empty() {
-            dcc_or_nac = Some(val);
-        }
-    }
-    if dcc_or_nac.is_none() {
-        if let Some(i) = s.find("NAC=") {
-            let val = s[i + 4..].split_whitespace().next().unwrap_or("").to_string();
-            if !val.is_empty() {
-                dcc_or_nac = Some(val);
-            }
-        }
-    }
-    (freq, rtype, dcc_or_nac)
+    let value = |key: &str| {
+        s.find(key)
+            .and_then(|i| s[i + key.len()..].split_whitespace().next())
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+    (freq, rtype, value("NAC="), value("DCC="))
 }
 
 #[inline]
@@ -88,24 +80,37 @@ fn parse_tg_rid(s: &str) -> (Option<String>, Option<String>) {
     for tok in s.split_whitespace() {
         if let Some(rest) = tok.strip_prefix("TG=") {
             if !rest.is_empty() { tg = Some(rest.to_string()); }
-        } else if let Some(rest) = tok.strip_prefix("RID=") {
-            if !rest.is_empty() { rid = Some(rest.to_string()); }
+        } else if let Some(rest) = tok.strip_prefix("RID=")
+            && !rest.is_empty()
+        {
+            rid = Some(rest.to_string());
         }
     }
     (tg, rid)
 }
 
+/// Whether the start of a file looks like an SRT log: a block index, then a
+/// `-->` timerange.
+pub fn sniff(head: &str) -> bool {
+    let mut lines = head.lines().map(|l| strip_bom(l.trim())).filter(|l| !l.is_empty());
+    match (lines.next(), lines.next()) {
+        (Some(idx), Some(range)) => idx.bytes().all(|b| b.is_ascii_digit()) && range.contains("-->"),
+        _ => false,
+    }
+}
+
 pub async fn stream_file(
     path: &Path,
-    tz_offset: Option<FixedOffset>,
+    opts: ParseOptions,
     tx: Sender<RadioRecord>,
 ) -> Result<(), AppError> {
-    let file = File::open(path)
-        .await
-        .map_err(|e| AppError::IO(format!("open {}: {}", path.display(), e)))?;
-    let mut r = BufReader::new(file).lines();
+    let file = input::open_input(path, &opts).await?;
+    let mut r = NumberedLines::new(file, &opts);
 
     const ABS_DT_FMT: &str = "%Y/%m/%d %H:%M:%S";
+    const TIME_ONLY_FMT: &str = "%H:%M:%S";
+    let mut clock = opts.base_date.map(TimeOnlyClock::new);
+    let source = input::source_name(path);
 
     loop {
         // 1) index
@@ -116,42 +121,67 @@ pub async fn stream_file(
         if idx_line.trim().is_empty() {
             continue;
         }
+        let block_line = r.line;
+        let skip = |reason: String| opts.note(ParseNote::Skipped { line: block_line, reason });
         let idx_raw = strip_bom(idx_line.trim());
         let record_number = match idx_raw.parse::<usize>() {
             Ok(v) => v,
             Err(_) => {
                 debug!("non-numeric index: {:?}", idx_raw);
-                drain_block(&mut r).await?;
+                skip(format!("block index {:?} is not a number", idx_raw));
+                drain_block(&mut r, opts.lenient).await?;
                 continue;
             }
         };
         trace!("block start: index={}", record_number);
 
         // 2) timerange (ignored)
-        let _timerange = match r.next_line().await? {
+        let timerange = match r.next_line().await? {
             Some(s) => s,
             None => break,
         };
+        if opts.lenient && !timerange.contains("-->") {
+            debug!("block index={} has no timerange; resynchronising", record_number);
+            skip(format!("block {} has no timerange line", record_number));
+            opts.recovered();
+            r.unread(timerange);
+            drain_block(&mut r, true).await?;
+            continue;
+        }
 
         // 3) absolute datetime
         let dt_line = match r.next_line().await? {
             Some(s) => s,
             None => break,
         };
+        if opts.lenient && starts_block(&mut r, &dt_line).await? {
+            skip(format!("block {} ends before its datetime line", record_number));
+            opts.recovered();
+            continue;
+        }
         let dt_s = strip_bom(dt_line.trim());
-        let naive_dt = match chrono::NaiveDateTime::parse_from_str(dt_s, ABS_DT_FMT) {
-            Ok(ndt) => ndt,
-            Err(_) => {
+        let parsed = chrono::NaiveDateTime::parse_from_str(dt_s, ABS_DT_FMT).ok().or_else(|| {
+            let clock = clock.as_mut()?;
+            NaiveTime::parse_from_str(dt_s, TIME_ONLY_FMT).ok().map(|t| clock.resolve(t))
+        });
+        let naive_dt = match parsed {
+            Some(ndt) => ndt,
+            None => {
                 debug!("discarding block index={} — bad datetime line: {:?}", record_number, dt_s);
-                drain_block(&mut r).await?;
+                skip(match clock {
+                    Some(_) => format!("datetime {:?} is neither YYYY/MM/DD HH:MM:SS nor HH:MM:SS", dt_s),
+                    None => format!("datetime {:?} is not YYYY/MM/DD HH:MM:SS (time-only needs --date-from-filename)", dt_s),
+                });
+                drain_block(&mut r, opts.lenient).await?;
                 continue;
             }
         };
-        let datetime = match apply_tz(naive_dt, tz_offset) {
+        let datetime = match apply_tz(naive_dt, opts.tz_offset) {
             Ok(dt) => dt,
             Err(e) => {
                 debug!("discarding block index={} — tz error: {}", record_number, e);
-                drain_block(&mut r).await?;
+                skip(format!("datetime {:?}: {}", dt_s, e));
+                drain_block(&mut r, opts.lenient).await?;
                 continue;
             }
         };
@@ -161,18 +191,63 @@ pub async fn stream_file(
             Some(s) => s,
             None => break,
         };
-        let (frequency, radio_type, dcc) = parse_freq_type_dcc(&freq_line);
+        if opts.lenient && starts_block(&mut r, &freq_line).await? {
+            skip(format!("block {} ends before its frequency line", record_number));
+            opts.recovered();
+            continue;
+        }
+        let (frequency, radio_type, nac, dcc) = parse_freq_type_dcc(&freq_line);
+        let policy = opts.slot_policies.for_type(radio_type.as_deref());
 
         // 5+) details: until blank or EOF
-        let mut slot1 = SlotData { tg: None, rid: None, text: None };
-        let mut slot2 = SlotData { tg: None, rid: None, text: None };
+        let mut slot1 = SlotData::default();
+        let mut slot2 = SlotData::default();
+        let mut call_type = CallType::detect(&freq_line);
+        let mut target_rid = None;
+        let mut encrypted = input::encrypted(&freq_line);
+        let (mut errors, mut quality) = input::signal_quality(&freq_line);
+        let mut extras = BTreeMap::new();
+        if opts.extras {
+            input::collect_extras(&freq_line, &mut extras);
+        }
+        let mut ignored = Vec::new();
 
         loop {
             let nxt = r.next_line().await?;
             let Some(line) = nxt else { break; };
             let s = line.trim();
             if s.is_empty() { break; }
+            // A block cut short runs straight into the next one.
+            if opts.lenient && starts_block(&mut r, s).await? {
+                debug!("block index={} has no blank separator; resynchronising", record_number);
+                opts.recovered();
+                break;
+            }
             let s_nb = strip_bom(s);
+            let line_type = CallType::detect(s_nb);
+            if let Some(ct) = line_type
+                && (call_type.is_none() || ct == CallType::Emergency)
+            {
+                call_type = Some(ct);
+            }
+            let line_target = s_nb.split_whitespace().find_map(|t| t.strip_prefix("TGT=")).filter(|t| !t.is_empty());
+            if let Some(t) = line_target {
+                target_rid = Some(t.to_string());
+            }
+            let line_encrypted = input::encrypted(s_nb);
+            encrypted |= line_encrypted;
+            let (line_errors, line_quality) = input::signal_quality(s_nb);
+            errors = line_errors.or(errors);
+            quality = line_quality.or(quality);
+            if opts.extras {
+                input::collect_extras(s_nb, &mut extras);
+            }
+            // Call-type / target / signal lines that carry no TG or RID.
+            let flags_only = line_type.is_some()
+                || line_target.is_some()
+                || line_encrypted
+                || line_errors.is_some()
+                || line_quality.is_some();
 
             if let Some(rest) = s_nb.strip_prefix("Slot ") {
                 let mut it = rest.split_whitespace();
@@ -180,28 +255,49 @@ pub async fn stream_file(
                 let rest_of_line = rest.get(slot_no.len()..).unwrap_or("").trim();
                 let (tg, rid) = parse_tg_rid(rest_of_line);
                 match slot_no {
-                    "1" => { if tg.is_some() { slot1.tg = tg; } if rid.is_some() { slot1.rid = rid; } }
-                    "2" => { if tg.is_some() { slot2.tg = tg; } if rid.is_some() { slot2.rid = rid; } }
+                    "1" => {
+                        if tg.is_some() { slot1.tg = tg; }
+                        if rid.is_some() { slot1.rid = rid; }
+                    }
+                    "2" => {
+                        if tg.is_some() { slot2.tg = tg; }
+                        if rid.is_some() { slot2.rid = rid; }
+                    }
                     _ => {}
                 }
             } else if s_nb.starts_with("TG=") || s_nb.contains(" TG=") || s_nb.contains("RID=") {
                 let (tg, rid) = parse_tg_rid(s_nb);
-                if slot1.tg.is_none() && tg.is_some() { slot1.tg = tg; } else if slot2.tg.is_none() && tg.is_some() { slot2.tg = tg; }
-                if slot1.rid.is_none() && rid.is_some() { slot1.rid = rid; } else if slot2.rid.is_none() && rid.is_some() { slot2.rid = rid; }
+                let dropped = policy.assign(&mut slot1, &mut slot2, tg, rid);
+                if opts.notes.is_some() && !dropped.is_empty() {
+                    let reason = format!("{} not used: its slot already has them ({} slot policy)", dropped.join(" "), policy.as_str());
+                    ignored.push(ParseNote::Ignored { line: r.line, reason });
+                }
+            } else if opts.notes.is_some() && !flags_only {
+                ignored.push(ParseNote::Ignored { line: r.line, reason: format!("{:?} has no Slot/TG=/RID=", s_nb) });
             }
         }
 
-        let rec = RadioRecord {
-            record_number,
-            datetime,
+        let mut rec = RadioRecord {
             frequency,
             radio_type,
+            nac,
             dcc,
             slot1,
             slot2,
-            duration: 1
+            call_type: call_type.unwrap_or_default(),
+            encrypted,
+            target_rid,
+            errors,
+            quality,
+            extras,
+            source: Some(Arc::clone(&source)),
+            ..RadioRecord::new(record_number, datetime)
         };
+        rec.map_private_target();
+        opts.finish(&mut rec);
 
+        opts.note(ParseNote::Record { line: block_line });
+        ignored.into_iter().for_each(|n| opts.note(n));
         if tx.send(rec).await.is_err() {
             warn!("downstream closed; aborting parser");
             break;
@@ -211,9 +307,30 @@ pub async fn stream_file(
     Ok(())
 }
 
-async fn drain_block<R: AsyncBufReadExt + Unpin>(r: &mut tokio::io::Lines<R>) -> Result<(), AppError> {
+/// Skip the rest of a block: up to a blank line or, when `lenient`, the
+/// next block header.
+async fn drain_block(r: &mut NumberedLines, lenient: bool) -> Result<(), AppError> {
     while let Some(line) = r.next_line().await? {
         if line.trim().is_empty() { break; }
+        if lenient && starts_block(r, &line).await? { break; }
     }
     Ok(())
 }
+
+/// Whether `line` is the index line of a new block (digits, followed by a
+/// `-->` timerange line). If so both lines are put back to be read again.
+async fn starts_block(r: &mut NumberedLines, line: &str) -> Result<bool, AppError> {
+    let idx = strip_bom(line.trim());
+    if idx.is_empty() || !idx.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(false);
+    }
+    let next = r.next_line().await?;
+    let header = next.as_deref().is_some_and(|n| n.contains("-->"));
+    if let Some(n) = next {
+        r.unread(n);
+    }
+    if header {
+        r.unread(line.to_string());
+    }
+    Ok(header)
+}
@@ -0,0 +1,425 @@
+// src/stats.rs
+use crate::model::RadioRecord;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::error::SendError;
+use tokio::sync::mpsc::{Receiver, Sender};
+
+/// Records seen entering / leaving one pipeline stage, and where its time went:
+/// waiting for input (`idle`), blocked on a full downstream channel
+/// (`blocked`), and the rest of its lifetime (`busy`, the stage's own work).
+#[derive(Debug, Default)]
+pub struct StageCounter {
+    records_in: AtomicU64,
+    records_out: AtomicU64,
+    wall_ns: AtomicU64,
+    idle_ns: AtomicU64,
+    blocked_ns: AtomicU64,
+}
+
+/// Adds the stage's lifetime to its counter when dropped; see [`StageCounter::timer`].
+pub struct StageTimer<'a> {
+    counter: &'a StageCounter,
+    started: Instant,
+}
+
+impl Drop for StageTimer<'_> {
+    fn drop(&mut self) {
+        add_ns(&self.counter.wall_ns, self.started.elapsed());
+    }
+}
+
+fn add_ns(cell: &AtomicU64, d: Duration) {
+    cell.fetch_add(d.as_nanos() as u64, Ordering::Relaxed);
+}
+
+/// Milliseconds, rounded to microseconds for readable manifests.
+fn round_ms(v: f64) -> f64 {
+    (v * 1000.0).round() / 1000.0
+}
+
+fn ms(cell: &AtomicU64) -> f64 {
+    cell.load(Ordering::Relaxed) as f64 / 1e6
+}
+
+impl StageCounter {
+    #[inline]
+    pub fn inc_in(&self) {
+        self.records_in.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn inc_out(&self) {
+        self.records_out.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn add_out(&self, n: u64) {
+        self.records_out.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn records_in(&self) -> u64 {
+        self.records_in.load(Ordering::Relaxed)
+    }
+
+    pub fn records_out(&self) -> u64 {
+        self.records_out.load(Ordering::Relaxed)
+    }
+
+    /// Start timing the stage; hold the guard for the stage's whole run.
+    pub fn timer(&self) -> StageTimer<'_> {
+        StageTimer { counter: self, started: Instant::now() }
+    }
+
+    /// `rx.recv()`, counting the wait as idle time.
+    pub async fn recv<T>(&self, rx: &mut Receiver<T>) -> Option<T> {
+        let t = Instant::now();
+        let v = rx.recv().await;
+        add_ns(&self.idle_ns, t.elapsed());
+        v
+    }
+
+    /// `tx.send()`, counting the wait as blocked-on-downstream time.
+    pub async fn send<T>(&self, tx: &Sender<T>, v: T) -> Result<(), SendError<T>> {
+        let t = Instant::now();
+        let r = tx.send(v).await;
+        add_ns(&self.blocked_ns, t.elapsed());
+        r
+    }
+
+    /// Await `fut` (e.g. a memory-budget permit), counting it as blocked time.
+    pub async fn blocked_on<F: std::future::Future>(&self, fut: F) -> F::Output {
+        let t = Instant::now();
+        let v = fut.await;
+        add_ns(&self.blocked_ns, t.elapsed());
+        v
+    }
+
+    pub fn snapshot(&self) -> StageSnapshot {
+        let (wall, idle, blocked) = (ms(&self.wall_ns), ms(&self.idle_ns), ms(&self.blocked_ns));
+        StageSnapshot {
+            records_in: self.records_in(),
+            records_out: self.records_out(),
+            busy_ms: round_ms((wall - idle - blocked).max(0.0)),
+            idle_ms: round_ms(idle),
+            blocked_ms: round_ms(blocked),
+        }
+    }
+}
+
+/// Live counters for one pipeline, shared (via `Arc`) by all of its stages.
+/// The parser itself is not instrumented: `filter.records_in` is the parsed count.
+#[derive(Debug, Default)]
+pub struct PipelineStats {
+    pub filter: StageCounter,
+    pub rle: StageCounter,
+    pub transcribe: StageCounter,
+    pub sink: StageCounter,
+    pub transcripts_attached: AtomicU64,
+    /// Runs dropped by RLE for being shorter than `--rle-min-blocks`.
+    pub rle_suppressed: AtomicU64,
+    /// Malformed input the parser recovered from (`--lenient`).
+    pub parse_recoveries: Arc<AtomicU64>,
+    /// What the calls leaving RLE contained.
+    pub content: Mutex<ContentStats>,
+    /// Transcript lookups that found a file, and those that didn't.
+    pub coverage: Mutex<CoverageStats>,
+    /// Bytes the parser has read, and the inputs' total size when known.
+    pub input_read: Arc<AtomicU64>,
+    pub input_size: AtomicU64,
+    /// A followed input's unread bytes and the calls waiting for
+    /// transcription, at the last check, and the most of each (see `lag`).
+    pub lag_bytes: AtomicU64,
+    pub lag_calls: AtomicU64,
+    pub lag_peak_bytes: AtomicU64,
+    pub lag_peak_calls: AtomicU64,
+    /// Transcription skips unwatched calls while set (`--lag-shed-stt`).
+    pub shed_stt: AtomicBool,
+    /// Calls forwarded without STT because of it.
+    pub stt_shed: AtomicU64,
+    /// Set when the pipeline has ended: whether it succeeded.
+    pub finished: OnceLock<bool>,
+    /// With several `--sink`s, each one's name and counters, in the order
+    /// listed; `None` for the first, which counts in `sink`.
+    pub sinks: Mutex<Vec<SinkCounter>>,
+}
+
+/// A sink's name and, unless it is the first, its own counters.
+pub type SinkCounter = (&'static str, Option<Arc<PipelineStats>>);
+
+/// Calls and airtime (seconds) under one key.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Tally {
+    pub calls: u64,
+    pub airtime_s: u64,
+}
+
+impl Tally {
+    fn add(&mut self, duration: u32) {
+        self.calls += 1;
+        self.airtime_s += u64::from(duration);
+    }
+
+    fn merge(&mut self, other: &Tally) {
+        self.calls += other.calls;
+        self.airtime_s += other.airtime_s;
+    }
+}
+
+/// Per-frequency, per-talkgroup and per-radio-type totals for one input;
+/// `-` stands for records without the field.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ContentStats {
+    pub total: Tally,
+    /// Calls flagged encrypted.
+    pub encrypted: Tally,
+    pub by_frequency: BTreeMap<String, Tally>,
+    /// By the call's slot 1 TG, else its slot 2 TG.
+    pub by_tg: BTreeMap<String, Tally>,
+    pub by_type: BTreeMap<String, Tally>,
+    /// All and encrypted calls per (day, talkgroup), for `--encryption-report`.
+    #[serde(skip)]
+    pub encryption_by_day: BTreeMap<(String, String), EncryptionTally>,
+}
+
+/// A talkgroup's calls on one day, and the encrypted part of them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EncryptionTally {
+    pub all: Tally,
+    pub encrypted: Tally,
+}
+
+impl ContentStats {
+    pub fn record(&mut self, rec: &RadioRecord) {
+        let key = |v: Option<&String>| v.cloned().unwrap_or_else(|| "-".into());
+        self.total.add(rec.duration);
+        if rec.encrypted {
+            self.encrypted.add(rec.duration);
+        }
+        self.by_frequency.entry(key(rec.frequency.as_ref())).or_default().add(rec.duration);
+        self.by_tg.entry(key(rec.slot1.tg.as_ref().or(rec.slot2.tg.as_ref()))).or_default().add(rec.duration);
+        self.by_type.entry(key(rec.radio_type.as_ref())).or_default().add(rec.duration);
+        let day = rec.datetime.format("%Y-%m-%d").to_string();
+        let share = self.encryption_by_day.entry((day, key(rec.slot1.tg.as_ref().or(rec.slot2.tg.as_ref())))).or_default();
+        share.all.add(rec.duration);
+        if rec.encrypted {
+            share.encrypted.add(rec.duration);
+        }
+    }
+
+    /// Add another input's totals (for run-wide figures).
+    pub fn merge(&mut self, other: &ContentStats) {
+        let add = |into: &mut BTreeMap<String, Tally>, from: &BTreeMap<String, Tally>| {
+            for (k, t) in from {
+                into.entry(k.clone()).or_default().merge(t);
+            }
+        };
+        self.total.merge(&other.total);
+        self.encrypted.merge(&other.encrypted);
+        add(&mut self.by_frequency, &other.by_frequency);
+        add(&mut self.by_tg, &other.by_tg);
+        add(&mut self.by_type, &other.by_type);
+        for (k, t) in &other.encryption_by_day {
+            let into = self.encryption_by_day.entry(k.clone()).or_default();
+            into.all.merge(&t.all);
+            into.encrypted.merge(&t.encrypted);
+        }
+    }
+
+    /// Frequency and type tables, busiest first.
+    pub fn report(&self) -> String {
+        let mut out = format!("{} calls, {} airtime", self.total.calls, hms(self.total.airtime_s));
+        if self.encrypted.calls > 0 {
+            out.push_str(&format!(" ({} calls encrypted)", self.encrypted.calls));
+        }
+        for (title, map) in [("frequency", &self.by_frequency), ("type", &self.by_type)] {
+            let mut rows: Vec<_> = map.iter().collect();
+            rows.sort_by(|a, b| b.1.airtime_s.cmp(&a.1.airtime_s).then(a.0.cmp(b.0)));
+            out.push_str(&format!("\n{:<14} {:>7} {:>10}", title, "calls", "airtime"));
+            for (k, t) in rows {
+                out.push_str(&format!("\n{:<14} {:>7} {:>10}", k, t.calls, hms(t.airtime_s)));
+            }
+        }
+        out
+    }
+}
+
+/// Calls a transcript was found for, and calls left without one.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Hits {
+    pub found: u64,
+    pub missing: u64,
+}
+
+impl Hits {
+    fn add(&mut self, found: bool) {
+        if found {
+            self.found += 1;
+        } else {
+            self.missing += 1;
+        }
+    }
+}
+
+/// Missed lookups kept as examples.
+const MISSED_SAMPLE: usize = 10;
+
+/// Transcript lookups by day and frequency, with a few of the calls nothing
+/// was found for, to check a `--record-dir` against the log.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CoverageStats {
+    pub total: Hits,
+    pub by_day: BTreeMap<String, Hits>,
+    pub by_frequency: BTreeMap<String, Hits>,
+    /// Date, time, frequency, TG and RID of the first calls without a transcript.
+    pub missed: Vec<String>,
+}
+
+impl CoverageStats {
+    pub fn record(&mut self, rec: &RadioRecord, found: bool) {
+        self.total.add(found);
+        self.by_day.entry(rec.datetime.format("%Y-%m-%d").to_string()).or_default().add(found);
+        let freq = rec.frequency.clone().unwrap_or_else(|| "-".into());
+        self.by_frequency.entry(freq).or_default().add(found);
+        if !found && self.missed.len() < MISSED_SAMPLE {
+            let opt = |v: Option<&String>| v.map_or("-", |s| s.as_str()).to_string();
+            self.missed.push(format!(
+                "{} {} TG {} RID {}",
+                rec.datetime.format("%Y-%m-%d %H:%M:%S"),
+                opt(rec.frequency.as_ref()),
+                opt(rec.slot1.tg.as_ref().or(rec.target_rid.as_ref())),
+                opt(rec.slot1.rid.as_ref())
+            ));
+        }
+    }
+
+    /// Day and frequency tables, then the sample of misses.
+    pub fn report(&self) -> String {
+        let calls = self.total.found + self.total.missing;
+        let pct = if calls > 0 { self.total.found as f64 * 100.0 / calls as f64 } else { 0.0 };
+        let mut out = format!("{} of {} calls have a transcript ({:.1}%)", self.total.found, calls, pct);
+        for (title, map) in [("day", &self.by_day), ("frequency", &self.by_frequency)] {
+            out.push_str(&format!("\n{:<14} {:>7} {:>7}", title, "found", "missing"));
+            for (k, h) in map {
+                out.push_str(&format!("\n{:<14} {:>7} {:>7}", k, h.found, h.missing));
+            }
+        }
+        if !self.missed.is_empty() {
+            out.push_str("\nno transcript for, e.g.:");
+            for m in &self.missed {
+                out.push_str(&format!("\n  {}", m));
+            }
+        }
+        out
+    }
+}
+
+fn hms(secs: u64) -> String {
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+impl PipelineStats {
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            parsed: self.filter.records_in(),
+            filter: self.filter.snapshot(),
+            rle: self.rle.snapshot(),
+            transcribe: self.transcribe.snapshot(),
+            sink: self.sink.snapshot(),
+            transcripts_attached: self.transcripts_attached.load(Ordering::Relaxed),
+            rle_suppressed: self.rle_suppressed.load(Ordering::Relaxed),
+            parse_recoveries: self.parse_recoveries.load(Ordering::Relaxed),
+            lag_peak_bytes: self.lag_peak_bytes.load(Ordering::Relaxed),
+            lag_peak_calls: self.lag_peak_calls.load(Ordering::Relaxed),
+            stt_shed: self.stt_shed.load(Ordering::Relaxed),
+            content: self.content.lock().map(|c| c.clone()).unwrap_or_default(),
+            coverage: self.coverage.lock().map(|c| c.clone()).unwrap_or_default(),
+            sinks: self
+                .sinks
+                .lock()
+                .map(|sinks| {
+                    sinks
+                        .iter()
+                        .map(|(name, s)| SinkSnapshot {
+                            name,
+                            counts: s.as_ref().map_or_else(|| self.sink.snapshot(), |s| s.sink.snapshot()),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageSnapshot {
+    pub records_in: u64,
+    pub records_out: u64,
+    pub busy_ms: f64,
+    pub idle_ms: f64,
+    pub blocked_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsSnapshot {
+    pub parsed: u64,
+    pub filter: StageSnapshot,
+    pub rle: StageSnapshot,
+    pub transcribe: StageSnapshot,
+    pub sink: StageSnapshot,
+    pub transcripts_attached: u64,
+    pub rle_suppressed: u64,
+    pub parse_recoveries: u64,
+    /// Most a followed input fell behind (`lag`).
+    pub lag_peak_bytes: u64,
+    pub lag_peak_calls: u64,
+    pub stt_shed: u64,
+    pub content: ContentStats,
+    pub coverage: CoverageStats,
+    /// Per sink, with several `--sink`s; `sink` is the first of them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sinks: Vec<SinkSnapshot>,
+}
+
+/// One of several `--sink`s and what it wrote.
+#[derive(Debug, Clone, Serialize)]
+pub struct SinkSnapshot {
+    pub name: &'static str,
+    #[serde(flatten)]
+    pub counts: StageSnapshot,
+}
+
+impl StatsSnapshot {
+    /// Rows written: by the sink that wrote fewest, with several.
+    pub fn rows_written(&self) -> u64 {
+        self.sinks.iter().map(|s| s.counts.records_out).min().unwrap_or(self.sink.records_out)
+    }
+}
+
+impl StatsSnapshot {
+    /// Per-stage timing table. The parser is not instrumented; the filter's
+    /// idle time is how long it waited for parsed records.
+    pub fn timing_report(&self) -> String {
+        let mut out = format!(
+            "{:<11} {:>9} {:>11} {:>9} {:>11} {:>11}\n",
+            "stage", "records", "busy ms", "us/rec", "idle ms", "blocked ms"
+        );
+        for (name, s) in [
+            ("filter", &self.filter),
+            ("rle", &self.rle),
+            ("transcribe", &self.transcribe),
+            ("sink", &self.sink),
+        ] {
+            let per = if s.records_in > 0 { s.busy_ms * 1000.0 / s.records_in as f64 } else { 0.0 };
+            out.push_str(&format!(
+                "{:<11} {:>9} {:>11.1} {:>9.1} {:>11.1} {:>11.1}\n",
+                name, s.records_in, s.busy_ms, per, s.idle_ms, s.blocked_ms
+            ));
+        }
+        out.truncate(out.trim_end().len());
+        out
+    }
+}
@@ -0,0 +1,122 @@
+// src/stt_registry.rs
+//
+// `--transcriber NAME` lookup. Built-in backends (`text`, `whisper`) are
+// registered by the binary, `[transcriber.NAME]` entries of the config file
+// are added as external backends (see `external_stt.rs`), and `none` (or an
+// empty name) means no transcription. Any other name is an error listing
+// the available ones, so a typo can't silently turn transcription off.
+//
+// A built backend is checked before any input is read (`--stt-check`): the
+// Record directory exists and has day folders, and the backend's own
+// `Transcriber::health` passes (whisper loads its model, a command's program
+// exists, an endpoint answers), so problems show up at startup rather than
+// thousands of records in.
+
+use crate::config::Config;
+use crate::errors::AppError;
+use crate::external_stt::ExternalTranscriber;
+use crate::freq::FreqTolerance;
+use crate::transcriber::Transcriber;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+pub type SharedTranscriber = Arc<dyn Transcriber + Send + Sync>;
+
+/// `--stt-check`: what happens when a backend fails its startup check.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HealthPolicy {
+    /// Stop before reading any input.
+    #[default]
+    Fail,
+    /// Log the problem and run without transcription.
+    Warn,
+    /// Don't check.
+    Off,
+}
+
+impl HealthPolicy {
+    pub fn parse(s: &str) -> Result<Self, AppError> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "fail" => Ok(HealthPolicy::Fail),
+            "warn" => Ok(HealthPolicy::Warn),
+            "off" => Ok(HealthPolicy::Off),
+            _ => Err(AppError::Parse(format!("invalid --stt-check '{}': expected fail, warn or off", s))),
+        }
+    }
+}
+
+/// What a backend is built from.
+pub struct BuildContext<'a> {
+    pub record_dir: Option<&'a Path>,
+    pub config: &'a Config,
+    pub freq_tolerance: FreqTolerance,
+}
+
+type Build<'a> = Box<dyn Fn(&BuildContext) -> Result<SharedTranscriber, AppError> + 'a>;
+
+#[derive(Default)]
+pub struct Registry<'a> {
+    backends: BTreeMap<String, Build<'a>>,
+}
+
+impl<'a> Registry<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(
+        &mut self,
+        name: &str,
+        build: impl Fn(&BuildContext) -> Result<SharedTranscriber, AppError> + 'a,
+    ) {
+        self.backends.insert(name.to_string(), Box::new(build));
+    }
+
+    /// Add the config file's `[transcriber.NAME]` backends. They can't
+    /// replace a built-in one.
+    pub fn add_external(&mut self, config: &Config) -> Result<(), AppError> {
+        for name in config.transcriber.keys() {
+            if name == "none" || self.backends.contains_key(name) {
+                return Err(AppError::Parse(format!("[transcriber.{}]: '{}' is a built-in transcriber", name, name)));
+            }
+            let owned = name.clone();
+            self.register(name, move |ctx| {
+                let root = ctx.record_dir.ok_or_else(|| {
+                    AppError::Other(format!("--transcriber {} needs --record-dir (to find the audio)", owned))
+                })?;
+                let t = ExternalTranscriber::new(
+                    &owned,
+                    &ctx.config.transcriber[&owned],
+                    root,
+                    &ctx.config.stt,
+                    ctx.config.record.audio_extensions.as_deref(),
+                    ctx.freq_tolerance,
+                )?;
+                Ok(Arc::new(t))
+            });
+        }
+        Ok(())
+    }
+
+    /// Every name `--transcriber` accepts.
+    pub fn names(&self) -> Vec<&str> {
+        std::iter::once("none").chain(self.backends.keys().map(String::as_str)).collect()
+    }
+
+    /// The backend called `name`; `None` for `none`.
+    pub fn build(&self, name: &str, ctx: &BuildContext) -> Result<Option<SharedTranscriber>, AppError> {
+        let name = name.trim();
+        if name.is_empty() || name == "none" {
+            return Ok(None);
+        }
+        match self.backends.get(name) {
+            Some(build) => build(ctx).map(Some),
+            None => Err(AppError::Other(format!(
+                "unknown --transcriber '{}' (available: {})",
+                name,
+                self.names().join(", ")
+            ))),
+        }
+    }
+}
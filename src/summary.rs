@@ -0,0 +1,217 @@
+// src/summary.rs
+//
+// `--stats-json stats.json`: what a run's calls contained, for static
+// dashboards and data-quality checks in archival jobs: calls and airtime per
+// talkgroup, frequency and radio type, the encrypted share, and how many
+// calls got a transcript. Figures are per input and for the whole run; the
+// manifest (`--manifest`) covers the run's mechanics instead.
+// `--encryption-report` breaks the encrypted share down per talkgroup and day.
+// Every run ends with [`ExitTable`], the same per-input counts on stderr.
+
+use crate::errors::AppError;
+use crate::stats::{ContentStats, StatsSnapshot, Tally};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Share {
+    pub calls: u64,
+    pub airtime_s: u64,
+    /// Fractions of all calls / all airtime, 0..1; `null` without calls.
+    pub calls_ratio: Option<f64>,
+    pub airtime_ratio: Option<f64>,
+}
+
+/// Calls that reached transcription and how many got text.
+#[derive(Debug, Clone, Serialize)]
+pub struct Coverage {
+    pub calls: u64,
+    pub transcribed: u64,
+    pub coverage_pct: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub calls: u64,
+    pub airtime_s: u64,
+    pub encrypted: Share,
+    /// `null` when the run had no transcriber.
+    pub transcription: Option<Coverage>,
+    pub by_tg: BTreeMap<String, Tally>,
+    pub by_frequency: BTreeMap<String, Tally>,
+    pub by_type: BTreeMap<String, Tally>,
+}
+
+fn ratio(part: u64, whole: u64) -> Option<f64> {
+    (whole > 0).then(|| part as f64 / whole as f64)
+}
+
+impl Summary {
+    /// From an input's (or the run's merged) content and the number of calls
+    /// given a transcript; `transcribed` is `None` without a transcriber.
+    pub fn new(content: &ContentStats, transcribed: Option<u64>) -> Self {
+        let total = content.total;
+        Self {
+            calls: total.calls,
+            airtime_s: total.airtime_s,
+            encrypted: Share {
+                calls: content.encrypted.calls,
+                airtime_s: content.encrypted.airtime_s,
+                calls_ratio: ratio(content.encrypted.calls, total.calls),
+                airtime_ratio: ratio(content.encrypted.airtime_s, total.airtime_s),
+            },
+            transcription: transcribed.map(|t| Coverage {
+                calls: total.calls,
+                transcribed: t,
+                coverage_pct: ratio(t, total.calls).map(|r| (r * 1000.0).round() / 10.0),
+            }),
+            by_tg: content.by_tg.clone(),
+            by_frequency: content.by_frequency.clone(),
+            by_type: content.by_type.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct InputSummary {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub summary: Summary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    pub generated_at: String,
+    pub total: Summary,
+    pub inputs: Vec<InputSummary>,
+}
+
+impl RunSummary {
+    /// From each input's path and final stats; `transcriber` says whether
+    /// transcripts were being looked up at all.
+    pub fn new(inputs: &[(PathBuf, StatsSnapshot)], transcriber: bool) -> Self {
+        let mut all = ContentStats::default();
+        let mut transcribed = 0;
+        let inputs = inputs
+            .iter()
+            .map(|(path, s)| {
+                all.merge(&s.content);
+                transcribed += s.transcripts_attached;
+                InputSummary {
+                    path: path.clone(),
+                    summary: Summary::new(&s.content, transcriber.then_some(s.transcripts_attached)),
+                }
+            })
+            .collect();
+        Self {
+            generated_at: chrono::Local::now().to_rfc3339(),
+            total: Summary::new(&all, transcriber.then_some(transcribed)),
+            inputs,
+        }
+    }
+}
+
+/// `--encryption-report`: per day and talkgroup, the share of calls and
+/// airtime DSDPlus flagged encrypted, as CSV
+/// (`day,tg,calls,encrypted_calls,calls_pct,airtime_s,encrypted_airtime_s,airtime_pct`).
+/// Runs over successive days line up, so a migration to encryption shows as
+/// the percentages climbing.
+pub fn write_encryption_report(path: &Path, content: &ContentStats) -> Result<(), AppError> {
+    let pct = |part: u64, whole: u64| ratio(part, whole).map(|r| format!("{:.1}", r * 100.0)).unwrap_or_default();
+    let mut out = String::from("day,tg,calls,encrypted_calls,calls_pct,airtime_s,encrypted_airtime_s,airtime_pct\n");
+    for ((day, tg), t) in &content.encryption_by_day {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            day,
+            tg,
+            t.all.calls,
+            t.encrypted.calls,
+            pct(t.encrypted.calls, t.all.calls),
+            t.all.airtime_s,
+            t.encrypted.airtime_s,
+            pct(t.encrypted.airtime_s, t.all.airtime_s)
+        ));
+    }
+    std::fs::write(path, out).map_err(|e| AppError::IO(format!("write encryption report {}: {}", path.display(), e)))
+}
+
+pub fn write_summary(path: &Path, summary: &RunSummary) -> Result<(), AppError> {
+    let json = serde_json::to_string_pretty(summary)
+        .map_err(|e| AppError::Other(format!("stats encode: {}", e)))?;
+    std::fs::write(path, json + "\n").map_err(|e| AppError::IO(format!("write stats {}: {}", path.display(), e)))
+}
+
+/// How one input's pipeline ended, for the table printed at exit.
+pub struct InputOutcome {
+    pub path: PathBuf,
+    pub stages: StatsSnapshot,
+    pub elapsed: Duration,
+    pub error: Option<String>,
+}
+
+/// The table printed at the end of a run: per input, records parsed, kept by
+/// the filters, merged away by run-length compression, transcripts attached,
+/// rows written and how long it took, then a total and the inputs that
+/// failed with why. With several sinks the rows are those of the one that
+/// wrote fewest, and sinks that fell short are named below. A failure further up is only a warning in the log; here
+/// it can't be missed.
+pub struct ExitTable<'a>(pub &'a [InputOutcome]);
+
+impl ExitTable<'_> {
+    pub fn failed(&self) -> usize {
+        self.0.iter().filter(|o| o.error.is_some()).count()
+    }
+}
+
+impl std::fmt::Display for ExitTable<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let row = |f: &mut std::fmt::Formatter<'_>, counts: [u64; 5], secs: f64, status: &str, name: &str| {
+            let [parsed, kept, collapsed, transcripts, rows] = counts;
+            writeln!(
+                f,
+                "{:>9} {:>9} {:>9} {:>11} {:>9} {:>8.1}s  {:<6}  {}",
+                parsed, kept, collapsed, transcripts, rows, secs, status, name
+            )
+        };
+        writeln!(
+            f,
+            "{:>9} {:>9} {:>9} {:>11} {:>9} {:>9}  {:<6}  input",
+            "parsed", "kept", "collapsed", "transcripts", "rows", "time", "status"
+        )?;
+        let mut total = [0u64; 5];
+        let mut longest = Duration::ZERO;
+        for o in self.0 {
+            let s = &o.stages;
+            let counts = [
+                s.parsed,
+                s.filter.records_out,
+                s.rle.records_in.saturating_sub(s.rle.records_out),
+                s.transcripts_attached,
+                s.rows_written(),
+            ];
+            for (t, c) in total.iter_mut().zip(counts) {
+                *t += c;
+            }
+            longest = longest.max(o.elapsed);
+            let status = if o.error.is_some() { "FAILED" } else { "ok" };
+            row(f, counts, o.elapsed.as_secs_f64(), status, &o.path.display().to_string())?;
+        }
+        if self.0.len() > 1 {
+            let failed = self.failed();
+            let status = if failed > 0 { format!("{} bad", failed) } else { "ok".into() };
+            row(f, total, longest.as_secs_f64(), &status, &format!("all {} inputs", self.0.len()))?;
+        }
+        for o in self.0 {
+            if let Some(e) = &o.error {
+                writeln!(f, "{}: {}", o.path.display(), e)?;
+            }
+            let most = o.stages.sinks.iter().map(|s| s.counts.records_out).max().unwrap_or(0);
+            for s in o.stages.sinks.iter().filter(|s| s.counts.records_out < most) {
+                writeln!(f, "{}: sink {} wrote {} of {} rows", o.path.display(), s.name, s.counts.records_out, most)?;
+            }
+        }
+        Ok(())
+    }
+}
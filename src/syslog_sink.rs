@@ -0,0 +1,214 @@
+// src/syslog_sink.rs
+//
+// RFC 5424 syslog sink for SIEM ingestion. Each record becomes one message
+// with the call fields as structured data, sent over UDP or TCP (RFC 6587
+// octet-counting framing).
+
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use crate::sink::{self, DurationFormat};
+use crate::stats::PipelineStats;
+use log::info;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::mpsc::Receiver;
+
+const APP_NAME: &str = "callscribe";
+const MSG_ID: &str = "call";
+/// SD-ID for our structured data (32473 is the documentation enterprise number).
+const SD_ID: &str = "call@32473";
+/// Severity "informational".
+const SEVERITY: u8 = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyslogTransport {
+    Udp,
+    Tcp,
+}
+
+#[derive(Clone, Debug)]
+pub struct SyslogSinkOptions {
+    pub transport: SyslogTransport,
+    /// `host:port`
+    pub addr: String,
+    pub facility: u8,
+    /// The message's duration as `00:02:37`, with `duration_hms` next to
+    /// the structured `duration` seconds (`--duration-format hms`).
+    pub duration: DurationFormat,
+}
+
+impl SyslogSinkOptions {
+    /// Parse `udp://host:port`, `tcp://host:port` or bare `host[:port]` (UDP).
+    pub fn parse(addr: &str, facility: &str) -> Result<Self, AppError> {
+        let (transport, rest) = if let Some(r) = addr.strip_prefix("tcp://") {
+            (SyslogTransport::Tcp, r)
+        } else if let Some(r) = addr.strip_prefix("udp://") {
+            (SyslogTransport::Udp, r)
+        } else {
+            (SyslogTransport::Udp, addr)
+        };
+        let addr = if rest.contains(':') {
+            rest.to_string()
+        } else {
+            let port = if transport == SyslogTransport::Tcp { 601 } else { 514 };
+            format!("{}:{}", rest, port)
+        };
+        Ok(Self { transport, addr, facility: parse_facility(facility)?, duration: DurationFormat::default() })
+    }
+}
+
+/// Facility names from RFC 5424 §6.2.1 (or a numeric code 0-23).
+pub fn parse_facility(s: &str) -> Result<u8, AppError> {
+    const NAMES: [&str; 24] = [
+        "kern", "user", "mail", "daemon", "auth", "syslog", "lpr", "news", "uucp", "cron",
+        "authpriv", "ftp", "ntp", "audit", "alert", "clock", "local0", "local1", "local2",
+        "local3", "local4", "local5", "local6", "local7",
+    ];
+    let t = s.trim().to_ascii_lowercase();
+    if let Some(i) = NAMES.iter().position(|n| *n == t) {
+        return Ok(i as u8);
+    }
+    match t.parse::<u8>() {
+        Ok(n) if n < 24 => Ok(n),
+        _ => Err(AppError::Parse(format!("unknown syslog facility '{}'", s))),
+    }
+}
+
+/// SD-PARAM values must escape '"', '\' and ']'.
+fn sd_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty() && h.is_ascii() && !h.contains(' '))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Render one RFC 5424 message (without transport framing).
+pub fn format_message(r: &RadioRecord, facility: u8, duration: DurationFormat, host: &str) -> String {
+    let pri = facility as u16 * 8 + SEVERITY as u16;
+    let tg = r.slot1.tg.as_ref().or(r.slot2.tg.as_ref());
+    let rid = r.slot1.rid.as_ref().or(r.slot2.rid.as_ref());
+    let text = r.slot1.text.as_ref().or(r.slot2.text.as_ref());
+    let radio_type = r.radio_type.as_deref().map(|t| t.trim_start_matches('+'));
+    let errors = r.errors.map(|e| e.to_string());
+    let quality = r.quality.map(|q| q.to_string());
+    let hms = (duration == DurationFormat::Hms).then(|| sink::hms(r.duration));
+
+    let mut sd = format!("[{} id=\"{}\" record=\"{}\" duration=\"{}\"", SD_ID, r.record_id(), r.record_number, r.duration);
+    for (k, v) in [
+        ("freq", r.frequency.as_deref()),
+        ("type", radio_type),
+        ("dcc", r.nac_or_dcc()),
+        ("nac", r.nac.as_deref()),
+        ("color_code", r.dcc.as_deref()),
+        ("tg", tg.map(String::as_str)),
+        ("rid", rid.map(String::as_str)),
+        ("call", Some(r.call_type.as_str())),
+        ("target_rid", r.target_rid.as_deref()),
+        ("errors", errors.as_deref()),
+        ("quality", quality.as_deref()),
+        ("alert", r.alert.as_deref()),
+        ("encrypted", r.encrypted.then_some("true")),
+        ("duration_hms", hms.as_deref()),
+    ] {
+        if let Some(v) = v {
+            sd.push_str(&format!(" {}=\"{}\"", k, sd_escape(v)));
+        }
+    }
+    sd.push(']');
+
+    let rid = rid.map(String::as_str).unwrap_or("-");
+    let parties = match &r.target_rid {
+        Some(t) => format!("RID {} to unit {}", rid, t),
+        None => format!("TG {} RID {}", tg.map(String::as_str).unwrap_or("-"), rid),
+    };
+    let took = hms.unwrap_or_else(|| format!("{}s", r.duration));
+    let mut msg = format!("{} on {} ({})", parties, r.frequency.as_deref().unwrap_or("-"), took);
+    if let Some(t) = text {
+        msg.push_str(": ");
+        msg.push_str(&t.replace(['\r', '\n'], " "));
+    }
+
+    // BOM marks MSG as UTF-8 (RFC 5424 §6.4).
+    format!(
+        "<{}>1 {} {} {} {} {} {} \u{FEFF}{}",
+        pri,
+        r.datetime.to_rfc3339(),
+        host,
+        APP_NAME,
+        std::process::id(),
+        MSG_ID,
+        sd,
+        msg
+    )
+}
+
+enum Conn {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+pub async fn write_syslog_stream(
+    opts: &SyslogSinkOptions,
+    mut rx: Receiver<RadioRecord>,
+    stats: Arc<PipelineStats>,
+) -> Result<(), AppError> {
+    let _timer = stats.sink.timer();
+    let mut conn = match opts.transport {
+        SyslogTransport::Udp => {
+            let sock = UdpSocket::bind("0.0.0.0:0").await?;
+            sock.connect(&opts.addr)
+                .await
+                .map_err(|e| AppError::IO(format!("syslog connect {}: {}", opts.addr, e)))?;
+            Conn::Udp(sock)
+        }
+        SyslogTransport::Tcp => Conn::Tcp(
+            TcpStream::connect(&opts.addr)
+                .await
+                .map_err(|e| AppError::IO(format!("syslog connect {}: {}", opts.addr, e)))?,
+        ),
+    };
+    let host = hostname();
+    let mut count: usize = 0;
+
+    while let Some(r) = stats.sink.recv(&mut rx).await {
+        stats.sink.inc_in();
+        let msg = format_message(&r, opts.facility, opts.duration, &host);
+        match &mut conn {
+            Conn::Udp(sock) => {
+                sock.send(msg.as_bytes())
+                    .await
+                    .map_err(|e| AppError::IO(format!("syslog send: {}", e)))?;
+            }
+            Conn::Tcp(stream) => {
+                // RFC 6587 octet counting: "<len> <msg>"
+                let framed = format!("{} {}", msg.len(), msg);
+                stream
+                    .write_all(framed.as_bytes())
+                    .await
+                    .map_err(|e| AppError::IO(format!("syslog send: {}", e)))?;
+            }
+        }
+        count += 1;
+        stats.sink.inc_out();
+    }
+
+    if let Conn::Tcp(stream) = &mut conn {
+        stream.flush().await?;
+    }
+    info!("Syslog sent {} messages to {}", count, opts.addr);
+    Ok(())
+}
@@ -0,0 +1,136 @@
+// src/template_sink.rs
+//
+// Text output through a Tera template (`--sink template --template FILE`),
+// for formats with no sink of their own: a dispatch log, a plain-text radio
+// log to post somewhere. The template is rendered once per call and the
+// results are written one after another to a file per input, named after
+// the template (`call.txt.tera` -> `capture.txt`).
+//
+// A call's fields are those of its JSON document (`record_id`, `datetime`,
+// `frequency`, `slot1.tg`, `slot1.text`, `call_type`, ...; see
+// `RadioRecord::to_json`), plus:
+//
+//   date, time     `2025-09-10`, `06:43:56` (local to the input)
+//   duration_hms   `00:00:07`
+//   text           the transcript of whichever slot has one
+//   index          1, 2, 3, ... within the file
+//
+//   {{ date }} {{ time }}  {{ frequency }}  TG {{ slot1.tg | default(value="-") }}  {{ text }}
+//
+// Missing values are empty. A rendering that doesn't end in a newline gets
+// one, so a one-line template gives one line per call.
+
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use crate::output_file::{OutputFile, WriteMode};
+use crate::sink;
+use crate::stats::PipelineStats;
+use log::info;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tera::{Context, Tera};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc::Receiver;
+
+const NAME: &str = "call";
+
+#[derive(Clone, Debug)]
+pub struct TemplateSinkOptions {
+    pub path: PathBuf,
+    /// Flush whenever the input channel runs dry (follow mode).
+    pub flush_when_idle: bool,
+    pub write_mode: WriteMode,
+}
+
+#[derive(Clone, Debug)]
+pub struct TemplateSink {
+    tera: Arc<Tera>,
+    extension: String,
+    opts: TemplateSinkOptions,
+}
+
+impl TemplateSink {
+    /// Read and compile the template, so mistakes in it stop the run before
+    /// any input is read.
+    pub fn load(opts: TemplateSinkOptions) -> Result<Self, AppError> {
+        let shown = opts.path.display().to_string();
+        let source = std::fs::read_to_string(&opts.path)
+            .map_err(|e| AppError::IO(format!("read --template '{}': {}", shown, e)))?;
+        let mut tera = Tera::default();
+        tera.add_raw_template(NAME, &source)
+            .map_err(|e| AppError::Parse(format!("--template '{}': {}", shown, describe(&e))))?;
+        Ok(Self { tera: Arc::new(tera), extension: extension(&opts.path), opts })
+    }
+
+    /// Extension of the output files.
+    pub fn extension(&self) -> &str {
+        &self.extension
+    }
+
+    fn render(&self, rec: &RadioRecord, index: usize) -> Result<String, AppError> {
+        let mut doc = rec.to_json();
+        doc["date"] = rec.datetime.format("%Y-%m-%d").to_string().into();
+        doc["time"] = rec.datetime.format("%H:%M:%S").to_string().into();
+        doc["duration_hms"] = sink::hms(rec.duration).into();
+        doc["text"] = rec.slot1.text.as_deref().or(rec.slot2.text.as_deref()).unwrap_or("").into();
+        doc["index"] = index.into();
+        let ctx = Context::from_value(doc).map_err(|e| AppError::Other(format!("template context: {}", e)))?;
+        let mut out = self.tera.render(NAME, &ctx).map_err(|e| {
+            let shown = self.opts.path.display();
+            AppError::Other(format!("--template '{}': rec#{}: {}", shown, rec.record_number, describe(&e)))
+        })?;
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+/// `call.txt.tera` -> `txt`; `txt` when the name has no other extension.
+fn extension(template: &Path) -> String {
+    let name = template.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let name = name.strip_suffix(".tera").unwrap_or(name);
+    Path::new(name).extension().and_then(|e| e.to_str()).filter(|e| !e.is_empty()).unwrap_or("txt").to_string()
+}
+
+/// Tera's message with its causes, which hold the useful part.
+fn describe(e: &tera::Error) -> String {
+    let mut msg = e.to_string();
+    let mut source = std::error::Error::source(e);
+    while let Some(s) = source {
+        msg.push_str(": ");
+        msg.push_str(&s.to_string());
+        source = s.source();
+    }
+    msg
+}
+
+pub async fn write_template_stream(
+    sink: &TemplateSink,
+    out_path: &Path,
+    mut rx: Receiver<RadioRecord>,
+    stats: Arc<PipelineStats>,
+) -> Result<Vec<PathBuf>, AppError> {
+    let _timer = stats.sink.timer();
+    let (out, file) = OutputFile::create(out_path, sink.opts.write_mode).await?;
+    let mut w = BufWriter::new(file);
+    let mut count: usize = 0;
+
+    while let Some(r) = stats.sink.recv(&mut rx).await {
+        stats.sink.inc_in();
+        count += 1;
+        w.write_all(sink.render(&r, count)?.as_bytes())
+            .await
+            .map_err(|e| AppError::IO(format!("template write: {}", e)))?;
+        stats.sink.inc_out();
+
+        if sink.opts.flush_when_idle && rx.is_empty() {
+            w.flush().await.map_err(|e| AppError::IO(format!("template flush: {}", e)))?;
+        }
+    }
+
+    w.flush().await.map_err(|e| AppError::IO(format!("template flush: {}", e)))?;
+    out.commit(w.into_inner()).await?;
+    info!("Template wrote {} calls to {}", count, out_path.display());
+    Ok(vec![out_path.to_path_buf()])
+}
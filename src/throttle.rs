@@ -0,0 +1,98 @@
+// src/throttle.rs
+//
+// Dedup and rate limits in front of every alert notifier, so a stuck
+// transmitter or a busy watched talkgroup can't send thousands of
+// notifications. Two rules, both from the config file's `[notify]` section:
+//
+// - cooldown: once a watched TG or RID has notified, further calls matching
+//   only TGs/RIDs still in their cooldown are dropped (per-ID overrides in
+//   `[notify] tg`/`rid`);
+// - burst: no more than `burst` notifications in any `burst_window_s`,
+//   over all watched calls.
+//
+// Calls still get their `alert` field either way; only the notifications
+// are held back. One throttle is shared by all pipelines of a run.
+
+use crate::alert::WatchKey;
+use crate::config::{Aliases, NotifyConfig};
+use crate::errors::AppError;
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+
+/// What to do with one watched call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Notify,
+    /// Every TG/RID it matched notified too recently.
+    Cooldown,
+    /// The burst limit is used up.
+    Burst,
+}
+
+#[derive(Default)]
+struct State {
+    /// Last notification per watched TG/RID (Unix seconds).
+    last: HashMap<WatchKey, i64>,
+    /// Times of the notifications in the current burst window.
+    window: VecDeque<i64>,
+}
+
+pub struct Throttle {
+    cooldown: i64,
+    overrides: HashMap<WatchKey, i64>,
+    burst: usize,
+    burst_window: i64,
+    state: Mutex<State>,
+}
+
+impl Throttle {
+    /// From `[notify]`; per-ID entries may use `[alias]` names.
+    pub fn new(cfg: &NotifyConfig, aliases: &Aliases) -> Result<Self, AppError> {
+        let mut overrides = HashMap::new();
+        let tables = [("tg", &cfg.tg, &aliases.tg), ("rid", &cfg.rid, &aliases.rid)];
+        for (kind, table, names) in tables {
+            for (name, secs) in table {
+                let id = Aliases::resolve(names, name).ok_or_else(|| {
+                    AppError::Parse(format!("[notify] {}: '{}' is neither an ID nor a [alias.{}] name", kind, name, kind))
+                })?;
+                let key = if kind == "tg" { WatchKey::Tg(id) } else { WatchKey::Rid(id) };
+                overrides.insert(key, *secs as i64);
+            }
+        }
+        Ok(Self {
+            cooldown: cfg.cooldown_s as i64,
+            overrides,
+            burst: cfg.burst,
+            burst_window: cfg.burst_window_s as i64,
+            state: Mutex::new(State::default()),
+        })
+    }
+
+    fn cooldown_of(&self, key: &WatchKey) -> i64 {
+        self.overrides.get(key).copied().unwrap_or(self.cooldown)
+    }
+
+    /// Decide for a call at `at` (Unix seconds) that matched `keys`, and
+    /// record it when it may notify. Inputs needn't be in order: a call
+    /// within the cooldown either side of the last notification is held back.
+    pub fn check(&self, keys: &[WatchKey], at: i64) -> Verdict {
+        let mut st = self.state.lock();
+        let cooling = |k: &WatchKey| st.last.get(k).is_some_and(|t| (at - t).abs() < self.cooldown_of(k));
+        if !keys.is_empty() && keys.iter().all(cooling) {
+            return Verdict::Cooldown;
+        }
+        if self.burst > 0 {
+            while st.window.front().is_some_and(|t| *t <= at - self.burst_window) {
+                st.window.pop_front();
+            }
+            if st.window.len() >= self.burst {
+                return Verdict::Burst;
+            }
+            st.window.push_back(at);
+        }
+        for k in keys {
+            st.last.insert(k.clone(), at);
+        }
+        Verdict::Notify
+    }
+}
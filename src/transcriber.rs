@@ -1,11 +1,15 @@
 // src/transcriber.rs
 use crate::errors::AppError;
+use crate::freq::{self, FreqTolerance};
 use crate::model::RadioRecord;
+use crate::record_name;
+use crate::winpath;
 use log::{debug, trace};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
+use parking_lot::RwLock;
+use std::sync::Arc;
 use walkdir::WalkDir;
 
 /// New contract:
@@ -19,28 +23,42 @@ pub trait Transcriber: Send + Sync {
         rec: &RadioRecord,
         record_dir: &Path,
     ) -> Result<Option<String>, Option<AppError>>;
+
+    /// Check, before any record arrives, that the backend can work at all
+    /// (its model, program or endpoint). Nothing to check by default.
+    fn health(&self) -> Result<(), AppError> {
+        Ok(())
+    }
 }
 
 /// Key for per-day indexing (date is implicit in the day shard).
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct K {
     time: u32,         // HHMMSS
-    freq: String,      // "153.450000"
+    freq: u64,         // Hz
     tg: Option<u32>,
     rid: Option<u32>,
 }
 
 #[derive(Default)]
 struct DayIndex {
+    /// Frequencies seen per HHMMSS, for lookups within a tolerance.
+    freqs: HashMap<u32, Vec<u64>>,
     // Most specific first; fallbacks after
     full: HashMap<K, PathBuf>,      // time+freq+tg+rid
     rid_only: HashMap<K, PathBuf>,  // time+freq+rid (tg=None)
     tg_only: HashMap<K, PathBuf>,   // time+freq+tg  (rid=None)
     bare: HashMap<K, PathBuf>,      // time+freq     (tg=None, rid=None)
+    /// Files already attached to a record (`--transcript-once`).
+    used: HashSet<PathBuf>,
 }
 
 impl DayIndex {
     fn insert_all(&mut self, k: K, path: PathBuf) {
+        let freqs = self.freqs.entry(k.time).or_default();
+        if !freqs.contains(&k.freq) {
+            freqs.push(k.freq);
+        }
         // full
         self.full.entry(k.clone()).or_insert_with(|| path.clone());
         // rid_only
@@ -58,94 +76,258 @@ impl DayIndex {
     }
 
     fn lookup(&self, k: &K) -> Option<&PathBuf> {
+        self.lookup_agreeing(k).map(|(_, p)| p)
+    }
+
+    /// The file `lookup` finds and how well its IDs agree with the key's:
+    /// 3 for TG and RID, 2 for the RID only, 1 for the TG only, 0 for neither.
+    fn lookup_agreeing(&self, k: &K) -> Option<(u32, &PathBuf)> {
         if let Some(p) = self.full.get(k) {
-            return Some(p);
+            return Some((3, p));
         }
         let rid_k = K { tg: None, ..k.clone() };
         if let Some(p) = self.rid_only.get(&rid_k) {
-            return Some(p);
+            return Some((2, p));
         }
         let tg_k = K { rid: None, ..k.clone() };
         if let Some(p) = self.tg_only.get(&tg_k) {
-            return Some(p);
+            return Some((1, p));
         }
         let bare_k = K {
             tg: None,
             rid: None,
             ..k.clone()
         };
-        self.bare.get(&bare_k)
+        self.bare.get(&bare_k).map(|p| (0, p))
+    }
+
+    /// `lookup` at the key's frequency, then at the other frequencies
+    /// indexed for that second within `tolerance`, nearest first.
+    fn lookup_near(&self, k: &K, tolerance: FreqTolerance) -> Option<&PathBuf> {
+        if let Some(p) = self.lookup(k) {
+            return Some(p);
+        }
+        if tolerance.0 == 0 {
+            return None;
+        }
+        let mut near: Vec<u64> = self
+            .freqs
+            .get(&k.time)?
+            .iter()
+            .copied()
+            .filter(|f| *f != k.freq && f.abs_diff(k.freq) <= tolerance.0)
+            .collect();
+        near.sort_by_key(|f| f.abs_diff(k.freq));
+        near.into_iter().find_map(|freq| self.lookup(&K { freq, ..k.clone() }))
+    }
+
+    /// The best file within `window` seconds of the key's time and within
+    /// `tolerance` of its frequency. Each second off and each ID that
+    /// doesn't agree costs a point; ties go to the nearer time, then the
+    /// nearer frequency. The window stops at midnight. With no window this
+    /// is `lookup_near`.
+    fn lookup_window(&self, k: &K, tolerance: FreqTolerance, window: u32) -> Option<&PathBuf> {
+        if window == 0 {
+            return self.lookup_near(k, tolerance);
+        }
+        let at = hhmmss_to_secs(k.time);
+        let mut best: Option<((u32, u32, u64), &PathBuf)> = None;
+        for secs in at.saturating_sub(window)..=(at + window).min(86_399) {
+            let time = secs_to_hhmmss(secs);
+            let Some(freqs) = self.freqs.get(&time) else { continue };
+            for &freq in freqs.iter().filter(|f| f.abs_diff(k.freq) <= tolerance.0) {
+                let Some((agree, path)) = self.lookup_agreeing(&K { time, freq, ..k.clone() }) else { continue };
+                let off = secs.abs_diff(at);
+                let score = (off + 3 - agree, off, freq.abs_diff(k.freq));
+                if best.as_ref().is_none_or(|(b, _)| score < *b) {
+                    best = Some((score, path));
+                }
+            }
+        }
+        best.map(|(_, path)| path)
+    }
+}
+
+fn hhmmss_to_secs(t: u32) -> u32 {
+    t / 10_000 * 3600 + t / 100 % 100 * 60 + t % 100
+}
+
+fn secs_to_hhmmss(s: u32) -> u32 {
+    s / 3600 * 10_000 + s / 60 % 60 * 100 + s % 60
+}
+
+/// How a Record directory shards its files by day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordLayout {
+    /// `<root>/<YYYYMMDD>/` (DSDPlus default).
+    Daily,
+    /// `<root>/<YYYY>/<MM>/<DD>/`.
+    YearMonthDay,
+    /// Every file directly under `<root>`; the day comes from a leading
+    /// `YYYYMMDD_` in the name, else from the file's modification time.
+    Flat,
+}
+
+impl RecordLayout {
+    /// Look at the top level of `root`: dated subfolders win, then year
+    /// folders, then matching files in the root itself.
+    pub fn detect<S: AsRef<str>>(root: &Path, extensions: &[S]) -> Self {
+        let Ok(entries) = fs::read_dir(root) else {
+            return RecordLayout::Daily;
+        };
+        let mut has_files = false;
+        let mut has_years = false;
+        for e in entries.flatten() {
+            let name = e.file_name();
+            let Some(name) = name.to_str() else { continue };
+            let all_digits = !name.is_empty() && name.bytes().all(|b| b.is_ascii_digit());
+            let is_dir = e.file_type().is_ok_and(|t| t.is_dir());
+            if is_dir && all_digits && name.len() == 8 {
+                return RecordLayout::Daily;
+            }
+            has_years |= is_dir && all_digits && name.len() == 4;
+            has_files |= !is_dir
+                && name
+                    .rsplit_once('.')
+                    .is_some_and(|(_, ext)| extensions.iter().any(|x| x.as_ref().eq_ignore_ascii_case(ext)));
+        }
+        if has_years {
+            RecordLayout::YearMonthDay
+        } else if has_files {
+            RecordLayout::Flat
+        } else {
+            RecordLayout::Daily
+        }
+    }
+
+    fn day_dir(self, root: &Path, day: u32) -> PathBuf {
+        match self {
+            RecordLayout::Daily => root.join(format!("{:08}", day)),
+            RecordLayout::YearMonthDay => root
+                .join(format!("{:04}", day / 10000))
+                .join(format!("{:02}", day / 100 % 100))
+                .join(format!("{:02}", day % 100)),
+            RecordLayout::Flat => root.to_path_buf(),
+        }
+    }
+}
+
+/// Fails when `root` can't be a Record directory: it is missing, or has
+/// neither day folders nor (flat layout) matching files at its top level.
+pub fn check_record_root<S: AsRef<str>>(root: &Path, extensions: &[S]) -> Result<(), AppError> {
+    let shown = winpath::simplified(root);
+    let entries = fs::read_dir(root).map_err(|e| AppError::IO(format!("record dir {}: {}", shown.display(), e)))?;
+    let usable = entries.flatten().any(|e| {
+        let name = e.file_name();
+        let Some(name) = name.to_str() else { return false };
+        if e.file_type().is_ok_and(|t| t.is_dir()) {
+            (name.len() == 8 || name.len() == 4) && name.bytes().all(|b| b.is_ascii_digit())
+        } else {
+            name.rsplit_once('.').is_some_and(|(_, ext)| extensions.iter().any(|x| x.as_ref().eq_ignore_ascii_case(ext)))
+        }
+    });
+    if usable {
+        Ok(())
+    } else {
+        Err(AppError::Other(format!(
+            "record dir {} has no day folders (YYYYMMDD/ or YYYY/MM/DD/) and no matching files",
+            shown.display()
+        )))
     }
 }
 
 #[derive(Default)]
 struct Index {
+    /// Probed on first use.
+    layout: Option<RecordLayout>,
     // Sharded by YYYYMMDD
     days: HashMap<u32, DayIndex>,
+    /// A flat directory has been walked; days it had no files for are empty.
+    flat_scanned: bool,
 }
 
-/// Incremental, on-demand, per-day indexed text transcriber.
-/// - Walks the Record directory lazily for the day shard requested by each record.
-/// - Two-level traversal: `<root>/<YYYYMMDD>/*.txt`
-/// - Filename pattern examples:
-///     "064356_153.450000_004_P25__GC_2_4506.txt"
-///     HHMMSS  FREQ                       TG  RID
-pub struct TextFileTranscriber {
-    // Root "Record" directory (top level that contains YYYYMMDD subfolders)
-    root: PathBuf,
+/// Incremental, on-demand, per-day index of Record files with the given extensions.
+/// - Walks the Record directory lazily for the day shard requested by each record
+///   (a flat directory is walked once, in full).
+/// - Layout is detected once per index; see [`RecordLayout`].
+/// - File names follow the DSDPlus grammar in [`record_name`], e.g.
+///   "064356_153.450000_004_P25__GC_2_4506.txt"
+///   HHMMSS  FREQ       CHAN PROTO CALL TG RID
+pub struct RecordFileIndex {
+    /// Lowercase; file extensions are compared case-insensitively.
+    extensions: Vec<String>,
     // Mutable, lazily-populated day shards
     index: Arc<RwLock<Index>>,
+    /// Files this far off the record's frequency still match.
+    freq_tolerance: FreqTolerance,
+    /// Files this many seconds off the record's time still match.
+    time_window: u32,
 }
 
-impl TextFileTranscriber {
-    /// Construct an incremental-indexer transcriber.
-    /// It does not pre-scan; day folders are indexed on first use.
-    pub fn new_indexed(root: &Path) -> Result<Self, AppError> {
-        Ok(Self {
-            root: root.to_path_buf(),
-            index: Arc::new(RwLock::new(Index::default())),
-        })
-    }
-
-    /// Convenience empty constructor (will error at runtime if no root is passed in at transcribe()).
-    pub fn new() -> Self {
+impl RecordFileIndex {
+    pub fn new<S: AsRef<str>>(extensions: &[S]) -> Self {
         Self {
-            root: PathBuf::new(),
+            extensions: extensions
+                .iter()
+                .map(|e| e.as_ref().trim_start_matches('.').to_ascii_lowercase())
+                .collect(),
             index: Arc::new(RwLock::new(Index::default())),
+            freq_tolerance: FreqTolerance::default(),
+            time_window: 0,
         }
     }
 
-    /// Ensure the YYYYMMDD shard is present; if not, scan `<root>/<day>/` once.
+    /// Match files whose frequency is within `tolerance` of the record's.
+    pub fn with_freq_tolerance(mut self, tolerance: FreqTolerance) -> Self {
+        self.freq_tolerance = tolerance;
+        self
+    }
+
+    /// Match files up to `seconds` before or after the record's start, the
+    /// best-scoring one winning (see `DayIndex::lookup_window`).
+    pub fn with_time_window(mut self, seconds: u32) -> Self {
+        self.time_window = seconds;
+        self
+    }
+
+    /// Ensure the YYYYMMDD shard is present; if not, scan its directory once.
     fn ensure_day_indexed(&self, day: u32, record_dir: &Path) -> Result<(), AppError> {
         // Fast path: read lock says it's already indexed
-        {
-            let guard = self
-                .index
-                .read()
-                .map_err(|_| AppError::Parse("index read lock poisoned".into()))?;
-            if guard.days.contains_key(&day) {
-                return Ok(());
-            }
+        if self.index.read().days.contains_key(&day) {
+            return Ok(());
         }
 
         // Slow path: acquire write and double-check
-        let mut guard = self
-            .index
-            .write()
-            .map_err(|_| AppError::Parse("index write lock poisoned".into()))?;
+        // parking_lot locks don't poison: if a scan panics, the shard was never
+        // inserted and the next lookup of that day simply scans again.
+        let mut guard = self.index.write();
         if guard.days.contains_key(&day) {
             return Ok(());
         }
 
-        let day_dir = record_dir.join(format!("{:08}", day));
+        // Long and UNC Record paths need the `\\?\` form on Windows.
+        let root = winpath::extended(record_dir);
+        let layout = *guard.layout.get_or_insert_with(|| {
+            let layout = RecordLayout::detect(&root, &self.extensions);
+            debug!("RecordFileIndex: {} looks like a {:?} layout", winpath::simplified(&root).display(), layout);
+            layout
+        });
+
+        if layout == RecordLayout::Flat && guard.flat_scanned {
+            guard.days.insert(day, DayIndex::default());
+            return Ok(());
+        }
+
+        let day_dir = layout.day_dir(&root, day);
         if !day_dir.is_dir() {
-            trace!("TextFileTranscriber: day dir missing: {}", day_dir.display());
+            trace!("RecordFileIndex: day dir missing: {}", winpath::simplified(&day_dir).display());
             // insert empty shard to avoid re-probing
             guard.days.insert(day, DayIndex::default());
             return Ok(());
         }
 
-        let mut shard = DayIndex::default();
+        // A flat directory holds every day: shard it all in one pass.
+        let mut shards: HashMap<u32, DayIndex> = HashMap::new();
 
         for entry in WalkDir::new(&day_dir).max_depth(1).min_depth(1) {
             let entry = match entry {
@@ -162,67 +344,56 @@ impl TextFileTranscriber {
                 Some(s) => s,
                 None => continue,
             };
-            if !name.ends_with(".txt") {
-                continue;
-            }
-
-            // Expect "HHMMSS_FREQ_....txt"
-            let stem = name.trim_end_matches(".txt");
-            let parts: Vec<&str> = stem.split('_').collect();
-            if parts.len() < 2 {
-                continue;
-            }
-
-            // HHMMSS
-            let time = match parts[0].parse::<u32>() {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-            // Frequency
-            let freq = normalize_freq(parts[1]);
-
-            // Extract TG and RID from numeric tokens in the remainder (heuristic).
-            // Example tail: "... _GC_2_4506" → TG=2, RID=4506
-            let mut tg: Option<u32> = None;
-            let mut rid: Option<u32> = None;
-            for &tok in &parts[2..] {
-                if let Ok(n) = tok.parse::<u32>() {
-                    if tg.is_none() {
-                        tg = Some(n);
-                    } else if rid.is_none() {
-                        rid = Some(n);
-                    } else {
-                        // already have both; ignore extras
-                    }
-                }
-            }
-
-            let k = K {
-                time,
-                freq,
-                tg,
-                rid,
+            let Some((file_day, k)) = self.parse_name(name) else { continue };
+            let file_day = match (layout, file_day) {
+                (RecordLayout::Flat, None) => match modified_day(&entry) {
+                    Some(d) => d,
+                    None => continue,
+                },
+                (RecordLayout::Flat, Some(d)) => d,
+                _ => day,
             };
-            let p = entry.into_path();
-            shard.insert_all(k, p);
+            shards.entry(file_day).or_default().insert_all(k, entry.into_path());
         }
 
-        debug!(
-            "TextFileTranscriber: indexed day {} (files: full={}, rid_only={}, tg_only={}, bare={})",
-            day,
-            shard.full.len(),
-            shard.rid_only.len(),
-            shard.tg_only.len(),
-            shard.bare.len()
-        );
-        guard.days.insert(day, shard);
+        shards.entry(day).or_default();
+        guard.flat_scanned |= layout == RecordLayout::Flat;
+        for (d, shard) in shards {
+            debug!(
+                "RecordFileIndex: indexed day {} {:?} (files: full={}, rid_only={}, tg_only={}, bare={})",
+                d,
+                self.extensions,
+                shard.full.len(),
+                shard.rid_only.len(),
+                shard.tg_only.len(),
+                shard.bare.len()
+            );
+            // Shards already indexed keep their `used` files (`--transcript-once`).
+            guard.days.entry(d).or_insert(shard);
+        }
         Ok(())
     }
 
+    /// `[YYYYMMDD_]HHMMSS_FREQ_....<ext>` → optional day and lookup key.
+    fn parse_name(&self, name: &str) -> Option<(Option<u32>, K)> {
+        let stem = match name.rsplit_once('.') {
+            Some((stem, ext)) if self.extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)) => stem,
+            _ => return None,
+        };
+        // Tolerate a second suffix left by STT tools ("..._4506.wav.txt").
+        let stem = match stem.rsplit_once('.') {
+            Some((inner, ext)) if !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphabetic()) => inner,
+            _ => stem,
+        };
+        let name = record_name::parse(stem)?;
+        let k = K { time: name.time, freq: freq::to_hz(&name.freq)?, tg: name.tg, rid: name.rid };
+        Some((name.day, k))
+    }
+
     fn lookup_in_day(&self, day: u32, k: &K) -> Option<PathBuf> {
-        let guard = self.index.read().ok()?;
+        let guard = self.index.read();
         let di = guard.days.get(&day)?;
-        di.lookup(k).cloned()
+        di.lookup_window(k, self.freq_tolerance, self.time_window).cloned()
     }
 
     fn day_from_rec(rec: &RadioRecord) -> Option<u32> {
@@ -240,12 +411,13 @@ impl TextFileTranscriber {
             .to_string()
             .parse::<u32>()
             .ok()?;
-        let freq_s = rec.frequency.as_deref()?;
-        let freq = normalize_freq(freq_s);
+        let freq = freq::to_hz(rec.frequency.as_deref()?)?;
+        // Private-call file names carry the called unit where the TG goes.
         let tg = rec
             .slot1
             .tg
             .as_deref()
+            .or(rec.target_rid.as_deref())
             .and_then(|s| s.parse::<u32>().ok());
         let rid = rec
             .slot1
@@ -254,15 +426,33 @@ impl TextFileTranscriber {
             .and_then(|s| s.parse::<u32>().ok());
         Some(K { time, freq, tg, rid })
     }
-}
 
-impl Transcriber for TextFileTranscriber {
-    fn transcribe(
-        &self,
-        rec: &RadioRecord,
-        record_dir: &Path,
-    ) -> Result<Option<String>, Option<AppError>> {
-        // Determine day shard & key
+    /// How `root` shards its files, detected on first use.
+    pub fn layout(&self, root: &Path) -> RecordLayout {
+        let root = winpath::extended(root);
+        *self.index.write().layout.get_or_insert_with(|| RecordLayout::detect(&root, &self.extensions))
+    }
+
+    /// The folder under `root` that `rec`'s day is looked up in; it need
+    /// not exist.
+    pub fn day_dir(&self, rec: &RadioRecord, root: &Path) -> Option<PathBuf> {
+        let day = Self::day_from_rec(rec)?;
+        Some(self.layout(root).day_dir(root, day))
+    }
+
+    /// Remember that `path`, found for `rec`, has been used; `false` if it
+    /// already was. Call after [`find`](Self::find) indexed the day.
+    pub fn first_use(&self, rec: &RadioRecord, path: &Path) -> bool {
+        let Some(day) = Self::day_from_rec(rec) else { return true };
+        match self.index.write().days.get_mut(&day) {
+            Some(shard) => shard.used.insert(path.to_path_buf()),
+            None => true,
+        }
+    }
+
+    /// Find the file for `rec` under `root`, indexing its day on first use.
+    /// Lookup falls back full → rid_only → tg_only → bare.
+    pub fn find(&self, rec: &RadioRecord, root: &Path) -> Result<Option<PathBuf>, AppError> {
         let day = match Self::day_from_rec(rec) {
             Some(d) => d,
             None => return Ok(None),
@@ -271,47 +461,168 @@ impl Transcriber for TextFileTranscriber {
             Some(k) => k,
             None => return Ok(None),
         };
+        self.ensure_day_indexed(day, root)?;
+        let found = self.lookup_in_day(day, &key);
+        if found.is_none() {
+            trace!("RecordFileIndex: no {:?} file for day={} key={:?}", self.extensions, day, key);
+        }
+        Ok(found)
+    }
+}
 
-        // Choose the correct root:
-        // - Prefer `record_dir` provided by the pipeline
-        // - Fallback to the root specified at construction time
-        let root = if record_dir.as_os_str().is_empty() {
-            if self.root.as_os_str().is_empty() {
-                return Err(Some(AppError::Parse(
-                    "TextFileTranscriber has no record_dir root".into(),
-                )));
-            }
-            self.root.as_path()
-        } else {
-            record_dir
-        };
+/// Transcript extensions indexed by default.
+pub const TRANSCRIPT_EXTENSIONS: &[&str] = &["txt", "text"];
+
+/// Recording extensions indexed by default (`m4a` needs a whisper-cli built
+/// with ffmpeg).
+pub const AUDIO_EXTENSIONS: &[&str] = &["wav", "mp3", "flac", "ogg", "m4a"];
+
+/// Text transcriber: reads the `.txt` transcript DSDPlus (or an external STT
+/// tool) left next to each recording.
+pub struct TextFileTranscriber {
+    // Root "Record" directory (top level that contains YYYYMMDD subfolders)
+    root: PathBuf,
+    files: RecordFileIndex,
+    /// Attach each file's text to the first record that finds it; later
+    /// records get a reference to the file instead.
+    once: bool,
+}
 
-        // Ensure the day shard is available
-        if let Err(e) = self.ensure_day_indexed(day, root) {
-            return Err(Some(e));
+impl TextFileTranscriber {
+    /// Construct an incremental-indexer transcriber.
+    /// It does not pre-scan; day folders are indexed on first use.
+    pub fn new_indexed(root: &Path) -> Result<Self, AppError> {
+        Ok(Self {
+            root: root.to_path_buf(),
+            files: RecordFileIndex::new(TRANSCRIPT_EXTENSIONS),
+            once: false,
+        })
+    }
+
+    /// Convenience empty constructor (will error at runtime if no root is passed in at transcribe()).
+    pub fn new() -> Self {
+        Self {
+            root: PathBuf::new(),
+            files: RecordFileIndex::new(TRANSCRIPT_EXTENSIONS),
+            once: false,
         }
+    }
+}
+
+impl TextFileTranscriber {
+    /// Index files with these extensions instead of [`TRANSCRIPT_EXTENSIONS`].
+    pub fn with_extensions<S: AsRef<str>>(mut self, extensions: &[S]) -> Self {
+        self.files = RecordFileIndex::new(extensions).with_freq_tolerance(self.files.freq_tolerance);
+        self
+    }
 
-        // Lookup with fallbacks (full → rid_only → tg_only → bare)
-        if let Some(path) = self.lookup_in_day(day, &key) {
-            debug!("TextFileTranscriber: using {}", path.display());
+    /// See [`RecordFileIndex::with_freq_tolerance`].
+    pub fn with_freq_tolerance(mut self, tolerance: FreqTolerance) -> Self {
+        self.files = self.files.with_freq_tolerance(tolerance);
+        self
+    }
+
+    /// Attach a transcript only to the first record it matches (fallback keys
+    /// can match one file for several calls in the same second); later
+    /// records get `[same transcript as <path>]`.
+    pub fn with_once(mut self, once: bool) -> Self {
+        self.once = once;
+        self
+    }
+}
+
+impl Default for TextFileTranscriber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Prefer `record_dir` provided by the pipeline; fall back to the root given
+/// at construction time.
+pub(crate) fn choose_root<'a>(record_dir: &'a Path, fallback: &'a Path) -> Result<&'a Path, AppError> {
+    if !record_dir.as_os_str().is_empty() {
+        Ok(record_dir)
+    } else if !fallback.as_os_str().is_empty() {
+        Ok(fallback)
+    } else {
+        Err(AppError::Parse("transcriber has no record_dir root".into()))
+    }
+}
+
+impl Transcriber for TextFileTranscriber {
+    fn transcribe(
+        &self,
+        rec: &RadioRecord,
+        record_dir: &Path,
+    ) -> Result<Option<String>, Option<AppError>> {
+        let root = choose_root(record_dir, &self.root).map_err(Some)?;
+
+        if let Some(path) = self.files.find(rec, root).map_err(Some)? {
+            let shown = winpath::simplified(&path);
+            if self.once && !self.files.first_use(rec, &path) {
+                debug!("TextFileTranscriber: {} already attached", shown.display());
+                return Ok(Some(format!("[same transcript as {}]", shown.display())));
+            }
+            debug!("TextFileTranscriber: using {}", shown.display());
             match fs::read_to_string(&path) {
                 Ok(s) => return Ok(Some(s)),
-                Err(e) => return Err(Some(AppError::IO(format!("read {}: {}", path.display(), e)))),
+                Err(e) => return Err(Some(AppError::IO(format!("read {}: {}", shown.display(), e)))),
             }
         }
-
-        trace!(
-            "TextFileTranscriber: no transcript for day={} key={:?}",
-            day,
-            key
-        );
         Ok(None)
     }
 }
 
-fn normalize_freq(s: &str) -> String {
+/// Local-time YYYYMMDD of a file's modification time.
+fn modified_day(entry: &walkdir::DirEntry) -> Option<u32> {
+    let mtime = entry.metadata().ok()?.modified().ok()?;
+    chrono::DateTime::<chrono::Local>::from(mtime).format("%Y%m%d").to_string().parse().ok()
+}
+
+/// Frequencies compare as `{:.6}` MHz strings (`153.45` == `153.450000`).
+pub(crate) fn normalize_freq(s: &str) -> String {
     match s.parse::<f64>() {
         Ok(v) => format!("{:.6}", v),
         Err(_) => s.to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, TimeZone};
+
+    fn rec(day: u32, time: (u32, u32, u32)) -> RadioRecord {
+        let dt = FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2025, 9, day, time.0, time.1, time.2)
+            .unwrap();
+        let mut r = RadioRecord::new(1, dt);
+        r.frequency = Some("153.450000".into());
+        r.slot1.tg = Some("2".into());
+        r.slot1.rid = Some("4506".into());
+        r
+    }
+
+    #[test]
+    fn flat_directory_is_scanned_once_and_keeps_used_files() {
+        let root = std::env::temp_dir().join(format!("callscribe-flat-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let names = ["20250910_064356_153.450000_P25__GC_2_4506.txt", "20250911_070000_153.450000_P25__GC_2_4506.txt"];
+        for name in names {
+            fs::write(root.join(name), "text").unwrap();
+        }
+        let index = RecordFileIndex::new(TRANSCRIPT_EXTENSIONS);
+        let first = index.find(&rec(10, (6, 43, 56)), &root).unwrap().expect("day 10 file");
+        assert!(index.first_use(&rec(10, (6, 43, 56)), &first));
+        // A day without files, then one indexed by the same scan.
+        assert_eq!(index.find(&rec(12, (6, 43, 56)), &root).unwrap(), None);
+        assert!(index.find(&rec(11, (7, 0, 0)), &root).unwrap().is_some());
+        // Adding a file doesn't trigger another walk of the directory.
+        fs::write(root.join("20250913_080000_153.450000_P25__GC_2_4506.txt"), "text").unwrap();
+        assert_eq!(index.find(&rec(13, (8, 0, 0)), &root).unwrap(), None);
+        // The day-10 shard survived the later lookups.
+        assert!(!index.first_use(&rec(10, (6, 43, 56)), &first));
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -1,18 +1,25 @@
+use crate::budget::PipelineBudget;
 use crate::errors::AppError;
 use crate::model::RadioRecord;
+use crate::stats::PipelineStats;
 use crate::transcriber::Transcriber;
-use log::{debug, trace, warn};
+use log::{debug, info, trace, warn};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::panic::AssertUnwindSafe;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
 use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 
 /// Stage: consumes records, optionally adds transcription text, forwards downstream.
 ///
 /// Behavior:
 /// - If `record_dir` or `transcriber` is `None`, or `max_concurrent == 0`,
 ///   this stage becomes a pass-through.
-/// - Otherwise, transcriptions are performed in a bounded `spawn_blocking` pool.
+/// - Otherwise, up to `max_concurrent` transcriptions run at once on the
+///   blocking pool, and records are forwarded in input order as the lookups
+///   ahead of them finish.
 /// - This stage does not perform any file-system probing itself; it delegates
 ///   responsibility entirely to the provided `Transcriber`.
 pub async fn add_transcriptions(
@@ -21,62 +28,312 @@ pub async fn add_transcriptions(
     record_dir: Option<PathBuf>,
     transcriber: Option<Arc<dyn Transcriber + Send + Sync>>,
     max_concurrent: usize,
+    stats: Arc<PipelineStats>,
+    budget: Option<Arc<PipelineBudget>>,
 ) -> Result<(), AppError> {
+    let _timer = stats.transcribe.timer();
     // Fast path: no enrichment, just forward records.
     if record_dir.is_none() || transcriber.is_none() || max_concurrent == 0 {
         trace!("transcription_adder: fast-path (no transcriber/dir or concurrency==0)");
-        while let Some(rec) = rx.recv().await {
-            if tx.send(rec).await.is_err() {
+        while let Some(rec) = stats.transcribe.recv(&mut rx).await {
+            stats.transcribe.inc_in();
+            if stats.transcribe.send(&tx, rec).await.is_err() {
                 warn!("transcription_adder: downstream closed (fast-path)");
                 break;
             }
+            if let Some(b) = &budget {
+                b.release(1);
+            }
+            stats.transcribe.inc_out();
         }
         return Ok(());
     }
 
     let dir = record_dir.unwrap();
     let t = transcriber.unwrap();
-    let sem = Arc::new(Semaphore::new(max_concurrent));
-
-    while let Some(mut rec) = rx.recv().await {
-        // Only attempt transcription if we don't already have text.
-        if rec.slot1.text.is_none() {
-            // Clone minimal state into the blocking task. If RadioRecord is large,
-            // this clone is still cheaper than blocking the async runtime thread.
-            let rec_for_lookup = rec.clone();
-            let dir_clone = dir.clone();
-            let t_clone = t.clone();
-            let permit = sem.clone().acquire_owned().await.unwrap();
-
-            let res = tokio::task::spawn_blocking(move || {
-                let _guard = permit; // hold the semaphore while we do blocking work
-                t_clone.transcribe(&rec_for_lookup, &dir_clone)
-            })
-            .await
-            .map_err(|e| AppError::IO(format!("transcriber join error: {e}")))?;
-
-            match res {
-                Ok(Some(text)) => {
-                    debug!("transcription_adder: rec#{} -> text attached", rec.record_number);
-                    rec.slot1.text = Some(text);
+    let window = max_concurrent * WINDOW_PER_TASK;
+    // Records in input order: ready to go, or waiting for lookup `n`.
+    let mut pending: VecDeque<Queued> = VecDeque::new();
+    let mut done: HashMap<u64, Lookup> = HashMap::new();
+    let mut running = JoinSet::new();
+    let (mut open, mut next) = (true, 0u64);
+    'run: loop {
+        while let Some(front) = pending.front() {
+            if let Queued::Running(n) = front
+                && !done.contains_key(n)
+            {
+                break;
+            }
+            let rec = match pending.pop_front().expect("front checked") {
+                Queued::Ready(rec) => *rec,
+                Queued::Running(n) => {
+                    let (mut rec, res) = done.remove(&n).expect("lookup done");
+                    attach(&mut rec, res, &stats);
+                    rec
                 }
-                Ok(None) => {
-                    // No transcript available; proceed silently.
+            };
+            if !forward(&tx, rec, &stats, &budget).await {
+                break 'run;
+            }
+        }
+        if !open && pending.is_empty() {
+            break;
+        }
+        tokio::select! {
+            got = stats.transcribe.recv(&mut rx), if open && running.len() < max_concurrent && pending.len() < window => {
+                let Some(rec) = got else {
+                    open = false;
+                    continue;
+                };
+                stats.transcribe.inc_in();
+                // Only attempt transcription if we don't already have text.
+                if rec.slot1.text.is_some() || shed(&stats, rec.alert.is_some()) {
+                    pending.push_back(Queued::Ready(Box::new(rec)));
+                    continue;
                 }
-                Err(Some(e)) => {
-                    debug!("transcription_adder: rec#{} transcription error: {}", rec.record_number, e);
+                let (n, t, dir) = (next, Arc::clone(&t), dir.clone());
+                next += 1;
+                running.spawn_blocking(move || {
+                    // A panicking transcriber costs this record its transcript, not the
+                    // pipeline; the index it was building is left consistent.
+                    let res = std::panic::catch_unwind(AssertUnwindSafe(|| t.transcribe(&rec, &dir))).unwrap_or_else(|_| {
+                        warn!("transcription_adder: rec#{} transcriber panicked; continuing without text", rec.record_number);
+                        Err(None)
+                    });
+                    (n, (rec, res))
+                });
+                pending.push_back(Queued::Running(n));
+            }
+            Some(finished) = running.join_next(), if !running.is_empty() => {
+                let (n, lookup) = finished.map_err(|e| AppError::IO(format!("transcriber join error: {e}")))?;
+                done.insert(n, lookup);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Records [`add_transcriptions`] holds per transcription it may run.
+const WINDOW_PER_TASK: usize = 4;
+
+/// A record and what its transcript lookup returned.
+type Lookup = (RadioRecord, Result<Option<String>, Option<AppError>>);
+
+/// A record waiting in [`add_transcriptions`] to be forwarded in order.
+enum Queued {
+    Ready(Box<RadioRecord>),
+    Running(u64),
+}
+
+/// Whether to forward a call without transcribing it because the pipeline is
+/// behind a followed input (`--lag-shed-stt`); `watched` calls never are.
+fn shed(stats: &PipelineStats, watched: bool) -> bool {
+    if watched || !stats.shed_stt.load(Ordering::Relaxed) {
+        return false;
+    }
+    stats.stt_shed.fetch_add(1, Ordering::Relaxed);
+    true
+}
+
+/// Record a lookup's outcome in the stats and attach its text.
+fn attach(rec: &mut RadioRecord, res: Result<Option<String>, Option<AppError>>, stats: &PipelineStats) {
+    if let Ok(mut c) = stats.coverage.lock() {
+        c.record(rec, matches!(res, Ok(Some(_))));
+    }
+    match res {
+        Ok(Some(text)) => {
+            debug!("transcription_adder: rec#{} -> text attached", rec.record_number);
+            rec.slot1.text = Some(text);
+            stats.transcripts_attached.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(None) => {
+            // No transcript available; proceed silently.
+        }
+        Err(Some(e)) => {
+            debug!("transcription_adder: rec#{} transcription error: {}", rec.record_number, e);
+        }
+        Err(None) => {
+            // Soft failure; intentionally ignored.
+        }
+    }
+}
+
+/// Default [`PriorityRules::lookahead`]: enough to see past a burst of bulk
+/// traffic without holding a lot of records.
+pub const PRIORITY_LOOKAHEAD: usize = 256;
+
+/// Which calls [`add_transcriptions_by_priority`] transcribes first, and how
+/// much work it keeps in hand.
+#[derive(Debug, Clone)]
+pub struct PriorityRules {
+    /// Talkgroups transcribed first (`--stt-priority-tg`), besides calls
+    /// with an `alert`.
+    pub tgs: BTreeSet<String>,
+    /// Transcriptions running at once.
+    pub concurrency: usize,
+    /// Records read ahead of the running transcriptions to find urgent ones.
+    pub lookahead: usize,
+}
+
+impl PriorityRules {
+    pub fn urgent(&self, rec: &RadioRecord) -> bool {
+        rec.alert.is_some()
+            || [&rec.slot1.tg, &rec.slot2.tg].iter().any(|tg| tg.as_ref().is_some_and(|t| self.tgs.contains(t)))
+    }
+}
+
+async fn forward(
+    tx: &Sender<RadioRecord>,
+    rec: RadioRecord,
+    stats: &PipelineStats,
+    budget: &Option<Arc<PipelineBudget>>,
+) -> bool {
+    if stats.transcribe.send(tx, rec).await.is_err() {
+        warn!("transcription_adder: downstream closed");
+        return false;
+    }
+    if let Some(b) = budget {
+        b.release(1);
+    }
+    stats.transcribe.inc_out();
+    true
+}
+
+/// Stage: like [`add_transcriptions`], but with up to `rules.lookahead`
+/// records queued in two tiers: watched calls and priority talkgroups are
+/// transcribed before bulk traffic, `rules.concurrency` at a time, and each
+/// record is forwarded as soon as its lookup finishes. Output is therefore
+/// in completion order, not input order.
+pub async fn add_transcriptions_by_priority(
+    mut rx: Receiver<RadioRecord>,
+    tx: Sender<RadioRecord>,
+    record_dir: PathBuf,
+    transcriber: Arc<dyn Transcriber + Send + Sync>,
+    rules: Arc<PriorityRules>,
+    stats: Arc<PipelineStats>,
+    budget: Option<Arc<PipelineBudget>>,
+) -> Result<(), AppError> {
+    let _timer = stats.transcribe.timer();
+    let (mut urgent, mut bulk) = (VecDeque::new(), VecDeque::new());
+    let mut running = JoinSet::new();
+    let (mut open, mut promoted) = (true, 0usize);
+    'run: loop {
+        // Behind a followed input: queued bulk calls go on without text.
+        while stats.shed_stt.load(Ordering::Relaxed)
+            && let Some(rec) = bulk.pop_front()
+        {
+            stats.stt_shed.fetch_add(1, Ordering::Relaxed);
+            if !forward(&tx, rec, &stats, &budget).await {
+                break 'run;
+            }
+        }
+        while running.len() < rules.concurrency.max(1) {
+            let Some(rec) = urgent.pop_front().or_else(|| bulk.pop_front()) else { break };
+            let (t, dir) = (Arc::clone(&transcriber), record_dir.clone());
+            running.spawn_blocking(move || {
+                // The record travels with its result, so a panicking
+                // transcriber costs only the transcript.
+                let res = std::panic::catch_unwind(AssertUnwindSafe(|| t.transcribe(&rec, &dir))).unwrap_or_else(|_| {
+                    warn!("transcription_adder: rec#{} transcriber panicked; continuing without text", rec.record_number);
+                    Err(None)
+                });
+                (rec, res)
+            });
+        }
+        let queued = urgent.len() + bulk.len();
+        if !open && queued == 0 && running.is_empty() {
+            break;
+        }
+        tokio::select! {
+            got = stats.transcribe.recv(&mut rx), if open && queued < rules.lookahead.max(1) => match got {
+                None => open = false,
+                Some(rec) => {
+                    stats.transcribe.inc_in();
+                    if rec.slot1.text.is_some() {
+                        if !forward(&tx, rec, &stats, &budget).await {
+                            break;
+                        }
+                    } else if rules.urgent(&rec) {
+                        if !bulk.is_empty() || running.len() >= rules.concurrency {
+                            promoted += 1;
+                        }
+                        urgent.push_back(rec);
+                    } else if shed(&stats, false) {
+                        if !forward(&tx, rec, &stats, &budget).await {
+                            break;
+                        }
+                    } else {
+                        bulk.push_back(rec);
+                    }
                 }
-                Err(None) => {
-                    // Soft failure; intentionally ignored.
+            },
+            Some(done) = running.join_next(), if !running.is_empty() => {
+                let (mut rec, res) = done.map_err(|e| AppError::IO(format!("transcriber join error: {e}")))?;
+                attach(&mut rec, res, &stats);
+                if !forward(&tx, rec, &stats, &budget).await {
+                    break;
                 }
             }
         }
+    }
+    if promoted > 0 {
+        info!("Transcription: {} priority calls moved ahead of queued traffic", promoted);
+    }
+    Ok(())
+}
 
-        if tx.send(rec).await.is_err() {
-            warn!("transcription_adder: downstream closed");
-            break;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, TimeZone};
+    use std::path::Path;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    /// Takes longer for earlier records and remembers the most calls it had
+    /// running at once.
+    #[derive(Default)]
+    struct Slow {
+        running: AtomicUsize,
+        most: AtomicUsize,
+    }
+
+    impl Transcriber for Slow {
+        fn transcribe(&self, rec: &RadioRecord, _record_dir: &Path) -> Result<Option<String>, Option<AppError>> {
+            let now = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(40 - 4 * rec.record_number as u64));
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(Some(format!("call {}", rec.record_number)))
         }
     }
 
-    Ok(())
+    #[tokio::test(flavor = "multi_thread")]
+    async fn runs_lookups_at_once_and_keeps_input_order() {
+        let dt = FixedOffset::east_opt(0).unwrap().with_ymd_and_hms(2025, 9, 10, 6, 0, 0).unwrap();
+        let slow = Arc::new(Slow::default());
+        let (tx_in, rx_in) = mpsc::channel(16);
+        let (tx_out, mut rx_out) = mpsc::channel(16);
+        for n in 1..=8 {
+            tx_in.send(RadioRecord::new(n, dt)).await.unwrap();
+        }
+        drop(tx_in);
+        let stats = Arc::new(PipelineStats::default());
+        let t: Arc<dyn Transcriber + Send + Sync> = slow.clone();
+        let stage = add_transcriptions(rx_in, tx_out, Some(PathBuf::from(".")), Some(t), 4, stats, None);
+        let collect = async {
+            let mut out = Vec::new();
+            while let Some(rec) = rx_out.recv().await {
+                out.push((rec.record_number, rec.slot1.text));
+            }
+            out
+        };
+        let (result, out) = tokio::join!(stage, collect);
+        result.unwrap();
+        let expected: Vec<_> = (1..=8).map(|n| (n, Some(format!("call {}", n)))).collect();
+        assert_eq!(out, expected);
+        assert!(slow.most.load(Ordering::SeqCst) > 1, "lookups ran one at a time");
+    }
 }
@@ -0,0 +1,26 @@
+// src/tz.rs
+use chrono::{FixedOffset, Utc};
+use chrono_tz::Tz;
+use log::warn;
+
+/// Resolve an IANA timezone name to the fixed offset it has *now*.
+/// Returns `None` (local time) when no name is given or it fails to parse.
+pub fn compute_tz_offset(args_tz: &Option<String>) -> Option<FixedOffset> {
+    if let Some(tzname) = args_tz.as_ref() {
+        match tzname.parse::<Tz>() {
+            Ok(tz) => {
+                // Map "now" in that time zone to a fixed offset
+                let now_utc = Utc::now();
+                let now_tz = now_utc.with_timezone(&tz);
+                let seconds = (now_tz.naive_local() - now_tz.naive_utc()).num_seconds();
+                FixedOffset::east_opt(seconds as i32)
+            }
+            Err(_) => {
+                warn!("Timezone parse failed; falling back to local");
+                None
+            }
+        }
+    } else {
+        None
+    }
+}
@@ -0,0 +1,255 @@
+// src/uniden.rs
+//
+// Uniden SDS100/SDS200 (and BCDx36HP) recordings as input. The scanner
+// writes one WAV per transmission, named after its start time
+// (`2025-09-10_06-43-56.wav`), and stores the scan context in the file: a
+// RIFF `LIST`/`INFO` chunk naming the favorites list, system, department
+// and channel, and Uniden's own `unid` chunk with the IDs and frequency.
+//
+// An input is either one recording or a directory of them (a recording
+// session, such as the card's `audio/user_rec` folder), walked in name
+// order, which is start time order. Each recording becomes one call with
+// `audio_path` set to the file, so `--transcriber whisper` transcribes it
+// directly.
+//
+// Fields are taken from the metadata text wherever they appear:
+//
+//   TGID:2  TGID=2         talkgroup
+//   UID:4506  UID=4506     radio ID
+//   851.012500MHz          frequency
+//   P25, DMR, NXDN, ...    radio type
+//   ICRD                   start time; else the file name, else its mtime
+//
+// The duration is the length of the audio. The INFO names are kept as the
+// extras `system`, `department` and `channel` with `--extras`.
+
+use crate::errors::AppError;
+use crate::input::{self, ParseNote, ParseOptions};
+use crate::model::{RadioRecord, SlotData};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone};
+use log::{debug, warn};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::Ordering;
+use tokio::sync::mpsc::Sender;
+
+/// What a recording's RIFF chunks say about it.
+#[derive(Debug, Default)]
+struct Metadata {
+    /// `LIST`/`INFO` entries (`INAM`, `ICRD`, ...).
+    info: Vec<(String, String)>,
+    /// Text fields of the `unid` chunk.
+    unid: Vec<String>,
+    /// Audio length in seconds, from `fmt ` and `data`.
+    seconds: Option<f64>,
+}
+
+impl Metadata {
+    fn info(&self, id: &str) -> Option<&str> {
+        self.info.iter().find(|(k, _)| k == id).map(|(_, v)| v.as_str()).filter(|v| !v.is_empty())
+    }
+
+    /// Every text field, `unid` first.
+    fn fields(&self) -> impl Iterator<Item = &str> {
+        self.unid.iter().map(String::as_str).chain(self.info.iter().map(|(_, v)| v.as_str()))
+    }
+}
+
+fn field_text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string()
+}
+
+/// Walk the chunks of a WAV file.
+fn read_metadata(bytes: &[u8]) -> Result<Metadata, String> {
+    if bytes.len() < 12 || &bytes[..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a RIFF/WAVE file".into());
+    }
+    let le32 = |b: &[u8]| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize;
+    let mut meta = Metadata::default();
+    let (mut byte_rate, mut data_len) = (None, None);
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = le32(&bytes[pos + 4..]);
+        let body = &bytes[pos + 8..(pos + 8 + size).min(bytes.len())];
+        match id {
+            b"fmt " if body.len() >= 12 => byte_rate = Some(le32(&body[8..])).filter(|r| *r > 0),
+            // A recording cut short still has the audio up to the end.
+            b"data" => data_len = Some(body.len()),
+            b"LIST" if body.starts_with(b"INFO") => {
+                let mut p = 4;
+                while p + 8 <= body.len() {
+                    let n = le32(&body[p + 4..]);
+                    let value = &body[p + 8..(p + 8 + n).min(body.len())];
+                    meta.info.push((String::from_utf8_lossy(&body[p..p + 4]).into_owned(), field_text(value)));
+                    p += 8 + n + (n & 1);
+                }
+            }
+            _ if id.eq_ignore_ascii_case(b"unid") => {
+                meta.unid = body
+                    .split(|b| matches!(b, 0 | b'\t' | b'\r' | b'\n'))
+                    .map(field_text)
+                    .filter(|s| !s.is_empty())
+                    .collect();
+            }
+            _ => {}
+        }
+        pos += 8 + size + (size & 1);
+    }
+    if let (Some(rate), Some(len)) = (byte_rate, data_len) {
+        meta.seconds = Some(len as f64 / rate as f64);
+    }
+    Ok(meta)
+}
+
+/// First capture of `re` in `s`.
+fn capture(re: &Regex, s: &str) -> Option<String> {
+    re.captures(s).and_then(|c| c.get(1)).map(|m| m.as_str().to_string())
+}
+
+fn parse_datetime(s: &str) -> Option<NaiveDateTime> {
+    ["%Y-%m-%d %H:%M:%S", "%Y/%m/%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(s.trim(), f).ok())
+}
+
+/// Start time from a Uniden file name (`2025-09-10_06-43-56.wav`).
+fn datetime_from_name(path: &Path) -> Option<NaiveDateTime> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| {
+        Regex::new(r"((?:19|20)\d{2})-?(\d{2})-?(\d{2})[_ T-](\d{2})[-:]?(\d{2})[-:]?(\d{2})").unwrap()
+    });
+    let caps = re.captures(path.file_name()?.to_str()?)?;
+    let n = |i: usize| caps[i].parse::<u32>().ok();
+    chrono::NaiveDate::from_ymd_opt(caps[1].parse().ok()?, n(2)?, n(3)?)?.and_hms_opt(n(4)?, n(5)?, n(6)?)
+}
+
+fn start_time(
+    path: &Path,
+    meta: &Metadata,
+    tz_offset: Option<FixedOffset>,
+) -> Result<DateTime<FixedOffset>, String> {
+    let naive = meta.info("ICRD").and_then(parse_datetime).or_else(|| datetime_from_name(path));
+    let local = |naive: NaiveDateTime| match tz_offset {
+        Some(off) => off.from_local_datetime(&naive).single().map(|dt| dt.fixed_offset()),
+        None => Local.from_local_datetime(&naive).single().map(|dt| dt.fixed_offset()),
+    };
+    if let Some(naive) = naive {
+        return local(naive).ok_or_else(|| format!("ambiguous/invalid local datetime {}", naive));
+    }
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).map_err(|e| e.to_string())?;
+    let dt: DateTime<Local> = modified.into();
+    Ok(match tz_offset {
+        Some(off) => dt.with_timezone(&off),
+        None => dt.fixed_offset(),
+    })
+}
+
+/// Build the call for one recording.
+fn parse_recording(
+    path: &Path,
+    bytes: &[u8],
+    record_number: usize,
+    opts: &ParseOptions,
+) -> Result<RadioRecord, String> {
+    static TG: OnceLock<Regex> = OnceLock::new();
+    static UID: OnceLock<Regex> = OnceLock::new();
+    static FREQ: OnceLock<Regex> = OnceLock::new();
+    static MODE: OnceLock<Regex> = OnceLock::new();
+    let tg_re = TG.get_or_init(|| Regex::new(r"(?i)\bTGID\s*[:=]\s*([0-9A-Za-z:.\-]+)").unwrap());
+    let uid_re = UID.get_or_init(|| Regex::new(r"(?i)\bUID\s*[:=]\s*(\d+)").unwrap());
+    let freq_re = FREQ.get_or_init(|| Regex::new(r"(?i)\b(\d{2,4}\.\d{2,6})\s*MHz").unwrap());
+    let mode_re = MODE.get_or_init(|| {
+        Regex::new(r"(?i)\b(P25(?:\s*Phase\s*(?:1|2|II|I))?|DMR|NXDN|dPMR|ProVoice|EDACS|LTR|NFM|FM)\b").unwrap()
+    });
+
+    let meta = read_metadata(bytes)?;
+    let all = meta.fields().collect::<Vec<_>>().join("\t");
+    // A field that is just the mode beats a name mentioning one ("County P25").
+    let mode = meta
+        .fields()
+        .find_map(|f| capture(mode_re, f).filter(|m| m.len() == f.len()))
+        .or_else(|| capture(mode_re, &all));
+    debug!("uniden: {}: {:?}", path.display(), all);
+
+    let mut rec = RadioRecord {
+        frequency: capture(freq_re, &all).map(|f| f.parse::<f64>().map_or(f, |v| format!("{:.6}", v))),
+        radio_type: mode,
+        slot1: SlotData { tg: capture(tg_re, &all), rid: capture(uid_re, &all), text: None },
+        encrypted: input::encrypted(&all),
+        duration: meta.seconds.map_or(1, |s| (s.round() as u32).max(1)),
+        audio_path: Some(path.to_path_buf()),
+        ..RadioRecord::new(record_number, start_time(path, &meta, opts.tz_offset)?)
+    };
+    if opts.extras {
+        for (id, key) in [("IGNR", "system"), ("IART", "department"), ("INAM", "channel")] {
+            if let Some(v) = meta.info(id) {
+                rec.extras.insert(key.to_string(), v.to_string());
+            }
+        }
+    }
+    opts.finish(&mut rec);
+    Ok(rec)
+}
+
+fn is_wav(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("wav"))
+}
+
+/// The recordings of an input: the file itself, or the WAV files under a
+/// directory in name order.
+fn recordings(path: &Path) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_path_buf()];
+    }
+    walkdir::WalkDir::new(path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file() && is_wav(e.path()))
+        .map(|e| e.into_path())
+        .collect()
+}
+
+/// Whether the start of a file looks like a WAV recording.
+pub fn sniff(head: &[u8]) -> bool {
+    head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WAVE".as_slice())
+}
+
+/// Stream one call per recording. `--follow` doesn't apply: Uniden writes
+/// each recording once.
+pub async fn stream_path(path: &Path, opts: ParseOptions, tx: Sender<RadioRecord>) -> Result<(), AppError> {
+    let source = input::source_name(path);
+    let files = recordings(path);
+    if files.is_empty() {
+        warn!("uniden: no .wav recordings under {}", path.display());
+    }
+    let mut recno = 1;
+    for (i, file) in files.iter().enumerate() {
+        let bytes = tokio::fs::read(file)
+            .await
+            .map_err(|e| AppError::IO(format!("open {}: {}", file.display(), e)))?;
+        if let Some(count) = &opts.bytes_read {
+            count.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        }
+        match parse_recording(file, &bytes, recno, &opts) {
+            Ok(mut rec) => {
+                rec.source = Some(std::sync::Arc::clone(&source));
+                opts.note(ParseNote::Record { line: i + 1 });
+                if tx.send(rec).await.is_err() {
+                    warn!("uniden: downstream closed; aborting");
+                    break;
+                }
+                recno += 1;
+            }
+            Err(e) if opts.lenient => {
+                warn!("uniden: skipping {}: {}", file.display(), e);
+                opts.recovered();
+                opts.note(ParseNote::Skipped { line: i + 1, reason: e });
+            }
+            Err(e) => return Err(AppError::Parse(format!("{}: {}", file.display(), e))),
+        }
+    }
+    Ok(())
+}
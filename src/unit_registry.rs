@@ -0,0 +1,295 @@
+// src/unit_registry.rs
+//
+// `--unit-registry FILE`: a JSON file of every talk group and radio ID ever
+// heard, kept across runs: when each was first and last heard, on how many
+// calls, for how much airtime, and on which frequency and radio type (and,
+// for a radio, which talk group) last. Each run adds its calls, so pointing every
+// conversion at the same file builds up a picture of the system without
+// keeping the old outputs around. `callscribe registry show` prints it.
+//
+// A call counts once for each distinct TG and RID on it (both DMR slots, and
+// the called unit of a private call). Converting the same log again counts
+// its calls again; cron jobs would use `--skip-processed`. The file is
+// written when the run ends (a daemon's, when it is stopped). `registry
+// export` turns it into an SDRTrunk playlist (see `sdrtrunk.rs`).
+
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use chrono::{DateTime, FixedOffset};
+use log::warn;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc::{Receiver, Sender};
+
+/// Registry file of `registry show` when none is given.
+pub const DEFAULT_FILE: &str = "callscribe-registry.json";
+
+/// What is known about one TG or RID.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Sighting {
+    pub first_seen: DateTime<FixedOffset>,
+    pub last_seen: DateTime<FixedOffset>,
+    pub calls: u64,
+    pub airtime_s: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_frequency: Option<String>,
+    /// For a radio: the talk group of its latest call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_tg: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_radio_type: Option<String>,
+}
+
+impl Sighting {
+    fn new(rec: &RadioRecord) -> Self {
+        Self {
+            first_seen: rec.datetime,
+            last_seen: rec.datetime,
+            calls: 0,
+            airtime_s: 0,
+            last_frequency: None,
+            last_tg: None,
+            last_radio_type: None,
+        }
+    }
+
+    fn add(&mut self, rec: &RadioRecord, tg: Option<&String>) {
+        self.calls += 1;
+        self.airtime_s += rec.duration as u64;
+        self.first_seen = self.first_seen.min(rec.datetime);
+        if rec.datetime >= self.last_seen {
+            self.last_seen = rec.datetime;
+            if rec.frequency.is_some() {
+                self.last_frequency = rec.frequency.clone();
+            }
+            if tg.is_some() {
+                self.last_tg = tg.cloned();
+            }
+            if rec.radio_type.is_some() {
+                self.last_radio_type = rec.radio_type.clone();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    #[serde(default)]
+    updated_at: Option<String>,
+    #[serde(default)]
+    tgs: BTreeMap<String, Sighting>,
+    #[serde(default)]
+    rids: BTreeMap<String, Sighting>,
+}
+
+/// Which part of the registry `registry show` prints.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    All,
+    Tg,
+    Rid,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Order {
+    Id,
+    Calls,
+    Airtime,
+    First,
+    Last,
+}
+
+pub struct ShowOptions {
+    pub kind: Kind,
+    pub order: Order,
+    /// Only these IDs; all when empty.
+    pub ids: Vec<String>,
+    /// Rows per table; 0 for all.
+    pub limit: usize,
+}
+
+impl ShowOptions {
+    pub fn parse(kind: &str, order: &str, ids: Vec<String>, limit: usize) -> Result<Self, AppError> {
+        let kind = match kind.trim().to_ascii_lowercase().as_str() {
+            "all" => Kind::All,
+            "tg" => Kind::Tg,
+            "rid" => Kind::Rid,
+            _ => return Err(AppError::Parse(format!("invalid --kind '{}': expected all, tg or rid", kind))),
+        };
+        let order = match order.trim().to_ascii_lowercase().as_str() {
+            "id" => Order::Id,
+            "calls" => Order::Calls,
+            "airtime" => Order::Airtime,
+            "first" => Order::First,
+            "last" => Order::Last,
+            _ => {
+                return Err(AppError::Parse(format!(
+                    "invalid --sort '{}': expected id, calls, airtime, first or last",
+                    order
+                )));
+            }
+        };
+        Ok(Self { kind, order, ids, limit })
+    }
+}
+
+pub struct UnitRegistry {
+    path: PathBuf,
+    state: Mutex<State>,
+}
+
+fn io_err(path: &Path) -> impl Fn(std::io::Error) -> AppError + '_ {
+    move |e| AppError::IO(format!("unit registry {}: {}", path.display(), e))
+}
+
+fn hms(secs: u64) -> String {
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Numeric IDs in numeric order, the rest after them.
+fn id_order(a: &str, b: &str) -> std::cmp::Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(x), Ok(y)) => x.cmp(&y),
+        (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+        (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+impl UnitRegistry {
+    /// Load `path`; a missing file is an empty registry.
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let state = match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| AppError::Parse(format!("unit registry {}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => State::default(),
+            Err(e) => return Err(io_err(path)(e)),
+        };
+        Ok(Self { path: path.to_path_buf(), state: Mutex::new(state) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Count one call.
+    pub fn observe(&self, rec: &RadioRecord) {
+        let mut tgs: Vec<&String> = Vec::new();
+        let mut rids: Vec<(&String, Option<&String>)> = Vec::new();
+        for slot in [&rec.slot1, &rec.slot2] {
+            if let Some(tg) = slot.tg.as_ref().filter(|t| !t.is_empty() && !tgs.contains(t)) {
+                tgs.push(tg);
+            }
+            if let Some(rid) = slot.rid.as_ref().filter(|r| !r.is_empty() && !rids.iter().any(|(x, _)| x == r)) {
+                rids.push((rid, slot.tg.as_ref()));
+            }
+        }
+        if let Some(rid) = rec.target_rid.as_ref().filter(|r| !r.is_empty() && !rids.iter().any(|(x, _)| x == r)) {
+            rids.push((rid, None));
+        }
+        let mut st = self.state.lock();
+        for tg in tgs {
+            st.tgs.entry(tg.clone()).or_insert_with(|| Sighting::new(rec)).add(rec, None);
+        }
+        for (rid, tg) in rids {
+            st.rids.entry(rid.clone()).or_insert_with(|| Sighting::new(rec)).add(rec, tg);
+        }
+    }
+
+    /// Write the file (via a temp file and rename, so an interrupted write
+    /// leaves the old registry).
+    pub fn save(&self) -> Result<(), AppError> {
+        let json = {
+            let mut st = self.state.lock();
+            st.updated_at = Some(chrono::Local::now().to_rfc3339());
+            serde_json::to_vec_pretty(&*st)
+                .map_err(|e| AppError::Other(format!("unit registry {}: {}", self.path.display(), e)))?
+        };
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, json).map_err(io_err(&tmp))?;
+        std::fs::rename(&tmp, &self.path).map_err(io_err(&self.path))?;
+        Ok(())
+    }
+
+    /// The rows of `map` that `opts` picks, in its order.
+    fn pick<'a>(map: &'a BTreeMap<String, Sighting>, opts: &ShowOptions) -> Vec<(&'a String, &'a Sighting)> {
+        let mut rows: Vec<(&String, &Sighting)> =
+            map.iter().filter(|(id, _)| opts.ids.is_empty() || opts.ids.contains(id)).collect();
+        match opts.order {
+            Order::Id => rows.sort_by(|a, b| id_order(a.0, b.0)),
+            Order::Calls => rows.sort_by(|a, b| b.1.calls.cmp(&a.1.calls).then(id_order(a.0, b.0))),
+            Order::Airtime => rows.sort_by(|a, b| b.1.airtime_s.cmp(&a.1.airtime_s).then(id_order(a.0, b.0))),
+            Order::First => rows.sort_by_key(|r| r.1.first_seen),
+            Order::Last => rows.sort_by_key(|r| std::cmp::Reverse(r.1.last_seen)),
+        }
+        if opts.limit > 0 {
+            rows.truncate(opts.limit);
+        }
+        rows
+    }
+
+    /// The talk groups (`Kind::Tg`) or radios that `opts` picks, in its
+    /// order.
+    pub fn rows(&self, kind: Kind, opts: &ShowOptions) -> Vec<(String, Sighting)> {
+        let st = self.state.lock();
+        let map = if kind == Kind::Rid { &st.rids } else { &st.tgs };
+        Self::pick(map, opts).into_iter().map(|(id, s)| (id.clone(), s.clone())).collect()
+    }
+
+    /// Print the talk group and/or radio tables.
+    pub fn show(&self, opts: &ShowOptions, out: &mut impl Write) -> Result<(), AppError> {
+        let io = |e: std::io::Error| AppError::IO(format!("write: {}", e));
+        let st = self.state.lock();
+        if let Some(at) = &st.updated_at {
+            writeln!(out, "{} (updated {})", self.path.display(), at).map_err(io)?;
+        }
+        let tables = [(Kind::Tg, "Talk groups", "tg", &st.tgs), (Kind::Rid, "Radios", "rid", &st.rids)];
+        for (kind, title, column, map) in tables {
+            if opts.kind != Kind::All && opts.kind != kind {
+                continue;
+            }
+            let rows = Self::pick(map, opts);
+            let shown = if rows.len() < map.len() { format!(" ({} shown)", rows.len()) } else { String::new() };
+            writeln!(out, "\n{}: {}{}", title, map.len(), shown).map_err(io)?;
+            let line = |id: &str, calls: &str, airtime: &str, first: &str, last: &str, freq: &str, tg: &str| {
+                let line = format!("{:<10} {:>7} {:>10}  {:<19}  {:<19}  {:<12}  {}", id, calls, airtime, first, last, freq, tg);
+                line.trim_end().to_string()
+            };
+            let tg_column = if kind == Kind::Rid { "last tg" } else { "" };
+            writeln!(out, "{}", line(column, "calls", "airtime", "first seen", "last seen", "last freq", tg_column)).map_err(io)?;
+            for (id, s) in rows {
+                let row = line(
+                    id,
+                    &s.calls.to_string(),
+                    &hms(s.airtime_s),
+                    &s.first_seen.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    &s.last_seen.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    s.last_frequency.as_deref().unwrap_or("-"),
+                    if kind == Kind::Rid { s.last_tg.as_deref().unwrap_or("-") } else { "" },
+                );
+                writeln!(out, "{}", row).map_err(io)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Forward every record unchanged while adding it to `registry`.
+pub async fn observe_stream(
+    mut rx: Receiver<RadioRecord>,
+    tx: Sender<RadioRecord>,
+    registry: Arc<UnitRegistry>,
+) -> Result<Vec<PathBuf>, AppError> {
+    while let Some(rec) = rx.recv().await {
+        registry.observe(&rec);
+        if tx.send(rec).await.is_err() {
+            warn!("unit registry: downstream closed");
+            break;
+        }
+    }
+    Ok(vec![])
+}
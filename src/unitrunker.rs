@@ -0,0 +1,238 @@
+// src/unitrunker.rs
+//
+// Unitrunker call logs, as exported to CSV or TSV: a header line naming the
+// columns, then one call per line. Columns are found by name, ignoring case,
+// spaces and punctuation, so exports with columns reordered or left out
+// still parse:
+//
+//   date + time, or datetime / timestamp / start   call start
+//   group, talkgroup, tg, tgid, target             talkgroup
+//   radio, source, rid, unit, uid                  radio ID
+//   frequency, freq                                MHz (or Hz)
+//   duration, length, seconds                      `7`, `7.4`, `0:07`, `00:00:07`
+//   type, call type                                group / private / emergency / data
+//   protocol, mode, system type                    radio type
+//   encrypted, enc                                 yes/true/1
+//
+// A delimiter is picked from the header (tab, else comma, else semicolon);
+// fields may be double-quoted. Rows that don't parse are skipped (see
+// `explain`). The site, system name and other unmapped columns are kept as
+// extras with `--extras`.
+//
+//   Date,Time,Site,Group,Radio,Frequency,Duration,Type
+//   09/10/2025,06:43:56,001-002,2,4506,851.0125,7,Group
+
+use crate::errors::AppError;
+use crate::input::{self, NumberedLines, ParseNote, ParseOptions, TimeOnlyClock};
+use crate::model::{CallType, RadioRecord, SlotData};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, NaiveTime, TimeZone};
+use log::{debug, warn};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+
+/// Header spellings of each field, after `column_key`.
+const DATETIME: &[&str] = &["datetime", "timestamp", "start", "starttime"];
+const DATE: &[&str] = &["date"];
+const TIME: &[&str] = &["time"];
+const TG: &[&str] = &["group", "talkgroup", "tg", "tgid", "target"];
+const RID: &[&str] = &["radio", "source", "rid", "unit", "uid", "radioid"];
+const FREQ: &[&str] = &["frequency", "freq"];
+const DURATION: &[&str] = &["duration", "length", "seconds", "secs"];
+const CALL_TYPE: &[&str] = &["type", "calltype"];
+const RADIO_TYPE: &[&str] = &["protocol", "mode", "systemtype"];
+const ENCRYPTED: &[&str] = &["encrypted", "enc", "encryption"];
+
+const DATETIME_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y/%m/%d %H:%M:%S",
+    "%m/%d/%Y %H:%M:%S",
+    "%m/%d/%Y %I:%M:%S %p",
+];
+
+fn column_key(s: &str) -> String {
+    s.chars().filter(char::is_ascii_alphanumeric).flat_map(|c| c.to_lowercase()).collect()
+}
+
+/// Split one line on `delim`, honouring double quotes (`""` inside quotes is `"`).
+fn split_fields(line: &str, delim: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut cur = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                cur.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delim && !quoted => fields.push(std::mem::take(&mut cur).trim().to_string()),
+            c => cur.push(c),
+        }
+    }
+    fields.push(cur.trim().to_string());
+    fields
+}
+
+/// Column positions, from the header line.
+struct Columns {
+    delim: char,
+    names: Vec<String>,
+    keys: Vec<String>,
+}
+
+impl Columns {
+    fn from_header(line: &str) -> Result<Self, String> {
+        let line = line.strip_prefix('\u{FEFF}').unwrap_or(line);
+        let delim = ['\t', ',', ';'].into_iter().find(|d| line.contains(*d)).unwrap_or(',');
+        let names = split_fields(line, delim);
+        let keys: Vec<String> = names.iter().map(|n| column_key(n)).collect();
+        let cols = Self { delim, names, keys };
+        let has = |k: &[&str]| cols.find(k).is_some();
+        if !has(DATETIME) && !has(TIME) {
+            return Err(format!("header {:?} has no date/time column", line));
+        }
+        if !has(TG) && !has(RID) && !has(FREQ) {
+            return Err(format!("header {:?} has no group, radio or frequency column", line));
+        }
+        Ok(cols)
+    }
+
+    fn find(&self, aliases: &[&str]) -> Option<usize> {
+        aliases.iter().find_map(|a| self.keys.iter().position(|k| k == a))
+    }
+
+    fn mapped(&self, i: usize) -> bool {
+        [DATETIME, DATE, TIME, TG, RID, FREQ, DURATION, CALL_TYPE, RADIO_TYPE, ENCRYPTED]
+            .iter()
+            .any(|aliases| self.find(aliases) == Some(i))
+    }
+}
+
+fn to_local(naive: NaiveDateTime, tz_offset: Option<FixedOffset>) -> Result<DateTime<FixedOffset>, String> {
+    match tz_offset {
+        Some(off) => off.from_local_datetime(&naive).single().map(|dt| dt.fixed_offset()),
+        None => Local.from_local_datetime(&naive).single().map(|dt| dt.fixed_offset()),
+    }
+    .ok_or_else(|| format!("ambiguous/invalid local datetime {}", naive))
+}
+
+/// Seconds from `7`, `7.4`, `7s`, `0:07` or `00:00:07`.
+fn parse_duration(s: &str) -> Option<u32> {
+    let s = s.trim().trim_end_matches('s');
+    if s.contains(':') {
+        let secs = s.split(':').try_fold(0.0f64, |acc, p| p.trim().parse::<f64>().ok().map(|v| acc * 60.0 + v))?;
+        return Some(secs.round() as u32);
+    }
+    s.parse::<f64>().ok().map(|v| v.round() as u32)
+}
+
+/// MHz with six decimals; values too large to be MHz are taken as Hz.
+fn parse_frequency(s: &str) -> Option<String> {
+    let v = s.trim().trim_end_matches(|c: char| c.is_ascii_alphabetic()).trim().parse::<f64>().ok()?;
+    let mhz = if v > 100_000.0 { v / 1e6 } else { v };
+    Some(format!("{:.6}", mhz))
+}
+
+fn truthy(s: &str) -> bool {
+    matches!(s.trim().to_ascii_lowercase().as_str(), "1" | "y" | "yes" | "true" | "encrypted" | "enc")
+}
+
+fn parse_row(
+    cols: &Columns,
+    line: &str,
+    record_number: usize,
+    opts: &ParseOptions,
+    clock: Option<&mut TimeOnlyClock>,
+) -> Result<RadioRecord, String> {
+    let fields = split_fields(line, cols.delim);
+    let get = |aliases: &[&str]| {
+        cols.find(aliases).and_then(|i| fields.get(i)).map(String::as_str).filter(|v| !v.is_empty())
+    };
+
+    let naive = match (get(DATETIME), get(DATE), get(TIME)) {
+        (Some(dt), _, _) => DATETIME_FORMATS.iter().find_map(|f| NaiveDateTime::parse_from_str(dt, f).ok()),
+        (None, Some(d), Some(t)) => {
+            let dt = format!("{} {}", d, t);
+            DATETIME_FORMATS.iter().find_map(|f| NaiveDateTime::parse_from_str(&dt, f).ok())
+        }
+        (None, None, Some(t)) => match (NaiveTime::parse_from_str(t, "%H:%M:%S"), clock) {
+            (Ok(t), Some(clock)) => Some(clock.resolve(t)),
+            _ => return Err(format!("time {:?} without a date (try --date-from-filename)", t)),
+        },
+        _ => None,
+    }
+    .ok_or_else(|| format!("no parseable date/time in {:?}", line))?;
+
+    let call_type = get(CALL_TYPE).and_then(|t| CallType::from_name(t).or_else(|| CallType::detect(t)));
+    let mut rec = RadioRecord {
+        frequency: get(FREQ).and_then(parse_frequency),
+        radio_type: get(RADIO_TYPE).map(str::to_string),
+        slot1: SlotData { tg: get(TG).map(str::to_string), rid: get(RID).map(str::to_string), text: None },
+        call_type: call_type.unwrap_or_default(),
+        encrypted: get(ENCRYPTED).is_some_and(truthy),
+        duration: get(DURATION).and_then(parse_duration).unwrap_or(1).max(1),
+        ..RadioRecord::new(record_number, to_local(naive, opts.tz_offset)?)
+    };
+    rec.map_private_target();
+    if opts.extras {
+        for (i, v) in fields.iter().enumerate() {
+            if let Some(k) = cols.keys.get(i)
+                && !k.is_empty()
+                && !v.is_empty()
+                && !cols.mapped(i)
+            {
+                rec.extras.insert(k.clone(), v.clone());
+            }
+        }
+    }
+    opts.finish(&mut rec);
+    Ok(rec)
+}
+
+/// Whether the start of a file looks like a Unitrunker export: its first
+/// line is a header with the columns a call needs.
+pub fn sniff(head: &str) -> bool {
+    head.lines().find(|l| !l.trim().is_empty()).is_some_and(|l| Columns::from_header(l).is_ok())
+}
+
+pub async fn stream_file(path: &Path, opts: ParseOptions, tx: Sender<RadioRecord>) -> Result<(), AppError> {
+    let file = input::open_input(path, &opts).await?;
+    let mut lines = NumberedLines::new(file, &opts);
+    let source = input::source_name(path);
+    let mut clock = opts.base_date.map(TimeOnlyClock::new);
+
+    let mut cols = None;
+    let mut recno: usize = 1;
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some(c) = &cols else {
+            let header = Columns::from_header(&line)
+                .map_err(|e| AppError::Parse(format!("{}: line {}: {}", path.display(), lines.line, e)))?;
+            let names = header.names.join(", ");
+            opts.note(ParseNote::Skipped { line: lines.line, reason: format!("header: {}", names) });
+            cols = Some(header);
+            continue;
+        };
+        match parse_row(c, &line, recno, &opts, clock.as_mut()) {
+            Ok(mut rec) => {
+                rec.source = Some(Arc::clone(&source));
+                opts.note(ParseNote::Record { line: lines.line });
+                if tx.send(rec).await.is_err() {
+                    warn!("unitrunker: downstream closed; aborting");
+                    break;
+                }
+                recno += 1;
+            }
+            Err(reason) => {
+                debug!("unitrunker: line {}: {}", lines.line, reason);
+                opts.note(ParseNote::Skipped { line: lines.line, reason });
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,170 @@
+// src/utilization.rs
+//
+// `--utilization csv|json`: per-frequency channel loading, as busy seconds
+// per minute, written next to the main output once the stream ends
+// (`<out stem>.utilization.csv` / `.json`). Calls are spread over the
+// minutes they cover, and overlapping calls on one frequency (both DMR
+// slots, duplicate log lines) count each second once. Minutes without
+// traffic between a frequency's first and last busy minute are written
+// as zero, so charts show idle periods instead of joining the dots.
+
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use chrono::{DateTime, FixedOffset};
+use log::{info, warn};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc::{Receiver, Sender};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UtilizationFormat {
+    Csv,
+    Json,
+}
+
+impl UtilizationFormat {
+    pub fn parse(s: &str) -> Result<Self, AppError> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "csv" => Ok(UtilizationFormat::Csv),
+            "json" => Ok(UtilizationFormat::Json),
+            _ => Err(AppError::Parse(format!("unknown --utilization '{}' (use csv, json)", s))),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            UtilizationFormat::Csv => "utilization.csv",
+            UtilizationFormat::Json => "utilization.json",
+        }
+    }
+}
+
+pub fn utilization_path(out_path: &Path, format: UtilizationFormat) -> PathBuf {
+    out_path.with_extension(format.extension())
+}
+
+#[derive(Clone, Copy)]
+struct Minute {
+    /// Bit n set: second n of the minute was on air.
+    busy: u64,
+    /// Calls starting in this minute.
+    calls: u32,
+    /// The offset of the calls, for printing the minute in local time.
+    offset: FixedOffset,
+}
+
+impl Minute {
+    fn idle(offset: FixedOffset) -> Self {
+        Self { busy: 0, calls: 0, offset }
+    }
+
+    fn label(&self, minute: i64, rfc3339: bool) -> String {
+        let t = DateTime::from_timestamp(minute * 60, 0).unwrap_or_default().with_timezone(&self.offset);
+        if rfc3339 { t.to_rfc3339() } else { t.format("%Y-%m-%d %H:%M").to_string() }
+    }
+}
+
+/// Busy seconds per frequency and minute (Unix minutes).
+#[derive(Default)]
+struct Timeline {
+    channels: BTreeMap<String, BTreeMap<i64, Minute>>,
+}
+
+impl Timeline {
+    fn add(&mut self, rec: &RadioRecord) {
+        let Some(freq) = rec.frequency.as_deref() else { return };
+        let offset = *rec.datetime.offset();
+        let minutes = self.channels.entry(freq.to_string()).or_default();
+        let start = rec.datetime.timestamp();
+        let end = start + rec.duration.max(1) as i64;
+        minutes.entry(start.div_euclid(60)).or_insert_with(|| Minute::idle(offset)).calls += 1;
+        let mut t = start;
+        while t < end {
+            let minute = t.div_euclid(60);
+            let (from, to) = (t - minute * 60, (end - minute * 60).min(60));
+            let mask = ((1u64 << (to - from)) - 1) << from;
+            minutes.entry(minute).or_insert_with(|| Minute::idle(offset)).busy |= mask;
+            t = (minute + 1) * 60;
+        }
+    }
+
+    /// Every minute from each frequency's first to its last, gaps as idle.
+    fn rows(&self) -> Vec<(&str, i64, Minute)> {
+        let mut rows = Vec::new();
+        for (freq, minutes) in &self.channels {
+            let (Some((&first, from)), Some((&last, _))) = (minutes.first_key_value(), minutes.last_key_value())
+            else {
+                continue;
+            };
+            let mut offset = from.offset;
+            for m in first..=last {
+                let minute = minutes.get(&m).copied().unwrap_or_else(|| Minute::idle(offset));
+                offset = minute.offset;
+                rows.push((freq.as_str(), m, minute));
+            }
+        }
+        rows
+    }
+
+    fn to_csv(&self) -> String {
+        let mut out = String::from("frequency,minute,busy_s,utilization_pct,calls\n");
+        for (freq, m, minute) in self.rows() {
+            let busy = minute.busy.count_ones();
+            out.push_str(&format!(
+                "{},{},{},{:.1},{}\n",
+                freq,
+                minute.label(m, false),
+                busy,
+                busy as f64 * 100.0 / 60.0,
+                minute.calls
+            ));
+        }
+        out
+    }
+
+    fn to_json(&self) -> String {
+        let mut channels: BTreeMap<&str, Vec<serde_json::Value>> = BTreeMap::new();
+        for (freq, m, minute) in self.rows() {
+            channels.entry(freq).or_default().push(json!({
+                "minute": minute.label(m, true),
+                "busy_s": minute.busy.count_ones(),
+                "calls": minute.calls,
+            }));
+        }
+        let doc = json!({ "bucket_s": 60, "frequencies": channels });
+        serde_json::to_string_pretty(&doc).unwrap_or_default() + "\n"
+    }
+}
+
+/// Forward every record unchanged; write the timeline once the stream ends.
+pub async fn write_utilization_stream(
+    mut rx: Receiver<RadioRecord>,
+    tx: Sender<RadioRecord>,
+    out_path: PathBuf,
+    format: UtilizationFormat,
+) -> Result<Vec<PathBuf>, AppError> {
+    let mut timeline = Timeline::default();
+    while let Some(rec) = rx.recv().await {
+        timeline.add(&rec);
+        if tx.send(rec).await.is_err() {
+            warn!("utilization: downstream closed");
+            break;
+        }
+    }
+
+    let path = utilization_path(&out_path, format);
+    let body = match format {
+        UtilizationFormat::Csv => timeline.to_csv(),
+        UtilizationFormat::Json => timeline.to_json(),
+    };
+    let file = tokio::fs::File::create(&path)
+        .await
+        .map_err(|e| AppError::IO(format!("open utilization '{}': {}", path.display(), e)))?;
+    let mut w = BufWriter::new(file);
+    w.write_all(body.as_bytes()).await?;
+    w.flush().await?;
+    info!("Utilization: {} frequencies -> {}", timeline.channels.len(), path.display());
+    Ok(vec![path])
+}
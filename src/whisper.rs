@@ -0,0 +1,199 @@
+// src/whisper.rs
+//
+// Local speech-to-text via whisper.cpp (feature `stt-whisper`). The matching
+// recording is located with the same day-sharded filename index the text
+// transcriber uses, then handed to the `whisper-cli` executable; its plain
+// text output becomes the record's transcript. `[stt] prompt` and
+// `vocabulary` (or a profile's) go in as the initial prompt, biasing it
+// toward local names it would otherwise mishear.
+
+use crate::audio_prep;
+use crate::config::LanguageHints;
+use crate::errors::AppError;
+use crate::model::RadioRecord;
+use crate::transcriber::{AUDIO_EXTENSIONS, RecordFileIndex, Transcriber, choose_root};
+use crate::winpath;
+use log::{debug, warn};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+/// Where inference runs. CUDA vs Metal is decided when whisper.cpp is built;
+/// this selects CPU-only or GPU, and which GPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SttDevice {
+    Cpu,
+    Cuda(u32),
+    Metal,
+}
+
+impl SttDevice {
+    /// `cpu`, `cuda`, `cuda:N`, or `metal`.
+    pub fn parse(s: &str) -> Result<Self, AppError> {
+        let bad = || AppError::Other(format!("invalid --stt-device '{}': use cpu, cuda, cuda:N or metal", s));
+        match s.to_ascii_lowercase().as_str() {
+            "cpu" => Ok(SttDevice::Cpu),
+            "cuda" | "gpu" => Ok(SttDevice::Cuda(0)),
+            "metal" => Ok(SttDevice::Metal),
+            other => match other.strip_prefix("cuda:") {
+                Some(n) => n.parse().map(SttDevice::Cuda).map_err(|_| bad()),
+                None => Err(bad()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WhisperOptions {
+    /// `whisper-cli` executable (name on PATH or full path).
+    pub bin: PathBuf,
+    /// ggml model file.
+    pub model: PathBuf,
+    pub device: SttDevice,
+    /// Decoder threads per call; whisper-cli's default when `None`.
+    pub threads: Option<usize>,
+    /// Resample/trim/normalise WAV audio first (see `audio_prep`).
+    pub preprocess: bool,
+    /// Per-TG/frequency language (`-l`); auto-detect when none applies.
+    pub languages: LanguageHints,
+    /// Initial prompt (`--prompt`), from `[stt] prompt` / `vocabulary`.
+    pub prompt: Option<String>,
+    /// Recording extensions to match; [`AUDIO_EXTENSIONS`] when `None`.
+    pub audio_extensions: Option<Vec<String>>,
+    /// Recordings this far off the record's frequency still match.
+    pub freq_tolerance: crate::freq::FreqTolerance,
+}
+
+/// Prompt tokens whisper.cpp conditions on (half its 448-token text context).
+const PROMPT_TOKENS: usize = 224;
+
+pub struct WhisperTranscriber {
+    root: PathBuf,
+    opts: WhisperOptions,
+    audio: RecordFileIndex,
+}
+
+impl WhisperTranscriber {
+    pub fn new(root: &Path, opts: WhisperOptions) -> Result<Self, AppError> {
+        if !opts.model.is_file() {
+            return Err(AppError::Other(format!("whisper model not found: {}", opts.model.display())));
+        }
+        let audio = match &opts.audio_extensions {
+            Some(exts) => RecordFileIndex::new(exts),
+            None => RecordFileIndex::new(AUDIO_EXTENSIONS),
+        }
+        .with_freq_tolerance(opts.freq_tolerance);
+        // whisper.cpp keeps only the last half-context of the prompt; at
+        // roughly four characters a token the start is cut off past this.
+        if let Some(prompt) = &opts.prompt
+            && prompt.len() > PROMPT_TOKENS * 4
+        {
+            warn!(
+                "whisper: the STT prompt is {} characters; whisper uses only the last ~{} tokens, so the first terms of the vocabulary may be ignored",
+                prompt.len(),
+                PROMPT_TOKENS
+            );
+        }
+        Ok(Self { root: root.to_path_buf(), opts, audio })
+    }
+
+    fn command(&self, audio: &Path, language: Option<&str>) -> Command {
+        let mut cmd = Command::new(&self.opts.bin);
+        cmd.arg("-m").arg(&self.opts.model);
+        cmd.arg("-f").arg(audio);
+        cmd.arg("-l").arg(language.unwrap_or("auto"));
+        if let Some(prompt) = &self.opts.prompt {
+            cmd.arg("--prompt").arg(prompt);
+        }
+        // Transcript text only: no timestamps, no progress/system info.
+        cmd.args(["-nt", "-np"]);
+        if let Some(t) = self.opts.threads {
+            cmd.arg("-t").arg(t.to_string());
+        }
+        match self.opts.device {
+            SttDevice::Cpu => {
+                cmd.arg("-ng");
+            }
+            SttDevice::Cuda(n) => {
+                cmd.arg("-dev").arg(n.to_string());
+            }
+            SttDevice::Metal => {}
+        }
+        cmd
+    }
+}
+
+impl Transcriber for WhisperTranscriber {
+    fn transcribe(
+        &self,
+        rec: &RadioRecord,
+        record_dir: &Path,
+    ) -> Result<Option<String>, Option<AppError>> {
+        // Recordings that came with the input (Uniden) need no lookup.
+        let audio = match &rec.audio_path {
+            Some(path) => path.clone(),
+            None => {
+                let root = choose_root(record_dir, &self.root).map_err(Some)?;
+                let Some(audio) = self.audio.find(rec, root).map_err(Some)? else {
+                    return Ok(None);
+                };
+                audio
+            }
+        };
+
+        let started = Instant::now();
+        let prepared = if self.opts.preprocess { Some(audio_prep::prepare(&audio).map_err(Some)?) } else { None };
+        let input = prepared.as_ref().map_or(audio.as_path(), |p| p.path.as_path());
+        let language = self.opts.languages.for_record(rec);
+        let out = self.command(input, language).output().map_err(|e| {
+            Some(AppError::IO(format!("run {}: {}", self.opts.bin.display(), e)))
+        })?;
+        let elapsed = started.elapsed().as_secs_f64();
+        if !out.status.success() {
+            return Err(Some(AppError::Other(format!(
+                "{} failed on {} ({}): {}",
+                self.opts.bin.display(),
+                winpath::simplified(&audio).display(),
+                out.status,
+                String::from_utf8_lossy(&out.stderr).trim()
+            ))));
+        }
+
+        // Realtime factor against the call's blocks (≈ seconds of audio).
+        debug!(
+            "whisper: rec#{} {} [{}] in {:.2}s ({:.2}x realtime, device {:?}, threads {})",
+            rec.record_number,
+            winpath::simplified(&audio).display(),
+            language.unwrap_or("auto"),
+            elapsed,
+            elapsed / rec.duration.max(1) as f64,
+            self.opts.device,
+            self.opts.threads.map_or("default".to_string(), |t| t.to_string())
+        );
+        let text = String::from_utf8_lossy(&out.stdout).split_whitespace().collect::<Vec<_>>().join(" ");
+        Ok(if text.is_empty() { None } else { Some(text) })
+    }
+
+    /// Run whisper-cli once on a second of silence. That loads the model on
+    /// the chosen device, so a wrong executable, model or GPU shows up now.
+    fn health(&self) -> Result<(), AppError> {
+        let probe = audio_prep::silence()?;
+        let started = Instant::now();
+        let out = self
+            .command(&probe.path, Some("en"))
+            .output()
+            .map_err(|e| AppError::IO(format!("run {}: {}", self.opts.bin.display(), e)))?;
+        if !out.status.success() {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            return Err(AppError::Other(format!(
+                "{} could not run model {} ({}): {}",
+                self.opts.bin.display(),
+                winpath::simplified(&self.opts.model).display(),
+                out.status,
+                stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("").trim()
+            )));
+        }
+        debug!("whisper: model loaded in {:.2}s", started.elapsed().as_secs_f64());
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+// src/winpath.rs
+//
+// Windows path clean-up for record directories. Win32 calls fail on paths
+// longer than MAX_PATH (260) unless they carry the `\\?\` prefix, and
+// network shares need the `\\?\UNC\server\share` form of that prefix.
+// Paths handed to the record-file index are converted here. On other
+// platforms they are returned unchanged.
+
+use std::path::{Path, PathBuf};
+
+/// `\\?\`-prefixed form of `p` on Windows (made absolute first). Other
+/// platforms, and paths that can't be made absolute, are returned unchanged.
+pub fn extended(p: &Path) -> PathBuf {
+    if cfg!(windows) {
+        let abs = std::path::absolute(p).unwrap_or_else(|_| p.to_path_buf());
+        match abs.to_str() {
+            Some(s) => PathBuf::from(extend_str(s)),
+            None => abs,
+        }
+    } else {
+        p.to_path_buf()
+    }
+}
+
+/// The conversion itself, on an absolute Windows path string:
+/// - `C:\dir` becomes `\\?\C:\dir`
+/// - `\\server\share\dir` becomes `\\?\UNC\server\share\dir`
+/// - `\\?\…` and `\\.\…` (device) paths, and anything relative, are left as is
+///
+/// Forward slashes are turned into backslashes, because the prefix turns off
+/// Win32's own separator handling.
+pub fn extend_str(s: &str) -> String {
+    let s = s.replace('/', "\\");
+    if s.starts_with(r"\\?\") || s.starts_with(r"\\.\") {
+        s
+    } else if let Some(unc) = s.strip_prefix(r"\\") {
+        format!(r"\\?\UNC\{}", unc)
+    } else if s.as_bytes().get(1) == Some(&b':') && s.as_bytes().get(2) == Some(&b'\\') {
+        format!(r"\\?\{}", s)
+    } else {
+        s
+    }
+}
+
+/// `p` without a `\\?\` prefix (`\\?\UNC\server\share` back to
+/// `\\server\share`), for display and for paths written to outputs.
+pub fn simplified(p: &Path) -> PathBuf {
+    match p.to_str() {
+        Some(s) => match s.strip_prefix(r"\\?\UNC\") {
+            Some(unc) => PathBuf::from(format!(r"\\{}", unc)),
+            None => PathBuf::from(s.strip_prefix(r"\\?\").unwrap_or(s)),
+        },
+        None => p.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(s: &str) -> String {
+        simplified(Path::new(&extend_str(s))).to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn drive_path() {
+        assert_eq!(extend_str(r"C:\x"), r"\\?\C:\x");
+        assert_eq!(extend_str("C:/Record/20250909"), r"\\?\C:\Record\20250909");
+        assert_eq!(round_trip(r"C:\x"), r"C:\x");
+    }
+
+    #[test]
+    fn unc_root() {
+        assert_eq!(extend_str(r"\\server\share\x"), r"\\?\UNC\server\share\x");
+        assert_eq!(extend_str("//server/share/Record"), r"\\?\UNC\server\share\Record");
+        assert_eq!(round_trip(r"\\server\share\x"), r"\\server\share\x");
+    }
+
+    #[test]
+    fn already_extended() {
+        assert_eq!(extend_str(r"\\?\C:\x"), r"\\?\C:\x");
+        assert_eq!(extend_str(r"\\?\UNC\server\share\x"), r"\\?\UNC\server\share\x");
+        assert_eq!(round_trip(r"\\?\C:\x"), r"C:\x");
+        assert_eq!(round_trip(r"\\?\UNC\server\share\x"), r"\\server\share\x");
+    }
+
+    #[test]
+    fn device_path_left_alone() {
+        assert_eq!(extend_str(r"\\.\pipe\callscribe"), r"\\.\pipe\callscribe");
+        assert_eq!(round_trip(r"\\.\pipe\callscribe"), r"\\.\pipe\callscribe");
+        assert_eq!(extend_str(r"\\.\COM3"), r"\\.\COM3");
+    }
+
+    #[test]
+    fn relative_path_left_alone() {
+        assert_eq!(extend_str(r"Record\20250909"), r"Record\20250909");
+        assert_eq!(round_trip("Record/x"), r"Record\x");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn extended_is_a_no_op_off_windows() {
+        assert_eq!(extended(Path::new("/data/Record")), Path::new("/data/Record"));
+        assert_eq!(simplified(Path::new("/data/Record")), Path::new("/data/Record"));
+    }
+}
@@ -0,0 +1,30 @@
+// tests/golden.rs
+//
+// Every fixture in `tests/fixtures` against its golden file, the same check
+// `callscribe golden` runs, so `cargo test` catches parser changes.
+
+use dsd_event_parser::golden::{self, Outcome};
+use std::path::Path;
+
+#[tokio::test]
+async fn fixtures_match_golden_files() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(golden::DEFAULT_DIR);
+    let fixtures = golden::fixtures(&dir).expect("list fixtures");
+    assert!(!fixtures.is_empty(), "no fixtures in {}", dir.display());
+    let mut failed = Vec::new();
+    for f in &fixtures {
+        let outcome = golden::check(f, false).await.unwrap_or_else(|e| panic!("{}: {}", f.display(), e));
+        match outcome {
+            Outcome::Match(_) => {}
+            Outcome::Mismatch { line, expected, got } => failed.push(format!(
+                "{}: record {} differs\n  expected: {}\n  got:      {}",
+                f.display(),
+                line,
+                expected.as_deref().unwrap_or("(end of file)"),
+                got.as_deref().unwrap_or("(no more records)")
+            )),
+            other => failed.push(format!("{}: {:?}", f.display(), other)),
+        }
+    }
+    assert!(failed.is_empty(), "{} of {} fixtures failed:\n{}", failed.len(), fixtures.len(), failed.join("\n"));
+}
@@ -0,0 +1,169 @@
+// tests/lenient.rs
+//
+// Property tests for `--lenient`: binary garbage, truncated blocks and
+// blocks cut short by the next one never abort the stream, and every intact
+// block after the damage is still parsed. Run with
+// `cargo test --features proptests`.
+
+#![cfg(feature = "proptests")]
+
+use dsd_event_parser::input::{self, ParseOptions};
+use dsd_event_parser::model::RadioRecord;
+use proptest::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::mpsc;
+
+static SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// A scratch input file, deleted on drop.
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new(ext: &str, bytes: &[u8]) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "callscribe-lenient-{}-{}.{}",
+            std::process::id(),
+            SEQ.fetch_add(1, Ordering::Relaxed),
+            ext
+        ));
+        std::fs::write(&path, bytes).expect("write scratch input");
+        Scratch(path)
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Parse `path` leniently; the records and the recovery count.
+fn parse(path: &Path) -> (Result<Vec<RadioRecord>, String>, u64) {
+    let recoveries = Arc::new(AtomicU64::new(0));
+    let opts = ParseOptions { lenient: true, recoveries: Some(Arc::clone(&recoveries)), ..Default::default() };
+    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().expect("runtime");
+    let result = rt.block_on(async {
+        let (tx, mut rx) = mpsc::channel(64);
+        let collect = async {
+            let mut out = Vec::new();
+            while let Some(rec) = rx.recv().await {
+                out.push(rec);
+            }
+            out
+        };
+        let (parsed, records) = tokio::join!(input::stream_input(path, opts, tx), collect);
+        parsed.map(|()| records).map_err(|e| e.to_string())
+    });
+    (result, recoveries.load(Ordering::Relaxed))
+}
+
+/// One well-formed SRT block.
+fn block(index: usize, tg: u32, rid: u32) -> Vec<String> {
+    vec![
+        index.to_string(),
+        "00:00:00,000 --> 00:00:01,000".into(),
+        format!("2025/09/10 06:{:02}:{:02}", index / 60 % 60, index % 60),
+        "153.450000 +P25p1 NAC=293".into(),
+        format!("TG={} RID={}", tg, rid),
+    ]
+}
+
+/// Damage put in front of an intact block.
+#[derive(Debug, Clone)]
+enum Damage {
+    None,
+    /// The first `n` lines of a block, with no blank line after them.
+    Truncated(usize),
+    /// Arbitrary bytes, then a line break.
+    Garbage(Vec<u8>),
+}
+
+fn damage() -> impl Strategy<Value = Damage> {
+    prop_oneof![
+        Just(Damage::None),
+        (1usize..5).prop_map(Damage::Truncated),
+        proptest::collection::vec(any::<u8>(), 1..80).prop_map(Damage::Garbage),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn srt_garbage_never_aborts(bytes in proptest::collection::vec(any::<u8>(), 0..2048)) {
+        let input = Scratch::new("srt", &bytes);
+        let (result, _) = parse(&input.0);
+        prop_assert!(result.is_ok(), "{:?}", result.err());
+    }
+
+    #[test]
+    fn event_garbage_never_aborts(bytes in proptest::collection::vec(any::<u8>(), 0..2048)) {
+        let input = Scratch::new("event", &bytes);
+        let (result, _) = parse(&input.0);
+        prop_assert!(result.is_ok(), "{:?}", result.err());
+    }
+
+    #[test]
+    fn srt_cut_anywhere_never_aborts(
+        ids in proptest::collection::vec((1u32..65535, 1u32..16_777_215), 1..6),
+        cut in any::<prop::sample::Index>(),
+    ) {
+        let text: String = ids
+            .iter()
+            .enumerate()
+            .map(|(i, &(tg, rid))| block(i + 1, tg, rid).join("\n") + "\n\n")
+            .collect();
+        let cut = cut.index(text.len() + 1);
+        let input = Scratch::new("srt", &text.as_bytes()[..cut]);
+        let (result, _) = parse(&input.0);
+        let records = result.map_err(TestCaseError::fail)?;
+        prop_assert!(records.len() <= ids.len());
+    }
+
+    /// Intact blocks are numbered from 1000 and damaged ones below, so the
+    /// intact ones can be picked out of whatever the damage produced.
+    #[test]
+    fn srt_resyncs_after_damage(
+        blocks in proptest::collection::vec((damage(), 1u32..65535, 1u32..16_777_215), 1..8),
+    ) {
+        let mut bytes = Vec::new();
+        for (i, (damage, tg, rid)) in blocks.iter().enumerate() {
+            match damage {
+                Damage::None => {}
+                Damage::Truncated(n) => {
+                    for line in block(i + 1, 9, 9).into_iter().take(*n) {
+                        bytes.extend_from_slice(line.as_bytes());
+                        bytes.push(b'\n');
+                    }
+                }
+                Damage::Garbage(g) => {
+                    bytes.extend_from_slice(g);
+                    bytes.push(b'\n');
+                }
+            }
+            for line in block(1000 + i, *tg, *rid) {
+                bytes.extend_from_slice(line.as_bytes());
+                bytes.push(b'\n');
+            }
+            bytes.push(b'\n');
+        }
+        let input = Scratch::new("srt", &bytes);
+        let (result, recoveries) = parse(&input.0);
+        let records = result.map_err(TestCaseError::fail)?;
+        let got: Vec<(usize, Option<String>, Option<String>)> = records
+            .into_iter()
+            .filter(|r| r.record_number >= 1000)
+            .map(|r| (r.record_number, r.slot1.tg, r.slot1.rid))
+            .collect();
+        let expected: Vec<(usize, Option<String>, Option<String>)> = blocks
+            .iter()
+            .enumerate()
+            .map(|(i, (_, tg, rid))| (1000 + i, Some(tg.to_string()), Some(rid.to_string())))
+            .collect();
+        prop_assert_eq!(got, expected);
+        let truncated = blocks.iter().filter(|(d, _, _)| matches!(d, Damage::Truncated(_))).count();
+        prop_assert!(recoveries as usize >= truncated);
+    }
+}